@@ -0,0 +1,77 @@
+use ddclient_rs::VotingResults;
+use plotters::prelude::*;
+
+pub const CHART_FILENAME: &str = "results.png";
+
+const CHART_WIDTH: u32 = 640;
+const CHART_HEIGHT: u32 = 320;
+
+const BAR_COLOR: RGBColor = RGBColor(88, 101, 242);
+const WINNER_COLOR: RGBColor = RGBColor(87, 242, 135);
+
+// PNG bar chart of the winning percentages, bars follow the ranking order of the results.
+// There are no labels, fonts would have to be bundled, the choices are listed in the results embed.
+pub fn render_results_chart(results: &VotingResults) -> Vec<u8> {
+    let mut pixels = vec![0u8; (CHART_WIDTH * CHART_HEIGHT * 3) as usize];
+
+    {
+        let root = BitMapBackend::with_buffer(&mut pixels, (CHART_WIDTH, CHART_HEIGHT))
+            .into_drawing_area();
+        if let Err(err) = draw_bars(&root, results) {
+            tracing::error!(error = ?err, "drawing results chart failed");
+        }
+    }
+
+    encode_png(&pixels)
+}
+
+fn draw_bars<DB: DrawingBackend>(
+    root: &DrawingArea<DB, plotters::coord::Shift>,
+    results: &VotingResults,
+) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+    root.fill(&WHITE)?;
+
+    // an empty chart still has room for one bar, so the axis range is never empty
+    let bars = results.results.len().max(1) as f32;
+    let mut chart = ChartBuilder::on(root)
+        .margin(16)
+        .build_cartesian_2d(0f32..bars, 0f32..100f32)?;
+
+    let top = results.results.first().map(|result| result.wins);
+    chart.draw_series(results.results.iter().enumerate().map(|(i, result)| {
+        let color = if Some(result.wins) == top {
+            WINNER_COLOR
+        } else {
+            BAR_COLOR
+        };
+        let x = i as f32;
+        Rectangle::new(
+            [
+                (x + 0.1, 0.0),
+                (x + 0.9, result.percentage.clamp(0.0, 100.0)),
+            ],
+            color.filled(),
+        )
+    }))?;
+
+    root.present()
+}
+
+fn encode_png(pixels: &[u8]) -> Vec<u8> {
+    let mut png = Vec::new();
+
+    let mut encoder = png::Encoder::new(&mut png, CHART_WIDTH, CHART_HEIGHT);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    // writing into a vec can only fail on a buffer size mismatch, which the constants rule out
+    let written = encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(pixels));
+    if let Err(err) = written {
+        tracing::error!(error = ?err, "encoding results chart failed");
+        return Vec::new();
+    }
+
+    png
+}
@@ -1,29 +1,224 @@
-use redb::{CommitError, Database, ReadableTable, StorageError, TableDefinition, TransactionError};
+use redb::{
+    Builder, CommitError, Database, Durability, ReadableTable, StorageError, TableDefinition,
+    TransactionError, WriteTransaction,
+};
 use serde::{Deserialize, Serialize};
-use std::{convert::TryFrom, sync::Arc};
+use std::{collections::HashMap, convert::TryFrom, ops::Bound, ops::ControlFlow, sync::Arc};
 use tokio::task::JoinError;
 
-// <votingID, votingJson>
-const VOTING_TABLE: TableDefinition<&str, &str> = TableDefinition::new("voting");
-// <votingID-userID, votingDialogJson>
-const VOTING_DIALOG_TABLE: TableDefinition<&str, &str> = TableDefinition::new("voting_dialog");
-// <customUUID, customIDJson>
-const CUSTOM_ID_TABLE: TableDefinition<&str, &str> = TableDefinition::new("custom_id");
-// <votingID-customUUID, customUUID>
-const VOTING_CUSTOMID_INDEX_TABLE: TableDefinition<&str, &str> =
-    TableDefinition::new("voting_customid_index");
+use crate::util;
+
+// Names of the tables, each starting with the `DbOptions::table_prefix`.
+// Owned instead of `const` definitions, so several bots can share one database file.
+struct Tables {
+    // <votingID, votingJson>
+    voting: String,
+    // <votingID-userID, votingDialogJson>
+    voting_dialog: String,
+    // <customUUID, customIDJson>
+    custom_id: String,
+    // <creatorUserID-votingID, votingID>
+    creator_voting_index: String,
+    // <guildID-votingID, votingID> votings started in the guild, see `Db::get_guild_votings`
+    guild_voting_index: String,
+    // <votingID-customUUID, customUUID>
+    voting_customid_index: String,
+    // <messageID, votingID-userID> dm message of the voting dialog
+    dialog_message_index: String,
+    // <userID-votingID, votingID-userID> voting dialogs of the user
+    user_dialog_index: String,
+    // <votingID-userID, votingID-userID> submitted voting dialogs, see `Db::count_submitted_dialogs`
+    submitted_dialog_index: String,
+    // <votingID, unixTimestamp> completed votings whose discord messages are not updated yet
+    pending_completion: String,
+    // <votingID-userID, pendingVoteJson> ballots which may not have been sent to the DD backend
+    pending_vote: String,
+    // <votingID-timestamp-sequence, auditEventJson> lifecycle events of the voting, kept after delete
+    audit: String,
+    // <guildID, guildConfigJson> overrides of the global defaults, see `GuildConfig`
+    guild_config: String,
+    // <key, json> bookkeeping of the database itself, see `MIGRATION_KEY_PREFIX`
+    meta: String,
+    // <votingID-userID, failedCleanupJson> dialog messages of closed votings still to be updated
+    failed_cleanup: String,
+    // <guildID-templateName, votingTemplateJson> named choices of the guild, see `VotingTemplate`
+    voting_template: String,
+    // <customUUID, votingsListingJson> page buttons of the votings listing, see `VotingsListing`
+    votings_listing: String,
+    // <votingID, votingBallotsJson> ballots and comments recorded on completion, see `VotingBallots`
+    voting_ballots: String,
+}
+
+impl Tables {
+    fn new(prefix: &str) -> Self {
+        let name = |table: &str| format!("{}{}", prefix, table);
+        Tables {
+            voting: name("voting"),
+            voting_dialog: name("voting_dialog"),
+            custom_id: name("custom_id"),
+            creator_voting_index: name("creator_voting_index"),
+            guild_voting_index: name("guild_voting_index"),
+            voting_customid_index: name("voting_customid_index"),
+            dialog_message_index: name("dialog_message_index"),
+            user_dialog_index: name("user_dialog_index"),
+            submitted_dialog_index: name("submitted_dialog_index"),
+            pending_completion: name("pending_completion"),
+            pending_vote: name("pending_vote"),
+            audit: name("audit"),
+            guild_config: name("guild_config"),
+            meta: name("meta"),
+            failed_cleanup: name("failed_cleanup"),
+            voting_template: name("voting_template"),
+            votings_listing: name("votings_listing"),
+            voting_ballots: name("voting_ballots"),
+        }
+    }
+
+    fn voting(&self) -> TableDefinition<'_, &'static str, &'static str> {
+        TableDefinition::new(&self.voting)
+    }
+
+    fn voting_dialog(&self) -> TableDefinition<'_, &'static str, &'static str> {
+        TableDefinition::new(&self.voting_dialog)
+    }
+
+    fn custom_id(&self) -> TableDefinition<'_, &'static str, &'static str> {
+        TableDefinition::new(&self.custom_id)
+    }
+
+    fn creator_voting_index(&self) -> TableDefinition<'_, &'static str, &'static str> {
+        TableDefinition::new(&self.creator_voting_index)
+    }
+
+    fn guild_voting_index(&self) -> TableDefinition<'_, &'static str, &'static str> {
+        TableDefinition::new(&self.guild_voting_index)
+    }
+
+    fn voting_customid_index(&self) -> TableDefinition<'_, &'static str, &'static str> {
+        TableDefinition::new(&self.voting_customid_index)
+    }
+
+    fn dialog_message_index(&self) -> TableDefinition<'_, &'static str, &'static str> {
+        TableDefinition::new(&self.dialog_message_index)
+    }
+
+    fn user_dialog_index(&self) -> TableDefinition<'_, &'static str, &'static str> {
+        TableDefinition::new(&self.user_dialog_index)
+    }
+
+    fn submitted_dialog_index(&self) -> TableDefinition<'_, &'static str, &'static str> {
+        TableDefinition::new(&self.submitted_dialog_index)
+    }
+
+    fn pending_completion(&self) -> TableDefinition<'_, &'static str, u64> {
+        TableDefinition::new(&self.pending_completion)
+    }
+
+    fn pending_vote(&self) -> TableDefinition<'_, &'static str, &'static str> {
+        TableDefinition::new(&self.pending_vote)
+    }
+
+    fn audit(&self) -> TableDefinition<'_, &'static str, &'static str> {
+        TableDefinition::new(&self.audit)
+    }
+
+    fn guild_config(&self) -> TableDefinition<'_, &'static str, &'static str> {
+        TableDefinition::new(&self.guild_config)
+    }
+
+    fn meta(&self) -> TableDefinition<'_, &'static str, &'static str> {
+        TableDefinition::new(&self.meta)
+    }
+
+    fn failed_cleanup(&self) -> TableDefinition<'_, &'static str, &'static str> {
+        TableDefinition::new(&self.failed_cleanup)
+    }
+
+    fn voting_template(&self) -> TableDefinition<'_, &'static str, &'static str> {
+        TableDefinition::new(&self.voting_template)
+    }
+
+    fn votings_listing(&self) -> TableDefinition<'_, &'static str, &'static str> {
+        TableDefinition::new(&self.votings_listing)
+    }
+
+    fn voting_ballots(&self) -> TableDefinition<'_, &'static str, &'static str> {
+        TableDefinition::new(&self.voting_ballots)
+    }
+}
+
+// Migrations applied to the database, see `Db::run_migrations`
+const MIGRATION_KEY_PREFIX: &str = "migration:";
+const BACKFILL_DIALOG_SUBMITTED: &str = "backfill_dialog_submitted";
+const DELETE_VOTINGS_PAGE_CUSTOM_IDS: &str = "delete_votings_page_custom_ids";
+const MOVE_VOTING_BALLOTS: &str = "move_voting_ballots";
+const INDEX_SUBMITTED_DIALOGS: &str = "index_submitted_dialogs";
 const ENCODE_DELIMITER: &str = "-";
 
+// How many custom ids `bulk_save_custom_ids` writes in a single transaction
+pub const CUSTOM_IDS_CHUNK_SIZE: usize = 500;
+// Most custom ids `bulk_save_custom_ids` accepts at once
+pub const MAX_BULK_CUSTOM_IDS: usize = 10_000;
+
 pub struct Db {
     pub db: Arc<Database>,
+    tables: Arc<Tables>,
+    durability: Durability,
+}
+
+pub const DB_PATH: &str = "voting.redb";
+
+#[derive(Debug, Clone)]
+pub struct DbOptions {
+    // Page cache of redb, a bigger cache saves disk reads on busy deployments.
+    // `None` keeps the redb default.
+    pub cache_bytes: Option<usize>,
+    // Durability of every write transaction. `Immediate` fsyncs on each commit.
+    // `Eventual` returns before the data is on disk, which makes commits faster but
+    // the last writes can be lost on a crash, so it only suits ephemeral polls.
+    pub durability: Durability,
+    // Prepended to every table name. Instances with different prefixes can use the same
+    // file without seeing each other's data, e.g. dev and staging bots.
+    pub table_prefix: String,
+}
+
+impl Default for DbOptions {
+    fn default() -> Self {
+        DbOptions {
+            cache_bytes: None,
+            durability: Durability::Immediate,
+            table_prefix: String::new(),
+        }
+    }
 }
 
 pub fn new() -> Db {
-    let db = Database::create("voting.redb").expect("failed to create database");
-    Db { db: Arc::new(db) }
+    new_with_path(DB_PATH, DbOptions::default()).expect("failed to create database")
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+// Opens or creates the database file.
+// Returns `DatabaseInUse` if the file is locked, i.e. another process already has it open.
+pub fn new_with_path(path: &str, options: DbOptions) -> Result<Db, DbError> {
+    let mut builder = Builder::new();
+    if let Some(cache_bytes) = options.cache_bytes {
+        builder.set_cache_size(cache_bytes);
+    }
+
+    let db = builder.create(path).map_err(|err| match err {
+        redb::DatabaseError::DatabaseAlreadyOpen => {
+            DbError::DatabaseInUse("another process has the database file open".to_string())
+        }
+        err => DbError::Other(err.to_string()),
+    })?;
+
+    Ok(Db {
+        db: Arc::new(db),
+        tables: Arc::new(Tables::new(&options.table_prefix)),
+        durability: options.durability,
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
 pub struct Voting {
     pub id: String,
     pub name: String,
@@ -34,6 +229,211 @@ pub struct Voting {
     pub channel_id: String,
     pub creator_message_id: String,
     pub creator_dm_channel_id: String,
+    #[serde(default)]
+    pub creator_user_id: String,
+    // Guild the voting was started in, empty for votings saved before it was recorded
+    #[serde(default)]
+    pub guild_id: String,
+    // Order in which the choices are displayed, as indices into `choices`.
+    // Ballots are always stored and submitted in the original `choices` order.
+    #[serde(default)]
+    pub display_order: Option<Vec<usize>>,
+    // When set, voters can only rank their top `max_ranks` choices, the rest stay unranked
+    #[serde(default)]
+    pub max_ranks: Option<u32>,
+    // Ballots with unranked choices are rejected, otherwise unranked choices are tied last
+    #[serde(default)]
+    pub require_full_ranking: bool,
+    // When set, only members with this role can vote
+    #[serde(default)]
+    pub allowed_role_id: Option<String>,
+    // Content of the completion message, see `render_completion_template` for the placeholders
+    #[serde(default)]
+    pub completion_template: Option<String>,
+    // Whether the completion message can ping the users and roles mentioned in the template
+    #[serde(default)]
+    pub completion_mentions: bool,
+    // Whether the announcement shows how many people have voted, see `schedule_vote_count_edit`
+    #[serde(default)]
+    pub show_vote_count: bool,
+    // Simple votings are single choice, voted with reactions on the announcement instead of ranked in dm
+    #[serde(default)]
+    pub simple: bool,
+    // Voting dialogs are sent as ephemeral messages in the channel instead of dms
+    #[serde(default)]
+    pub in_channel: bool,
+    // Every voter sees the choices in their own order, see `VoteDialog::display_order`
+    #[serde(default)]
+    pub shuffle: bool,
+    // Unix timestamp at which the voting is completed automatically
+    #[serde(default)]
+    pub deadline_unix: Option<u64>,
+    // Submitted ballots ranking each choice first, by choice index. Recorded on completion,
+    // before the voting dialogs holding the ballots are cleaned up.
+    #[serde(default)]
+    pub first_choice_counts: Option<HashMap<usize, u32>>,
+    // Banner image shown in the channel announcement, always an http(s) url
+    #[serde(default)]
+    pub image_url: Option<String>,
+    // How the winner is picked when the top choices are tied
+    #[serde(default)]
+    pub tie_break: TieBreak,
+    // Results are only shown to the creator, the channel message just notes the voting is over
+    #[serde(default)]
+    pub private_results: bool,
+    // Unix timestamp of the last reminder sent to the voters who didn't submit their dialog
+    #[serde(default)]
+    pub reminded_unix: Option<u64>,
+    // Voting weight of members with these roles, see `Voting::voter_weight`
+    #[serde(default)]
+    pub role_weights: Vec<(String, u32)>,
+    // Voters are not asked for comments and none are shown with the results
+    #[serde(default)]
+    pub anonymous: bool,
+    // A bar chart of the results is attached to the completion message, needs the `chart` feature
+    #[serde(default)]
+    pub chart: bool,
+    // A summary of the results is posted as a new channel message and pinned on completion
+    #[serde(default)]
+    pub pin_result: bool,
+    // Users without a voting dialog can no longer open one, existing dialogs can still be submitted
+    #[serde(default)]
+    pub dialogs_locked: bool,
+    // Further questions of a survey, ranked in the same dialog after the choices of the voting
+    #[serde(default)]
+    pub questions: Vec<Question>,
+    // Short descriptions of the choices, by choice index, shown in the vote select menus.
+    // Empty when no choice has one, a choice without description has an empty string.
+    #[serde(default)]
+    pub choice_descriptions: Vec<String>,
+    // Label and unicode emoji of the announcement vote button, the defaults are used when not set
+    #[serde(default)]
+    pub vote_button_label: Option<String>,
+    #[serde(default)]
+    pub vote_button_emoji: Option<String>,
+    // Bumped on every update of a saved voting, see `Db::update_voting_checked`
+    #[serde(default)]
+    pub version: u64,
+}
+
+// Question of a survey voting besides the voting itself. Every question is a voting of its own
+// in the DD backend, so its ballots are counted separately.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+pub struct Question {
+    pub dd_voting_id: String,
+    pub name: String,
+    pub choices: Vec<String>,
+}
+
+// Strategy picking a single winner out of the tied top choices
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy, Default)]
+pub enum TieBreak {
+    // the voting ends in a tie
+    #[default]
+    None,
+    // random draw, seeded with the voting id so every render picks the same winner
+    Random,
+    // the choice listed first by the creator
+    EarliestChoice,
+    // the choice ranked first on the most ballots
+    FirstChoiceCount,
+}
+
+// Lifecycle state of a voting, see `Db::get_open_voting`
+#[derive(Debug, PartialEq)]
+pub enum VotingState {
+    Open(Voting),
+    Completed,
+    Deleted,
+    NotFound,
+}
+
+// A deleted voting stays deleted, even when it was completed before
+impl From<Voting> for VotingState {
+    fn from(voting: Voting) -> Self {
+        if voting.is_deleted {
+            VotingState::Deleted
+        } else if voting.is_completed {
+            VotingState::Completed
+        } else {
+            VotingState::Open(voting)
+        }
+    }
+}
+
+impl Voting {
+    // Highest rank a voter can give to a choice
+    pub fn rank_limit(&self) -> usize {
+        match self.max_ranks {
+            Some(max_ranks) => usize::min(max_ranks as usize, self.choices.len()),
+            None => self.choices.len(),
+        }
+    }
+
+    // Indices of the choices in the order they should be displayed
+    pub fn display_indices(&self) -> Vec<usize> {
+        match self.display_order {
+            Some(ref order) => order.clone(),
+            None => (0..self.choices.len()).collect(),
+        }
+    }
+
+    // The voting itself is question 0, followed by `questions`
+    pub fn question_count(&self) -> usize {
+        1 + self.questions.len()
+    }
+
+    // Description of the choice at the index, None when it has none
+    pub fn choice_description(&self, index: usize) -> Option<&str> {
+        self.choice_descriptions
+            .get(index)
+            .map(String::as_str)
+            .filter(|description| !description.is_empty())
+    }
+
+    // The voting as the dialog ranks question `question`, `None` if there is no such question.
+    // Further questions keep the ranking rules of the voting, but not its display order,
+    // which indexes the choices of the voting.
+    pub fn question_view(&self, question: usize) -> Option<Voting> {
+        if question == 0 {
+            return Some(self.clone());
+        }
+
+        let further = self.questions.get(question - 1)?;
+        Some(Voting {
+            name: further.name.clone(),
+            choices: further.choices.clone(),
+            display_order: None,
+            questions: Vec::new(),
+            choice_descriptions: Vec::new(),
+            ..self.clone()
+        })
+    }
+
+    // Highest weight among the member roles, members without a weighted role count once
+    pub fn voter_weight<T: ToString>(&self, roles: &[T]) -> u32 {
+        roles
+            .iter()
+            .filter_map(|role_id| {
+                let role_id = role_id.to_string();
+                self.role_weights
+                    .iter()
+                    .find(|(weighted_role_id, _)| *weighted_role_id == role_id)
+                    .map(|(_, weight)| *weight)
+            })
+            .max()
+            .unwrap_or(1)
+    }
+
+    // Highest weight a ballot of the voting can have
+    pub fn max_voter_weight(&self) -> u32 {
+        self.role_weights
+            .iter()
+            .map(|(_, weight)| *weight)
+            .max()
+            .unwrap_or(1)
+            .max(1)
+    }
 }
 
 impl TryFrom<&str> for Voting {
@@ -57,6 +457,195 @@ pub struct VoteDialog {
     pub ballot: Vec<i32>,
     pub message_id: String,
     pub channel_id: String,
+    // set once the ballot was sent to the DD backend, the dialog is kept for the undo window
+    #[serde(default)]
+    pub submitted: bool,
+    #[serde(default)]
+    pub submitted_unix: u64,
+    // Order of the choices for this voter, overrides the voting display order when set.
+    // Like the voting display order, it only affects rendering, the ballot keeps the original order.
+    #[serde(default)]
+    pub display_order: Option<Vec<usize>>,
+    // When the dialog was opened, used to expire abandoned dialogs. 0 when unknown, for dialogs
+    // saved before it was recorded.
+    #[serde(default)]
+    pub created_unix: u64,
+    // How many times the ballot is counted, taken from the member roles when the dialog is opened
+    // since dm interactions carry no member
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+    // Reason the voter gave for the ballot, added after submitting it
+    #[serde(default)]
+    pub comment: Option<String>,
+    // Question of a survey voting the dialog is at, 0 is the voting itself, see `Voting::questions`
+    #[serde(default)]
+    pub question: usize,
+    // Ballots of the further questions the voter got to, `ballot` is the one of the voting itself
+    #[serde(default)]
+    pub question_ballots: Vec<Vec<i32>>,
+    // Saved by a `DialogSlot` before the dialog message is sent, cleared when the slot is committed
+    #[serde(default)]
+    pub placeholder: bool,
+}
+
+impl VoteDialog {
+    // Ballot of question `question`, `None` if the voter did not get to it yet
+    pub fn question_ballot(&self, question: usize) -> Option<&Vec<i32>> {
+        match question {
+            0 => Some(&self.ballot),
+            question => self.question_ballots.get(question - 1),
+        }
+    }
+
+    fn current_ballot_mut(&mut self) -> Option<&mut Vec<i32>> {
+        match self.question {
+            0 => Some(&mut self.ballot),
+            question => self.question_ballots.get_mut(question - 1),
+        }
+    }
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+// Placeholder voting dialog claimed before the dm is sent, see `Db::claim_dialog_slot`.
+// A slot which can't be committed is released, so a failed dm doesn't block voting.
+// Dropping it without either releases it in the background.
+pub struct DialogSlot {
+    db: Arc<Database>,
+    tables: Arc<Tables>,
+    durability: Durability,
+    voting_id: String,
+    user_id: String,
+    weight: u32,
+    // tells our placeholder apart from one saved by a later slot, see `rollback_dialog_slot`
+    created_unix: u64,
+    // saved with the placeholder by `Db::open_dialog_with_custom_ids`, removed with it on rollback
+    custom_uuids: Vec<String>,
+    // committed or released, nothing is left to roll back
+    settled: bool,
+}
+
+impl DialogSlot {
+    pub fn with_weight(mut self, weight: u32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    pub async fn commit(
+        mut self,
+        ballot: Vec<i32>,
+        display_order: Option<Vec<usize>>,
+        message_id: String,
+        channel_id: String,
+    ) -> Result<(), DbError> {
+        let id = encode_key(&self.voting_id, &self.user_id);
+        let dialog = VoteDialog {
+            voting_id: self.voting_id.clone(),
+            user_id: self.user_id.clone(),
+            ballot,
+            message_id,
+            channel_id,
+            submitted: false,
+            submitted_unix: 0,
+            display_order,
+            created_unix: util::unix_timestamp(),
+            weight: self.weight,
+            comment: None,
+            question: 0,
+            question_ballots: Vec::new(),
+            placeholder: false,
+        };
+
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+        let written = tokio::task::spawn_blocking(move || {
+            write_voting_dialog(&db, &tables, durability, &id, &dialog, true)
+        })
+        .await
+        .map_err(|e| DbError::Other(e.to_string()))
+        .and_then(|written| written);
+
+        if let Err(err) = written {
+            // the failed write left the placeholder, releasing it lets the user click vote again
+            if let Err(err) = self.release().await {
+                tracing::error!(error = ?err, "releasing dialog slot failed");
+            }
+            return Err(err);
+        }
+
+        self.settled = true;
+
+        Ok(())
+    }
+
+    // Deletes the placeholder dialog and its custom ids
+    pub async fn release(mut self) -> Result<(), DbError> {
+        if self.settled {
+            return Ok(());
+        }
+        self.settled = true;
+
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+        let id = encode_key(&self.voting_id, &self.user_id);
+        let created_unix = self.created_unix;
+        let voting_id = self.voting_id.clone();
+        let custom_uuids = std::mem::take(&mut self.custom_uuids);
+        tokio::task::spawn_blocking(move || {
+            rollback_dialog_slot(
+                &db,
+                &tables,
+                durability,
+                &id,
+                created_unix,
+                &voting_id,
+                &custom_uuids,
+            )
+        })
+        .await
+        .map_err(|e| DbError::Other(e.to_string()))?
+    }
+}
+
+impl Drop for DialogSlot {
+    fn drop(&mut self) {
+        if self.settled {
+            return;
+        }
+
+        // drop can't await, the rollback is left to the blocking pool. It may run late, it only
+        // deletes the dialog while it is still this slot's placeholder.
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            tracing::error!(voting_id = %self.voting_id, user_id = %self.user_id, "dialog slot dropped outside of the runtime, it can't be rolled back");
+            return;
+        };
+
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+        let id = encode_key(&self.voting_id, &self.user_id);
+        let created_unix = self.created_unix;
+        let voting_id = self.voting_id.clone();
+        let user_id = self.user_id.clone();
+        let custom_uuids = std::mem::take(&mut self.custom_uuids);
+        runtime.spawn_blocking(move || {
+            if let Err(err) = rollback_dialog_slot(
+                &db,
+                &tables,
+                durability,
+                &id,
+                created_unix,
+                &voting_id,
+                &custom_uuids,
+            ) {
+                tracing::error!(%voting_id, %user_id, error = ?err, "rolling back dialog slot failed");
+            }
+        });
+    }
 }
 
 impl TryFrom<&str> for VoteDialog {
@@ -73,6 +662,212 @@ impl From<&VoteDialog> for String {
     }
 }
 
+// Ballot as submitted to the DD backend, keyed by choice name
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct PendingVote {
+    pub voting_id: String,
+    pub user_id: String,
+    pub ballot: HashMap<String, i32>,
+    // the ballot is submitted once per weight, see `VoteDialog::weight`
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+}
+
+impl TryFrom<&str> for PendingVote {
+    type Error = DbError;
+
+    fn try_from(vote: &str) -> Result<Self, Self::Error> {
+        serde_json::from_str(vote).map_err(|e| DbError::Other(e.to_string()))
+    }
+}
+
+impl From<&PendingVote> for String {
+    fn from(vote: &PendingVote) -> Self {
+        serde_json::to_string(&vote).expect("failed to serialize pending vote")
+    }
+}
+
+// Dialog message of a closed voting which could not be updated, retried until `retry_unix`
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct FailedCleanup {
+    pub voting_id: String,
+    pub user_id: String,
+    pub channel_id: String,
+    pub message_id: String,
+    // content the dialog message is replaced with
+    pub content: String,
+    pub attempts: u32,
+    pub retry_unix: u64,
+}
+
+impl TryFrom<&str> for FailedCleanup {
+    type Error = DbError;
+
+    fn try_from(cleanup: &str) -> Result<Self, Self::Error> {
+        serde_json::from_str(cleanup).map_err(|e| DbError::Other(e.to_string()))
+    }
+}
+
+impl From<&FailedCleanup> for String {
+    fn from(cleanup: &FailedCleanup) -> Self {
+        serde_json::to_string(&cleanup).expect("failed to serialize failed cleanup")
+    }
+}
+
+// Settings of a single guild, unset fields fall back to the global defaults of the bot
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+pub struct GuildConfig {
+    // Votings are anonymous unless the creator sets the `anonymous` option
+    #[serde(default)]
+    pub anonymous_by_default: bool,
+    // Fewest choices a voting can have, overrides `Config::min_choices`
+    #[serde(default)]
+    pub min_choices: Option<usize>,
+    // Most choices a voting can have, lower than the choice options of the voting command.
+    // Overrides `Config::max_choices`.
+    #[serde(default)]
+    pub max_choices: Option<usize>,
+    // Overrides `Config::max_active_votings_per_creator`
+    #[serde(default)]
+    pub max_active_votings_per_creator: Option<usize>,
+}
+
+impl TryFrom<&str> for GuildConfig {
+    type Error = DbError;
+
+    fn try_from(config: &str) -> Result<Self, Self::Error> {
+        serde_json::from_str(config).map_err(|e| DbError::Other(e.to_string()))
+    }
+}
+
+impl From<&GuildConfig> for String {
+    fn from(config: &GuildConfig) -> Self {
+        serde_json::to_string(&config).expect("failed to serialize guild config")
+    }
+}
+
+// Named choices a guild reuses across votings, picked with the `template` option of the voting command
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct VotingTemplate {
+    pub name: String,
+    pub choices: Vec<String>,
+}
+
+impl TryFrom<&str> for VotingTemplate {
+    type Error = DbError;
+
+    fn try_from(template: &str) -> Result<Self, Self::Error> {
+        serde_json::from_str(template).map_err(|e| DbError::Other(e.to_string()))
+    }
+}
+
+impl From<&VotingTemplate> for String {
+    fn from(template: &VotingTemplate) -> Self {
+        serde_json::to_string(&template).expect("failed to serialize voting template")
+    }
+}
+
+// Ballots and comments of a completed voting which is not anonymous. Recorded on completion,
+// before the voting dialogs holding them are cleaned up. They are kept apart from the voting,
+// which is read far more often than they are.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+pub struct VotingBallots {
+    // Comments of the submitted ballots as (user id, comment)
+    #[serde(default)]
+    pub comments: Vec<(String, String)>,
+    // Submitted ballots as (user id, ranks by choice index), for the creator to download
+    #[serde(default)]
+    pub ballots: Vec<(String, Vec<i32>)>,
+}
+
+impl TryFrom<&str> for VotingBallots {
+    type Error = DbError;
+
+    fn try_from(ballots: &str) -> Result<Self, Self::Error> {
+        serde_json::from_str(ballots).map_err(|e| DbError::Other(e.to_string()))
+    }
+}
+
+impl From<&VotingBallots> for String {
+    fn from(ballots: &VotingBallots) -> Self {
+        serde_json::to_string(&ballots).expect("failed to serialize voting ballots")
+    }
+}
+
+// Page of the votings listing behind one of its buttons. Pages are walked with the voting ids
+// they start after instead of a page number, so a click reads only the votings of its page.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct VotingsListing {
+    pub guild_id: String,
+    // voting id each page up to this one starts after, empty for the first page
+    pub cursors: Vec<String>,
+    // the button is deleted after this, see `Db::delete_expired_votings_listings`
+    pub expires_unix: u64,
+}
+
+impl VotingsListing {
+    pub fn page(&self) -> usize {
+        self.cursors.len() + 1
+    }
+}
+
+impl TryFrom<&str> for VotingsListing {
+    type Error = DbError;
+
+    fn try_from(listing: &str) -> Result<Self, Self::Error> {
+        serde_json::from_str(listing).map_err(|e| DbError::Other(e.to_string()))
+    }
+}
+
+impl From<&VotingsListing> for String {
+    fn from(listing: &VotingsListing) -> Self {
+        serde_json::to_string(&listing).expect("failed to serialize votings listing")
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub enum AuditEventKind {
+    Created,
+    Completed,
+    Deleted,
+    Transferred,
+}
+
+// Actor of the audit events the bot triggers on its own, e.g. a voting completed at its deadline
+pub const SYSTEM_USER_ID: &str = "system";
+
+// Entry of the voting audit log, `user_id` is who triggered the event or `SYSTEM_USER_ID`
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct AuditEvent {
+    pub kind: AuditEventKind,
+    pub user_id: String,
+    pub timestamp_unix: u64,
+}
+
+impl AuditEvent {
+    pub fn new(kind: AuditEventKind, user_id: &str) -> Self {
+        AuditEvent {
+            kind,
+            user_id: user_id.to_string(),
+            timestamp_unix: util::unix_timestamp(),
+        }
+    }
+}
+
+impl TryFrom<&str> for AuditEvent {
+    type Error = DbError;
+
+    fn try_from(event: &str) -> Result<Self, Self::Error> {
+        serde_json::from_str(event).map_err(|e| DbError::Other(e.to_string()))
+    }
+}
+
+impl From<&AuditEvent> for String {
+    fn from(event: &AuditEvent) -> Self {
+        serde_json::to_string(&event).expect("failed to serialize audit event")
+    }
+}
+
 #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq, Clone)]
 pub struct CustomID {
     pub action: Action,
@@ -85,9 +880,9 @@ pub struct CustomID {
     pub index: Option<usize>,
 }
 
-impl ToString for CustomID {
-    fn to_string(&self) -> String {
-        serde_json::to_string(self).unwrap()
+impl std::fmt::Display for CustomID {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", serde_json::to_string(self).unwrap())
     }
 }
 
@@ -108,13 +903,45 @@ pub enum Action {
     VotePrevious,
     Complete,
     Delete,
+    UndoVote,
+    ResultNext,
+    ResultPrevious,
+    SortChoices,
+    ViewAudit,
+    ExtendDeadline,
+    ClearDeadline,
+    RemindNonVoters,
+    // opens the rename modal from the creator dm, the modal submit carries the same custom id
+    EditName,
+    // opens the comment modal after voting, like `EditName`
+    AddComment,
+    // sends the recorded ballots to the creator dm after completion
+    DownloadBallots,
+    // replaces the vote button custom id of the announcement and edits it again
+    Refresh,
+    // toggles `Voting::dialogs_locked` from the creator dm
+    LockDialogs,
+    // unranks every choice of the voting dialog
+    ResetBallot,
+    // move the voting dialog between the questions of a survey voting
+    NextQuestion,
+    PreviousQuestion,
 }
 
 #[derive(Debug, PartialEq)]
 pub enum DbError {
     NotFound,
     IndexOutOfRange,
+    // ranks go from 1 to the number of choices
+    InvalidRank,
     AlreadyExists,
+    TooManyItems,
+    // the voting was written by someone else since it was read
+    VersionConflict,
+    // the voting was completed before, see `Db::complete_open_voting`
+    AlreadyCompleted,
+    // redb's file lock is held, another process has the database file open
+    DatabaseInUse(String),
     Other(String),
 }
 
@@ -159,117 +986,2042 @@ impl From<JoinError> for DbError {
 }
 
 impl Db {
-    // Saves voting to the database.
-    // Returns `AlreadyExists` if the voting with the same id already exists.
-    pub async fn save_voting(&self, voting: Voting) -> Result<(), DbError> {
+    // Checks the database can be read.
+    pub async fn ping(&self) -> Result<(), DbError> {
         let db = self.db.clone();
+        let tables = self.tables.clone();
 
         tokio::task::spawn_blocking(move || {
-            let write_txn = db.begin_write()?;
-            {
-                let mut table = write_txn.open_table(VOTING_TABLE)?;
-
-                if table.get(voting.id.as_str())?.is_some() {
-                    return Err(DbError::AlreadyExists);
-                }
-                table.insert(voting.id.clone().as_str(), String::from(&voting).as_str())?;
-            }
+            let read_txn = db.begin_read()?;
 
-            write_txn.commit()?;
+            let res = match read_txn.open_table(tables.voting()) {
+                Ok(_) => Ok(()),
+                // fresh database without any voting
+                Err(redb::TableError::TableDoesNotExist(_)) => Ok(()),
+                Err(err) => Err(err.into()),
+            };
 
-            Ok(())
+            res
         })
         .await?
     }
 
-    // Marks voting as completed.
-    // Returns `NotFound` if the voting is not found, or if it was marked as deleted.
-    pub async fn complete_voting(&self, id: &str) -> Result<Voting, DbError> {
+    // Opens every table once, so a corrupted or mismatched table fails the startup
+    // instead of the first interaction touching it. Missing tables are fine.
+    pub async fn self_check(&self) -> Result<(), DbError> {
         let db = self.db.clone();
-        let id = id.to_owned();
+        let tables = self.tables.clone();
 
         tokio::task::spawn_blocking(move || {
             let read_txn = db.begin_read()?;
 
-            let table = read_txn.open_table(VOTING_TABLE)?;
-
-            let res = table.get(id.as_str())?;
+            check_table(read_txn.open_table(tables.voting()), &tables.voting)?;
+            check_table(
+                read_txn.open_table(tables.voting_dialog()),
+                &tables.voting_dialog,
+            )?;
+            check_table(read_txn.open_table(tables.custom_id()), &tables.custom_id)?;
+            check_table(
+                read_txn.open_table(tables.creator_voting_index()),
+                &tables.creator_voting_index,
+            )?;
+            check_table(
+                read_txn.open_table(tables.guild_voting_index()),
+                &tables.guild_voting_index,
+            )?;
+            check_table(
+                read_txn.open_table(tables.voting_customid_index()),
+                &tables.voting_customid_index,
+            )?;
+            check_table(
+                read_txn.open_table(tables.dialog_message_index()),
+                &tables.dialog_message_index,
+            )?;
+            check_table(
+                read_txn.open_table(tables.user_dialog_index()),
+                &tables.user_dialog_index,
+            )?;
+            check_table(
+                read_txn.open_table(tables.submitted_dialog_index()),
+                &tables.submitted_dialog_index,
+            )?;
+            check_table(
+                read_txn.open_table(tables.pending_completion()),
+                &tables.pending_completion,
+            )?;
+            check_table(
+                read_txn.open_table(tables.pending_vote()),
+                &tables.pending_vote,
+            )?;
+            check_table(read_txn.open_table(tables.audit()), &tables.audit)?;
+            check_table(
+                read_txn.open_table(tables.guild_config()),
+                &tables.guild_config,
+            )?;
+            check_table(read_txn.open_table(tables.meta()), &tables.meta)?;
+            check_table(
+                read_txn.open_table(tables.failed_cleanup()),
+                &tables.failed_cleanup,
+            )?;
+            check_table(
+                read_txn.open_table(tables.voting_template()),
+                &tables.voting_template,
+            )?;
+            check_table(
+                read_txn.open_table(tables.votings_listing()),
+                &tables.votings_listing,
+            )?;
+            check_table(
+                read_txn.open_table(tables.voting_ballots()),
+                &tables.voting_ballots,
+            )?;
+
+            Ok(())
+        })
+        .await?
+    }
+
+    // Applies the one-time data migrations which did not run against this database yet.
+    // Each migration records itself in the meta table in the same transaction as its changes,
+    // so running this on every startup is safe. Returns the names of the applied migrations.
+    pub async fn run_migrations(&self) -> Result<Vec<&'static str>, DbError> {
+        let mut applied = Vec::new();
+
+        if self.backfill_dialog_submitted().await?.is_some() {
+            applied.push(BACKFILL_DIALOG_SUBMITTED);
+        }
+        if self.delete_votings_page_custom_ids().await?.is_some() {
+            applied.push(DELETE_VOTINGS_PAGE_CUSTOM_IDS);
+        }
+        if self.move_voting_ballots().await?.is_some() {
+            applied.push(MOVE_VOTING_BALLOTS);
+        }
+        if self.index_submitted_dialogs().await?.is_some() {
+            applied.push(INDEX_SUBMITTED_DIALOGS);
+        }
+
+        Ok(applied)
+    }
+
+    // Sets `submitted` on dialogs written before the flag existed, which deserialize as not
+    // submitted. Old dialogs carry no record of the submit itself, so the flag is inferred:
+    // - a pending vote for the dialog means its ballot was (being) sent to the DD backend
+    // - a dialog of a completed, deleted or missing voting can not be voted anymore, it is
+    //   closed as submitted so it is neither reminded, expired nor reopened
+    // Everything else stays an open dialog. `submitted_unix` is left at 0, so the undo window
+    // of backfilled dialogs is already over. Dialogs which have the field are not touched.
+    // Returns the number of dialogs marked as submitted, or `None` if the backfill already ran.
+    pub async fn backfill_dialog_submitted(&self) -> Result<Option<usize>, DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+
+        tokio::task::spawn_blocking(move || {
+            let marker = format!("{}{}", MIGRATION_KEY_PREFIX, BACKFILL_DIALOG_SUBMITTED);
+            let mut marked = 0;
+
+            let write_txn = begin_write(&db, durability)?;
+            {
+                let mut meta = write_txn.open_table(tables.meta())?;
+                if meta.get(marker.as_str())?.is_some() {
+                    return Ok(None);
+                }
+
+                let mut dialogs = write_txn.open_table(tables.voting_dialog())?;
+                let votings = write_txn.open_table(tables.voting())?;
+                let pending_votes = write_txn.open_table(tables.pending_vote())?;
+
+                let mut legacy = Vec::new();
+                for (key, value) in dialogs.iter()?.flatten() {
+                    let raw: serde_json::Value = serde_json::from_str(value.value())
+                        .map_err(|e| DbError::Other(e.to_string()))?;
+                    if raw.get("submitted").is_none() {
+                        legacy.push((
+                            key.value().to_string(),
+                            VoteDialog::try_from(value.value())?,
+                        ));
+                    }
+                }
+
+                for (key, mut dialog) in legacy {
+                    let is_closed = match votings.get(dialog.voting_id.as_str())? {
+                        Some(v) => {
+                            let voting = Voting::try_from(v.value())?;
+                            voting.is_completed || voting.is_deleted
+                        }
+                        None => true,
+                    };
+                    let is_pending = pending_votes.get(key.as_str())?.is_some();
+
+                    dialog.submitted = is_closed || is_pending;
+                    if dialog.submitted {
+                        marked += 1;
+                    }
+                    set_submitted_dialog_index(&write_txn, &tables, &key, dialog.submitted)?;
+                    // rewritten either way, so the dialog has the field from now on
+                    dialogs.insert(key.as_str(), String::from(&dialog).as_str())?;
+                }
+
+                let applied_unix = util::unix_timestamp().to_string();
+                meta.insert(marker.as_str(), applied_unix.as_str())?;
+            }
+
+            write_txn.commit()?;
+
+            Ok(Some(marked))
+        })
+        .await?
+    }
+
+    // Deletes the page buttons of the votings listing which were saved as custom ids under an
+    // empty voting id. They were never deleted with a voting and the listing keeps its pages in
+    // `votings_listing` now. Returns the number of deleted custom ids, or `None` if it already ran.
+    pub async fn delete_votings_page_custom_ids(&self) -> Result<Option<usize>, DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+
+        tokio::task::spawn_blocking(move || {
+            let marker = format!("{}{}", MIGRATION_KEY_PREFIX, DELETE_VOTINGS_PAGE_CUSTOM_IDS);
+            // the index key of an empty voting id is only the delimiter and the uuid
+            let index_prefix = encode_key("", "");
+            let mut deleted = 0;
+
+            let write_txn = begin_write(&db, durability)?;
+            {
+                let mut meta = write_txn.open_table(tables.meta())?;
+                if meta.get(marker.as_str())?.is_some() {
+                    return Ok(None);
+                }
+
+                let mut custom_id_table = write_txn.open_table(tables.custom_id())?;
+                let mut index_table = write_txn.open_table(tables.voting_customid_index())?;
+
+                let mut legacy = Vec::new();
+                for v in index_table.range(index_prefix.as_str()..)?.flatten() {
+                    if !v.0.value().starts_with(index_prefix.as_str()) {
+                        break;
+                    }
+                    legacy.push((v.0.value().to_string(), v.1.value().to_string()));
+                }
+
+                for (index_key, custom_uuid) in legacy {
+                    index_table.remove(index_key.as_str())?;
+                    if custom_id_table.remove(custom_uuid.as_str())?.is_some() {
+                        deleted += 1;
+                    }
+                }
+
+                let applied_unix = util::unix_timestamp().to_string();
+                meta.insert(marker.as_str(), applied_unix.as_str())?;
+            }
+
+            write_txn.commit()?;
+
+            Ok(Some(deleted))
+        })
+        .await?
+    }
+
+    // Moves the ballots and comments which were recorded on the voting itself into the
+    // `voting_ballots` table, so they are no longer read with every voting. The votings are
+    // rewritten without them. Returns the number of votings which had ballots or comments,
+    // or `None` if the migration already ran.
+    pub async fn move_voting_ballots(&self) -> Result<Option<usize>, DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+
+        tokio::task::spawn_blocking(move || {
+            let marker = format!("{}{}", MIGRATION_KEY_PREFIX, MOVE_VOTING_BALLOTS);
+            let mut moved = 0;
+
+            let write_txn = begin_write(&db, durability)?;
+            {
+                let mut meta = write_txn.open_table(tables.meta())?;
+                if meta.get(marker.as_str())?.is_some() {
+                    return Ok(None);
+                }
+
+                let mut votings = write_txn.open_table(tables.voting())?;
+                let mut ballots_table = write_txn.open_table(tables.voting_ballots())?;
+
+                let mut legacy = Vec::new();
+                for (key, value) in votings.iter()?.flatten() {
+                    let raw: serde_json::Value = serde_json::from_str(value.value())
+                        .map_err(|e| DbError::Other(e.to_string()))?;
+                    if raw.get("ballots").is_none() && raw.get("comments").is_none() {
+                        continue;
+                    }
+
+                    let ballots = VotingBallots::try_from(value.value())?;
+                    let voting = Voting::try_from(value.value())?;
+                    legacy.push((key.value().to_string(), voting, ballots));
+                }
+
+                for (key, voting, ballots) in legacy {
+                    if !ballots.ballots.is_empty() || !ballots.comments.is_empty() {
+                        ballots_table.insert(key.as_str(), String::from(&ballots).as_str())?;
+                        moved += 1;
+                    }
+                    // rewritten either way, the voting has no such fields anymore
+                    votings.insert(key.as_str(), String::from(&voting).as_str())?;
+                }
+
+                let applied_unix = util::unix_timestamp().to_string();
+                meta.insert(marker.as_str(), applied_unix.as_str())?;
+            }
+
+            write_txn.commit()?;
+
+            Ok(Some(moved))
+        })
+        .await?
+    }
+
+    // Indexes the dialogs which were submitted before `submitted_dialog_index` existed, so
+    // they can be counted without deserializing every dialog of the voting.
+    // Returns the number of indexed dialogs, or `None` if the migration already ran.
+    pub async fn index_submitted_dialogs(&self) -> Result<Option<usize>, DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+
+        tokio::task::spawn_blocking(move || {
+            let marker = format!("{}{}", MIGRATION_KEY_PREFIX, INDEX_SUBMITTED_DIALOGS);
+            let mut indexed = 0;
+
+            let write_txn = begin_write(&db, durability)?;
+            {
+                let mut meta = write_txn.open_table(tables.meta())?;
+                if meta.get(marker.as_str())?.is_some() {
+                    return Ok(None);
+                }
+
+                let dialogs = write_txn.open_table(tables.voting_dialog())?;
+                let mut index_table = write_txn.open_table(tables.submitted_dialog_index())?;
+
+                for (key, value) in dialogs.iter()?.flatten() {
+                    if VoteDialog::try_from(value.value())?.submitted {
+                        index_table.insert(key.value(), key.value())?;
+                        indexed += 1;
+                    }
+                }
+
+                let applied_unix = util::unix_timestamp().to_string();
+                meta.insert(marker.as_str(), applied_unix.as_str())?;
+            }
+
+            write_txn.commit()?;
+
+            Ok(Some(indexed))
+        })
+        .await?
+    }
+
+    // Compacts the database file, returns true if space was reclaimed.
+    // Needs exclusive access to the database, so it can only run before the db is shared
+    // (e.g. on startup), which also guarantees no writes happen during compaction.
+    // This is blocking and should be called from a blocking task.
+    pub fn compact(&mut self) -> Result<bool, DbError> {
+        let Some(db) = Arc::get_mut(&mut self.db) else {
+            return Err(DbError::Other(
+                "database is in use, it can not be compacted".to_string(),
+            ));
+        };
+
+        db.compact().map_err(|e| DbError::Other(e.to_string()))
+    }
+
+    // Saves voting to the database.
+    // Returns `AlreadyExists` if the voting with the same id already exists.
+    pub async fn save_voting(&self, voting: Voting) -> Result<(), DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+
+        tokio::task::spawn_blocking(move || {
+            let write_txn = begin_write(&db, durability)?;
+            {
+                let mut table = write_txn.open_table(tables.voting())?;
+
+                if table.get(voting.id.as_str())?.is_some() {
+                    return Err(DbError::AlreadyExists);
+                }
+                table.insert(voting.id.clone().as_str(), String::from(&voting).as_str())?;
+
+                if !voting.creator_user_id.is_empty() {
+                    let mut index_table = write_txn.open_table(tables.creator_voting_index())?;
+                    let index_key = encode_key(&voting.creator_user_id, &voting.id);
+                    index_table.insert(index_key.as_str(), voting.id.as_str())?;
+                }
+
+                if !voting.guild_id.is_empty() {
+                    let mut index_table = write_txn.open_table(tables.guild_voting_index())?;
+                    let index_key = encode_key(&voting.guild_id, &voting.id);
+                    index_table.insert(index_key.as_str(), voting.id.as_str())?;
+                }
+            }
+
+            let event = AuditEvent::new(AuditEventKind::Created, &voting.creator_user_id);
+            write_audit(&write_txn, &tables, &voting.id, &event)?;
+
+            write_txn.commit()?;
+
+            Ok(())
+        })
+        .await?
+    }
+
+    // Applies `mutator` to the voting only if it is still at `expected_version`, i.e. nobody wrote
+    // it since the caller read it. Returns `VersionConflict` otherwise, the caller can re-read and retry.
+    pub async fn update_voting_checked<F>(
+        &self,
+        id: &str,
+        expected_version: u64,
+        mutator: F,
+    ) -> Result<Voting, DbError>
+    where
+        F: FnOnce(&mut Voting) + Send + 'static,
+    {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+        let id = id.to_owned();
+
+        tokio::task::spawn_blocking(move || {
+            let write_txn = begin_write(&db, durability)?;
+            let voting = {
+                let mut table = write_txn.open_table(tables.voting())?;
+
+                let mut voting = match table.get(id.as_str())? {
+                    Some(v) => Voting::try_from(v.value())?,
+                    None => return Err(DbError::NotFound),
+                };
+
+                if voting.version != expected_version {
+                    return Err(DbError::VersionConflict);
+                }
+
+                let creator_user_id = voting.creator_user_id.clone();
+                mutator(&mut voting);
+                // the id is the key, it can not be changed here
+                voting.id = id.clone();
+
+                if voting.creator_user_id != creator_user_id {
+                    let mut index_table = write_txn.open_table(tables.creator_voting_index())?;
+                    if !creator_user_id.is_empty() {
+                        index_table.remove(encode_key(&creator_user_id, &id).as_str())?;
+                    }
+                    if !voting.creator_user_id.is_empty() {
+                        let index_key = encode_key(&voting.creator_user_id, &id);
+                        index_table.insert(index_key.as_str(), id.as_str())?;
+                    }
+                }
+
+                voting.version = expected_version + 1;
+                table.insert(id.as_str(), String::from(&voting).as_str())?;
+                voting
+            };
+
+            write_txn.commit()?;
+            Ok(voting)
+        })
+        .await?
+    }
+
+    // Reassigns the voting to another creator, moving it in the creator index.
+    // Hands the voting over to the new creator, audited as a transfer by `user_id`
+    pub async fn set_voting_creator(
+        &self,
+        id: &str,
+        new_creator_user_id: &str,
+        user_id: &str,
+    ) -> Result<Voting, DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+        let id = id.to_owned();
+        let new_creator_user_id = new_creator_user_id.to_owned();
+        let event = AuditEvent::new(AuditEventKind::Transferred, user_id);
+
+        tokio::task::spawn_blocking(move || {
+            let write_txn = begin_write(&db, durability)?;
+            let voting = {
+                let mut table = write_txn.open_table(tables.voting())?;
+
+                let mut voting = match table.get(id.as_str())? {
+                    Some(v) => Voting::try_from(v.value())?,
+                    None => return Err(DbError::NotFound),
+                };
+
+                let mut index_table = write_txn.open_table(tables.creator_voting_index())?;
+                if !voting.creator_user_id.is_empty() {
+                    let old_key = encode_key(&voting.creator_user_id, &voting.id);
+                    index_table.remove(old_key.as_str())?;
+                }
+                let index_key = encode_key(&new_creator_user_id, &voting.id);
+                index_table.insert(index_key.as_str(), voting.id.as_str())?;
+
+                voting.creator_user_id = new_creator_user_id;
+
+                voting.version += 1;
+                table.insert(id.as_str(), String::from(&voting).as_str())?;
+                voting
+            };
+            write_audit(&write_txn, &tables, &id, &event)?;
+
+            write_txn.commit()?;
+            Ok(voting)
+        })
+        .await?
+    }
+
+    // Counts votings of the creator which are neither completed nor deleted.
+    pub async fn count_active_votings_by_creator(&self, user_id: &str) -> Result<usize, DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let user_id = user_id.to_owned();
+
+        tokio::task::spawn_blocking(move || {
+            let read_txn = db.begin_read()?;
+
+            let index_table = match read_txn.open_table(tables.creator_voting_index()) {
+                Ok(t) => t,
+                Err(redb::TableError::TableDoesNotExist(_)) => return Ok(0),
+                Err(err) => return Err(err.into()),
+            };
+
+            let table = read_txn.open_table(tables.voting())?;
+
+            let index_prefix = format!("{}{}", user_id, ENCODE_DELIMITER);
+
+            let res = index_table.range(index_prefix.as_str()..)?;
+
+            let mut count = 0;
+            for v in res.flatten() {
+                if !v.0.value().starts_with(index_prefix.as_str()) {
+                    break;
+                }
+
+                if let Some(voting) = table.get(v.1.value())? {
+                    let voting = Voting::try_from(voting.value())?;
+                    if !voting.is_completed && !voting.is_deleted {
+                        count += 1;
+                    }
+                }
+            }
+
+            Ok(count)
+        })
+        .await?
+    }
+
+    // Completes the voting only if it is still open, checked and written in a single transaction.
+    // Returns `AlreadyCompleted` if it was completed before, so only one caller publishes the results.
+    // The completion is recorded as pending in the same transaction, it should be deleted once the
+    // discord messages are updated. Returns `NotFound` if the voting is missing or deleted.
+    pub async fn complete_open_voting(&self, id: &str, user_id: &str) -> Result<Voting, DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+        let id = id.to_owned();
+        let user_id = user_id.to_owned();
+
+        tokio::task::spawn_blocking(move || {
+            let write_txn = begin_write(&db, durability)?;
+
+            let mut voting = {
+                let table = write_txn.open_table(tables.voting())?;
+                let voting = match table.get(id.as_str())? {
+                    Some(v) => Voting::try_from(v.value())?,
+                    None => return Err(DbError::NotFound),
+                };
+                voting
+            };
+
+            if voting.is_deleted {
+                return Err(DbError::NotFound);
+            }
+            if voting.is_completed {
+                return Err(DbError::AlreadyCompleted);
+            }
+
+            write_completed_voting(&write_txn, &tables, &id, &mut voting, &user_id)?;
+            write_txn.commit()?;
+            Ok(voting)
+        })
+        .await?
+    }
+
+    pub async fn save_pending_completion(&self, voting_id: &str) -> Result<(), DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+        let voting_id = voting_id.to_owned();
+
+        tokio::task::spawn_blocking(move || {
+            let write_txn = begin_write(&db, durability)?;
+            {
+                let mut table = write_txn.open_table(tables.pending_completion())?;
+                table.insert(voting_id.as_str(), util::unix_timestamp())?;
+            }
+
+            write_txn.commit()?;
+
+            Ok(())
+        })
+        .await?
+    }
+
+    pub async fn delete_pending_completion(&self, voting_id: &str) -> Result<(), DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+        let voting_id = voting_id.to_owned();
+
+        tokio::task::spawn_blocking(move || {
+            let write_txn = begin_write(&db, durability)?;
+            {
+                let mut table = write_txn.open_table(tables.pending_completion())?;
+                table.remove(voting_id.as_str())?;
+            }
+
+            write_txn.commit()?;
+
+            Ok(())
+        })
+        .await?
+    }
+
+    // Ids of all votings with a pending completion, an entry is only deleted once the results are published.
+    pub async fn get_pending_completions(&self) -> Result<Vec<String>, DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let read_txn = db.begin_read()?;
+
+            let table = match read_txn.open_table(tables.pending_completion()) {
+                Ok(t) => t,
+                Err(redb::TableError::TableDoesNotExist(_)) => return Ok(Vec::new()),
+                Err(err) => return Err(err.into()),
+            };
+
+            let mut voting_ids = Vec::new();
+            for v in table.iter()?.flatten() {
+                voting_ids.push(v.0.value().to_string());
+            }
+
+            Ok(voting_ids)
+        })
+        .await?
+    }
+
+    // Same as `get_pending_completions`, but only the completions which started before
+    // `started_before`, so a completion still being published is left alone.
+    pub async fn get_pending_completions_before(
+        &self,
+        started_before: u64,
+    ) -> Result<Vec<String>, DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let read_txn = db.begin_read()?;
+
+            let table = match read_txn.open_table(tables.pending_completion()) {
+                Ok(t) => t,
+                Err(redb::TableError::TableDoesNotExist(_)) => return Ok(Vec::new()),
+                Err(err) => return Err(err.into()),
+            };
+
+            let mut voting_ids = Vec::new();
+            for v in table.iter()?.flatten() {
+                if v.1.value() < started_before {
+                    voting_ids.push(v.0.value().to_string());
+                }
+            }
+
+            Ok(voting_ids)
+        })
+        .await?
+    }
+
+    // Records the ballot before it is sent to the DD backend, overwriting the previous one.
+    pub async fn save_pending_vote(&self, vote: PendingVote) -> Result<(), DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+        let id = encode_key(&vote.voting_id, &vote.user_id);
+
+        tokio::task::spawn_blocking(move || {
+            let write_txn = begin_write(&db, durability)?;
+            {
+                let mut table = write_txn.open_table(tables.pending_vote())?;
+                table.insert(id.as_str(), String::from(&vote).as_str())?;
+            }
+
+            write_txn.commit()?;
+
+            Ok(())
+        })
+        .await?
+    }
+
+    pub async fn delete_pending_vote(&self, voting_id: &str, user_id: &str) -> Result<(), DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+        let id = encode_key(voting_id, user_id);
+
+        tokio::task::spawn_blocking(move || {
+            let write_txn = begin_write(&db, durability)?;
+            {
+                let mut table = write_txn.open_table(tables.pending_vote())?;
+                table.remove(id.as_str())?;
+            }
+
+            write_txn.commit()?;
+
+            Ok(())
+        })
+        .await?
+    }
+
+    // Config of the guild, the default config when the guild never set one
+    pub async fn get_guild_config(&self, guild_id: &str) -> Result<GuildConfig, DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let guild_id = guild_id.to_owned();
+
+        tokio::task::spawn_blocking(move || {
+            let read_txn = db.begin_read()?;
+
+            let table = match read_txn.open_table(tables.guild_config()) {
+                Ok(t) => t,
+                Err(redb::TableError::TableDoesNotExist(_)) => return Ok(GuildConfig::default()),
+                Err(err) => return Err(err.into()),
+            };
+
+            let res = table.get(guild_id.as_str())?;
+            match res {
+                Some(v) => GuildConfig::try_from(v.value()),
+                None => Ok(GuildConfig::default()),
+            }
+        })
+        .await?
+    }
+
+    pub async fn set_guild_config(
+        &self,
+        guild_id: &str,
+        config: GuildConfig,
+    ) -> Result<(), DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+        let guild_id = guild_id.to_owned();
+
+        tokio::task::spawn_blocking(move || {
+            let write_txn = begin_write(&db, durability)?;
+            {
+                let mut table = write_txn.open_table(tables.guild_config())?;
+                table.insert(guild_id.as_str(), String::from(&config).as_str())?;
+            }
+
+            write_txn.commit()?;
+
+            Ok(())
+        })
+        .await?
+    }
+
+    // Saves the template of the guild, replacing the template with the same name
+    pub async fn save_template(
+        &self,
+        guild_id: &str,
+        name: &str,
+        choices: Vec<String>,
+    ) -> Result<(), DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+        let key = encode_key(guild_id, name);
+        let template = VotingTemplate {
+            name: name.to_owned(),
+            choices,
+        };
+
+        tokio::task::spawn_blocking(move || {
+            let write_txn = begin_write(&db, durability)?;
+            {
+                let mut table = write_txn.open_table(tables.voting_template())?;
+                table.insert(key.as_str(), String::from(&template).as_str())?;
+            }
+
+            write_txn.commit()?;
+
+            Ok(())
+        })
+        .await?
+    }
+
+    pub async fn get_template(
+        &self,
+        guild_id: &str,
+        name: &str,
+    ) -> Result<VotingTemplate, DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let key = encode_key(guild_id, name);
+
+        tokio::task::spawn_blocking(move || {
+            let read_txn = db.begin_read()?;
+
+            let table = read_txn.open_table(tables.voting_template())?;
+
+            match table.get(key.as_str())? {
+                Some(v) => VotingTemplate::try_from(v.value()),
+                None => Err(DbError::NotFound),
+            }
+        })
+        .await?
+    }
+
+    // Templates of the guild ordered by name
+    pub async fn list_templates(&self, guild_id: &str) -> Result<Vec<VotingTemplate>, DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let prefix = format!("{}{}", guild_id, ENCODE_DELIMITER);
+
+        tokio::task::spawn_blocking(move || {
+            let read_txn = db.begin_read()?;
+
+            let table = match read_txn.open_table(tables.voting_template()) {
+                Ok(t) => t,
+                Err(redb::TableError::TableDoesNotExist(_)) => return Ok(Vec::new()),
+                Err(err) => return Err(err.into()),
+            };
+
+            let mut templates = Vec::new();
+            for v in table.range(prefix.as_str()..)?.flatten() {
+                if !v.0.value().starts_with(prefix.as_str()) {
+                    break;
+                }
+                templates.push(VotingTemplate::try_from(v.1.value())?);
+            }
+
+            Ok(templates)
+        })
+        .await?
+    }
+
+    pub async fn delete_template(&self, guild_id: &str, name: &str) -> Result<(), DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+        let key = encode_key(guild_id, name);
+
+        tokio::task::spawn_blocking(move || {
+            let write_txn = begin_write(&db, durability)?;
+            {
+                let mut table = write_txn.open_table(tables.voting_template())?;
+                if table.remove(key.as_str())?.is_none() {
+                    return Err(DbError::NotFound);
+                }
+            }
+
+            write_txn.commit()?;
+
+            Ok(())
+        })
+        .await?
+    }
+
+    pub async fn get_pending_votes(&self) -> Result<Vec<PendingVote>, DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let read_txn = db.begin_read()?;
+
+            let table = match read_txn.open_table(tables.pending_vote()) {
+                Ok(t) => t,
+                Err(redb::TableError::TableDoesNotExist(_)) => return Ok(Vec::new()),
+                Err(err) => return Err(err.into()),
+            };
+
+            let mut votes = Vec::new();
+            for v in table.iter()?.flatten() {
+                votes.push(PendingVote::try_from(v.1.value())?);
+            }
+
+            Ok(votes)
+        })
+        .await?
+    }
+
+    // Replaces the failed cleanup of the same dialog, if any
+    pub async fn save_failed_cleanup(&self, cleanup: FailedCleanup) -> Result<(), DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+        let id = encode_key(&cleanup.voting_id, &cleanup.user_id);
+
+        tokio::task::spawn_blocking(move || {
+            let write_txn = begin_write(&db, durability)?;
+            {
+                let mut table = write_txn.open_table(tables.failed_cleanup())?;
+                table.insert(id.as_str(), String::from(&cleanup).as_str())?;
+            }
+
+            write_txn.commit()?;
+
+            Ok(())
+        })
+        .await?
+    }
+
+    pub async fn delete_failed_cleanup(
+        &self,
+        voting_id: &str,
+        user_id: &str,
+    ) -> Result<(), DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+        let id = encode_key(voting_id, user_id);
+
+        tokio::task::spawn_blocking(move || {
+            let write_txn = begin_write(&db, durability)?;
+            {
+                let mut table = write_txn.open_table(tables.failed_cleanup())?;
+                table.remove(id.as_str())?;
+            }
+
+            write_txn.commit()?;
+
+            Ok(())
+        })
+        .await?
+    }
+
+    // Failed cleanups whose retry is due at `now_unix`
+    pub async fn get_due_failed_cleanups(
+        &self,
+        now_unix: u64,
+    ) -> Result<Vec<FailedCleanup>, DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let read_txn = db.begin_read()?;
+
+            let table = match read_txn.open_table(tables.failed_cleanup()) {
+                Ok(t) => t,
+                Err(redb::TableError::TableDoesNotExist(_)) => return Ok(Vec::new()),
+                Err(err) => return Err(err.into()),
+            };
+
+            let mut cleanups = Vec::new();
+            for v in table.iter()?.flatten() {
+                let cleanup = FailedCleanup::try_from(v.1.value())?;
+                if cleanup.retry_unix <= now_unix {
+                    cleanups.push(cleanup);
+                }
+            }
+
+            Ok(cleanups)
+        })
+        .await?
+    }
+
+    pub async fn delete_voting(&self, id: &str, user_id: &str) -> Result<Voting, DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+        let id = id.to_owned();
+        let event = AuditEvent::new(AuditEventKind::Deleted, user_id);
+
+        tokio::task::spawn_blocking(move || {
+            let write_txn = begin_write(&db, durability)?;
+            let voting = {
+                let mut table = write_txn.open_table(tables.voting())?;
+
+                let mut voting = match table.get(id.as_str())? {
+                    Some(v) => Voting::try_from(v.value())?,
+                    None => return Err(DbError::NotFound),
+                };
+                if voting.is_deleted {
+                    return Err(DbError::NotFound);
+                }
+
+                voting.is_deleted = true;
+
+                voting.version += 1;
+                table.insert(id.as_str(), String::from(&voting).as_str())?;
+                voting
+            };
+            write_audit(&write_txn, &tables, &id, &event)?;
+
+            write_txn.commit()?;
+            Ok(voting)
+        })
+        .await?
+    }
+
+    pub async fn append_audit(&self, voting_id: &str, event: AuditEvent) -> Result<(), DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+        let voting_id = voting_id.to_owned();
+
+        tokio::task::spawn_blocking(move || {
+            let write_txn = begin_write(&db, durability)?;
+            write_audit(&write_txn, &tables, &voting_id, &event)?;
+            write_txn.commit()?;
+
+            Ok(())
+        })
+        .await?
+    }
+
+    // Audit log of the voting, oldest event first.
+    pub async fn get_audit(&self, voting_id: &str) -> Result<Vec<AuditEvent>, DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let prefix = format!("{}{}", voting_id, ENCODE_DELIMITER);
+
+        tokio::task::spawn_blocking(move || {
+            let read_txn = db.begin_read()?;
+
+            let table = match read_txn.open_table(tables.audit()) {
+                Ok(t) => t,
+                Err(redb::TableError::TableDoesNotExist(_)) => return Ok(Vec::new()),
+                Err(err) => return Err(err.into()),
+            };
+
+            let mut events = Vec::new();
+            for v in table.range(prefix.as_str()..)?.flatten() {
+                if !v.0.value().starts_with(prefix.as_str()) {
+                    break;
+                }
+
+                events.push(AuditEvent::try_from(v.1.value())?);
+            }
+
+            Ok(events)
+        })
+        .await?
+    }
+
+    // Sets or clears the deadline of the voting.
+    // Returns `NotFound` if the voting is not found, or if it is already completed or deleted.
+    pub async fn set_voting_deadline(
+        &self,
+        id: &str,
+        deadline_unix: Option<u64>,
+    ) -> Result<Voting, DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+        let id = id.to_owned();
+
+        tokio::task::spawn_blocking(move || {
+            let write_txn = begin_write(&db, durability)?;
+            let voting = {
+                let mut table = write_txn.open_table(tables.voting())?;
+
+                let mut voting = match table.get(id.as_str())? {
+                    Some(v) => Voting::try_from(v.value())?,
+                    None => return Err(DbError::NotFound),
+                };
+                if voting.is_deleted || voting.is_completed {
+                    return Err(DbError::NotFound);
+                }
+
+                voting.deadline_unix = deadline_unix;
+
+                voting.version += 1;
+                table.insert(id.as_str(), String::from(&voting).as_str())?;
+                voting
+            };
+
+            write_txn.commit()?;
+            Ok(voting)
+        })
+        .await?
+    }
+
+    // Locks or unlocks the voting for users who have no voting dialog yet.
+    // Returns `NotFound` if the voting is not found, or if it is already completed or deleted.
+    pub async fn set_voting_dialogs_locked(
+        &self,
+        id: &str,
+        dialogs_locked: bool,
+    ) -> Result<Voting, DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+        let id = id.to_owned();
+
+        tokio::task::spawn_blocking(move || {
+            let write_txn = begin_write(&db, durability)?;
+            let voting = {
+                let mut table = write_txn.open_table(tables.voting())?;
+
+                let mut voting = match table.get(id.as_str())? {
+                    Some(v) => Voting::try_from(v.value())?,
+                    None => return Err(DbError::NotFound),
+                };
+                if voting.is_deleted || voting.is_completed {
+                    return Err(DbError::NotFound);
+                }
+
+                voting.dialogs_locked = dialogs_locked;
+
+                voting.version += 1;
+                table.insert(id.as_str(), String::from(&voting).as_str())?;
+                voting
+            };
+
+            write_txn.commit()?;
+            Ok(voting)
+        })
+        .await?
+    }
+
+    // Sets the ids of the discord messages of the voting, once they are posted.
+    pub async fn set_voting_message_ids(
+        &self,
+        id: &str,
+        message_id: &str,
+        channel_id: &str,
+        creator_message_id: &str,
+        creator_dm_channel_id: &str,
+    ) -> Result<Voting, DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+        let id = id.to_owned();
+        let message_id = message_id.to_owned();
+        let channel_id = channel_id.to_owned();
+        let creator_message_id = creator_message_id.to_owned();
+        let creator_dm_channel_id = creator_dm_channel_id.to_owned();
+
+        tokio::task::spawn_blocking(move || {
+            let write_txn = begin_write(&db, durability)?;
+            let voting = {
+                let mut table = write_txn.open_table(tables.voting())?;
+
+                let mut voting = match table.get(id.as_str())? {
+                    Some(v) => Voting::try_from(v.value())?,
+                    None => return Err(DbError::NotFound),
+                };
+
+                voting.message_id = message_id;
+                voting.channel_id = channel_id;
+                voting.creator_message_id = creator_message_id;
+                voting.creator_dm_channel_id = creator_dm_channel_id;
+
+                voting.version += 1;
+                table.insert(id.as_str(), String::from(&voting).as_str())?;
+                voting
+            };
+
+            write_txn.commit()?;
+            Ok(voting)
+        })
+        .await?
+    }
+
+    // Sets the order in which the choices are displayed, `None` restores the original order.
+    // Returns `IndexOutOfRange` if the order is not a permutation of the choice indices.
+    pub async fn set_voting_display_order(
+        &self,
+        id: &str,
+        display_order: Option<Vec<usize>>,
+    ) -> Result<Voting, DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+        let id = id.to_owned();
+
+        tokio::task::spawn_blocking(move || {
+            let write_txn = begin_write(&db, durability)?;
+            let voting = {
+                let mut table = write_txn.open_table(tables.voting())?;
+
+                let mut voting = match table.get(id.as_str())? {
+                    Some(v) => Voting::try_from(v.value())?,
+                    None => return Err(DbError::NotFound),
+                };
+
+                if let Some(ref order) = display_order {
+                    let mut sorted = order.clone();
+                    sorted.sort_unstable();
+                    if !sorted.iter().copied().eq(0..voting.choices.len()) {
+                        return Err(DbError::IndexOutOfRange);
+                    }
+                }
+
+                voting.display_order = display_order;
+
+                voting.version += 1;
+                table.insert(id.as_str(), String::from(&voting).as_str())?;
+                voting
+            };
+
+            write_txn.commit()?;
+            Ok(voting)
+        })
+        .await?
+    }
+
+    // Calls `f` for every voting inside a single read transaction, without loading them all.
+    // Iteration stops early when `f` returns `ControlFlow::Break`.
+    pub async fn for_each_voting<F>(&self, mut f: F) -> Result<(), DbError>
+    where
+        F: FnMut(Voting) -> ControlFlow<()> + Send + 'static,
+    {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let read_txn = db.begin_read()?;
+
+            let table = match read_txn.open_table(tables.voting()) {
+                Ok(t) => t,
+                Err(redb::TableError::TableDoesNotExist(_)) => return Ok(()),
+                Err(err) => return Err(err.into()),
+            };
+
+            for v in table.iter()? {
+                let v = v?;
+                let voting = Voting::try_from(v.1.value())?;
+                if f(voting).is_break() {
+                    break;
+                }
+            }
+
+            Ok(())
+        })
+        .await?
+    }
+
+    // Up to `limit` votings in key order, starting after the voting id `after`.
+    // The returned cursor is the id to continue from, None when there are no more votings.
+    pub async fn get_votings_page(
+        &self,
+        after: Option<&str>,
+        limit: usize,
+        include_deleted: bool,
+    ) -> Result<(Vec<Voting>, Option<String>), DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let after = after.map(str::to_owned);
+
+        tokio::task::spawn_blocking(move || {
+            let read_txn = db.begin_read()?;
+
+            let table = match read_txn.open_table(tables.voting()) {
+                Ok(t) => t,
+                Err(redb::TableError::TableDoesNotExist(_)) => return Ok((Vec::new(), None)),
+                Err(err) => return Err(err.into()),
+            };
+
+            let start = match after {
+                Some(ref after) => Bound::Excluded(after.as_str()),
+                None => Bound::Unbounded,
+            };
+
+            let mut votings = Vec::new();
+            for v in table.range::<&str>((start, Bound::Unbounded))? {
+                let v = v?;
+                let voting = Voting::try_from(v.1.value())?;
+                if voting.is_deleted && !include_deleted {
+                    continue;
+                }
+
+                // one more voting past the limit means there is a next page
+                if votings.len() == limit {
+                    let cursor = votings.last().map(|v: &Voting| v.id.clone());
+                    return Ok((votings, cursor));
+                }
+                votings.push(voting);
+            }
+
+            Ok((votings, None))
+        })
+        .await?
+    }
+
+    // Up to `limit` votings started in the guild, in key order after the voting id `after`.
+    // Deleted votings are skipped. The returned cursor is the id of the last voting when there
+    // are more votings, None otherwise. Votings saved before the guild was recorded are not listed.
+    pub async fn get_guild_votings(
+        &self,
+        guild_id: &str,
+        after: Option<&str>,
+        limit: usize,
+    ) -> Result<(Vec<Voting>, Option<String>), DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let prefix = format!("{}{}", guild_id, ENCODE_DELIMITER);
+        // without `after` this is the bare prefix, which is never a key itself
+        let start = encode_key(guild_id, after.unwrap_or_default());
+
+        tokio::task::spawn_blocking(move || {
+            let read_txn = db.begin_read()?;
+
+            let index_table = match read_txn.open_table(tables.guild_voting_index()) {
+                Ok(t) => t,
+                Err(redb::TableError::TableDoesNotExist(_)) => return Ok((Vec::new(), None)),
+                Err(err) => return Err(err.into()),
+            };
+
+            let table = read_txn.open_table(tables.voting())?;
+
+            let mut votings = Vec::new();
+            let range = (Bound::Excluded(start.as_str()), Bound::Unbounded);
+            for v in index_table.range::<&str>(range)? {
+                let v = v?;
+                if !v.0.value().starts_with(prefix.as_str()) {
+                    break;
+                }
+
+                let Some(voting) = table.get(v.1.value())? else {
+                    continue;
+                };
+                let voting = Voting::try_from(voting.value())?;
+                if voting.is_deleted {
+                    continue;
+                }
+
+                // one more voting past the limit means there is a next page
+                if votings.len() == limit {
+                    let cursor = votings.last().map(|v: &Voting| v.id.clone());
+                    return Ok((votings, cursor));
+                }
+                votings.push(voting);
+            }
+
+            Ok((votings, None))
+        })
+        .await?
+    }
+
+    // Replaces the recorded ballots and comments of the voting
+    pub async fn set_voting_ballots(
+        &self,
+        voting_id: &str,
+        ballots: VotingBallots,
+    ) -> Result<(), DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+        let voting_id = voting_id.to_owned();
+
+        tokio::task::spawn_blocking(move || {
+            let write_txn = begin_write(&db, durability)?;
+            {
+                let mut table = write_txn.open_table(tables.voting_ballots())?;
+                table.insert(voting_id.as_str(), String::from(&ballots).as_str())?;
+            }
+
+            write_txn.commit()?;
+
+            Ok(())
+        })
+        .await?
+    }
+
+    // Recorded ballots and comments of the voting, empty if none were recorded
+    pub async fn get_voting_ballots(&self, voting_id: &str) -> Result<VotingBallots, DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let voting_id = voting_id.to_owned();
+
+        tokio::task::spawn_blocking(move || {
+            let read_txn = db.begin_read()?;
+
+            let table = match read_txn.open_table(tables.voting_ballots()) {
+                Ok(t) => t,
+                Err(redb::TableError::TableDoesNotExist(_)) => return Ok(VotingBallots::default()),
+                Err(err) => return Err(err.into()),
+            };
+
+            match table.get(voting_id.as_str())? {
+                Some(v) => VotingBallots::try_from(v.value()),
+                None => Ok(VotingBallots::default()),
+            }
+        })
+        .await?
+    }
+
+    // Saves the (customUUID, VotingsListing) pairs of the listing buttons
+    pub async fn save_votings_listings(
+        &self,
+        listings: Vec<(String, VotingsListing)>,
+    ) -> Result<(), DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+
+        tokio::task::spawn_blocking(move || {
+            let write_txn = begin_write(&db, durability)?;
+            {
+                let mut table = write_txn.open_table(tables.votings_listing())?;
+                for (custom_uuid, listing) in &listings {
+                    table.insert(custom_uuid.as_str(), String::from(listing).as_str())?;
+                }
+            }
+
+            write_txn.commit()?;
+
+            Ok(())
+        })
+        .await?
+    }
+
+    // Returns `NotFound` for unknown and deleted buttons, expired ones are returned until
+    // `delete_expired_votings_listings` runs, it is up to the caller to check `expires_unix`.
+    pub async fn get_votings_listing(&self, custom_uuid: &str) -> Result<VotingsListing, DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let custom_uuid = custom_uuid.to_owned();
+
+        tokio::task::spawn_blocking(move || {
+            let read_txn = db.begin_read()?;
+
+            let table = match read_txn.open_table(tables.votings_listing()) {
+                Ok(t) => t,
+                Err(redb::TableError::TableDoesNotExist(_)) => return Err(DbError::NotFound),
+                Err(err) => return Err(err.into()),
+            };
+
+            match table.get(custom_uuid.as_str())? {
+                Some(v) => VotingsListing::try_from(v.value()),
+                None => Err(DbError::NotFound),
+            }
+        })
+        .await?
+    }
+
+    // Deletes the listing buttons which expired before `now_unix`, returns how many were deleted
+    pub async fn delete_expired_votings_listings(&self, now_unix: u64) -> Result<usize, DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+
+        tokio::task::spawn_blocking(move || {
+            let write_txn = begin_write(&db, durability)?;
+            let deleted = {
+                let mut table = write_txn.open_table(tables.votings_listing())?;
+
+                let mut expired = Vec::new();
+                for v in table.iter()?.flatten() {
+                    let listing = VotingsListing::try_from(v.1.value())?;
+                    if listing.expires_unix < now_unix {
+                        expired.push(v.0.value().to_string());
+                    }
+                }
+
+                for custom_uuid in &expired {
+                    table.remove(custom_uuid.as_str())?;
+                }
+                expired.len()
+            };
+
+            write_txn.commit()?;
+
+            Ok(deleted)
+        })
+        .await?
+    }
+
+    // Get voting for the provided id.
+    // Voting marked as deleted or completed are returned successfully.
+    // It is up to the caller to check the state of the voting
+    pub async fn get_voting(&self, id: &str) -> Result<Voting, DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let id = id.to_owned();
+
+        tokio::task::spawn_blocking(move || {
+            let read_txn = db.begin_read()?;
+
+            let table = read_txn.open_table(tables.voting())?;
+
+            let res = table.get(id.as_str())?;
+
+            match res {
+                Some(v) => Ok(Voting::try_from(v.value())?),
+                None => Err(DbError::NotFound),
+            }
+        })
+        .await
+        .map_err(|e| DbError::Other(e.to_string()))?
+    }
+
+    // Get votings for all the provided ids within a single read transaction.
+    // Every id gets its own result, so a missing or broken voting does not fail the whole batch.
+    pub async fn batch_get_votings(
+        &self,
+        ids: &[String],
+    ) -> Result<Vec<(String, Result<Voting, DbError>)>, DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let ids = ids.to_vec();
+
+        tokio::task::spawn_blocking(move || {
+            let read_txn = db.begin_read()?;
+
+            let table = match read_txn.open_table(tables.voting()) {
+                Ok(t) => t,
+                Err(redb::TableError::TableDoesNotExist(_)) => {
+                    return Ok(ids
+                        .into_iter()
+                        .map(|id| (id, Err(DbError::NotFound)))
+                        .collect());
+                }
+                Err(err) => return Err(err.into()),
+            };
+
+            let mut votings = Vec::with_capacity(ids.len());
+            for id in ids {
+                let res = match table.get(id.as_str())? {
+                    Some(v) => Voting::try_from(v.value()),
+                    None => Err(DbError::NotFound),
+                };
+                votings.push((id, res));
+            }
+
+            Ok(votings)
+        })
+        .await?
+    }
+
+    // Get voting for the provided id, only an open voting is returned as is.
+    // Missing votings are a state as well, the error is left to failing reads.
+    pub async fn get_open_voting(&self, id: &str) -> Result<VotingState, DbError> {
+        match self.get_voting(id).await {
+            Ok(voting) => Ok(VotingState::from(voting)),
+            Err(DbError::NotFound) => Ok(VotingState::NotFound),
+            Err(err) => Err(err),
+        }
+    }
+
+    // Stored JSON of the voting as is, to inspect votings that fail to deserialize
+    pub async fn get_voting_raw(&self, id: &str) -> Result<String, DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let id = id.to_owned();
+
+        tokio::task::spawn_blocking(move || {
+            let read_txn = db.begin_read()?;
+
+            let table = read_txn.open_table(tables.voting())?;
+
+            let res = table.get(id.as_str())?;
+
+            match res {
+                Some(v) => Ok(v.value().to_string()),
+                None => Err(DbError::NotFound),
+            }
+        })
+        .await
+        .map_err(|e| DbError::Other(e.to_string()))?
+    }
+
+    // Whether the voting is stored, without deserializing it. Deleted votings are stored too.
+    pub async fn voting_exists(&self, id: &str) -> Result<bool, DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let id = id.to_owned();
+
+        tokio::task::spawn_blocking(move || {
+            let read_txn = db.begin_read()?;
+
+            let table = match read_txn.open_table(tables.voting()) {
+                Ok(t) => t,
+                Err(redb::TableError::TableDoesNotExist(_)) => return Ok(false),
+                Err(err) => return Err(err.into()),
+            };
+
+            let exists = table.get(id.as_str())?.is_some();
+            Ok(exists)
+        })
+        .await?
+    }
+
+    // Whether the user has a voting dialog, placeholder or submitted, without deserializing it.
+    pub async fn dialog_exists(&self, voting_id: &str, user_id: &str) -> Result<bool, DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let id = encode_key(voting_id, user_id);
+
+        tokio::task::spawn_blocking(move || {
+            let read_txn = db.begin_read()?;
+
+            let table = match read_txn.open_table(tables.voting_dialog()) {
+                Ok(t) => t,
+                Err(redb::TableError::TableDoesNotExist(_)) => return Ok(false),
+                Err(err) => return Err(err.into()),
+            };
+
+            let exists = table.get(id.as_str())?.is_some();
+            Ok(exists)
+        })
+        .await?
+    }
+
+    /// Updates vote value in the ballot of the voting dialog.
+    /// Index is the index of the choice in the ballot. It starts from 0.
+    /// Returns `IndexOutOfRange` if the index is bigger than the ballot size.
+    pub async fn vote_voting_dialog(
+        &self,
+        voting_id: &str,
+        user_id: &str,
+        vote: i32,
+        index: usize,
+    ) -> Result<(), DbError> {
+        let id = encode_key(voting_id, user_id);
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+
+        tokio::task::spawn_blocking(move || {
+            let read_txn = db.begin_read()?;
+
+            let table = read_txn.open_table(tables.voting_dialog())?;
+            let res = table.get(id.as_str())?;
 
             match res {
                 Some(v) => {
-                    let mut voting = Voting::try_from(v.value())?;
-                    if voting.is_deleted {
-                        return Err(DbError::NotFound);
+                    let mut voting_dialog = VoteDialog::try_from(v.value())?;
+
+                    // the selects rank the question the dialog is at
+                    let Some(ballot) = voting_dialog.current_ballot_mut() else {
+                        return Err(DbError::IndexOutOfRange);
+                    };
+                    if index >= ballot.len() {
+                        return Err(DbError::IndexOutOfRange);
+                    }
+
+                    // the ballot has an entry per choice
+                    if vote < 1 || vote as usize > ballot.len() {
+                        return Err(DbError::InvalidRank);
+                    }
+
+                    ballot[index] = vote;
+
+                    let write_txn = begin_write(&db, durability)?;
+                    {
+                        let mut table = write_txn.open_table(tables.voting_dialog())?;
+                        table.insert(id.as_str(), String::from(&voting_dialog).as_str())?;
                     }
 
-                    voting.is_completed = true;
+                    write_txn.commit()?;
+                    Ok(())
+                }
+                None => Err(DbError::NotFound),
+            }
+        })
+        .await
+        .map_err(|e| DbError::Other(e.to_string()))?
+    }
+
+    /// Unranks every choice of the question the voting dialog is at, the ballot keeps its size.
+    pub async fn reset_dialog_ballot(&self, voting_id: &str, user_id: &str) -> Result<(), DbError> {
+        let id = encode_key(voting_id, user_id);
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+
+        tokio::task::spawn_blocking(move || {
+            let write_txn = begin_write(&db, durability)?;
+            {
+                let mut table = write_txn.open_table(tables.voting_dialog())?;
+
+                let mut voting_dialog = match table.get(id.as_str())? {
+                    Some(v) => VoteDialog::try_from(v.value())?,
+                    None => return Err(DbError::NotFound),
+                };
+
+                if let Some(ballot) = voting_dialog.current_ballot_mut() {
+                    ballot.fill(0);
+                }
+                table.insert(id.as_str(), String::from(&voting_dialog).as_str())?;
+            }
+
+            write_txn.commit()?;
+            Ok(())
+        })
+        .await?
+    }
+
+    /// Moves the voting dialog to question `question` of a survey voting, an unranked ballot of
+    /// `choice_count` choices is added the first time the voter gets to a question.
+    /// Dialogs move one question at a time, returns `IndexOutOfRange` if a question is skipped.
+    pub async fn set_dialog_question(
+        &self,
+        voting_id: &str,
+        user_id: &str,
+        question: usize,
+        choice_count: usize,
+    ) -> Result<VoteDialog, DbError> {
+        let id = encode_key(voting_id, user_id);
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+
+        tokio::task::spawn_blocking(move || {
+            let write_txn = begin_write(&db, durability)?;
+            let voting_dialog = {
+                let mut table = write_txn.open_table(tables.voting_dialog())?;
+
+                let mut voting_dialog = match table.get(id.as_str())? {
+                    Some(v) => VoteDialog::try_from(v.value())?,
+                    None => return Err(DbError::NotFound),
+                };
+
+                if question > voting_dialog.question_ballots.len() + 1 {
+                    return Err(DbError::IndexOutOfRange);
+                }
+                if question == voting_dialog.question_ballots.len() + 1 {
+                    voting_dialog.question_ballots.push(vec![0; choice_count]);
+                }
+
+                voting_dialog.question = question;
+                table.insert(id.as_str(), String::from(&voting_dialog).as_str())?;
+                voting_dialog
+            };
+
+            write_txn.commit()?;
+            Ok(voting_dialog)
+        })
+        .await
+        .map_err(|e| DbError::Other(e.to_string()))?
+    }
+
+    /// Replaces the whole ballot of the voting dialog in a single transaction.
+    /// Returns `IndexOutOfRange` if the ballot size differs from the stored ballot.
+    pub async fn set_dialog_ballot(
+        &self,
+        voting_id: &str,
+        user_id: &str,
+        ballot: Vec<i32>,
+    ) -> Result<(), DbError> {
+        let id = encode_key(voting_id, user_id);
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+
+        tokio::task::spawn_blocking(move || {
+            let write_txn = begin_write(&db, durability)?;
+            {
+                let mut table = write_txn.open_table(tables.voting_dialog())?;
+
+                let mut voting_dialog = match table.get(id.as_str())? {
+                    Some(v) => VoteDialog::try_from(v.value())?,
+                    None => return Err(DbError::NotFound),
+                };
+
+                if ballot.len() != voting_dialog.ballot.len() {
+                    return Err(DbError::IndexOutOfRange);
+                }
+
+                // unranked choices are 0, the rest goes from 1 to the number of choices
+                if ballot
+                    .iter()
+                    .any(|&vote| vote < 0 || vote as usize > ballot.len())
+                {
+                    return Err(DbError::InvalidRank);
+                }
+
+                voting_dialog.ballot = ballot;
+                table.insert(id.as_str(), String::from(&voting_dialog).as_str())?;
+            }
+
+            write_txn.commit()?;
+            Ok(())
+        })
+        .await
+        .map_err(|e| DbError::Other(e.to_string()))?
+    }
+
+    /// Sets the comment of the voting dialog, `None` removes it.
+    pub async fn set_dialog_comment(
+        &self,
+        voting_id: &str,
+        user_id: &str,
+        comment: Option<String>,
+    ) -> Result<(), DbError> {
+        let id = encode_key(voting_id, user_id);
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+
+        tokio::task::spawn_blocking(move || {
+            let write_txn = begin_write(&db, durability)?;
+            {
+                let mut table = write_txn.open_table(tables.voting_dialog())?;
+
+                let mut voting_dialog = match table.get(id.as_str())? {
+                    Some(v) => VoteDialog::try_from(v.value())?,
+                    None => return Err(DbError::NotFound),
+                };
+
+                voting_dialog.comment = comment;
+                table.insert(id.as_str(), String::from(&voting_dialog).as_str())?;
+            }
+
+            write_txn.commit()?;
+            Ok(())
+        })
+        .await
+        .map_err(|e| DbError::Other(e.to_string()))?
+    }
+
+    /// Points the voting dialog at a re-posted message, the ballot and the rest are kept.
+    /// The message index moves to the new message in the same transaction.
+    pub async fn update_dialog_message(
+        &self,
+        voting_id: &str,
+        user_id: &str,
+        message_id: &str,
+        channel_id: &str,
+    ) -> Result<(), DbError> {
+        let id = encode_key(voting_id, user_id);
+        let message_id = message_id.to_owned();
+        let channel_id = channel_id.to_owned();
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+
+        tokio::task::spawn_blocking(move || {
+            let write_txn = begin_write(&db, durability)?;
+            let mut voting_dialog = {
+                let table = write_txn.open_table(tables.voting_dialog())?;
+
+                let voting_dialog = match table.get(id.as_str())? {
+                    Some(v) => VoteDialog::try_from(v.value())?,
+                    None => return Err(DbError::NotFound),
+                };
+                voting_dialog
+            };
+
+            voting_dialog.message_id = message_id;
+            voting_dialog.channel_id = channel_id;
+            insert_voting_dialog(&write_txn, &tables, &id, &voting_dialog, true)?;
+
+            write_txn.commit()?;
+            Ok(())
+        })
+        .await
+        .map_err(|e| DbError::Other(e.to_string()))?
+    }
+
+    // Saves voting dialog to the database.
+    // Returns `AlreadyExists` if the dialog with the same voting id and user id already exists.
+    pub async fn save_voting_dialog(
+        &self,
+        voting_id: String,
+        user_id: String,
+        ballot: Vec<i32>,
+        message_id: String,
+        channel_id: String,
+        overwrite: bool,
+    ) -> Result<(), DbError> {
+        let id = encode_key(&voting_id, &user_id);
+        let dialog = VoteDialog {
+            voting_id,
+            user_id,
+            ballot,
+            message_id,
+            channel_id,
+            submitted: false,
+            submitted_unix: 0,
+            display_order: None,
+            created_unix: util::unix_timestamp(),
+            weight: 1,
+            comment: None,
+            question: 0,
+            question_ballots: Vec::new(),
+            placeholder: false,
+        };
+
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+
+        tokio::task::spawn_blocking(move || {
+            write_voting_dialog(&db, &tables, durability, &id, &dialog, overwrite)
+        })
+        .await
+        .map_err(|e| DbError::Other(e.to_string()))?
+    }
+
+    /// Saves an empty placeholder dialog for the user, failing with AlreadyExists when one exists
+    /// and `overwrite` is false. The placeholder is removed when the slot is released.
+    pub async fn claim_dialog_slot(
+        &self,
+        voting_id: &str,
+        user_id: &str,
+        overwrite: bool,
+    ) -> Result<DialogSlot, DbError> {
+        let id = encode_key(voting_id, user_id);
+        let dialog = placeholder_dialog(voting_id, user_id);
+        let created_unix = dialog.created_unix;
+
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+        tokio::task::spawn_blocking(move || {
+            write_voting_dialog(&db, &tables, durability, &id, &dialog, overwrite)
+        })
+        .await
+        .map_err(|e| DbError::Other(e.to_string()))??;
 
-                    let write_txn = db.begin_write()?;
-                    {
-                        let mut table = write_txn.open_table(VOTING_TABLE)?;
-                        table.insert(id.as_str(), String::from(&voting).as_str())?;
-                    }
+        Ok(DialogSlot {
+            db: self.db.clone(),
+            tables: self.tables.clone(),
+            durability: self.durability,
+            voting_id: voting_id.to_string(),
+            user_id: user_id.to_string(),
+            weight: 1,
+            created_unix,
+            custom_uuids: Vec::new(),
+            settled: false,
+        })
+    }
 
-                    write_txn.commit()?;
-                    Ok(voting)
+    /// Same as `claim_dialog_slot`, but saves the custom ids of the dialog components in the same
+    /// transaction. Neither is saved when the slot is taken, both are removed on rollback.
+    /// A submitted dialog only lingers for the undo window, so it is replaced in the same
+    /// transaction instead of taking the slot, together with its undo and comment custom ids.
+    pub async fn open_dialog_with_custom_ids(
+        &self,
+        voting_id: &str,
+        user_id: &str,
+        custom_ids: Vec<(String, CustomID)>,
+    ) -> Result<DialogSlot, DbError> {
+        if custom_ids.len() > MAX_BULK_CUSTOM_IDS {
+            return Err(DbError::TooManyItems);
+        }
+
+        let id = encode_key(voting_id, user_id);
+        let dialog = placeholder_dialog(voting_id, user_id);
+        let created_unix = dialog.created_unix;
+        let custom_uuids = custom_ids.iter().map(|(uuid, _)| uuid.clone()).collect();
+
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+        tokio::task::spawn_blocking(move || {
+            let write_txn = begin_write(&db, durability)?;
+            let existing = {
+                let table = write_txn.open_table(tables.voting_dialog())?;
+                let res = table.get(id.as_str())?;
+                match res {
+                    Some(v) => Some(VoteDialog::try_from(v.value())?),
+                    None => None,
                 }
-                None => Err(DbError::NotFound),
+            };
+            match existing {
+                Some(existing) if !existing.submitted => return Err(DbError::AlreadyExists),
+                Some(existing) => {
+                    delete_submitted_dialog_custom_ids(
+                        &write_txn,
+                        &tables,
+                        &existing.voting_id,
+                        &existing.user_id,
+                    )?;
+                }
+                None => {}
             }
+
+            insert_voting_dialog(&write_txn, &tables, &id, &dialog, true)?;
+            insert_custom_ids(&write_txn, &tables, &custom_ids)?;
+            write_txn.commit()?;
+
+            Ok::<(), DbError>(())
+        })
+        .await
+        .map_err(|e| DbError::Other(e.to_string()))??;
+
+        Ok(DialogSlot {
+            db: self.db.clone(),
+            tables: self.tables.clone(),
+            durability: self.durability,
+            voting_id: voting_id.to_string(),
+            user_id: user_id.to_string(),
+            weight: 1,
+            created_unix,
+            custom_uuids,
+            settled: false,
         })
-        .await?
     }
 
-    pub async fn delete_voting(&self, id: &str) -> Result<Voting, DbError> {
+    /// Marks the voting dialog as submitted (or reverts it when `submitted` is false).
+    /// The ballot is left untouched so an undone vote can be restored from it.
+    pub async fn set_voting_dialog_submitted(
+        &self,
+        voting_id: &str,
+        user_id: &str,
+        submitted: bool,
+        submitted_unix: u64,
+    ) -> Result<(), DbError> {
+        let id = encode_key(voting_id, user_id);
         let db = self.db.clone();
-        let id = id.to_owned();
+        let tables = self.tables.clone();
+        let durability = self.durability;
 
         tokio::task::spawn_blocking(move || {
-            let read_txn = db.begin_read()?;
-
-            let table = read_txn.open_table(VOTING_TABLE)?;
+            let write_txn = begin_write(&db, durability)?;
+            {
+                let mut table = write_txn.open_table(tables.voting_dialog())?;
 
-            let res = table.get(id.as_str())?;
+                let mut voting_dialog = match table.get(id.as_str())? {
+                    Some(v) => VoteDialog::try_from(v.value())?,
+                    None => return Err(DbError::NotFound),
+                };
 
-            match res {
-                Some(v) => {
-                    let mut voting = Voting::try_from(v.value())?;
-                    if voting.is_deleted {
-                        return Err(DbError::NotFound);
-                    }
+                voting_dialog.submitted = submitted;
+                voting_dialog.submitted_unix = submitted_unix;
 
-                    voting.is_deleted = true;
+                table.insert(id.as_str(), String::from(&voting_dialog).as_str())?;
+            }
+            set_submitted_dialog_index(&write_txn, &tables, &id, submitted)?;
 
-                    let write_txn = db.begin_write()?;
-                    {
-                        let mut table = write_txn.open_table(VOTING_TABLE)?;
-                        table.insert(id.as_str(), String::from(&voting).as_str())?;
-                    }
+            write_txn.commit()?;
 
-                    write_txn.commit()?;
-                    Ok(voting)
-                }
-                None => Err(DbError::NotFound),
-            }
+            Ok(())
         })
-        .await?
+        .await
+        .map_err(|e| DbError::Other(e.to_string()))?
     }
 
-    // Get voting for the provided id.
-    // Voting marked as deleted or completed are returned successfully.
-    // It is up to the caller to check the state of the voting
-    pub async fn get_voting(&self, id: &str) -> Result<Voting, DbError> {
+    pub async fn get_voting_dialog(
+        &self,
+        voting_id: &str,
+        user_id: &str,
+    ) -> Result<VoteDialog, DbError> {
+        let id = encode_key(voting_id, user_id);
         let db = self.db.clone();
-        let id = id.to_owned();
+        let tables = self.tables.clone();
 
         tokio::task::spawn_blocking(move || {
             let read_txn = db.begin_read()?;
 
-            let table = read_txn.open_table(VOTING_TABLE)?;
+            let table = read_txn.open_table(tables.voting_dialog())?;
 
             let res = table.get(id.as_str())?;
 
             match res {
-                Some(v) => Ok(Voting::try_from(v.value())?),
+                Some(v) => Ok(VoteDialog::try_from(v.value())?),
                 None => Err(DbError::NotFound),
             }
         })
@@ -277,111 +3029,276 @@ impl Db {
         .map_err(|e| DbError::Other(e.to_string()))?
     }
 
-    /// Updates vote value in the ballot of the voting dialog.
-    /// Index is the index of the choice in the ballot. It starts from 0.
-    /// Returns `IndexOutOfRange` if the index is bigger than the ballot size.
-    pub async fn vote_voting_dialog(
+    /// Returns the voting dialog of the user, or creates one with an unranked ballot of
+    /// `choice_count` choices when there is none. The bool is true when the dialog was created.
+    /// Both happen in one transaction, so a concurrent request can't create it in between.
+    pub async fn get_or_create_dialog(
         &self,
         voting_id: &str,
         user_id: &str,
-        vote: i32,
-        index: usize,
-    ) -> Result<(), DbError> {
+        choice_count: usize,
+    ) -> Result<(VoteDialog, bool), DbError> {
+        let id = encode_key(voting_id, user_id);
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+        let voting_id = voting_id.to_owned();
+        let user_id = user_id.to_owned();
+
+        tokio::task::spawn_blocking(move || {
+            let write_txn = begin_write(&db, durability)?;
+
+            let existing = {
+                let table = write_txn.open_table(tables.voting_dialog())?;
+                let res = table.get(id.as_str())?;
+                match res {
+                    Some(v) => Some(VoteDialog::try_from(v.value())?),
+                    None => None,
+                }
+            };
+
+            // nothing was written, the transaction is dropped
+            if let Some(dialog) = existing {
+                return Ok((dialog, false));
+            }
+
+            let dialog = VoteDialog {
+                voting_id,
+                user_id,
+                ballot: vec![0; choice_count],
+                message_id: "".to_string(),
+                channel_id: "".to_string(),
+                submitted: false,
+                submitted_unix: 0,
+                display_order: None,
+                created_unix: util::unix_timestamp(),
+                weight: 1,
+                comment: None,
+                question: 0,
+                question_ballots: Vec::new(),
+                placeholder: false,
+            };
+            insert_voting_dialog(&write_txn, &tables, &id, &dialog, false)?;
+            write_txn.commit()?;
+
+            Ok((dialog, true))
+        })
+        .await
+        .map_err(|e| DbError::Other(e.to_string()))?
+    }
+
+    /// Reads the voting and the voting dialog of the user in one transaction, so a dialog page
+    /// is rendered from a consistent snapshot. Returns `NotFound` when either is missing.
+    pub async fn get_dialog_render_state(
+        &self,
+        voting_id: &str,
+        user_id: &str,
+    ) -> Result<(Voting, VoteDialog), DbError> {
         let id = encode_key(voting_id, user_id);
+        let voting_id = voting_id.to_owned();
         let db = self.db.clone();
+        let tables = self.tables.clone();
 
         tokio::task::spawn_blocking(move || {
             let read_txn = db.begin_read()?;
 
-            let table = read_txn.open_table(VOTING_DIALOG_TABLE)?;
-            let res = table.get(id.as_str())?;
+            let voting_table = read_txn.open_table(tables.voting())?;
+            let voting = match voting_table.get(voting_id.as_str())? {
+                Some(v) => Voting::try_from(v.value())?,
+                None => return Err(DbError::NotFound),
+            };
+
+            let dialog_table = read_txn.open_table(tables.voting_dialog())?;
+            let dialog = match dialog_table.get(id.as_str())? {
+                Some(v) => VoteDialog::try_from(v.value())?,
+                None => return Err(DbError::NotFound),
+            };
+
+            Ok((voting, dialog))
+        })
+        .await
+        .map_err(|e| DbError::Other(e.to_string()))?
+    }
+
+    // Resolves the voting dialog from its dm message id.
+    pub async fn get_voting_dialog_by_message_id(
+        &self,
+        message_id: &str,
+    ) -> Result<VoteDialog, DbError> {
+        let message_id = message_id.to_owned();
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let read_txn = db.begin_read()?;
+
+            let index_table = read_txn.open_table(tables.dialog_message_index())?;
+
+            let Some(id) = index_table.get(message_id.as_str())? else {
+                return Err(DbError::NotFound);
+            };
+
+            let table = read_txn.open_table(tables.voting_dialog())?;
+
+            let res = table.get(id.value())?;
 
             match res {
-                Some(v) => {
-                    let mut voting_dialog = VoteDialog::try_from(v.value())?;
-                    if index >= voting_dialog.ballot.len() {
-                        return Err(DbError::IndexOutOfRange);
-                    }
+                Some(v) => Ok(VoteDialog::try_from(v.value())?),
+                None => Err(DbError::NotFound),
+            }
+        })
+        .await
+        .map_err(|e| DbError::Other(e.to_string()))?
+    }
 
-                    voting_dialog.ballot[index] = vote;
+    // Get all voting dialogs of the user, across votings.
+    pub async fn get_dialogs_for_user(&self, user_id: &str) -> Result<Vec<VoteDialog>, DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let user_id = user_id.to_owned();
 
-                    let write_txn = db.begin_write()?;
-                    {
-                        let mut table = write_txn.open_table(VOTING_DIALOG_TABLE)?;
-                        table.insert(id.as_str(), String::from(&voting_dialog).as_str())?;
-                    }
+        tokio::task::spawn_blocking(move || {
+            let read_txn = db.begin_read()?;
 
-                    write_txn.commit()?;
-                    Ok(())
+            let index_table = match read_txn.open_table(tables.user_dialog_index()) {
+                Ok(t) => t,
+                Err(redb::TableError::TableDoesNotExist(_)) => return Ok(Vec::new()),
+                Err(err) => return Err(err.into()),
+            };
+
+            let table = read_txn.open_table(tables.voting_dialog())?;
+
+            let index_prefix = format!("{}{}", user_id, ENCODE_DELIMITER);
+
+            let res = index_table.range(index_prefix.as_str()..)?;
+
+            let mut dialogs = Vec::new();
+            for v in res.flatten() {
+                if !v.0.value().starts_with(index_prefix.as_str()) {
+                    break;
+                }
+
+                if let Some(dialog) = table.get(v.1.value())? {
+                    dialogs.push(VoteDialog::try_from(dialog.value())?);
                 }
-                None => Err(DbError::NotFound),
             }
+
+            Ok(dialogs)
         })
         .await
         .map_err(|e| DbError::Other(e.to_string()))?
     }
 
-    // Saves voting dialog to the database.
-    // Returns `AlreadyExists` if the dialog with the same voting id and user id already exists.
-    pub async fn save_voting_dialog(
+    // Dialogs which were opened before `older_than_unix` and never submitted.
+    // Dialogs of unknown age are left out, they may have been opened just now.
+    pub async fn list_stale_dialogs(
         &self,
-        voting_id: String,
-        user_id: String,
-        ballot: Vec<i32>,
-        message_id: String,
-        channel_id: String,
-        overwrite: bool,
-    ) -> Result<(), DbError> {
-        let id = encode_key(&voting_id, &user_id);
-        let dialog = VoteDialog {
-            voting_id,
-            user_id,
-            ballot,
-            message_id,
-            channel_id,
-        };
-
+        older_than_unix: u64,
+    ) -> Result<Vec<VoteDialog>, DbError> {
         let db = self.db.clone();
+        let tables = self.tables.clone();
 
         tokio::task::spawn_blocking(move || {
-            let write_txn = db.begin_write()?;
-            {
-                let mut table = write_txn.open_table(VOTING_DIALOG_TABLE)?;
+            let read_txn = db.begin_read()?;
 
-                if !overwrite && table.get(id.as_str())?.is_some() {
-                    return Err(DbError::AlreadyExists);
+            let table = match read_txn.open_table(tables.voting_dialog()) {
+                Ok(t) => t,
+                Err(redb::TableError::TableDoesNotExist(_)) => return Ok(Vec::new()),
+                Err(err) => return Err(err.into()),
+            };
+
+            let mut dialogs = vec![];
+            for v in table.iter()?.flatten() {
+                let dialog = VoteDialog::try_from(v.1.value())?;
+                if !dialog.submitted
+                    && dialog.created_unix != 0
+                    && dialog.created_unix < older_than_unix
+                {
+                    dialogs.push(dialog);
                 }
+            }
+
+            Ok(dialogs)
+        })
+        .await
+        .map_err(|e| DbError::Other(e.to_string()))?
+    }
+
+    // Placeholder dialogs whose slot was never committed nor rolled back, e.g. left behind by a
+    // crash while sending the dialog dm. They block the voter, this lists them for an operator.
+    // In-channel and reply dialogs have no message ids either, but they are committed.
+    pub async fn find_incomplete_dialogs(&self) -> Result<Vec<VoteDialog>, DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let read_txn = db.begin_read()?;
+
+            let table = match read_txn.open_table(tables.voting_dialog()) {
+                Ok(t) => t,
+                Err(redb::TableError::TableDoesNotExist(_)) => return Ok(Vec::new()),
+                Err(err) => return Err(err.into()),
+            };
 
-                table.insert(id.as_str(), String::from(&dialog).as_str())?;
+            let mut dialogs = vec![];
+            for v in table.iter()?.flatten() {
+                let dialog = VoteDialog::try_from(v.1.value())?;
+                if dialog.placeholder {
+                    dialogs.push(dialog);
+                }
             }
 
-            write_txn.commit()?;
+            Ok(dialogs)
+        })
+        .await
+        .map_err(|e| DbError::Other(e.to_string()))?
+    }
 
-            Ok(())
+    // Counts the voting dialogs from their keys, without deserializing the dialogs.
+    pub async fn count_voting_dialogs(&self, voting_id: &str) -> Result<usize, DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let prefix = format!("{}{}", voting_id, ENCODE_DELIMITER);
+
+        tokio::task::spawn_blocking(move || {
+            let read_txn = db.begin_read()?;
+
+            let table = match read_txn.open_table(tables.voting_dialog()) {
+                Ok(t) => t,
+                Err(redb::TableError::TableDoesNotExist(_)) => return Ok(0),
+                Err(err) => return Err(err.into()),
+            };
+
+            count_dialog_keys(&table, &prefix)
         })
         .await
         .map_err(|e| DbError::Other(e.to_string()))?
     }
 
-    pub async fn get_voting_dialog(
-        &self,
-        voting_id: &str,
-        user_id: &str,
-    ) -> Result<VoteDialog, DbError> {
-        let id = encode_key(voting_id, user_id);
+    // Gets the voting and counts its dialogs within a single read transaction.
+    pub async fn get_voting_with_dialog_count(&self, id: &str) -> Result<(Voting, usize), DbError> {
         let db = self.db.clone();
+        let tables = self.tables.clone();
+        let id = id.to_owned();
 
         tokio::task::spawn_blocking(move || {
             let read_txn = db.begin_read()?;
 
-            let table = read_txn.open_table(VOTING_DIALOG_TABLE)?;
+            let voting = {
+                let table = read_txn.open_table(tables.voting())?;
+                match table.get(id.as_str())? {
+                    Some(v) => Voting::try_from(v.value())?,
+                    None => return Err(DbError::NotFound),
+                }
+            };
 
-            let res = table.get(id.as_str())?;
+            let count = match read_txn.open_table(tables.voting_dialog()) {
+                Ok(table) => count_dialog_keys(&table, &format!("{}{}", id, ENCODE_DELIMITER))?,
+                Err(redb::TableError::TableDoesNotExist(_)) => 0,
+                Err(err) => return Err(err.into()),
+            };
 
-            match res {
-                Some(v) => Ok(VoteDialog::try_from(v.value())?),
-                None => Err(DbError::NotFound),
-            }
+            Ok((voting, count))
         })
         .await
         .map_err(|e| DbError::Other(e.to_string()))?
@@ -389,24 +3306,88 @@ impl Db {
 
     pub async fn get_voting_dialogs(&self, voting_id: &str) -> Result<Vec<VoteDialog>, DbError> {
         let db = self.db.clone();
+        let tables = self.tables.clone();
         let voting_id = voting_id.to_owned();
 
         tokio::task::spawn_blocking(move || {
             let read_txn = db.begin_read()?;
 
-            let table = read_txn.open_table(VOTING_DIALOG_TABLE)?;
+            let table = read_txn.open_table(tables.voting_dialog())?;
 
             let res = table.range(format!("{}{}", voting_id, ENCODE_DELIMITER).as_str()..)?;
 
-            let mut dialogs = vec![];
-            for v in res.flatten() {
-                let dialog = VoteDialog::try_from(v.1.value())?;
-                if dialog.voting_id == voting_id {
-                    dialogs.push(dialog);
+            let mut dialogs = vec![];
+            for v in res.flatten() {
+                let dialog = VoteDialog::try_from(v.1.value())?;
+                if dialog.voting_id == voting_id {
+                    dialogs.push(dialog);
+                }
+            }
+
+            Ok(dialogs)
+        })
+        .await
+        .map_err(|e| DbError::Other(e.to_string()))?
+    }
+
+    // Same as `get_voting_dialogs`, but only the submitted dialogs are read, through
+    // `submitted_dialog_index`
+    pub async fn get_submitted_dialogs(&self, voting_id: &str) -> Result<Vec<VoteDialog>, DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let voting_id = voting_id.to_owned();
+
+        tokio::task::spawn_blocking(move || {
+            let read_txn = db.begin_read()?;
+
+            let index_table = match read_txn.open_table(tables.submitted_dialog_index()) {
+                Ok(table) => table,
+                Err(redb::TableError::TableDoesNotExist(_)) => return Ok(vec![]),
+                Err(err) => return Err(err.into()),
+            };
+            let table = read_txn.open_table(tables.voting_dialog())?;
+
+            let prefix = format!("{}{}", voting_id, ENCODE_DELIMITER);
+            let res = index_table.range(prefix.as_str()..)?;
+
+            let mut dialogs = vec![];
+            for v in res.flatten() {
+                if !v.0.value().starts_with(prefix.as_str()) {
+                    break;
+                }
+
+                let Some(value) = table.get(v.1.value())? else {
+                    continue;
+                };
+                let dialog = VoteDialog::try_from(value.value())?;
+                if dialog.submitted && dialog.voting_id == voting_id {
+                    dialogs.push(dialog);
+                }
+            }
+
+            Ok(dialogs)
+        })
+        .await
+        .map_err(|e| DbError::Other(e.to_string()))?
+    }
+
+    // Counts the submitted dialogs of the voting without reading them, cheap enough to run
+    // on every announcement edit
+    pub async fn count_submitted_dialogs(&self, voting_id: &str) -> Result<usize, DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let voting_id = voting_id.to_owned();
+
+        tokio::task::spawn_blocking(move || {
+            let read_txn = db.begin_read()?;
+
+            match read_txn.open_table(tables.submitted_dialog_index()) {
+                Ok(table) => {
+                    count_dialog_keys(&table, &format!("{}{}", voting_id, ENCODE_DELIMITER))
                 }
+                Err(redb::TableError::TableDoesNotExist(_)) => Ok(0),
+                Err(err) => Err(err.into()),
             }
-
-            Ok(dialogs)
         })
         .await
         .map_err(|e| DbError::Other(e.to_string()))?
@@ -419,43 +3400,70 @@ impl Db {
     ) -> Result<(), DbError> {
         let id = encode_key(voting_id, user_id);
         let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+
+        tokio::task::spawn_blocking(move || remove_voting_dialog(&db, &tables, durability, &id))
+            .await
+            .map_err(|e| DbError::Other(e.to_string()))?
+    }
+
+    pub async fn bulk_save_custom_ids(
+        &self,
+        custom_ids: Vec<(String, CustomID)>,
+    ) -> Result<(), DbError> {
+        if custom_ids.len() > MAX_BULK_CUSTOM_IDS {
+            return Err(DbError::TooManyItems);
+        }
+
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
 
+        // every chunk is its own transaction, a failed chunk leaves the previous ones saved
         tokio::task::spawn_blocking(move || {
-            let write_txn = db.begin_write()?;
-            {
-                let mut table = write_txn.open_table(VOTING_DIALOG_TABLE)?;
-                table.remove(id.as_str())?;
+            for chunk in custom_ids.chunks(CUSTOM_IDS_CHUNK_SIZE) {
+                let write_txn = begin_write(&db, durability)?;
+                insert_custom_ids(&write_txn, &tables, chunk)?;
+                write_txn.commit()?;
             }
 
-            write_txn.commit()?;
-
             Ok(())
         })
         .await
         .map_err(|e| DbError::Other(e.to_string()))?
     }
 
-    pub async fn bulk_save_custom_ids(
+    // Replaces a single custom id of the voting with `new_uuid`, the rest is left as is.
+    // Returns `NotFound` if the old custom id does not belong to the voting.
+    pub async fn replace_custom_id(
         &self,
-        custom_ids: Vec<(String, CustomID)>,
+        voting_id: &str,
+        old_uuid: &str,
+        new_uuid: &str,
+        custom_id: CustomID,
     ) -> Result<(), DbError> {
         let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+        let old_uuid = old_uuid.to_owned();
+        let old_index_key = encode_key(voting_id, &old_uuid);
+        let new_custom_id = (new_uuid.to_owned(), custom_id);
 
         tokio::task::spawn_blocking(move || {
-            let write_txn = db.begin_write()?;
+            let write_txn = begin_write(&db, durability)?;
             {
-                let mut table = write_txn.open_table(CUSTOM_ID_TABLE)?;
-                let mut index_table = write_txn.open_table(VOTING_CUSTOMID_INDEX_TABLE)?;
+                let mut table = write_txn.open_table(tables.custom_id())?;
+                let mut index_table = write_txn.open_table(tables.voting_customid_index())?;
 
-                for (custom_uuid, custom_id) in custom_ids {
-                    table.insert(custom_uuid.as_str(), custom_id.to_string().as_str())?;
-                    let index_key = encode_key(&custom_id.voting_id, &custom_uuid);
-                    index_table.insert(index_key.as_str(), custom_uuid.as_str())?;
+                if index_table.remove(old_index_key.as_str())?.is_none() {
+                    return Err(DbError::NotFound);
                 }
+                table.remove(old_uuid.as_str())?;
             }
+            insert_custom_ids(&write_txn, &tables, &[new_custom_id])?;
 
             write_txn.commit()?;
-
             Ok(())
         })
         .await
@@ -464,12 +3472,13 @@ impl Db {
 
     pub async fn get_custom_id(&self, id: &str) -> Result<CustomID, DbError> {
         let db = self.db.clone();
+        let tables = self.tables.clone();
         let id = id.to_owned();
 
         tokio::task::spawn_blocking(move || {
             let read_txn = db.begin_read()?;
 
-            let table = read_txn.open_table(CUSTOM_ID_TABLE)?;
+            let table = read_txn.open_table(tables.custom_id())?;
 
             let res = table.get(id.as_str())?;
 
@@ -482,17 +3491,54 @@ impl Db {
         .map_err(|e| DbError::Other(e.to_string()))?
     }
 
-    #[allow(dead_code)]
-    pub async fn get_custom_ids(&self, voting_id: &str) -> Result<Vec<CustomID>, DbError> {
+    // Get the custom id and its voting in a single read transaction.
+    // Returns `NotFound` if either of them is missing.
+    pub async fn get_custom_id_with_voting(
+        &self,
+        custom_uuid: &str,
+    ) -> Result<(CustomID, Voting), DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let custom_uuid = custom_uuid.to_owned();
+
+        tokio::task::spawn_blocking(move || {
+            let read_txn = db.begin_read()?;
+
+            let custom_id_table = read_txn.open_table(tables.custom_id())?;
+
+            let custom_id = match custom_id_table.get(custom_uuid.as_str())? {
+                Some(v) => CustomID::try_from(v.value())?,
+                None => return Err(DbError::NotFound),
+            };
+
+            let voting_table = read_txn.open_table(tables.voting())?;
+
+            let res = voting_table.get(custom_id.voting_id.as_str())?;
+
+            match res {
+                Some(v) => Ok((custom_id, Voting::try_from(v.value())?)),
+                None => Err(DbError::NotFound),
+            }
+        })
+        .await
+        .map_err(|e| DbError::Other(e.to_string()))?
+    }
+
+    // Returns (customUUID, CustomID) pairs for the voting.
+    pub async fn get_custom_ids(
+        &self,
+        voting_id: &str,
+    ) -> Result<Vec<(String, CustomID)>, DbError> {
         let db = self.db.clone();
+        let tables = self.tables.clone();
         let voting_id = voting_id.to_owned();
 
         tokio::task::spawn_blocking(move || {
             let read_txn = db.begin_read()?;
 
-            let table = read_txn.open_table(CUSTOM_ID_TABLE)?;
+            let table = read_txn.open_table(tables.custom_id())?;
 
-            let table_index = read_txn.open_table(VOTING_CUSTOMID_INDEX_TABLE)?;
+            let table_index = read_txn.open_table(tables.voting_customid_index())?;
 
             let index_prefix = format!("{}{}", voting_id, ENCODE_DELIMITER);
 
@@ -510,7 +3556,7 @@ impl Db {
                 let v = table.get(custom_uuid);
                 if let Ok(Some(custom_id_v)) = v {
                     let custom_id = CustomID::try_from(custom_id_v.value())?;
-                    custom_ids.push(custom_id);
+                    custom_ids.push((custom_uuid.to_string(), custom_id));
                 } else {
                     tracing::error!("failed to get custom id for index: {}", index);
                 }
@@ -524,14 +3570,16 @@ impl Db {
 
     pub async fn delete_custom_ids(&self, voting_id: &str) -> Result<(), DbError> {
         let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
         let voting_id = voting_id.to_owned();
 
         tokio::task::spawn_blocking(move || {
-            let write_txn = db.begin_write()?;
+            let write_txn = begin_write(&db, durability)?;
             {
-                let mut custom_id_table = write_txn.open_table(CUSTOM_ID_TABLE)?;
+                let mut custom_id_table = write_txn.open_table(tables.custom_id())?;
 
-                let mut index_table = write_txn.open_table(VOTING_CUSTOMID_INDEX_TABLE)?;
+                let mut index_table = write_txn.open_table(tables.voting_customid_index())?;
 
                 let index_prefix = format!("{}{}", voting_id, ENCODE_DELIMITER);
 
@@ -563,8 +3611,513 @@ impl Db {
         .await
         .map_err(|e| DbError::Other(e.to_string()))?
     }
+
+    // Deletes the custom ids of the voting which were created for the user, e.g. of their dialog.
+    pub async fn delete_custom_ids_for_user(
+        &self,
+        voting_id: &str,
+        user_id: &str,
+    ) -> Result<(), DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+        let voting_id = voting_id.to_owned();
+        let user_id = user_id.to_owned();
+
+        tokio::task::spawn_blocking(move || {
+            let write_txn = begin_write(&db, durability)?;
+            {
+                let mut custom_id_table = write_txn.open_table(tables.custom_id())?;
+
+                let mut index_table = write_txn.open_table(tables.voting_customid_index())?;
+
+                let index_prefix = format!("{}{}", voting_id, ENCODE_DELIMITER);
+
+                let mut to_remove: Vec<(String, String)> = Vec::new();
+                {
+                    let res = index_table.range(index_prefix.as_str()..)?;
+
+                    // (index, custom_uuid)
+                    for v in res.flatten() {
+                        let index = v.0.value();
+                        if !index.starts_with(index_prefix.as_str()) {
+                            break;
+                        }
+
+                        let custom_uuid = v.1.value();
+                        if let Some(custom_id) = custom_id_table.get(custom_uuid)? {
+                            let custom_id = CustomID::try_from(custom_id.value())?;
+                            if custom_id.user_id.as_deref() == Some(user_id.as_str()) {
+                                to_remove.push((index.to_string(), custom_uuid.to_string()));
+                            }
+                        }
+                    }
+                }
+
+                for (index, custom_uuid) in to_remove {
+                    custom_id_table.remove(custom_uuid.as_str())?;
+                    index_table.remove(index.as_str())?;
+                }
+            }
+
+            write_txn.commit()?;
+
+            Ok(())
+        })
+        .await
+        .map_err(|e| DbError::Other(e.to_string()))?
+    }
+
+    // Removes the index entries of the voting whose custom id row is gone, returns how many.
+    // `get_custom_ids` skips such entries, this cleans them up for good.
+    pub async fn repair_custom_id_index(&self, voting_id: &str) -> Result<usize, DbError> {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+        let voting_id = voting_id.to_owned();
+
+        tokio::task::spawn_blocking(move || {
+            let write_txn = begin_write(&db, durability)?;
+            let pruned = {
+                let custom_id_table = write_txn.open_table(tables.custom_id())?;
+
+                let mut index_table = write_txn.open_table(tables.voting_customid_index())?;
+
+                let index_prefix = format!("{}{}", voting_id, ENCODE_DELIMITER);
+
+                let mut orphans: Vec<String> = Vec::new();
+                {
+                    let res = index_table.range(index_prefix.as_str()..)?;
+
+                    for v in res.flatten() {
+                        let index = v.0.value();
+                        if !index.starts_with(index_prefix.as_str()) {
+                            break;
+                        }
+
+                        if custom_id_table.get(v.1.value())?.is_none() {
+                            orphans.push(index.to_string());
+                        }
+                    }
+                }
+
+                for index in orphans.iter() {
+                    index_table.remove(index.as_str())?;
+                }
+
+                orphans.len()
+            };
+
+            write_txn.commit()?;
+
+            Ok(pruned)
+        })
+        .await
+        .map_err(|e| DbError::Other(e.to_string()))?
+    }
+
+    // Deletes custom ids of the voting, keeping the ones with the provided actions.
+    pub async fn delete_custom_ids_except(
+        &self,
+        voting_id: &str,
+        keep: Vec<Action>,
+    ) -> Result<(), DbError> {
+        self.delete_custom_ids_matching(voting_id, move |action| !keep.contains(action))
+            .await
+    }
+
+    // Deletes custom ids of the voting with the provided actions, keeping the rest.
+    pub async fn delete_custom_ids_with_actions(
+        &self,
+        voting_id: &str,
+        actions: Vec<Action>,
+    ) -> Result<(), DbError> {
+        self.delete_custom_ids_matching(voting_id, move |action| actions.contains(action))
+            .await
+    }
+
+    // Index entries whose custom id row is gone are always removed
+    async fn delete_custom_ids_matching<F>(&self, voting_id: &str, remove: F) -> Result<(), DbError>
+    where
+        F: Fn(&Action) -> bool + Send + 'static,
+    {
+        let db = self.db.clone();
+        let tables = self.tables.clone();
+        let durability = self.durability;
+        let voting_id = voting_id.to_owned();
+
+        tokio::task::spawn_blocking(move || {
+            let write_txn = begin_write(&db, durability)?;
+            {
+                let mut custom_id_table = write_txn.open_table(tables.custom_id())?;
+
+                let mut index_table = write_txn.open_table(tables.voting_customid_index())?;
+
+                let index_prefix = format!("{}{}", voting_id, ENCODE_DELIMITER);
+
+                let mut to_remove: Vec<(String, String)> = Vec::new();
+                {
+                    let res = index_table.range(index_prefix.as_str()..)?;
+
+                    // (index, custom_uuid)
+                    for v in res.flatten() {
+                        let index = v.0.value();
+                        if !index.starts_with(index_prefix.as_str()) {
+                            break;
+                        }
+
+                        let custom_uuid = v.1.value();
+                        if let Some(custom_id) = custom_id_table.get(custom_uuid)? {
+                            let custom_id = CustomID::try_from(custom_id.value())?;
+                            if !remove(&custom_id.action) {
+                                continue;
+                            }
+                        }
+
+                        to_remove.push((index.to_string(), custom_uuid.to_string()));
+                    }
+                }
+
+                for (index, custom_uuid) in to_remove {
+                    custom_id_table.remove(custom_uuid.as_str())?;
+                    index_table.remove(index.as_str())?;
+                }
+            }
+
+            write_txn.commit()?;
+
+            Ok(())
+        })
+        .await
+        .map_err(|e| DbError::Other(e.to_string()))?
+    }
+}
+
+fn write_voting_dialog(
+    db: &Database,
+    tables: &Tables,
+    durability: Durability,
+    id: &str,
+    dialog: &VoteDialog,
+    overwrite: bool,
+) -> Result<(), DbError> {
+    let write_txn = begin_write(db, durability)?;
+    insert_voting_dialog(&write_txn, tables, id, dialog, overwrite)?;
+    write_txn.commit()?;
+
+    Ok(())
+}
+
+fn insert_voting_dialog(
+    write_txn: &WriteTransaction,
+    tables: &Tables,
+    id: &str,
+    dialog: &VoteDialog,
+    overwrite: bool,
+) -> Result<(), DbError> {
+    let mut table = write_txn.open_table(tables.voting_dialog())?;
+    let mut index_table = write_txn.open_table(tables.dialog_message_index())?;
+
+    let old_message_id = match table.get(id)? {
+        // rejected before deserializing, claiming a taken slot is the common case
+        Some(_) if !overwrite => return Err(DbError::AlreadyExists),
+        Some(v) => Some(VoteDialog::try_from(v.value())?.message_id),
+        None => None,
+    };
+
+    if let Some(old_message_id) = old_message_id {
+        index_table.remove(old_message_id.as_str())?;
+    }
+
+    table.insert(id, String::from(dialog).as_str())?;
+
+    // placeholder dialogs are saved before the dm message exists
+    if !dialog.message_id.is_empty() {
+        index_table.insert(dialog.message_id.as_str(), id)?;
+    }
+
+    let mut user_index_table = write_txn.open_table(tables.user_dialog_index())?;
+    let user_index_key = encode_key(&dialog.user_id, &dialog.voting_id);
+    user_index_table.insert(user_index_key.as_str(), id)?;
+
+    set_submitted_dialog_index(write_txn, tables, id, dialog.submitted)?;
+
+    Ok(())
+}
+
+fn set_submitted_dialog_index(
+    write_txn: &WriteTransaction,
+    tables: &Tables,
+    id: &str,
+    submitted: bool,
+) -> Result<(), DbError> {
+    let mut table = write_txn.open_table(tables.submitted_dialog_index())?;
+    if submitted {
+        table.insert(id, id)?;
+    } else {
+        table.remove(id)?;
+    }
+
+    Ok(())
+}
+
+fn remove_voting_dialog(
+    db: &Database,
+    tables: &Tables,
+    durability: Durability,
+    id: &str,
+) -> Result<(), DbError> {
+    let write_txn = begin_write(db, durability)?;
+    delete_voting_dialog_entries(&write_txn, tables, id)?;
+    write_txn.commit()?;
+
+    Ok(())
+}
+
+fn delete_voting_dialog_entries(
+    write_txn: &WriteTransaction,
+    tables: &Tables,
+    id: &str,
+) -> Result<(), DbError> {
+    let mut table = write_txn.open_table(tables.voting_dialog())?;
+    let dialog = match table.remove(id)? {
+        Some(v) => Some(VoteDialog::try_from(v.value())?),
+        None => None,
+    };
+
+    if let Some(dialog) = dialog {
+        let mut index_table = write_txn.open_table(tables.dialog_message_index())?;
+        index_table.remove(dialog.message_id.as_str())?;
+
+        let mut user_index_table = write_txn.open_table(tables.user_dialog_index())?;
+        let user_index_key = encode_key(&dialog.user_id, &dialog.voting_id);
+        user_index_table.remove(user_index_key.as_str())?;
+
+        set_submitted_dialog_index(write_txn, tables, id, false)?;
+    }
+
+    Ok(())
+}
+
+// Marks the voting as completed, with a pending completion until its results are published
+fn write_completed_voting(
+    write_txn: &WriteTransaction,
+    tables: &Tables,
+    id: &str,
+    voting: &mut Voting,
+    user_id: &str,
+) -> Result<(), DbError> {
+    voting.is_completed = true;
+    voting.version += 1;
+
+    {
+        let mut table = write_txn.open_table(tables.voting())?;
+        table.insert(id, String::from(&*voting).as_str())?;
+
+        let mut pending_table = write_txn.open_table(tables.pending_completion())?;
+        pending_table.insert(id, util::unix_timestamp())?;
+    }
+
+    let event = AuditEvent::new(AuditEventKind::Completed, user_id);
+    write_audit(write_txn, tables, id, &event)
+}
+
+// Custom ids go with their voting index entry, see `Tables::voting_customid_index`
+fn insert_custom_ids(
+    write_txn: &WriteTransaction,
+    tables: &Tables,
+    custom_ids: &[(String, CustomID)],
+) -> Result<(), DbError> {
+    let mut table = write_txn.open_table(tables.custom_id())?;
+    let mut index_table = write_txn.open_table(tables.voting_customid_index())?;
+
+    for (custom_uuid, custom_id) in custom_ids {
+        table.insert(custom_uuid.as_str(), custom_id.to_string().as_str())?;
+        let index_key = encode_key(&custom_id.voting_id, custom_uuid);
+        index_table.insert(index_key.as_str(), custom_uuid.as_str())?;
+    }
+
+    Ok(())
+}
+
+// Removes the undo and comment custom ids left on the message of a submitted dialog
+fn delete_submitted_dialog_custom_ids(
+    write_txn: &WriteTransaction,
+    tables: &Tables,
+    voting_id: &str,
+    user_id: &str,
+) -> Result<(), DbError> {
+    let mut table = write_txn.open_table(tables.custom_id())?;
+    let mut index_table = write_txn.open_table(tables.voting_customid_index())?;
+
+    let index_prefix = format!("{}{}", voting_id, ENCODE_DELIMITER);
+
+    let mut to_remove: Vec<(String, String)> = Vec::new();
+    {
+        let res = index_table.range(index_prefix.as_str()..)?;
+
+        // (index, custom_uuid)
+        for v in res.flatten() {
+            let index = v.0.value();
+            if !index.starts_with(index_prefix.as_str()) {
+                break;
+            }
+
+            let custom_uuid = v.1.value();
+            if let Some(custom_id) = table.get(custom_uuid)? {
+                let custom_id = CustomID::try_from(custom_id.value())?;
+                if custom_id.user_id.as_deref() == Some(user_id)
+                    && matches!(custom_id.action, Action::UndoVote | Action::AddComment)
+                {
+                    to_remove.push((index.to_string(), custom_uuid.to_string()));
+                }
+            }
+        }
+    }
+
+    for (index, custom_uuid) in to_remove {
+        table.remove(custom_uuid.as_str())?;
+        index_table.remove(index.as_str())?;
+    }
+
+    Ok(())
+}
+
+// Empty dialog holding the slot of the user until the dialog message is sent
+fn placeholder_dialog(voting_id: &str, user_id: &str) -> VoteDialog {
+    VoteDialog {
+        voting_id: voting_id.to_string(),
+        user_id: user_id.to_string(),
+        ballot: Vec::new(),
+        message_id: "".to_string(),
+        channel_id: "".to_string(),
+        submitted: false,
+        submitted_unix: 0,
+        display_order: None,
+        created_unix: util::unix_timestamp(),
+        weight: 1,
+        comment: None,
+        question: 0,
+        question_ballots: Vec::new(),
+        placeholder: true,
+    }
+}
+
+// The slot's custom ids are always removed, the dialog only while it is still the placeholder
+// saved at `created_unix`. A rollback running late must not delete a dialog opened after it.
+fn rollback_dialog_slot(
+    db: &Database,
+    tables: &Tables,
+    durability: Durability,
+    id: &str,
+    created_unix: u64,
+    voting_id: &str,
+    custom_uuids: &[String],
+) -> Result<(), DbError> {
+    let write_txn = begin_write(db, durability)?;
+    let is_own_placeholder = {
+        let table = write_txn.open_table(tables.voting_dialog())?;
+        let res = table.get(id)?;
+        match res {
+            Some(v) => {
+                let dialog = VoteDialog::try_from(v.value())?;
+                dialog.placeholder && dialog.created_unix == created_unix
+            }
+            None => false,
+        }
+    };
+    if is_own_placeholder {
+        delete_voting_dialog_entries(&write_txn, tables, id)?;
+    }
+    if !custom_uuids.is_empty() {
+        let mut table = write_txn.open_table(tables.custom_id())?;
+        let mut index_table = write_txn.open_table(tables.voting_customid_index())?;
+
+        for custom_uuid in custom_uuids {
+            table.remove(custom_uuid.as_str())?;
+            index_table.remove(encode_key(voting_id, custom_uuid).as_str())?;
+        }
+    }
+    write_txn.commit()?;
+
+    Ok(())
+}
+
+fn begin_write(db: &Database, durability: Durability) -> Result<WriteTransaction<'_>, DbError> {
+    let mut write_txn = db.begin_write()?;
+    write_txn.set_durability(durability);
+    Ok(write_txn)
+}
+
+// Appends the event to the voting audit log. Keys are zero padded so they sort chronologically,
+// the sequence keeps events of the same second apart.
+fn write_audit(
+    write_txn: &WriteTransaction,
+    tables: &Tables,
+    voting_id: &str,
+    event: &AuditEvent,
+) -> Result<(), DbError> {
+    let mut table = write_txn.open_table(tables.audit())?;
+
+    let prefix = format!("{}{}", voting_id, ENCODE_DELIMITER);
+    let mut sequence = 0;
+    for v in table.range(prefix.as_str()..)?.flatten() {
+        if !v.0.value().starts_with(prefix.as_str()) {
+            break;
+        }
+        sequence += 1;
+    }
+
+    let key = encode_key(
+        voting_id,
+        &format!(
+            "{:020}{}{:010}",
+            event.timestamp_unix, ENCODE_DELIMITER, sequence
+        ),
+    );
+    table.insert(key.as_str(), String::from(event).as_str())?;
+
+    Ok(())
+}
+
+// Voting ids are the first part of composite keys, see `encode_key`. An id with the delimiter
+// would match the entries of other votings in the prefix scans.
+pub fn is_valid_voting_id(id: &str) -> bool {
+    !id.is_empty() && !id.contains(ENCODE_DELIMITER)
 }
 
 fn encode_key(voting_id: &str, user_id: &str) -> String {
     format!("{}{}{}", voting_id, ENCODE_DELIMITER, user_id)
 }
+
+// Result of opening a table in `Db::self_check`, tables are only created by their first write
+fn check_table<T>(opened: Result<T, redb::TableError>, name: &str) -> Result<(), DbError> {
+    match opened {
+        Ok(_) | Err(redb::TableError::TableDoesNotExist(_)) => Ok(()),
+        Err(err) => Err(DbError::Other(format!(
+            "opening table {} failed: {}",
+            name, err
+        ))),
+    }
+}
+
+// Counts the dialog keys starting with the voting `prefix`, without deserializing the dialogs.
+fn count_dialog_keys(
+    table: &impl ReadableTable<&'static str, &'static str>,
+    prefix: &str,
+) -> Result<usize, DbError> {
+    let mut count = 0;
+    for v in table.range(prefix..)?.flatten() {
+        let key = v.0.value();
+        if !key.starts_with(prefix) {
+            break;
+        }
+
+        // user ids have no delimiter, the rest belongs to a voting id sharing the prefix
+        if !key[prefix.len()..].contains(ENCODE_DELIMITER) {
+            count += 1;
+        }
+    }
+
+    Ok(count)
+}
@@ -1,41 +1,371 @@
+#[cfg(feature = "chart")]
+pub mod chart;
 pub mod db;
 pub mod util;
 
-use crate::db::{Action, CustomID, Db, Voting};
+use crate::db::{
+    Action, AuditEventKind, CustomID, Db, DialogSlot, FailedCleanup, GuildConfig, PendingVote,
+    Question, TieBreak, VoteDialog, Voting, VotingBallots, VotingState, VotingsListing,
+};
 
-use axum::extract::State;
+use axum::extract::{Path, State};
 use axum::response::{IntoResponse, Response};
 use axum::Json;
-use ddclient_rs::Client;
+use ddclient_rs::{ApiError, Client, VotingResult, VotingResults};
 use http::{HeaderMap, StatusCode};
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::future::{Future, IntoFuture};
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use subtle::ConstantTimeEq;
+use tokio::task::AbortHandle;
 use tokio_util::task::TaskTracker;
+use tracing::Instrument;
+use twilight_http::request::channel::reaction::RequestReactionType;
 use twilight_model::application::interaction::application_command::{
     CommandData, CommandOptionValue,
 };
 use twilight_model::application::interaction::message_component::MessageComponentInteractionData;
+use twilight_model::application::interaction::modal::ModalInteractionData;
 use twilight_model::application::interaction::{Interaction, InteractionData, InteractionType};
 use twilight_model::channel::message::component::{
-    ActionRow, Button, ButtonStyle, Component, SelectMenuOption,
+    ActionRow, Button, ButtonStyle, Component, SelectMenuOption, TextInput, TextInputStyle,
+};
+use twilight_model::channel::message::{
+    AllowedMentions, Embed, MentionType, MessageFlags, ReactionType,
 };
-use twilight_model::channel::message::{Embed, MessageFlags};
-use twilight_model::channel::Message;
+use twilight_model::channel::{Channel, Message};
+use twilight_model::guild::Permissions;
 use twilight_model::http::interaction::{
     InteractionResponse, InteractionResponseData, InteractionResponseType,
 };
-use twilight_model::id::marker::{ChannelMarker, MessageMarker};
+use twilight_model::id::marker::{ChannelMarker, InteractionMarker, MessageMarker, UserMarker};
 use twilight_model::id::Id;
-use twilight_util::builder::embed::{EmbedBuilder, EmbedFieldBuilder};
+use twilight_util::builder::embed::{EmbedBuilder, EmbedFieldBuilder, ImageSource};
 
 pub type InteractionResult = Result<(StatusCode, Json<InteractionResponse>), InteractionError>;
 
+// Shown above the in channel voting dialog when the dm could not be sent
+const DM_DISABLED_MESSAGE: &str =
+    "We couldn't send you a dm, so you can vote here instead. Enable dms from server members to vote in dm.";
+
+// How long after submitting a vote the voter can still undo it
+pub const UNDO_VOTE_WINDOW_SECS: u64 = 60;
+
+// Discord allows at most 20 different reactions on a message
+pub const SIMPLE_VOTING_MAX_CHOICES: usize = 20;
+
+const STATUS_CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+
+// Opening dm channels fails more often than the other discord endpoints, so it is retried
+const DM_CHANNEL_ATTEMPTS: u32 = 3;
+const DM_CHANNEL_TIMEOUT: Duration = Duration::from_secs(5);
+const DM_CHANNEL_RETRY_DELAY: Duration = Duration::from_millis(250);
+// Time for all attempts of a dm channel opened before the interaction response, which discord
+// expects within 3 seconds
+const DM_CHANNEL_INLINE_BUDGET: Duration = Duration::from_millis(2000);
+// Time for all attempts of a dm channel opened after the interaction response
+const DM_CHANNEL_BACKGROUND_BUDGET: Duration = Duration::from_secs(20);
+
+// Reactions of a message share one rate limit bucket, a rate limited reaction is retried
+const REACTION_ATTEMPTS: u32 = 3;
+// Wait of a rate limited reaction when discord does not say how long, and the longest wait
+const REACTION_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_REACTION_RETRY_DELAY: Duration = Duration::from_secs(10);
+
+const DM_UNAVAILABLE_MESSAGE: &str = "Couldn't open a DM — check your DM privacy settings.";
+const BOT_USER_MESSAGE: &str = "The bot can't send direct messages to itself.";
+// Neutral grey of the results embed of a voting nobody voted in
+const NO_VOTES_COLOR: u32 = 0x99AAB5;
+// How much the "Extend +1h" button of the creator dm adds to the deadline
+const DEADLINE_EXTENSION_SECS: u64 = 60 * 60;
+
+// Channel message of a completed voting whose results are only shown to the creator
+const PRIVATE_RESULTS_MESSAGE: &str = "Voting has concluded.";
+
+// Non-voters of a voting are reminded at most once per interval
+const REMINDER_INTERVAL_SECS: u64 = 60 * 60;
+// voting fetched to check the DD backend is reachable
+const STATUS_CHECK_VOTING_ID: &str = "status-check";
+
+// Votings listed on one page of the /votings embed
+const VOTINGS_PAGE_SIZE: usize = 10;
+// Custom ids of the /votings page buttons start with this, they are kept apart from the
+// voting custom ids, see `db::VotingsListing`
+const VOTINGS_LISTING_PREFIX: &str = "votings-";
+// How long the /votings page buttons work, after that the listing has to be run again
+const VOTINGS_LISTING_TTL_SECS: u64 = 15 * 60;
+
+// Longest choice accepted, a choice has to fit into a single embed field value
+pub const MAX_CHOICE_LENGTH: usize = 1024;
+// Discord rejects embed field values longer than this
+pub const EMBED_FIELD_VALUE_MAX_LENGTH: usize = 1024;
+// Dialog pages with a choice longer than this show every choice in its own embed field
+const LONG_CHOICE_LENGTH: usize = 100;
+
+// Stored voting JSON shown by /voting-debug, leaves room for the code block in the message
+const DEBUG_VOTING_MAX_CHARS: usize = 1980;
+
+// Largest interaction body accepted, discord payloads are far smaller even with resolved data
+pub const MAX_INTERACTION_BODY_BYTES: usize = 256 * 1024;
+
+// Text input of the rename modal
+const NAME_INPUT_ID: &str = "name";
+const VOTING_NAME_MAX_LENGTH: u16 = 100;
+
+// Text input of the comment modal
+const COMMENT_INPUT_ID: &str = "comment";
+const COMMENT_MAX_LENGTH: u16 = 300;
+// Comments listed with the results, the embed description holds at most 4096 characters
+const MAX_LISTED_COMMENTS: usize = 10;
+
+// Every weight unit is a separate ballot in DD, so the weight is kept small
+pub const MAX_ROLE_WEIGHT: u32 = 10;
+
+// Further questions of a survey, each of them is a separate voting in DD
+pub const MAX_QUESTIONS: usize = 4;
+pub const MAX_QUESTION_CHOICES: usize = 10;
+
+// Discord limit of the select option description
+pub const MAX_CHOICE_DESCRIPTION_LENGTH: usize = 100;
+
+// Announcements are edited at most this often with the vote count, to stay clear of rate limits
+const VOTE_COUNT_EDIT_INTERVAL: Duration = Duration::from_secs(5);
+
+// Most voting templates a single guild can store
+pub const MAX_GUILD_TEMPLATES: usize = 25;
+
+// Upper bound of the cached results of completed votings, see `AppState::cache_completed_results`
+const MAX_CACHED_RESULTS: usize = 100;
+
+// Upper bound of the remembered interaction ids, see `AppState::first_delivery`
+const MAX_SEEN_INTERACTIONS: usize = 10_000;
+
+// Discord limit of the button label
+pub const MAX_BUTTON_LABEL_LENGTH: usize = 80;
+
+// Longest emoji sequence accepted for the vote button, e.g. flags and skin tones
+const MAX_BUTTON_EMOJI_CHARS: usize = 8;
+
+// First retry of a failed dialog cleanup, doubled on every further attempt
+const CLEANUP_RETRY_BASE_SECS: u64 = 60;
+// A dialog message still failing after this many attempts is left as it is
+const MAX_CLEANUP_ATTEMPTS: u32 = 8;
+
+pub struct Config {
+    // How many not completed votings a single user can have at once
+    pub max_active_votings_per_creator: usize,
+    // Keep the buttons of closed votings visible but disabled, instead of removing them
+    pub keep_closed_components: bool,
+    // Dialogs which were not submitted within this many seconds are expired
+    pub dialog_max_age_secs: u64,
+    // Shared secret of the results endpoint, the endpoint rejects every request when not set
+    pub results_api_secret: Option<String>,
+    // Shared secret of the admin endpoints, they reject every request when not set
+    pub admin_api_secret: Option<String>,
+    // Voting command registered again by the admin register endpoint
+    pub voting_command: Option<VotingCommandRegistration>,
+    // Fewest choices a voting can have, never less than 2
+    pub min_choices: usize,
+    // Most choices a voting can have, only limited by the voting command options when not set
+    pub max_choices: Option<usize>,
+    // Where users can report failing interactions, added to internal error responses when set
+    pub support_url: Option<String>,
+    // Attach the `ResultsArtifact` json to the results of completed votings
+    pub attach_results_json: bool,
+    // Redelivered interactions within this many seconds are only acked, 0 disables the check
+    pub interaction_dedup_secs: u64,
+}
+
+// Everything needed to register the voting command, see `util::register_voting_command`
+pub struct VotingCommandRegistration {
+    pub bot_token: String,
+    pub register_url: String,
+    pub max_choices: usize,
+    pub localizations: HashMap<String, (String, String)>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            max_active_votings_per_creator: 10,
+            keep_closed_components: false,
+            dialog_max_age_secs: 7 * 24 * 60 * 60,
+            results_api_secret: None,
+            admin_api_secret: None,
+            voting_command: None,
+            min_choices: 2,
+            max_choices: None,
+            support_url: None,
+            attach_results_json: false,
+            interaction_dedup_secs: 0,
+        }
+    }
+}
+
 pub struct AppState {
     pub db: Db,
     pub discord_client: twilight_http::Client,
     pub dd_client: Client,
     pub discord_public_key: String,
     pub task_tracker: TaskTracker,
+    pub config: Config,
+    // Scheduled deadline completions as <votingID, (deadlineUnix, task)>, see `schedule_deadline`
+    scheduled_deadlines: Mutex<HashMap<String, (u64, AbortHandle)>>,
+    // Mutating interactions are rejected while set, toggled by the admin read-only endpoint
+    read_only: AtomicBool,
+    // User of the bot itself, fetched once at startup, see `fetch_bot_user_id`
+    bot_user_id: OnceLock<Id<UserMarker>>,
+    // Recently handled interactions, see `AppState::first_delivery`
+    seen_interactions: Mutex<SeenInteractions>,
+    // Throttled vote count edits of the announcements as <votingID, edit>, see `schedule_vote_count_edit`
+    vote_count_edits: Mutex<HashMap<String, VoteCountEdit>>,
+    // Results of completed votings as <votingID, results>, paged without querying DD again
+    completed_results: Mutex<HashMap<String, Arc<CompletedResults>>>,
+}
+
+// Results of a completed voting with the results of its further questions
+struct CompletedResults {
+    results: VotingResults,
+    // served by the results endpoint, see `handle_voting_results`
+    artifact: ResultsArtifact,
+    question_embeds: Vec<Embed>,
+    // shown on the first results page, see `voting_comments`
+    comments: Vec<(String, String)>,
+}
+
+#[derive(Default)]
+struct VoteCountEdit {
+    last_edit: Option<Instant>,
+    pending: bool,
+}
+
+// Interaction ids in the order they were seen, the deque drives the expiry of the set
+#[derive(Default)]
+struct SeenInteractions {
+    ids: HashSet<Id<InteractionMarker>>,
+    order: VecDeque<(Id<InteractionMarker>, Instant)>,
+}
+
+impl AppState {
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.load(Ordering::Relaxed)
+    }
+
+    pub fn set_read_only(&self, read_only: bool) {
+        self.read_only.store(read_only, Ordering::Relaxed);
+    }
+
+    // Only the first id is kept, the bot user does not change while running
+    pub fn set_bot_user_id(&self, user_id: Id<UserMarker>) {
+        let _ = self.bot_user_id.set(user_id);
+    }
+
+    fn is_bot_user(&self, user_id: Id<UserMarker>) -> bool {
+        self.bot_user_id.get() == Some(&user_id)
+    }
+
+    // Records the interaction, false when it was already delivered within the dedup window.
+    // The oldest ids are dropped early once `MAX_SEEN_INTERACTIONS` is reached.
+    fn first_delivery(&self, interaction_id: Id<InteractionMarker>) -> bool {
+        if self.config.interaction_dedup_secs == 0 {
+            return true;
+        }
+
+        let ttl = Duration::from_secs(self.config.interaction_dedup_secs);
+        let now = Instant::now();
+        let mut seen = self
+            .seen_interactions
+            .lock()
+            .expect("seen interactions lock poisoned");
+
+        while let Some(&(id, seen_at)) = seen.order.front() {
+            if now.duration_since(seen_at) < ttl && seen.order.len() < MAX_SEEN_INTERACTIONS {
+                break;
+            }
+            seen.order.pop_front();
+            seen.ids.remove(&id);
+        }
+
+        if !seen.ids.insert(interaction_id) {
+            return false;
+        }
+        seen.order.push_back((interaction_id, now));
+        true
+    }
+
+    fn cached_results(&self, voting_id: &str) -> Option<Arc<CompletedResults>> {
+        self.completed_results
+            .lock()
+            .expect("completed results lock poisoned")
+            .get(voting_id)
+            .cloned()
+    }
+
+    // Results of completed votings don't change, an arbitrary entry makes room once full
+    fn cache_completed_results(&self, voting_id: &str, results: Arc<CompletedResults>) {
+        let mut cached = self
+            .completed_results
+            .lock()
+            .expect("completed results lock poisoned");
+
+        if cached.len() >= MAX_CACHED_RESULTS && !cached.contains_key(voting_id) {
+            if let Some(evicted) = cached.keys().next().cloned() {
+                cached.remove(&evicted);
+            }
+        }
+        cached.insert(voting_id.to_string(), results);
+    }
+
+    // Ids of the votings whose deadline completion is scheduled and has not started yet
+    pub fn scheduled_deadlines(&self) -> Vec<String> {
+        let mut voting_ids: Vec<String> = self
+            .scheduled_deadlines
+            .lock()
+            .expect("scheduled deadlines lock poisoned")
+            .keys()
+            .cloned()
+            .collect();
+        voting_ids.sort();
+        voting_ids
+    }
+
+    // Aborts every scheduled deadline completion which has not started yet, e.g. on shutdown so
+    // the task tracker doesn't wait for them. They are scheduled again from the db on the next start.
+    pub fn cancel_all_deadlines(&self) -> usize {
+        let scheduled: Vec<(u64, AbortHandle)> = self
+            .scheduled_deadlines
+            .lock()
+            .expect("scheduled deadlines lock poisoned")
+            .drain()
+            .map(|(_, scheduled)| scheduled)
+            .collect();
+
+        for (_, task) in &scheduled {
+            task.abort();
+        }
+        scheduled.len()
+    }
+
+    // Aborts the scheduled deadline completion of the voting.
+    // Returns false when nothing was scheduled or the completion already started.
+    pub fn cancel_deadline(&self, voting_id: &str) -> bool {
+        let scheduled = self
+            .scheduled_deadlines
+            .lock()
+            .expect("scheduled deadlines lock poisoned")
+            .remove(voting_id);
+
+        match scheduled {
+            Some((_, task)) => {
+                task.abort();
+                true
+            }
+            None => false,
+        }
+    }
 }
 
 pub fn new_app_state(
@@ -43,6 +373,7 @@ pub fn new_app_state(
     discord_client: twilight_http::Client,
     dd_client: Client,
     discord_public_key: String,
+    config: Config,
 ) -> Arc<AppState> {
     Arc::new(AppState {
         db,
@@ -50,25 +381,343 @@ pub fn new_app_state(
         dd_client,
         discord_public_key,
         task_tracker: TaskTracker::new(),
+        config,
+        scheduled_deadlines: Mutex::new(HashMap::new()),
+        read_only: AtomicBool::new(false),
+        bot_user_id: OnceLock::new(),
+        seen_interactions: Mutex::new(SeenInteractions::default()),
+        vote_count_edits: Mutex::new(HashMap::new()),
+        completed_results: Mutex::new(HashMap::new()),
     })
 }
 
+// Remembers the user of the bot, so it is never sent a dm. Without it the guard is skipped.
+pub async fn fetch_bot_user_id(data: &Arc<AppState>) {
+    let user = match data.discord_client.current_user().await {
+        Ok(response) => response.model().await,
+        Err(err) => {
+            tracing::error!(error = ?err, "getting current user failed");
+            return;
+        }
+    };
+
+    match user {
+        Ok(user) => data.set_bot_user_id(user.id),
+        Err(err) => tracing::error!(error = ?err, "getting current user model failed"),
+    }
+}
+
 pub async fn handle_interaction(
     State(data): State<Arc<AppState>>,
     headers: HeaderMap,
     body: String,
 ) -> InteractionResult {
+    // the route body limit rejects these first, this keeps the handler safe on its own
+    if body.len() > MAX_INTERACTION_BODY_BYTES {
+        tracing::warn!(size = body.len(), "interaction body too large");
+        return Err(InteractionError::Status(StatusCode::PAYLOAD_TOO_LARGE));
+    }
+
+    // discord always posts json, anything else comes from a misconfigured proxy
+    if !is_json_content_type(&headers) {
+        tracing::warn!(content_type = ?headers.get(http::header::CONTENT_TYPE), "interaction body is not json");
+        return Err(InteractionError::Status(StatusCode::UNSUPPORTED_MEDIA_TYPE));
+    }
+
     let interaction: Interaction = serde_json::from_str(&body).map_err(|err| {
         tracing::error!(error = ?err, "parsing interaction from body failed");
         InteractionError::Status(StatusCode::BAD_REQUEST)
     })?;
 
+    // every log line of the interaction carries these fields, the voting id is recorded once resolved
+    let span = tracing::info_span!(
+        "interaction",
+        interaction_id = %interaction.id,
+        kind = ?interaction.kind,
+        voting_id = tracing::field::Empty,
+        outcome = tracing::field::Empty,
+        status = tracing::field::Empty,
+    );
+
+    async move {
+        let interaction_id = interaction.id;
+        let support_url = data.config.support_url.clone();
+        let result = dispatch_interaction(data, headers, body, interaction).await;
+
+        let (outcome, status) = match &result {
+            Ok((status, _)) => ("ok", *status),
+            Err(err) => ("err", err.into_response().status()),
+        };
+        let span = tracing::Span::current();
+        span.record("outcome", outcome);
+        span.record("status", status.as_u16());
+        tracing::info!("interaction handled");
+
+        // the user gets a reference to the failed interaction, which can be looked up in the logs
+        match result {
+            Err(InteractionError::InternalServerError) => {
+                let correlation_id = interaction_id.to_string();
+                tracing::error!(correlation_id, "interaction failed");
+                Ok((
+                    StatusCode::OK,
+                    internal_error_response(&correlation_id, support_url.as_deref()),
+                ))
+            }
+            result => result,
+        }
+    }
+    .instrument(span)
+    .await
+}
+
+// Parameters like the charset are ignored
+fn is_json_content_type(headers: &HeaderMap) -> bool {
+    headers
+        .get(http::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("application/json"))
+}
+
+// Compared in constant time, so the response time doesn't tell how much of the secret was guessed
+fn header_matches_secret(headers: &HeaderMap, header: &str, secret: &str) -> bool {
+    headers
+        .get(header)
+        .is_some_and(|value| bool::from(value.as_bytes().ct_eq(secret.as_bytes())))
+}
+
+// Header carrying `Config::results_api_secret` on results endpoint requests
+pub const RESULTS_SECRET_HEADER: &str = "x-results-secret";
+
+// Choices are ranked by the DD backend
+#[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq, Clone)]
+pub struct RankedChoice {
+    pub choice: String,
+    pub wins: i32,
+    pub percentage: f32,
+}
+
+// GET /votings/:id/results, current results of the voting for external integrations.
+// Completed votings are served the results they were published with while those are cached.
+pub async fn handle_voting_results(
+    State(data): State<Arc<AppState>>,
+    Path(voting_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<ResultsArtifact>, StatusCode> {
+    let authorized = match data.config.results_api_secret {
+        Some(ref secret) => header_matches_secret(&headers, RESULTS_SECRET_HEADER, secret),
+        None => false,
+    };
+    if !authorized {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    // private results are only for the creator, integrations don't get them either
+    let voting = match data.db.get_voting(&voting_id).await {
+        Ok(v) if !v.is_deleted && !v.private_results => v,
+        Ok(_) | Err(db::DbError::NotFound) => return Err(StatusCode::NOT_FOUND),
+        Err(err) => {
+            tracing::error!(%voting_id, error = ?err, "db get voting failed");
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    if voting.is_completed {
+        if let Some(completed) = data.cached_results(&voting_id) {
+            return Ok(Json(completed.artifact.clone()));
+        }
+    }
+
+    let results = fetch_voting_results(&data, &voting)
+        .await
+        .map_err(|err| match err {
+            InteractionError::VotingNotFound => StatusCode::NOT_FOUND,
+            InteractionError::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            _ => StatusCode::BAD_GATEWAY,
+        })?;
+
+    Ok(Json(ResultsArtifact::new(&voting, &results)))
+}
+
+fn ranked_choices(results: &VotingResults) -> Vec<RankedChoice> {
+    results
+        .results
+        .iter()
+        .map(|result| RankedChoice {
+            choice: result.choice.clone(),
+            wins: result.wins,
+            percentage: result.percentage,
+        })
+        .collect()
+}
+
+// Machine readable results of a voting, without anything about the voters.
+// Built once on completion, so attachments and integrations share the same shape.
+#[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq, Clone)]
+pub struct ResultsArtifact {
+    pub name: String,
+    // false for the current results of an open voting, see `handle_voting_results`
+    pub is_completed: bool,
+    pub choices: Vec<String>,
+    pub ranking: Vec<RankedChoice>,
+    pub duels: Vec<DuelResult>,
+    pub tie: bool,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq, Clone)]
+pub struct DuelResult {
+    pub left: String,
+    pub left_strength: i32,
+    pub right: String,
+    pub right_strength: i32,
+}
+
+impl ResultsArtifact {
+    pub fn new(voting: &Voting, results: &VotingResults) -> Self {
+        ResultsArtifact {
+            name: voting.name.clone(),
+            is_completed: voting.is_completed,
+            choices: voting.choices.clone(),
+            ranking: ranked_choices(results),
+            duels: results
+                .duels
+                .iter()
+                .flatten()
+                .map(|duel| DuelResult {
+                    left: duel.left.choice.clone(),
+                    left_strength: duel.left.strength,
+                    right: duel.right.choice.clone(),
+                    right_strength: duel.right.strength,
+                })
+                .collect(),
+            tie: results.tie,
+        }
+    }
+}
+
+// Name of the `ResultsArtifact` attachment of completed votings
+pub const RESULTS_ARTIFACT_FILENAME: &str = "results.json";
+
+// Header carrying `Config::admin_api_secret` on admin endpoint requests
+pub const ADMIN_SECRET_HEADER: &str = "x-admin-secret";
+
+// Body of the admin register endpoint
+#[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq)]
+pub struct RegisterResponse {
+    pub discord_status: u16,
+}
+
+// POST /admin/register, registers the voting command again, e.g. after changing its localizations
+pub async fn handle_admin_register(
+    State(data): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<RegisterResponse>, StatusCode> {
+    if !is_admin_authorized(&data, &headers) {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    let Some(ref command) = data.config.voting_command else {
+        tracing::error!("voting command registration is not configured");
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    };
+
+    let status = util::try_register_voting_command(
+        &command.bot_token,
+        &command.register_url,
+        command.max_choices,
+        &command.localizations,
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(error = ?err, "registering voting command failed");
+        StatusCode::BAD_GATEWAY
+    })?;
+
+    tracing::info!(%status, "voting command registered again");
+
+    Ok(Json(RegisterResponse {
+        discord_status: status.as_u16(),
+    }))
+}
+
+fn is_admin_authorized(data: &AppState, headers: &HeaderMap) -> bool {
+    match data.config.admin_api_secret {
+        Some(ref secret) => header_matches_secret(headers, ADMIN_SECRET_HEADER, secret),
+        None => false,
+    }
+}
+
+// Body of the admin read-only endpoint, both for the request and the response
+#[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq)]
+pub struct ReadOnlyMode {
+    pub read_only: bool,
+}
+
+// POST /admin/read-only, rejects mutating interactions during maintenance, e.g. a backup.
+// The background writers pause as well, scheduled deadlines complete once it is turned off.
+pub async fn handle_admin_read_only(
+    State(data): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(mode): Json<ReadOnlyMode>,
+) -> Result<Json<ReadOnlyMode>, StatusCode> {
+    if !is_admin_authorized(&data, &headers) {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    data.set_read_only(mode.read_only);
+    tracing::info!(read_only = mode.read_only, "read-only mode changed");
+
+    Ok(Json(ReadOnlyMode {
+        read_only: data.is_read_only(),
+    }))
+}
+
+// A pending completion is retried once it is this old, see `retry_pending_completions`
+pub const PENDING_COMPLETION_RETRY_SECS: u64 = 5 * 60;
+
+const READ_ONLY_MESSAGE: &str = "The bot is in maintenance mode, please try again shortly.";
+
+// How often a deadline which passed in read-only mode checks whether it can complete
+const READ_ONLY_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+// Commands which only show something, everything else is rejected in read-only mode
+fn is_read_only_command(name: &str) -> bool {
+    matches!(name, "ping" | "my-votes" | "votings" | "voting-debug")
+}
+
+// Actions which only show something, everything else is rejected in read-only mode,
+// so a new action is not let through by accident
+fn is_read_only_action(action: &Action) -> bool {
+    matches!(
+        action,
+        Action::VoteNext
+            | Action::VotePrevious
+            | Action::ResultNext
+            | Action::ResultPrevious
+            | Action::ViewAudit
+            | Action::DownloadBallots
+    )
+}
+
+async fn dispatch_interaction(
+    data: Arc<AppState>,
+    headers: HeaderMap,
+    body: String,
+    interaction: Interaction,
+) -> InteractionResult {
     tracing::debug!(?interaction, "received interaction");
     util::verify_signature(headers, body, &data.discord_public_key).map_err(|err| {
         tracing::error!(error = ?err,"verifying signature failed");
         InteractionError::Status(StatusCode::UNAUTHORIZED)
     })?;
 
+    // discord retries deliveries, the handlers are not idempotent so a retry is only acked.
+    // This runs after the signature check, unsigned requests can't claim interaction ids.
+    if interaction.kind != InteractionType::Ping && !data.first_delivery(interaction.id) {
+        tracing::info!("duplicate interaction delivery");
+        return ack_response();
+    }
+
     match interaction.kind {
         // this is a ping sent by discord
         InteractionType::Ping => Ok((
@@ -85,8 +734,19 @@ pub async fn handle_interaction(
                 return Err(InteractionError::InternalServerError);
             };
 
+            if data.is_read_only() && !is_read_only_command(&command.name) {
+                return Ok((StatusCode::OK, ephemeral_response(READ_ONLY_MESSAGE)));
+            }
+
             match command.name.as_str() {
-                "ping" => handle_ping(),
+                "ping" => handle_ping(&data).await,
+                "my-votes" => handle_my_votes(&data, &interaction).await,
+                "voting-transfer" => handle_voting_transfer(&data, command, &interaction).await,
+                "voting-clone" => handle_voting_clone(&data, command, &interaction).await,
+                "votings" => handle_votings(&data, &interaction).await,
+                "voting-debug" => handle_voting_debug(&data, command, &interaction).await,
+                "voting-config" => handle_voting_config(&data, command, &interaction).await,
+                "voting-template" => handle_voting_template(&data, command, &interaction).await,
                 "voting" => handle_slash_voting(&data, command, &interaction).await,
                 _ => {
                     tracing::error!(data = ?interaction.data, "Application command not handled");
@@ -101,30 +761,114 @@ pub async fn handle_interaction(
                 return Err(InteractionError::InternalServerError);
             };
 
-            let Ok(custom_id) = data.db.get_custom_id(&command.custom_id).await else {
+            // listing pages have no voting behind their custom id
+            if let Some(custom_uuid) = command.custom_id.strip_prefix(VOTINGS_LISTING_PREFIX) {
+                return handle_votings_page(&data, &interaction, custom_uuid).await;
+            }
+
+            let Ok((custom_id, voting)) =
+                data.db.get_custom_id_with_voting(&command.custom_id).await
+            else {
                 // this can happen with lingering dialogs while completing or deleting voting
                 tracing::info!(data = ?interaction.data, "received interaction with unknown custom id");
                 return ack_response();
             };
 
+            tracing::Span::current().record("voting_id", custom_id.voting_id.as_str());
+
             match &custom_id.action {
+                action if data.is_read_only() && !is_read_only_action(action) => {
+                    Ok((StatusCode::OK, ephemeral_response(READ_ONLY_MESSAGE)))
+                }
                 Action::VoteFromChannel => {
-                    handle_vote_channel(&data, &interaction, &custom_id.voting_id).await
+                    handle_vote_channel(&data, &interaction, voting, &custom_id.voting_id).await
                 }
                 Action::VoteFromDM => {
-                    handle_dm_vote(&data, &interaction, &custom_id.voting_id).await
+                    handle_dm_vote(&data, &interaction, voting, &custom_id.voting_id).await
                 }
                 Action::VoteSelect => {
-                    handle_vote_select(&data, &interaction, command, &custom_id).await
+                    handle_vote_select(&data, &interaction, voting, command, &custom_id).await
+                }
+                Action::VoteNext => handle_vote_page(data, &interaction, voting, &custom_id).await,
+                Action::VotePrevious => {
+                    handle_vote_page(data, &interaction, voting, &custom_id).await
                 }
-                Action::VoteNext => handle_vote_page(data, &interaction, &custom_id).await,
-                Action::VotePrevious => handle_vote_page(data, &interaction, &custom_id).await,
                 Action::Complete => {
                     handle_complete_voting(&data, &interaction, &custom_id.voting_id).await
                 }
                 Action::Delete => {
                     handle_delete_voting(&data, &interaction, &custom_id.voting_id).await
                 }
+                Action::UndoVote => {
+                    handle_undo_vote(&data, &interaction, voting, &custom_id.voting_id).await
+                }
+                Action::ResultNext => {
+                    handle_result_page(&data, &interaction, voting, &custom_id).await
+                }
+                Action::ResultPrevious => {
+                    handle_result_page(&data, &interaction, voting, &custom_id).await
+                }
+                Action::SortChoices => {
+                    handle_sort_choices(&data, &interaction, voting, &custom_id.voting_id).await
+                }
+                Action::ViewAudit => handle_view_audit(&data, &interaction, voting).await,
+                Action::ExtendDeadline | Action::ClearDeadline => {
+                    handle_set_deadline(&data, &interaction, voting, &custom_id.action).await
+                }
+                Action::RemindNonVoters => {
+                    handle_remind_non_voters(&data, &interaction, voting).await
+                }
+                Action::EditName => {
+                    handle_edit_name(&interaction, voting, &command.custom_id).await
+                }
+                Action::AddComment => {
+                    handle_add_comment(&data, &interaction, voting, &command.custom_id).await
+                }
+                Action::DownloadBallots => {
+                    handle_download_ballots(&data, &interaction, voting).await
+                }
+                Action::Refresh => handle_refresh(&data, &interaction, voting).await,
+                Action::LockDialogs => handle_lock_dialogs(&data, &interaction, voting).await,
+                Action::ResetBallot => {
+                    handle_reset_ballot(data, &interaction, voting, &custom_id).await
+                }
+                Action::NextQuestion | Action::PreviousQuestion => {
+                    handle_question_page(data, &interaction, voting, &custom_id).await
+                }
+            }
+        }
+
+        InteractionType::ModalSubmit => {
+            let Some(InteractionData::ModalSubmit(modal)) = &interaction.data else {
+                tracing::error!(data = ?interaction.data, "modal submit data not found");
+                return Err(InteractionError::InternalServerError);
+            };
+
+            // modals reuse the custom id of the component which opened them
+            let Ok((custom_id, voting)) = data.db.get_custom_id_with_voting(&modal.custom_id).await
+            else {
+                tracing::info!(data = ?interaction.data, "received modal with unknown custom id");
+                return ack_response();
+            };
+
+            tracing::Span::current().record("voting_id", custom_id.voting_id.as_str());
+
+            // both modals save what was entered
+            if data.is_read_only() {
+                return Ok((StatusCode::OK, ephemeral_response(READ_ONLY_MESSAGE)));
+            }
+
+            match &custom_id.action {
+                Action::EditName => {
+                    handle_edit_name_submit(&data, &interaction, voting, modal).await
+                }
+                Action::AddComment => {
+                    handle_add_comment_submit(&data, &interaction, voting, modal).await
+                }
+                _ => {
+                    tracing::error!(data = ?interaction.data, action = ?custom_id.action, "modal action not handled");
+                    Err(InteractionError::InternalServerError)
+                }
             }
         }
 
@@ -138,6 +882,7 @@ pub async fn handle_interaction(
 async fn handle_vote_page(
     data: Arc<AppState>,
     interaction: &Interaction,
+    voting: Voting,
     custom_id: &CustomID,
 ) -> InteractionResult {
     let voting_id = &custom_id.voting_id;
@@ -146,27 +891,23 @@ async fn handle_vote_page(
         return Err(InteractionError::InternalServerError);
     };
 
-    let voting = data.db.get_voting(voting_id).await.map_err(|err| {
-        tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "db get voting failed");
-        InteractionError::InternalServerError
-    })?;
-
     // this can happen with lingering dialogs while completing or deleting voting
-    if voting.is_deleted || voting.is_completed {
+    let VotingState::Open(_) = VotingState::from(voting) else {
         return ack_response();
-    }
+    };
 
-    let Some(ref user) = interaction.user else {
+    let Some(user) = interaction.author() else {
         tracing::error!(%voting_id, data = ?interaction.data, "interaction user not found");
         return Err(InteractionError::InternalServerError);
     };
 
-    let voting_dialog = match data
+    // the voting and the ballot are read together, a concurrent select can't tear the page
+    let (mut voting, voting_dialog) = match data
         .db
-        .get_voting_dialog(voting_id, &user.id.to_string())
+        .get_dialog_render_state(voting_id, &user.id.to_string())
         .await
     {
-        Ok(v) => v,
+        Ok(state) => state,
         Err(db::DbError::NotFound) => {
             return ack_response();
         }
@@ -176,8 +917,28 @@ async fn handle_vote_page(
         }
     };
 
-    let (title, components, custom_ids) =
-        create_vote_components(voting_id, voting, page, voting_dialog.ballot);
+    let VotingState::Open(voting) = VotingState::from(voting) else {
+        return ack_response();
+    };
+
+    let question = voting_dialog.question;
+    let Some(ballot) = voting_dialog.question_ballot(question).cloned() else {
+        tracing::error!(%voting_id, question, data = ?interaction.data, "question ballot not found");
+        return Err(InteractionError::InternalServerError);
+    };
+
+    if voting_dialog.display_order.is_some() {
+        voting.display_order = voting_dialog.display_order;
+    }
+
+    let (title, components, custom_ids) = create_vote_components(
+        voting_id,
+        voting,
+        question,
+        page,
+        ballot,
+        &user.id.to_string(),
+    );
     data.db
         .bulk_save_custom_ids(custom_ids)
         .await
@@ -186,126 +947,192 @@ async fn handle_vote_page(
             InteractionError::InternalServerError
         })?;
 
-    let Some(ref channel) = interaction.channel else {
-        tracing::error!(%voting_id, data = ?interaction.data, "interaction channel not found");
-        return Err(InteractionError::InternalServerError);
+    update_dialog(&data, interaction, None, &title, &components).await
+}
+
+// Unranks every choice of the dialog and shows its first page again
+async fn handle_reset_ballot(
+    data: Arc<AppState>,
+    interaction: &Interaction,
+    voting: Voting,
+    custom_id: &CustomID,
+) -> InteractionResult {
+    let voting_id = &custom_id.voting_id;
+
+    // this can happen with lingering dialogs while completing or deleting voting
+    let VotingState::Open(voting) = VotingState::from(voting) else {
+        return ack_response();
     };
 
-    let Some(ref message) = interaction.message else {
-        tracing::error!(%voting_id, data = ?interaction.data, "interaction message not found");
+    let Some(user) = interaction.author() else {
+        tracing::error!(%voting_id, data = ?interaction.data, "interaction user not found");
         return Err(InteractionError::InternalServerError);
     };
 
-    update_message(
-        &data.discord_client,
-        channel.id,
-        message.id,
-        None,
-        Some(&title),
-        Some(&components),
-    )
-    .await?;
-
-    ack_response()
-}
-
-async fn handle_complete_voting(
-    data: &Arc<AppState>,
-    interaction: &Interaction,
-    voting_id: &str,
-) -> InteractionResult {
-    let results = data
-        .dd_client
-        .get_voting_results_duels(voting_id)
+    match data
+        .db
+        .reset_dialog_ballot(voting_id, &user.id.to_string())
         .await
-        .map_err(|err| {
-            tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "getting voting results duels failed");
-            InteractionError::InternalServerError
-        })?;
-
-    let voting = match data.db.complete_voting(voting_id).await {
-        Ok(v) => v,
-        Err(db::DbError::NotFound) => {
-            // this can happen during delete
-            return ack_response();
-        }
+    {
+        Ok(()) => {}
+        Err(db::DbError::NotFound) => return ack_response(),
         Err(err) => {
-            tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "completing voting in db failed");
+            tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "resetting ballot in db failed");
             return Err(InteractionError::InternalServerError);
         }
-    };
+    }
 
-    let description = if results.tie {
-        "Its a tie!"
-    } else {
-        "Voting results were calculated using Shultze method. The users are ranked by winning percentages."
+    let first_page = CustomID {
+        page: Some(1),
+        ..custom_id.clone()
     };
+    handle_vote_page(data, interaction, voting, &first_page).await
+}
 
-    let mut fields = Vec::new();
-    for result in &results.results {
-        let field_text = format!(
-            "Wins: {}, Percentage: {:.2}%",
-            result.wins, result.percentage
-        );
-        fields.push(EmbedFieldBuilder::new(&result.choice, field_text).build());
-    }
+// Moves the dialog of a survey voting to the next or the previous question and shows its first
+// page. The ranking of the current question has to be valid before moving on.
+async fn handle_question_page(
+    data: Arc<AppState>,
+    interaction: &Interaction,
+    voting: Voting,
+    custom_id: &CustomID,
+) -> InteractionResult {
+    let voting_id = &custom_id.voting_id;
 
-    let mut result_embed = EmbedBuilder::new()
-        .title(voting.name.clone())
-        .description(description);
+    // this can happen with lingering dialogs while completing or deleting voting
+    let VotingState::Open(voting) = VotingState::from(voting) else {
+        return ack_response();
+    };
 
-    for field in fields {
-        result_embed = result_embed.field(field);
-    }
+    let Some(user) = interaction.author() else {
+        tracing::error!(%voting_id, data = ?interaction.data, "interaction user not found");
+        return Err(InteractionError::InternalServerError);
+    };
 
-    let mut result_embeds = vec![result_embed.build()];
+    let voting_dialog = match data
+        .db
+        .get_voting_dialog(voting_id, &user.id.to_string())
+        .await
+    {
+        Ok(v) => v,
+        Err(db::DbError::NotFound) => return ack_response(),
+        Err(err) => {
+            tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "getting voting dialog from db failed");
+            return Err(InteractionError::InternalServerError);
+        }
+    };
 
-    if let Some(duels) = results.duels {
-        if !duels.is_empty() && !results.tie {
-            let mut duels_fields = Vec::new();
-            for duel in duels {
-                let message;
-                if duel.left.strength == duel.right.strength {
-                    message = format!(
-                        "**{}** and **{}** are tied",
-                        duel.left.choice, duel.right.choice
-                    );
-                } else {
-                    let left;
-                    let right;
-                    if duel.left.strength > duel.right.strength {
-                        left = duel.left;
-                        right = duel.right;
-                    } else {
-                        left = duel.right;
-                        right = duel.left;
-                    }
+    if voting_dialog.submitted {
+        return ack_response();
+    }
 
-                    message = format!(
-                        "**{}** defeats **{}** by ({} - {}) = {} votes",
-                        left.choice,
-                        right.choice,
-                        left.strength,
-                        right.strength,
-                        left.strength - right.strength
-                    );
+    let question = voting_dialog.question;
+    let target = match custom_id.action {
+        Action::NextQuestion => {
+            let ballot = voting_dialog
+                .question_ballot(question)
+                .cloned()
+                .unwrap_or_default();
+            if let Some(view) = voting.question_view(question) {
+                if let Err(err) = validate_ballot(&view, &ballot) {
+                    return Ok((StatusCode::OK, ephemeral_response(&err)));
                 }
-                duels_fields.push(EmbedFieldBuilder::new("", &message).build());
             }
+            question + 1
+        }
+        _ if question == 0 => return ack_response(),
+        _ => question - 1,
+    };
 
-            let mut duels_embed = EmbedBuilder::new().title("Result breakdown");
+    // a stale button of a survey whose questions changed
+    let Some(view) = voting.question_view(target) else {
+        return ack_response();
+    };
 
-            for field in duels_fields {
-                duels_embed = duels_embed.field(field);
-            }
+    data.db
+        .set_dialog_question(voting_id, &user.id.to_string(), target, view.choices.len())
+        .await
+        .map_err(|err| {
+            tracing::error!(%voting_id, question = target, error = ?err, data = ?interaction.data, "setting dialog question in db failed");
+            InteractionError::InternalServerError
+        })?;
 
-            result_embeds.push(duels_embed.build());
-        }
+    let first_page = CustomID {
+        page: Some(1),
+        ..custom_id.clone()
+    };
+    handle_vote_page(data, interaction, voting, &first_page).await
+}
+
+async fn handle_result_page(
+    data: &Arc<AppState>,
+    interaction: &Interaction,
+    voting: Voting,
+    custom_id: &CustomID,
+) -> InteractionResult {
+    let voting_id = &custom_id.voting_id;
+    let Some(page) = custom_id.page else {
+        tracing::error!(%voting_id, data = ?interaction.data, "page not found");
+        return Err(InteractionError::InternalServerError);
+    };
+
+    if voting.is_deleted || !voting.is_completed {
+        return ack_response();
     }
 
+    let completed = match data.cached_results(voting_id) {
+        Some(completed) => completed,
+        None => {
+            let results = fetch_voting_results(data, &voting).await?;
+            let completed = Arc::new(CompletedResults {
+                artifact: ResultsArtifact::new(&voting, &results),
+                results,
+                question_embeds: create_question_result_embeds(data, &voting).await,
+                comments: voting_comments(data, &voting).await,
+            });
+            data.cache_completed_results(voting_id, completed.clone());
+            completed
+        }
+    };
+
+    let existing_custom_ids = data.db.get_custom_ids(voting_id).await.map_err(|err| {
+        tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "getting custom ids from db failed");
+        InteractionError::InternalServerError
+    })?;
+    let page_ids = ResultPageIds::from_custom_ids(&existing_custom_ids);
+
+    let (mut embeds, components, custom_ids) = create_results_components(
+        voting_id,
+        &voting,
+        &completed.results,
+        &completed.comments,
+        page,
+        &page_ids,
+    );
+    embeds.extend(completed.question_embeds.iter().cloned());
+    data.db
+        .bulk_save_custom_ids(custom_ids)
+        .await
+        .map_err(|err| {
+            tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "bulk saving custom ids into db failed");
+            InteractionError::InternalServerError
+        })?;
+
+    // private results are paged in the creator dm, below the closed creator buttons
+    let (message_id, channel_id, components) = if voting.private_results {
+        let creator_components =
+            closed_components(data, create_creator_components(&voting, &mut Vec::new()));
+        (
+            &voting.creator_message_id,
+            &voting.creator_dm_channel_id,
+            [components, creator_components].concat(),
+        )
+    } else {
+        (&voting.message_id, &voting.channel_id, components)
+    };
+
     let message_id = Id::new(
-        voting
-            .message_id
+        message_id
             .parse::<u64>()
             .map_err(|err| {
                 tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "parsing message id failed");
@@ -314,8 +1141,7 @@ async fn handle_complete_voting(
     );
 
     let channel_id = Id::new(
-        voting
-            .channel_id
+        channel_id
             .parse::<u64>()
             .map_err(|err| {
                 tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "parsing channel id failed");
@@ -328,752 +1154,5532 @@ async fn handle_complete_voting(
         channel_id,
         message_id,
         Some("Voting completed!"),
-        Some(&result_embeds),
-        Some(&Vec::new()),
+        Some(&embeds),
+        Some(&components),
     )
     .await?;
 
-    // update dm creator to "voting completed"
-    let creator_dm_channel_id = Id::new(
-        voting
-            .creator_dm_channel_id
-            .parse::<u64>()
-            .map_err(|err| {
-                tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "parsing dm channel id failed");
-                InteractionError::InternalServerError
-            })?
-
-    );
-    let creator_message_id = Id::new(
-        voting
-            .creator_message_id
-            .parse::<u64>()
-            .map_err(|err| {
-                tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "parsing creator message id failed");
-                InteractionError::InternalServerError
-            })?
-    );
+    ack_response()
+}
 
-    update_message(
-        &data.discord_client,
-        creator_dm_channel_id,
-        creator_message_id,
-        Some("Voting completed!"),
-        Some(&Vec::new()),
-        Some(&Vec::new()),
-    )
-    .await?;
+async fn handle_complete_voting(
+    data: &Arc<AppState>,
+    interaction: &Interaction,
+    voting_id: &str,
+) -> InteractionResult {
+    let Some(user_id) = interaction.author_id() else {
+        tracing::error!(%voting_id, data = ?interaction.data, "interaction user not found");
+        return Err(InteractionError::InternalServerError);
+    };
 
-    let data_clone = data.clone();
-    spawn_clean_voting_dialogs(voting, data_clone, "Voting completed".to_string());
+    complete_voting(data, voting_id, &user_id.to_string()).await?;
 
     ack_response()
 }
 
-async fn handle_delete_voting(
+// Completes the open voting and publishes its results. Returns `None` when there was nothing to
+// complete, the results are also cached for the results pages and `handle_voting_results`.
+async fn complete_voting(
     data: &Arc<AppState>,
-    interaction: &Interaction,
     voting_id: &str,
-) -> InteractionResult {
-    let voting = match data.db.delete_voting(voting_id).await {
+    user_id: &str,
+) -> Result<Option<ResultsArtifact>, InteractionError> {
+    // only the click which completes the voting fetches and publishes the results,
+    // e.g. a double click on the complete button does nothing the second time
+    let voting = match data.db.complete_open_voting(voting_id, user_id).await {
         Ok(v) => v,
         Err(db::DbError::NotFound) => {
-            // handle double click or complete already in progress
-            return ack_response();
+            // this can happen during delete
+            return Ok(None);
+        }
+        Err(db::DbError::AlreadyCompleted) => {
+            tracing::info!(%voting_id, "voting is already completed");
+            return Ok(None);
         }
         Err(err) => {
-            tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "deleting voting from db failed");
+            tracing::error!(%voting_id, error = ?err, "completing voting in db failed");
             return Err(InteractionError::InternalServerError);
         }
     };
 
-    let message_id = Id::new(
-        voting
-            .message_id
-            .parse::<u64>()
-            .map_err(|err| {
-                tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "parsing message id failed");
-                InteractionError::InternalServerError
-            })?
-    );
-    let channel_id = Id::new(
-        voting
-            .channel_id
-            .parse::<u64>()
-            .map_err(|err| {
-                tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "parsing channel id failed");
-                InteractionError::InternalServerError
-            })?
-    );
+    // the pending completion of the voting is retried when this fails, see
+    // `retry_pending_completions`
+    let results = fetch_voting_results(data, &voting).await?;
 
-    update_message(
-        &data.discord_client,
-        channel_id,
-        message_id,
-        Some(format!("Voting deleted: {}", voting.name).as_str()),
-        Some(&Vec::new()),
-        Some(&Vec::new()),
-    )
-    .await?;
+    publish_completed_voting(data, voting, results)
+        .await
+        .map(Some)
+}
 
-    let creator_dm_channel_id = Id::new(
-        voting
-            .creator_dm_channel_id
-            .parse::<u64>()
-            .map_err(|err| {
-                tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "parsing dm channel id failed");
-                InteractionError::InternalServerError
-            })?
-    );
-    let creator_message_id = Id::new(
-        voting
-            .creator_message_id
-            .parse::<u64>()
+// Simple votings are tallied from the announcement reactions, the rest by the DD backend
+async fn fetch_voting_results(
+    data: &Arc<AppState>,
+    voting: &Voting,
+) -> Result<VotingResults, InteractionError> {
+    let voting_id = voting.id.as_str();
+
+    if !voting.simple {
+        return data
+            .dd_client
+            .get_voting_results_duels(voting_id)
+            .await
             .map_err(|err| {
-                tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "parsing creator message id failed");
-                InteractionError::InternalServerError
-            })?
-    );
+                tracing::error!(%voting_id, error = ?err, "getting voting results duels failed");
+                InteractionError::from(err)
+            });
+    }
 
-    update_message(
-        &data.discord_client,
-        creator_dm_channel_id,
-        creator_message_id,
-        Some(format!("Voting deleted: {}", voting.name).as_str()),
-        Some(&Vec::new()),
-        Some(&Vec::new()),
-    )
-    .await?;
+    let message_id = Id::new(voting.message_id.parse::<u64>().map_err(|err| {
+        tracing::error!(%voting_id, error = ?err, "parsing message id failed");
+        InteractionError::InternalServerError
+    })?);
 
-    let data_clone = data.clone();
-    spawn_clean_voting_dialogs(voting, data_clone, "Voting deleted".to_string());
+    let channel_id = Id::new(voting.channel_id.parse::<u64>().map_err(|err| {
+        tracing::error!(%voting_id, error = ?err, "parsing channel id failed");
+        InteractionError::InternalServerError
+    })?);
 
-    ack_response()
+    let message = data
+        .discord_client
+        .message(channel_id, message_id)
+        .await
+        .map_err(|err| {
+            tracing::error!(%voting_id, error = ?err, "getting announcement message failed");
+            InteractionError::InternalServerError
+        })?
+        .model()
+        .await
+        .map_err(|err| {
+            tracing::error!(%voting_id, error = ?err, "getting announcement message model failed");
+            InteractionError::InternalServerError
+        })?;
+
+    Ok(tally_reactions(voting, &message))
 }
 
-fn spawn_clean_voting_dialogs(voting: Voting, data_clone: Arc<AppState>, message: String) {
-    let data = data_clone.clone();
-    data.task_tracker.spawn(async move {
-        if let Ok(dialogs) = data_clone.db.get_voting_dialogs(voting.id.as_str()).await {
-            for dialog in dialogs {
-                let Ok(dm_channel_id) = dialog.channel_id.parse::<u64>() else {
-                    tracing::error!(%voting.id, "parsing dm channel id failed");
-                    continue;
-                };
+// Counts the choice reactions on the announcement, leaving out the ones added by the bot
+fn tally_reactions(voting: &Voting, message: &Message) -> VotingResults {
+    let counts: Vec<u64> = (0..voting.choices.len())
+        .map(|i| {
+            let emoji = choice_emoji(i);
+            message
+                .reactions
+                .iter()
+                .find(|reaction| {
+                    matches!(&reaction.emoji, ReactionType::Unicode { name } if *name == emoji)
+                })
+                .map(|reaction| reaction.count - u64::from(reaction.me))
+                .unwrap_or(0)
+        })
+        .collect();
 
-                let Ok(message_id) = dialog.message_id.parse::<u64>() else {
-                    tracing::error!(%voting.id, "parsing message id failed");
-                    continue;
-                };
+    let total: u64 = counts.iter().sum();
+    let mut results: Vec<VotingResult> = voting
+        .choices
+        .iter()
+        .zip(&counts)
+        .enumerate()
+        .map(|(i, (choice, &count))| VotingResult {
+            choice: choice.clone(),
+            index: i as i32,
+            wins: count as i32,
+            percentage: if total == 0 {
+                0.0
+            } else {
+                count as f32 * 100.0 / total as f32
+            },
+            strength: count as usize,
+            advantage: 0,
+        })
+        .collect();
+    results.sort_by_key(|result| std::cmp::Reverse(result.wins));
 
-                if let Err(err) = update_message(
-                    &data_clone.discord_client,
-                    Id::new(dm_channel_id),
-                    Id::new(message_id),
-                    Some(format!("{}: {}", message, voting.name).as_str()),
-                    Some(&Vec::new()),
-                    Some(&Vec::new()),
-                )
-                .await
-                {
-                    tracing::error!(error = ?err, "updating message failed");
-                    continue;
-                }
+    let tie = results.len() > 1 && results[0].wins == results[1].wins;
 
-                if let Err(err) = data_clone
-                    .db
-                    .delete_voting_dialog(&dialog.voting_id, &dialog.user_id)
-                    .await
-                {
-                    tracing::error!(error = ?err, "deleting voting dialog from db failed")
-                }
+    VotingResults {
+        tie,
+        results,
+        duels: None,
+    }
+}
+
+// Reaction emoji of the choice in a simple voting, regional indicators 🇦, 🇧, ...
+pub fn choice_emoji(index: usize) -> String {
+    char::from_u32(0x1F1E6 + index as u32)
+        .map(String::from)
+        .unwrap_or_default()
+}
+
+// Replays completions interrupted between the db write and the discord edits, e.g. by a crash.
+// Should be called on startup, before serving interactions. A completion stays pending until
+// its results are published, a failed replay is retried on the next call.
+pub async fn reconcile_pending_completions(data: &Arc<AppState>) {
+    match data.db.get_pending_completions().await {
+        Ok(voting_ids) => replay_pending_completions(data, voting_ids).await,
+        Err(err) => tracing::error!(error = ?err, "getting pending completions from db failed"),
+    }
+}
+
+// Replays the completions which failed while the bot is running, e.g. the DD backend was down
+// when the results were fetched. Should be called periodically, it does nothing in read-only mode.
+// Completions younger than `PENDING_COMPLETION_RETRY_SECS` may still be publishing, they are skipped.
+pub async fn retry_pending_completions(data: &Arc<AppState>) {
+    if data.is_read_only() {
+        return;
+    }
+
+    let started_before = util::unix_timestamp().saturating_sub(PENDING_COMPLETION_RETRY_SECS);
+    match data.db.get_pending_completions_before(started_before).await {
+        Ok(voting_ids) => replay_pending_completions(data, voting_ids).await,
+        Err(err) => tracing::error!(error = ?err, "getting pending completions from db failed"),
+    }
+}
+
+async fn replay_pending_completions(data: &Arc<AppState>, voting_ids: Vec<String>) {
+    for voting_id in voting_ids {
+        tracing::info!(%voting_id, "replaying pending voting completion");
+
+        let voting = match data.db.get_voting(&voting_id).await {
+            Ok(v) => v,
+            Err(err) => {
+                tracing::error!(%voting_id, error = ?err, "db get voting failed");
+                continue;
+            }
+        };
+
+        // a deleted voting has no messages left to publish the results in
+        if voting.is_deleted {
+            if let Err(err) = data.db.delete_pending_completion(&voting_id).await {
+                tracing::error!(%voting_id, error = ?err, "deleting pending completion from db failed");
             }
+            continue;
         }
 
-        if let Err(err) = data_clone.db.delete_custom_ids(&voting.id).await {
-            tracing::debug!("deleting custom ids from db failed: {:?}", err);
+        let results = match fetch_voting_results(data, &voting).await {
+            Ok(results) => results,
+            Err(err) => {
+                tracing::error!(%voting_id, error = ?err, "fetching voting results failed");
+                continue;
+            }
+        };
+
+        if let Err(err) = publish_completed_voting(data, voting, results).await {
+            tracing::error!(%voting_id, error = ?err, "publishing completed voting failed");
         }
-    });
+    }
 }
 
-async fn handle_dm_vote(
-    data: &Arc<AppState>,
-    interaction: &Interaction,
-    voting_id: &str,
-) -> InteractionResult {
-    let Some(ref user_id) = interaction.user else {
-        tracing::error!(%voting_id, data = ?interaction.data, "user id not found");
-        return Err(InteractionError::InternalServerError);
-    };
-
-    let voting = data.db.get_voting(voting_id).await.map_err(|err| {
-        tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "db get voting failed");
-        InteractionError::InternalServerError
-    })?;
-
-    // this can happen with lingering dialogs while completing or deleting voting
-    if voting.is_deleted || voting.is_completed {
-        return ack_response();
+// Deletes the /votings page buttons which expired, should be called periodically.
+// Nothing is deleted in read-only mode.
+pub async fn expire_votings_listings(data: &Arc<AppState>) {
+    if data.is_read_only() {
+        return;
     }
 
-    let voting_dialog = match data
+    match data
         .db
-        .get_voting_dialog(voting_id, &user_id.id.to_string())
+        .delete_expired_votings_listings(util::unix_timestamp())
         .await
     {
-        Ok(v) => v,
-        Err(db::DbError::NotFound) => {
-            return ack_response();
-        }
+        Ok(0) => {}
+        Ok(deleted) => tracing::info!(deleted, "deleted expired votings listings"),
+        Err(err) => tracing::error!(error = ?err, "deleting expired votings listings failed"),
+    }
+}
+
+// Closes the dialogs which were opened but not submitted within `Config::dialog_max_age_secs`,
+// so abandoned dialogs do not pile up until the voting is closed. Should be called periodically,
+// it does nothing in read-only mode.
+pub async fn expire_stale_dialogs(data: &Arc<AppState>) {
+    if data.is_read_only() {
+        return;
+    }
+
+    let older_than = util::unix_timestamp().saturating_sub(data.config.dialog_max_age_secs);
+    let dialogs = match data.db.list_stale_dialogs(older_than).await {
+        Ok(dialogs) => dialogs,
         Err(err) => {
-            tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "getting voting dialog from db failed");
-            return Err(InteractionError::InternalServerError);
+            tracing::error!(error = ?err, "listing stale dialogs from db failed");
+            return;
         }
     };
 
-    let mut ballot = HashMap::new();
+    for dialog in dialogs {
+        let voting_id = dialog.voting_id.as_str();
+        tracing::info!(%voting_id, user_id = %dialog.user_id, "expiring stale voting dialog");
+
+        // placeholder and in channel dialogs have no dm message to update
+        if let (Ok(channel_id), Ok(message_id)) = (
+            dialog.channel_id.parse::<u64>(),
+            dialog.message_id.parse::<u64>(),
+        ) {
+            if let Err(err) = update_message(
+                &data.discord_client,
+                Id::new(channel_id),
+                Id::new(message_id),
+                Some("This voting dialog expired"),
+                Some(&Vec::new()),
+                Some(&Vec::new()),
+            )
+            .await
+            {
+                tracing::error!(%voting_id, error = ?err, "updating expired dialog message failed");
+            }
+        }
 
-    // todo: test this ordering
-    for (name, value) in voting.choices.iter().zip(voting_dialog.ballot.iter()) {
-        ballot.insert(name.clone(), *value);
+        if let Err(err) = data
+            .db
+            .delete_voting_dialog(voting_id, &dialog.user_id)
+            .await
+        {
+            tracing::error!(%voting_id, error = ?err, "deleting voting dialog from db failed");
+            continue;
+        }
+
+        if let Err(err) = data
+            .db
+            .delete_custom_ids_for_user(voting_id, &dialog.user_id)
+            .await
+        {
+            tracing::error!(%voting_id, error = ?err, "deleting dialog custom ids from db failed");
+        }
     }
+}
 
-    data.dd_client
-        .vote(voting_id, &user_id.id.to_string(), ballot)
-        .await
-        .map_err(|err| {
-            tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "voting failed");
-            InteractionError::InternalServerError
-        })?;
+// Retries the dialog messages `spawn_clean_voting_dialogs` failed to update, backing off
+// exponentially. Should be called periodically, it does nothing in read-only mode.
+pub async fn retry_failed_cleanups(data: &Arc<AppState>) {
+    if data.is_read_only() {
+        return;
+    }
 
-    let Some(ref channel) = interaction.channel else {
-        tracing::error!(%voting_id, data = ?interaction.data, "channel not found");
-        return Err(InteractionError::InternalServerError);
+    let now = util::unix_timestamp();
+    let cleanups = match data.db.get_due_failed_cleanups(now).await {
+        Ok(cleanups) => cleanups,
+        Err(err) => {
+            tracing::error!(error = ?err, "getting failed cleanups from db failed");
+            return;
+        }
     };
 
-    let Some(ref message) = interaction.message else {
-        tracing::error!(%voting_id, data = ?interaction.data, "message not found");
-        return Err(InteractionError::InternalServerError);
-    };
+    let total = cleanups.len();
+    let mut failed = 0;
 
-    update_message(
-        &data.discord_client,
-        channel.id,
-        message.id,
-        Some("Thank you for voting! Your vote has been successfully submitted."),
-        Some(&Vec::new()),
-        Some(&Vec::new()),
-    )
-    .await?;
+    for mut cleanup in cleanups {
+        let voting_id = cleanup.voting_id.clone();
 
-    data.db
-                .delete_voting_dialog(voting_id, &user_id.id.to_string())
-                .await
-                .map_err(|err| {
-                    tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "deleting voting dialog from db failed");
-                    InteractionError::InternalServerError
-                })?;
+        if let Err(err) = clear_dialog_message(
+            &data.discord_client,
+            &cleanup.channel_id,
+            &cleanup.message_id,
+            &cleanup.content,
+        )
+        .await
+        {
+            if cleanup.attempts >= MAX_CLEANUP_ATTEMPTS {
+                tracing::error!(%voting_id, user_id = %cleanup.user_id, error = ?err, "giving up updating dialog message");
+            } else {
+                failed += 1;
+                cleanup.attempts += 1;
+                cleanup.retry_unix = now + cleanup_backoff_secs(cleanup.attempts);
+                if let Err(err) = data.db.save_failed_cleanup(cleanup).await {
+                    tracing::error!(%voting_id, error = ?err, "saving failed cleanup into db failed");
+                }
+                continue;
+            }
+        }
 
-    ack_response()
+        if let Err(err) = data
+            .db
+            .delete_failed_cleanup(&voting_id, &cleanup.user_id)
+            .await
+        {
+            tracing::error!(%voting_id, error = ?err, "deleting failed cleanup from db failed");
+        }
+    }
+
+    if failed > 0 {
+        tracing::warn!(failed, total, "retried dialog cleanups failed again");
+    }
 }
 
-async fn handle_vote_select(
-    data: &Arc<AppState>,
-    interaction: &Interaction,
-    command: &MessageComponentInteractionData,
-    custom_id: &CustomID,
-) -> InteractionResult {
-    let voting_id = &custom_id.voting_id;
-    let Some(index) = custom_id.index else {
-        tracing::error!(%voting_id, data = ?interaction.data, "index not found");
-        return Err(InteractionError::InternalServerError);
+// Re-sends ballots which were recorded but not confirmed before a restart, e.g. by a crash.
+// Voting again replaces the previous ballot of the voter, so a replay can not double vote.
+// Should be called on startup, before serving interactions.
+pub async fn replay_pending_votes(data: &Arc<AppState>) {
+    let votes = match data.db.get_pending_votes().await {
+        Ok(votes) => votes,
+        Err(err) => {
+            tracing::error!(error = ?err, "getting pending votes from db failed");
+            return;
+        }
     };
 
-    let Some(ref user_id) = interaction.user else {
-        tracing::error!(%voting_id, data = ?interaction.data, "user id not found");
-        return Err(InteractionError::InternalServerError);
-    };
+    for vote in votes {
+        let voting_id = vote.voting_id.as_str();
+        tracing::info!(%voting_id, user_id = %vote.user_id, "replaying pending vote");
 
-    let Some(vote) = command.values.first() else {
-        tracing::error!(%voting_id, data = ?interaction.data, "vote not found");
-        return Err(InteractionError::InternalServerError);
-    };
+        let voting = match data.db.get_voting(voting_id).await {
+            Ok(voting) if !voting.is_deleted && !voting.is_completed => voting,
+            Ok(_) | Err(db::DbError::NotFound) => {
+                // the voting is closed, the ballot can not be counted anymore
+                if let Err(err) = data.db.delete_pending_vote(voting_id, &vote.user_id).await {
+                    tracing::error!(%voting_id, error = ?err, "deleting pending vote from db failed");
+                }
+                continue;
+            }
+            Err(err) => {
+                tracing::error!(%voting_id, error = ?err, "db get voting failed");
+                continue;
+            }
+        };
+
+        if let Err(err) = submit_weighted_vote(
+            data,
+            voting_id,
+            &vote.user_id,
+            vote.weight,
+            voting.max_voter_weight(),
+            &vote.ballot,
+        )
+        .await
+        {
+            tracing::error!(%voting_id, error = ?err, "replaying vote failed");
+            continue;
+        }
 
-    let vote = vote.parse::<i32>().map_err(|err| {
-        tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "parsing vote failed");
-        InteractionError::InternalServerError
-    })?;
+        match data
+            .db
+            .set_voting_dialog_submitted(voting_id, &vote.user_id, true, util::unix_timestamp())
+            .await
+        {
+            Ok(_) | Err(db::DbError::NotFound) => {}
+            Err(err) => {
+                tracing::error!(%voting_id, error = ?err, "marking voting dialog as submitted failed");
+            }
+        }
 
-    data
-        .db
-        .vote_voting_dialog(voting_id, &user_id.id.to_string(), vote, index)
-        .await
-    .map_err(|err| {
-        tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "updating vote in db failed");
-        InteractionError::InternalServerError
-    })?;
+        if let Err(err) = data.db.delete_pending_vote(voting_id, &vote.user_id).await {
+            tracing::error!(%voting_id, error = ?err, "deleting pending vote from db failed");
+        }
+    }
+}
 
-    ack_response()
+// Replaces {name}, {winner} and {runner_up} in the completion template.
+// {winner} is "tie" when the top choices are tied and the voting has no tie break.
+pub fn render_completion_template(
+    template: &str,
+    voting: &Voting,
+    results: &VotingResults,
+) -> String {
+    let winner = winner_name(voting, results);
+
+    let runner_up = results
+        .results
+        .get(1)
+        .map(|result| result.choice.as_str())
+        .unwrap_or_default();
+
+    template
+        .replace("{name}", &voting.name)
+        .replace("{winner}", winner)
+        .replace("{runner_up}", runner_up)
 }
 
-async fn handle_vote_channel(
-    data: &Arc<AppState>,
-    interaction: &Interaction,
-    voting_id: &str,
-) -> InteractionResult {
-    let voting = data.db.get_voting(voting_id).await.map_err(|err| {
-        tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "db get voting failed");
-        InteractionError::InternalServerError
-    })?;
+// Choice which won the voting, "tie" when the top choices are tied and the voting has no tie break.
+// Empty when there are no results.
+fn winner_name<'a>(voting: &Voting, results: &'a VotingResults) -> &'a str {
+    match results.results.first() {
+        Some(_) if results.tie => break_tie(voting, results)
+            .map(|result| result.choice.as_str())
+            .unwrap_or("tie"),
+        Some(result) => result.choice.as_str(),
+        None => "",
+    }
+}
 
-    // this can happen with lingering dialogs while completing or deleting voting
-    if voting.is_deleted || voting.is_completed {
-        return ack_response();
+// Content of the summary message posted when `Voting::pin_result` is set
+pub fn result_summary(voting: &Voting, results: &VotingResults) -> String {
+    match winner_name(voting, results) {
+        "" => format!("**{}** is completed, nobody voted.", voting.name),
+        "tie" if results.tie => format!("**{}** is completed and ended in a tie.", voting.name),
+        winner => format!(
+            "**{}** is completed, the winner is **{}**.",
+            voting.name, winner
+        ),
+    }
+}
+
+// Winner of tied results picked with the tie break of the voting.
+// None when the results are not tied or the voting has no tie break.
+pub fn break_tie<'a>(voting: &Voting, results: &'a VotingResults) -> Option<&'a VotingResult> {
+    let top = results.results.first()?;
+    if !results.tie {
+        return None;
     }
 
-    let Some(ref member) = interaction.member else {
-        tracing::error!(%voting_id, data = ?interaction.data, "member not found");
-        return Err(InteractionError::InternalServerError);
-    };
+    let tied: Vec<&VotingResult> = results
+        .results
+        .iter()
+        .filter(|result| result.wins == top.wins)
+        .collect();
 
-    let Some(ref user) = member.user else {
-        tracing::error!(%voting_id, data = ?interaction.data, "user id not found");
-        return Err(InteractionError::InternalServerError);
+    match voting.tie_break {
+        TieBreak::None => None,
+        TieBreak::Random => {
+            let draw = util::stable_permutation(&voting.id, tied.len());
+            draw.first().map(|&i| tied[i])
+        }
+        TieBreak::EarliestChoice => tied.into_iter().min_by_key(|result| result.index),
+        // simple votings have no ballots, equal counts fall back to the earliest choice
+        TieBreak::FirstChoiceCount => tied.into_iter().max_by_key(|result| {
+            let count = voting
+                .first_choice_counts
+                .as_ref()
+                .and_then(|counts| counts.get(&(result.index as usize)).copied())
+                .unwrap_or(0);
+            (count, std::cmp::Reverse(result.index))
+        }),
+    }
+}
+
+// Name of the tie break shown with the broken tie
+fn tie_break_name(tie_break: TieBreak) -> &'static str {
+    match tie_break {
+        TieBreak::None => "none",
+        TieBreak::Random => "random draw",
+        TieBreak::EarliestChoice => "earliest choice",
+        TieBreak::FirstChoiceCount => "first-choice votes",
+    }
+}
+
+// How many submitted ballots rank each choice first, keyed by choice index.
+// A ballot can rank several choices first, each of them is counted, weighted ballots count several times.
+pub fn first_choice_counts(dialogs: &[VoteDialog]) -> HashMap<usize, u32> {
+    let mut counts = HashMap::new();
+    for dialog in dialogs.iter().filter(|dialog| dialog.submitted) {
+        for (index, &rank) in dialog.ballot.iter().enumerate() {
+            if rank == 1 {
+                *counts.entry(index).or_insert(0) += dialog.weight;
+            }
+        }
+    }
+
+    counts
+}
+
+// Comments of the submitted ballots as (user id, comment)
+fn ballot_comments(dialogs: &[VoteDialog]) -> Vec<(String, String)> {
+    dialogs
+        .iter()
+        .filter(|dialog| dialog.submitted)
+        .filter_map(|dialog| Some((dialog.user_id.clone(), dialog.comment.clone()?)))
+        .collect()
+}
+
+// Ranks of the submitted ballots as (user id, ranks by choice index)
+fn submitted_ballots(dialogs: &[VoteDialog]) -> Vec<(String, Vec<i32>)> {
+    dialogs
+        .iter()
+        .filter(|dialog| dialog.submitted)
+        .map(|dialog| (dialog.user_id.clone(), dialog.ballot.clone()))
+        .collect()
+}
+
+// Stores the first choice counts on the voting and records the comments and the ballots,
+// the results are shown without them on failure
+async fn record_first_choice_counts(data: &Arc<AppState>, voting: Voting) -> Voting {
+    let voting_id = voting.id.as_str();
+
+    // abandoned dialogs hold no ballot, they are left for the cleanup
+    let dialogs = match data.db.get_submitted_dialogs(voting_id).await {
+        Ok(dialogs) => dialogs,
+        Err(err) => {
+            tracing::error!(%voting_id, error = ?err, "getting submitted dialogs from db failed");
+            return voting;
+        }
     };
 
+    // recorded before the counts, which mark the voting as recorded
+    if !voting.anonymous {
+        let ballots = VotingBallots {
+            comments: ballot_comments(&dialogs),
+            ballots: submitted_ballots(&dialogs),
+        };
+        if let Err(err) = data.db.set_voting_ballots(voting_id, ballots).await {
+            tracing::error!(%voting_id, error = ?err, "saving voting ballots into db failed");
+            return voting;
+        }
+    }
+
+    let counts = first_choice_counts(&dialogs);
     match data
         .db
-        .save_voting_dialog(
-            voting_id.to_string(),
-            user.id.to_string(),
-            Vec::new(),
-            "".to_string(),
-            "".to_string(),
-            false,
-        )
+        .update_voting_checked(voting_id, voting.version, move |v| {
+            v.first_choice_counts = Some(counts);
+        })
         .await
     {
-        Ok(_) => (),
-        Err(db::DbError::AlreadyExists) => {
-            return Ok((StatusCode::OK, ephemeral_response("You already have voting dialog open or it is being sent to you. If that is not the case, please contact support.")));
-        }
+        Ok(updated) => updated,
         Err(err) => {
-            tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "saving voting dialog into db failed");
-            return Err(InteractionError::InternalServerError);
+            tracing::error!(%voting_id, error = ?err, "saving first choice counts into db failed");
+            voting
         }
     }
+}
 
-    let ballot: Vec<i32> = vec![0; voting.choices.len()];
-    let (title, components, custom_ids) =
-        create_vote_components(voting_id, voting, 1, ballot.clone());
+// Recorded comments of the voting, comments name their voters, so anonymous votings have none
+async fn voting_comments(data: &Arc<AppState>, voting: &Voting) -> Vec<(String, String)> {
+    if voting.anonymous || voting.simple {
+        return Vec::new();
+    }
 
-    data.db.bulk_save_custom_ids(custom_ids).await.map_err(|err| {
-        tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "bulk saving custom ids into db failed");
-        InteractionError::InternalServerError
-    })?;
+    let voting_id = voting.id.as_str();
+    match data.db.get_voting_ballots(voting_id).await {
+        Ok(ballots) => ballots.comments,
+        Err(err) => {
+            tracing::error!(%voting_id, error = ?err, "getting voting ballots from db failed");
+            Vec::new()
+        }
+    }
+}
 
-    let dm_channel = data.discord_client.create_private_channel(user.id).await.map_err(|err| {
-        tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "creating dm channel failed");
-        InteractionError::InternalServerError
-    })?;
+// Edits the channel and creator messages of a voting already marked as completed in the db,
+// then clears its pending completion. Returns the published results as `ResultsArtifact`.
+async fn publish_completed_voting(
+    data: &Arc<AppState>,
+    voting: Voting,
+    results: VotingResults,
+) -> Result<ResultsArtifact, InteractionError> {
+    // simple votings are counted from reactions, there are no ballots
+    let voting = if voting.simple || voting.first_choice_counts.is_some() {
+        voting
+    } else {
+        record_first_choice_counts(data, voting).await
+    };
+    let voting_id = voting.id.as_str();
 
-    let dm_channel =  dm_channel
-        .model()
-        .await
-        .map_err(|err| {
-            tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "getting dm channel model failed");
-            InteractionError::InternalServerError
-        })?;
+    let completed = Arc::new(CompletedResults {
+        artifact: ResultsArtifact::new(&voting, &results),
+        results,
+        question_embeds: create_question_result_embeds(data, &voting).await,
+        comments: voting_comments(data, &voting).await,
+    });
+    let results = &completed.results;
+
+    let (mut result_embeds, result_components, mut custom_ids) = create_results_components(
+        voting_id,
+        &voting,
+        results,
+        &completed.comments,
+        1,
+        &ResultPageIds::new(),
+    );
+    result_embeds.extend(completed.question_embeds.iter().cloned());
 
-    let message = create_message(&data.discord_client, dm_channel.id, &title, &components).await?;
+    // ballots are only recorded for ranked votings which are not anonymous
+    let download_components = if voting.simple || voting.anonymous {
+        Vec::new()
+    } else {
+        vec![create_download_ballots_component(&voting, &mut custom_ids)]
+    };
 
-    data
-        .db
-        .save_voting_dialog(
-            voting_id.to_string(),
-            user.id.to_string(),
-            ballot.clone(),
-            message.id.to_string(),
-            dm_channel.id.to_string(),
-            true,
-        )
+    data.db
+        .bulk_save_custom_ids(custom_ids)
         .await
         .map_err(|err| {
-            tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "saving voting dialog into db failed");
+            tracing::error!(%voting_id, error = ?err, "bulk saving custom ids into db failed");
             InteractionError::InternalServerError
         })?;
 
-    let response = Json(InteractionResponse {
-        kind: InteractionResponseType::ChannelMessageWithSource,
-        data: Some(InteractionResponseData {
-            content: Some("You will receive dm with voting dialog".to_string()),
-            flags: Some(MessageFlags::EPHEMERAL),
-            ..Default::default()
-        }),
-    });
-
-    Ok((StatusCode::OK, response))
-}
+    let message_id = Id::new(voting.message_id.parse::<u64>().map_err(|err| {
+        tracing::error!(%voting_id, error = ?err, "parsing message id failed");
+        InteractionError::InternalServerError
+    })?);
 
-fn create_vote_components(
-    voting_id: &str,
-    voting: Voting,
-    page: usize,
-    ballot: Vec<i32>,
-) -> (Vec<Embed>, Vec<Component>, Vec<(String, CustomID)>) {
-    let page_size = 4;
-    let total_pages = (voting.choices.len() + page_size - 1) / page_size;
-    let start = (page - 1) * page_size;
-    let end = usize::min(start + page_size, voting.choices.len());
+    let channel_id = Id::new(voting.channel_id.parse::<u64>().map_err(|err| {
+        tracing::error!(%voting_id, error = ?err, "parsing channel id failed");
+        InteractionError::InternalServerError
+    })?);
 
-    let paginated_choices = voting.choices[start..end]
-        .iter()
-        .enumerate()
-        .map(|(i, choice)| format!("**{}**: {}", start + i + 1, choice))
-        .collect::<Vec<_>>()
-        .join("\n");
+    let content = match voting.completion_template {
+        Some(ref template) => render_completion_template(template, &voting, results),
+        None => "Voting completed!".to_string(),
+    };
 
-    let embed_title = if voting.choices.len() > page_size {
-        format!("Voting Choices - Page {} of {}", page, total_pages)
+    // choices and templates are user input, they can only ping when the creator allowed it
+    let allowed_mentions = if voting.completion_mentions {
+        AllowedMentions {
+            parse: vec![MentionType::Roles, MentionType::Users],
+            ..Default::default()
+        }
     } else {
-        "Voting Choices".to_string()
+        AllowedMentions::default()
     };
 
-    let title = EmbedBuilder::new()
-        .title(embed_title)
-        .description(paginated_choices)
-        .build();
+    // private results are only shown to the creator, the channel just learns the voting is over
+    if voting.private_results {
+        update_message(
+            &data.discord_client,
+            channel_id,
+            message_id,
+            Some(PRIVATE_RESULTS_MESSAGE),
+            Some(&Vec::new()),
+            Some(&Vec::new()),
+        )
+        .await?;
+    } else {
+        update_message_with_mentions(
+            &data.discord_client,
+            channel_id,
+            message_id,
+            Some(&content),
+            Some(&result_embeds),
+            Some(&result_components),
+            Some(&allowed_mentions),
+        )
+        .await?;
+    }
 
-    let options: Vec<SelectMenuOption> = (1..=voting.choices.len())
-        .map(|i| SelectMenuOption {
-            default: false,
-            description: None,
-            emoji: None,
-            label: i.to_string(),
-            value: i.to_string(),
-        })
-        .collect();
+    // the summary is a channel message, private results stay with the creator
+    if voting.pin_result && !voting.private_results {
+        post_pinned_summary(
+            data,
+            voting_id,
+            channel_id,
+            &result_summary(&voting, results),
+            &allowed_mentions,
+        )
+        .await;
+    }
 
-    let mut custom_ids: Vec<(String, CustomID)> = Vec::new();
+    // update dm creator to "voting completed"
+    let creator_dm_channel_id =
+        Id::new(voting.creator_dm_channel_id.parse::<u64>().map_err(|err| {
+            tracing::error!(%voting_id, error = ?err, "parsing dm channel id failed");
+            InteractionError::InternalServerError
+        })?);
+    let creator_message_id = Id::new(voting.creator_message_id.parse::<u64>().map_err(|err| {
+        tracing::error!(%voting_id, error = ?err, "parsing creator message id failed");
+        InteractionError::InternalServerError
+    })?);
 
-    let mut components: Vec<Component> = voting.choices[start..end]
-        .iter()
-        .enumerate()
-        .map(|(i, _)| {
-            let placeholder = match ballot[i + start] {
-                0 => "Select".to_string(),
-                _ => ballot[i + start].to_string(),
-            };
+    // disabled buttons do not need stored custom ids
+    let creator_components =
+        closed_components(data, create_creator_components(&voting, &mut Vec::new()));
 
-            let custom_uuid = util::generate_random_custom_uuid();
-            let custom_id = CustomID {
-                action: Action::VoteSelect,
+    let (creator_embeds, creator_components) = if voting.private_results {
+        (
+            result_embeds,
+            [result_components, creator_components, download_components].concat(),
+        )
+    } else {
+        (
+            Vec::new(),
+            [creator_components, download_components].concat(),
+        )
+    };
+
+    update_message(
+        &data.discord_client,
+        creator_dm_channel_id,
+        creator_message_id,
+        Some("Voting completed!"),
+        Some(&creator_embeds),
+        Some(&creator_components),
+    )
+    .await?;
+
+    // the attachments go with the results, private ones are only in the creator dm
+    let (results_channel_id, results_message_id) = if voting.private_results {
+        (creator_dm_channel_id, creator_message_id)
+    } else {
+        (channel_id, message_id)
+    };
+    attach_results(
+        data,
+        &voting,
+        results_channel_id,
+        results_message_id,
+        results,
+        &completed.artifact,
+    )
+    .await;
+
+    if let Err(err) = data.db.delete_pending_completion(voting_id).await {
+        tracing::error!(%voting_id, error = ?err, "deleting pending completion from db failed");
+    }
+
+    data.cache_completed_results(voting_id, completed.clone());
+
+    let data_clone = data.clone();
+    spawn_clean_voting_dialogs(voting, data_clone, "Voting completed".to_string());
+
+    Ok(completed.artifact.clone())
+}
+
+async fn handle_delete_voting(
+    data: &Arc<AppState>,
+    interaction: &Interaction,
+    voting_id: &str,
+) -> InteractionResult {
+    let Some(user_id) = interaction.author_id() else {
+        tracing::error!(%voting_id, data = ?interaction.data, "interaction user not found");
+        return Err(InteractionError::InternalServerError);
+    };
+
+    let voting = match data.db.delete_voting(voting_id, &user_id.to_string()).await {
+        Ok(v) => v,
+        Err(db::DbError::NotFound) => {
+            // handle double click or complete already in progress
+            return ack_response();
+        }
+        Err(err) => {
+            tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "deleting voting from db failed");
+            return Err(InteractionError::InternalServerError);
+        }
+    };
+
+    // the scheduled completion would only find the voting deleted
+    data.cancel_deadline(voting_id);
+
+    let message_id = Id::new(
+        voting
+            .message_id
+            .parse::<u64>()
+            .map_err(|err| {
+                tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "parsing message id failed");
+                InteractionError::InternalServerError
+            })?
+    );
+    let channel_id = Id::new(
+        voting
+            .channel_id
+            .parse::<u64>()
+            .map_err(|err| {
+                tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "parsing channel id failed");
+                InteractionError::InternalServerError
+            })?
+    );
+
+    // disabled buttons do not need stored custom ids
+    let announcement_components = if voting.simple {
+        Vec::new()
+    } else {
+        let (_, components) =
+            create_announcement_components(&voting, util::generate_random_custom_uuid(), 0);
+        closed_components(data, components)
+    };
+
+    update_message(
+        &data.discord_client,
+        channel_id,
+        message_id,
+        Some(format!("Voting deleted: {}", voting.name).as_str()),
+        Some(&Vec::new()),
+        Some(&announcement_components),
+    )
+    .await?;
+
+    let creator_dm_channel_id = Id::new(
+        voting
+            .creator_dm_channel_id
+            .parse::<u64>()
+            .map_err(|err| {
+                tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "parsing dm channel id failed");
+                InteractionError::InternalServerError
+            })?
+    );
+    let creator_message_id = Id::new(
+        voting
+            .creator_message_id
+            .parse::<u64>()
+            .map_err(|err| {
+                tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "parsing creator message id failed");
+                InteractionError::InternalServerError
+            })?
+    );
+
+    let creator_components =
+        closed_components(data, create_creator_components(&voting, &mut Vec::new()));
+
+    update_message(
+        &data.discord_client,
+        creator_dm_channel_id,
+        creator_message_id,
+        Some(format!("Voting deleted: {}", voting.name).as_str()),
+        Some(&Vec::new()),
+        Some(&creator_components),
+    )
+    .await?;
+
+    let data_clone = data.clone();
+    spawn_clean_voting_dialogs(voting, data_clone, "Voting deleted".to_string());
+
+    ack_response()
+}
+
+// Displays the choices alphabetically in the announcement and in newly rendered vote dialogs.
+// Stored indices are untouched, so ballots keep mapping to the original choices.
+async fn handle_sort_choices(
+    data: &Arc<AppState>,
+    interaction: &Interaction,
+    voting: Voting,
+    voting_id: &str,
+) -> InteractionResult {
+    if voting.is_deleted || voting.is_completed {
+        return ack_response();
+    }
+
+    let mut display_order: Vec<usize> = (0..voting.choices.len()).collect();
+    display_order.sort_by_key(|&i| voting.choices[i].to_lowercase());
+
+    let voting = data
+        .db
+        .set_voting_display_order(voting_id, Some(display_order))
+        .await
+        .map_err(|err| {
+            tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "setting display order in db failed");
+            InteractionError::InternalServerError
+        })?;
+
+    rerender_channel_announcement(data, &voting).await?;
+
+    Ok((
+        StatusCode::OK,
+        ephemeral_response("Choices are now displayed alphabetically."),
+    ))
+}
+
+// Replaces the vote button custom id and edits the announcement again, e.g. after a partial failure
+// left stale buttons behind. Open voting dialogs keep their custom ids.
+async fn handle_refresh(
+    data: &Arc<AppState>,
+    interaction: &Interaction,
+    voting: Voting,
+) -> InteractionResult {
+    if voting.is_deleted || voting.is_completed {
+        return ack_response();
+    }
+
+    let voting_id = voting.id.as_str();
+
+    // simple votings are voted with reactions, there is no vote button
+    if !voting.simple {
+        data.db
+            .delete_custom_ids_with_actions(voting_id, vec![Action::VoteFromChannel])
+            .await
+            .map_err(|err| {
+                tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "deleting vote custom ids from db failed");
+                InteractionError::InternalServerError
+            })?;
+
+        let vote_custom_id = (
+            util::generate_random_custom_uuid(),
+            CustomID {
+                action: Action::VoteFromChannel,
                 voting_id: voting_id.to_string(),
                 user_id: None,
                 page: None,
-                index: Some(i + start),
+                index: None,
+            },
+        );
+        data.db
+            .bulk_save_custom_ids(vec![vote_custom_id])
+            .await
+            .map_err(|err| {
+                tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "bulk saving custom ids into db failed");
+                InteractionError::InternalServerError
+            })?;
+    }
+
+    rerender_channel_announcement(data, &voting).await?;
+
+    Ok((
+        StatusCode::OK,
+        ephemeral_response("The voting announcement was refreshed."),
+    ))
+}
+
+// Edits the channel announcement after the voting was changed.
+// The vote button keeps its stored custom id, so it stays usable.
+async fn rerender_channel_announcement(
+    data: &Arc<AppState>,
+    voting: &Voting,
+) -> Result<(), InteractionError> {
+    let voting_id = voting.id.as_str();
+
+    let (embeds, components) = if voting.simple {
+        create_simple_announcement_components(voting)
+    } else {
+        let custom_ids = data.db.get_custom_ids(voting_id).await.map_err(|err| {
+            tracing::error!(%voting_id, error = ?err, "getting custom ids from db failed");
+            InteractionError::InternalServerError
+        })?;
+
+        let Some((vote_custom_uuid, _)) = custom_ids
+            .into_iter()
+            .find(|(_, custom_id)| custom_id.action == Action::VoteFromChannel)
+        else {
+            tracing::error!(%voting_id, "vote custom id not found");
+            return Err(InteractionError::InternalServerError);
+        };
+
+        let vote_count = if voting.show_vote_count {
+            data.db
+                .count_submitted_dialogs(voting_id)
+                .await
+                .map_err(|err| {
+                    tracing::error!(%voting_id, error = ?err, "counting submitted dialogs in db failed");
+                    InteractionError::InternalServerError
+                })?
+        } else {
+            0
+        };
+
+        create_announcement_components(voting, vote_custom_uuid, vote_count)
+    };
+
+    let message_id = Id::new(voting.message_id.parse::<u64>().map_err(|err| {
+        tracing::error!(%voting_id, error = ?err, "parsing message id failed");
+        InteractionError::InternalServerError
+    })?);
+
+    let channel_id = Id::new(voting.channel_id.parse::<u64>().map_err(|err| {
+        tracing::error!(%voting_id, error = ?err, "parsing channel id failed");
+        InteractionError::InternalServerError
+    })?);
+
+    update_message(
+        &data.discord_client,
+        channel_id,
+        message_id,
+        None,
+        Some(&embeds),
+        Some(&components),
+    )
+    .await?;
+
+    Ok(())
+}
+
+// Replies with the lifecycle events of the voting, only the creator dm has this button
+async fn handle_view_audit(
+    data: &Arc<AppState>,
+    interaction: &Interaction,
+    voting: Voting,
+) -> InteractionResult {
+    let voting_id = voting.id.as_str();
+
+    let events = data.db.get_audit(voting_id).await.map_err(|err| {
+        tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "getting audit log from db failed");
+        InteractionError::InternalServerError
+    })?;
+
+    if events.is_empty() {
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response("No events recorded for this voting."),
+        ));
+    }
+
+    let lines: Vec<String> = events
+        .iter()
+        .map(|event| {
+            let kind = match event.kind {
+                AuditEventKind::Created => "Created",
+                AuditEventKind::Completed => "Completed",
+                AuditEventKind::Deleted => "Deleted",
+                AuditEventKind::Transferred => "Transferred",
             };
+            let actor = if event.user_id == db::SYSTEM_USER_ID {
+                "the bot".to_string()
+            } else {
+                format!("<@{}>", event.user_id)
+            };
+            format!("<t:{}:f> {} by {}", event.timestamp_unix, kind, actor)
+        })
+        .collect();
+
+    let embed = EmbedBuilder::new()
+        .title(format!("Audit log: {}", voting.name))
+        .description(lines.join("\n"))
+        .build();
+
+    Ok((
+        StatusCode::OK,
+        Json(InteractionResponse {
+            kind: InteractionResponseType::ChannelMessageWithSource,
+            data: Some(InteractionResponseData {
+                embeds: Some(vec![embed]),
+                flags: Some(MessageFlags::EPHEMERAL),
+                ..Default::default()
+            }),
+        }),
+    ))
+}
+
+// Sends the recorded ballots of a completed voting to the creator dm as a csv attachment
+async fn handle_download_ballots(
+    data: &Arc<AppState>,
+    interaction: &Interaction,
+    voting: Voting,
+) -> InteractionResult {
+    let voting_id = voting.id.as_str();
+
+    if !voting.is_completed || voting.is_deleted {
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response("Ballots can only be downloaded after the voting is completed."),
+        ));
+    }
+
+    let is_creator = interaction
+        .author()
+        .is_some_and(|user| user.id.to_string() == voting.creator_user_id);
+    if !is_creator {
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response("Only the creator of the voting can download its ballots."),
+        ));
+    }
+
+    let dm_channel_id = Id::new(voting.creator_dm_channel_id.parse::<u64>().map_err(|err| {
+        tracing::error!(%voting_id, error = ?err, "parsing dm channel id failed");
+        InteractionError::InternalServerError
+    })?);
+
+    let ballots = data.db.get_voting_ballots(voting_id).await.map_err(|err| {
+        tracing::error!(%voting_id, error = ?err, "getting voting ballots from db failed");
+        InteractionError::InternalServerError
+    })?;
+
+    let attachments = [twilight_model::http::attachment::Attachment::from_bytes(
+        format!("ballots-{}.csv", voting_id),
+        ballots_csv(&voting, &ballots.ballots).into_bytes(),
+        0,
+    )];
+
+    data.discord_client
+        .create_message(dm_channel_id)
+        .content(&format!("Ballots of {}", voting.name))
+        .map_err(|err| {
+            tracing::error!(%voting_id, error = ?err, "message content failed");
+            InteractionError::InternalServerError
+        })?
+        .attachments(&attachments)
+        .map_err(|err| {
+            tracing::error!(%voting_id, error = ?err, "attachments failed");
+            InteractionError::InternalServerError
+        })?
+        .await
+        .map_err(|err| {
+            tracing::error!(%voting_id, error = ?err, "creating ballots message failed");
+            InteractionError::InternalServerError
+        })?;
+
+    ack_response()
+}
+
+// One row per recorded ballot, the choices are the columns in their original order.
+// Unranked choices are left empty.
+pub fn ballots_csv(voting: &Voting, ballots: &[(String, Vec<i32>)]) -> String {
+    let header: Vec<String> = std::iter::once("voter".to_string())
+        .chain(voting.choices.iter().map(|choice| csv_field(choice)))
+        .collect();
+
+    let mut lines = vec![header.join(",")];
+    for (user_id, ballot) in ballots {
+        let row: Vec<String> = std::iter::once(csv_field(user_id))
+            .chain(ballot.iter().map(|&rank| {
+                if rank == 0 {
+                    String::new()
+                } else {
+                    rank.to_string()
+                }
+            }))
+            .collect();
+        lines.push(row.join(","));
+    }
+
+    lines.join("\n") + "\n"
+}
+
+// Choices are user input, fields with separators or quotes are quoted. Fields which a
+// spreadsheet would run as a formula are prefixed with a quote, so they stay text.
+fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+// Opens the rename modal, from the creator dm
+async fn handle_edit_name(
+    interaction: &Interaction,
+    voting: Voting,
+    custom_uuid: &str,
+) -> InteractionResult {
+    if voting.is_deleted || voting.is_completed {
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response("The voting is already closed."),
+        ));
+    }
+
+    tracing::debug!(voting_id = %voting.id, data = ?interaction.data, "opening rename modal");
+
+    let name_input = TextInput {
+        custom_id: NAME_INPUT_ID.to_string(),
+        label: "Name".to_string(),
+        max_length: Some(VOTING_NAME_MAX_LENGTH),
+        min_length: Some(1),
+        placeholder: None,
+        required: Some(true),
+        style: TextInputStyle::Short,
+        value: Some(voting.name),
+    };
+
+    Ok((
+        StatusCode::OK,
+        Json(InteractionResponse {
+            kind: InteractionResponseType::Modal,
+            data: Some(InteractionResponseData {
+                custom_id: Some(custom_uuid.to_string()),
+                title: Some("Rename voting".to_string()),
+                components: Some(vec![Component::ActionRow(ActionRow {
+                    components: vec![Component::TextInput(name_input)],
+                })]),
+                ..Default::default()
+            }),
+        }),
+    ))
+}
+
+async fn handle_edit_name_submit(
+    data: &Arc<AppState>,
+    interaction: &Interaction,
+    voting: Voting,
+    modal: &ModalInteractionData,
+) -> InteractionResult {
+    let voting_id = voting.id.clone();
+
+    if voting.is_deleted || voting.is_completed {
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response("The voting is already closed."),
+        ));
+    }
+
+    let Some(name) = modal_text_input(modal, NAME_INPUT_ID)
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+    else {
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response("The voting name can not be empty."),
+        ));
+    };
+
+    let new_name = name.clone();
+    let voting = match data
+        .db
+        .update_voting_checked(&voting_id, voting.version, move |voting| {
+            voting.name = new_name;
+        })
+        .await
+    {
+        Ok(voting) => voting,
+        Err(db::DbError::VersionConflict) => {
+            return Ok((
+                StatusCode::OK,
+                ephemeral_response("The voting was changed meanwhile, please try again."),
+            ));
+        }
+        Err(err) => {
+            tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "renaming voting in db failed");
+            return Err(InteractionError::InternalServerError);
+        }
+    };
+
+    // the announcement shows the name too, renaming again retries a failed edit
+    rerender_channel_announcement(data, &voting).await?;
+
+    Ok((
+        StatusCode::OK,
+        ephemeral_response(&format!("Voting renamed to **{}**.", name)),
+    ))
+}
+
+// Opens the comment modal, from the reply to a submitted vote
+async fn handle_add_comment(
+    data: &Arc<AppState>,
+    interaction: &Interaction,
+    voting: Voting,
+    custom_uuid: &str,
+) -> InteractionResult {
+    let voting_id = voting.id.as_str();
+
+    if voting.is_deleted || voting.is_completed {
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response("The voting is already closed."),
+        ));
+    }
+
+    let Some(user) = interaction.author() else {
+        tracing::error!(%voting_id, data = ?interaction.data, "user id not found");
+        return Err(InteractionError::InternalServerError);
+    };
+
+    let comment = match data
+        .db
+        .get_voting_dialog(voting_id, &user.id.to_string())
+        .await
+    {
+        Ok(dialog) if dialog.submitted => dialog.comment,
+        Ok(_) | Err(db::DbError::NotFound) => {
+            return Ok((
+                StatusCode::OK,
+                ephemeral_response("Submit your vote before adding a comment."),
+            ));
+        }
+        Err(err) => {
+            tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "getting voting dialog from db failed");
+            return Err(InteractionError::InternalServerError);
+        }
+    };
+
+    let comment_input = TextInput {
+        custom_id: COMMENT_INPUT_ID.to_string(),
+        label: "Why did you vote this way?".to_string(),
+        max_length: Some(COMMENT_MAX_LENGTH),
+        min_length: None,
+        placeholder: None,
+        // an empty comment removes the saved one
+        required: Some(false),
+        style: TextInputStyle::Paragraph,
+        value: comment,
+    };
+
+    Ok((
+        StatusCode::OK,
+        Json(InteractionResponse {
+            kind: InteractionResponseType::Modal,
+            data: Some(InteractionResponseData {
+                custom_id: Some(custom_uuid.to_string()),
+                title: Some("Comment".to_string()),
+                components: Some(vec![Component::ActionRow(ActionRow {
+                    components: vec![Component::TextInput(comment_input)],
+                })]),
+                ..Default::default()
+            }),
+        }),
+    ))
+}
+
+async fn handle_add_comment_submit(
+    data: &Arc<AppState>,
+    interaction: &Interaction,
+    voting: Voting,
+    modal: &ModalInteractionData,
+) -> InteractionResult {
+    let voting_id = voting.id.as_str();
+
+    if voting.is_deleted || voting.is_completed {
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response("The voting is already closed."),
+        ));
+    }
+
+    let Some(user) = interaction.author() else {
+        tracing::error!(%voting_id, data = ?interaction.data, "user id not found");
+        return Err(InteractionError::InternalServerError);
+    };
+
+    let comment = modal_text_input(modal, COMMENT_INPUT_ID)
+        .map(str::trim)
+        .filter(|comment| !comment.is_empty())
+        .map(str::to_string);
+    let removed = comment.is_none();
+
+    match data
+        .db
+        .set_dialog_comment(voting_id, &user.id.to_string(), comment)
+        .await
+    {
+        Ok(()) => {}
+        Err(db::DbError::NotFound) => {
+            return Ok((
+                StatusCode::OK,
+                ephemeral_response("Submit your vote before adding a comment."),
+            ));
+        }
+        Err(err) => {
+            tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "saving comment into db failed");
+            return Err(InteractionError::InternalServerError);
+        }
+    }
+
+    let message = if removed {
+        "Your comment was removed."
+    } else {
+        "Your comment was saved, it will be shown with the results."
+    };
+
+    Ok((StatusCode::OK, ephemeral_response(message)))
+}
+
+// Value of the submitted text input with the custom id, inputs are nested in action rows
+fn modal_text_input<'a>(modal: &'a ModalInteractionData, custom_id: &str) -> Option<&'a str> {
+    modal
+        .components
+        .iter()
+        .flat_map(|row| row.components.iter())
+        .find(|component| component.custom_id == custom_id)
+        .and_then(|component| component.value.as_deref())
+}
+
+// Extends the deadline by an hour or clears it, from the creator dm
+async fn handle_set_deadline(
+    data: &Arc<AppState>,
+    interaction: &Interaction,
+    voting: Voting,
+    action: &Action,
+) -> InteractionResult {
+    let voting_id = voting.id.as_str();
+
+    let deadline_unix = match voting.deadline_unix {
+        Some(deadline_unix) if !voting.is_deleted && !voting.is_completed => deadline_unix,
+        Some(_) => {
+            return Ok((
+                StatusCode::OK,
+                ephemeral_response("The voting is already closed."),
+            ));
+        }
+        None => {
+            return Ok((
+                StatusCode::OK,
+                ephemeral_response("The voting has no deadline."),
+            ));
+        }
+    };
+
+    // the scheduled completion may not have run yet
+    if deadline_unix <= util::unix_timestamp() {
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response("The deadline of the voting has already passed."),
+        ));
+    }
+
+    let new_deadline_unix = match action {
+        Action::ExtendDeadline => Some(deadline_unix + DEADLINE_EXTENSION_SECS),
+        _ => None,
+    };
+
+    let voting = match data
+        .db
+        .set_voting_deadline(voting_id, new_deadline_unix)
+        .await
+    {
+        Ok(v) => v,
+        Err(db::DbError::NotFound) => {
+            return Ok((
+                StatusCode::OK,
+                ephemeral_response("The voting is already closed."),
+            ));
+        }
+        Err(err) => {
+            tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "setting voting deadline in db failed");
+            return Err(InteractionError::InternalServerError);
+        }
+    };
+
+    // rescheduling aborts the completion scheduled for the previous deadline
+    match new_deadline_unix {
+        Some(new_deadline_unix) => schedule_deadline(data, voting_id, new_deadline_unix),
+        None => {
+            data.cancel_deadline(voting_id);
+        }
+    }
+
+    // the deadline is already changed, a failed announcement edit doesn't fail the button
+    if let Err(err) = rerender_channel_announcement(data, &voting).await {
+        tracing::error!(%voting_id, error = ?err, "updating deadline of announcement failed");
+    }
+
+    let mut custom_ids = Vec::new();
+    let components = create_creator_components(&voting, &mut custom_ids);
+    data.db.bulk_save_custom_ids(custom_ids).await.map_err(|err| {
+        tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "bulk saving custom ids into db failed");
+        InteractionError::InternalServerError
+    })?;
+
+    let embeds = interaction
+        .message
+        .iter()
+        .flat_map(|message| message.embeds.iter().cloned())
+        .map(|embed| with_deadline_field(embed, new_deadline_unix))
+        .collect();
+
+    Ok((
+        StatusCode::OK,
+        Json(InteractionResponse {
+            kind: InteractionResponseType::UpdateMessage,
+            data: Some(InteractionResponseData {
+                embeds: Some(embeds),
+                components: Some(components),
+                ..Default::default()
+            }),
+        }),
+    ))
+}
+
+// Toggles whether users without a voting dialog can open one, and relabels the button
+async fn handle_lock_dialogs(
+    data: &Arc<AppState>,
+    interaction: &Interaction,
+    voting: Voting,
+) -> InteractionResult {
+    let voting_id = voting.id.as_str();
+
+    let voting = match data
+        .db
+        .set_voting_dialogs_locked(voting_id, !voting.dialogs_locked)
+        .await
+    {
+        Ok(v) => v,
+        Err(db::DbError::NotFound) => {
+            return Ok((
+                StatusCode::OK,
+                ephemeral_response("The voting is already closed."),
+            ));
+        }
+        Err(err) => {
+            tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "setting dialogs locked in db failed");
+            return Err(InteractionError::InternalServerError);
+        }
+    };
+
+    let mut custom_ids = Vec::new();
+    let components = create_creator_components(&voting, &mut custom_ids);
+    data.db.bulk_save_custom_ids(custom_ids).await.map_err(|err| {
+        tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "bulk saving custom ids into db failed");
+        InteractionError::InternalServerError
+    })?;
+
+    Ok((
+        StatusCode::OK,
+        Json(InteractionResponse {
+            kind: InteractionResponseType::UpdateMessage,
+            data: Some(InteractionResponseData {
+                components: Some(components),
+                ..Default::default()
+            }),
+        }),
+    ))
+}
+
+// Reminds the users who opened a dm voting dialog but didn't submit it, with a link to the dialog.
+// The bot doesn't track the members of the channel, so users who never opened a dialog are not reminded.
+async fn handle_remind_non_voters(
+    data: &Arc<AppState>,
+    interaction: &Interaction,
+    voting: Voting,
+) -> InteractionResult {
+    let voting_id = voting.id.as_str();
+
+    if voting.is_deleted || voting.is_completed {
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response("The voting is already closed."),
+        ));
+    }
+
+    let now = util::unix_timestamp();
+    if let Some(reminded_unix) = voting.reminded_unix {
+        let next_unix = reminded_unix + REMINDER_INTERVAL_SECS;
+        if now < next_unix {
+            return Ok((
+                StatusCode::OK,
+                ephemeral_response(&format!(
+                    "Non-voters were already reminded, you can remind them again <t:{}:R>.",
+                    next_unix
+                )),
+            ));
+        }
+    }
+
+    let dialogs = data.db.get_voting_dialogs(voting_id).await.map_err(|err| {
+        tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "getting voting dialogs from db failed");
+        InteractionError::InternalServerError
+    })?;
+
+    // in channel dialogs are ephemeral, there is no dm to point to
+    let dialogs: Vec<VoteDialog> = dialogs
+        .into_iter()
+        .filter(|dialog| !dialog.submitted && !dialog.message_id.is_empty())
+        .collect();
+
+    if dialogs.is_empty() {
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response("There are no voters to remind."),
+        ));
+    }
+
+    match data
+        .db
+        .update_voting_checked(voting_id, voting.version, move |voting| {
+            voting.reminded_unix = Some(now);
+        })
+        .await
+    {
+        Ok(_) => {}
+        // another click is sending the reminders
+        Err(db::DbError::VersionConflict) => {
+            return Ok((
+                StatusCode::OK,
+                ephemeral_response("Reminders are already being sent."),
+            ));
+        }
+        Err(err) => {
+            tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "saving reminder time into db failed");
+            return Err(InteractionError::InternalServerError);
+        }
+    }
+
+    let count = dialogs.len();
+    let data_clone = data.clone();
+    let name = voting.name.clone();
+    data.task_tracker.spawn(async move {
+        for dialog in dialogs {
+            let voting_id = dialog.voting_id.as_str();
+            let (Ok(channel_id), Ok(message_id)) = (
+                dialog.channel_id.parse::<u64>(),
+                dialog.message_id.parse::<u64>(),
+            ) else {
+                tracing::error!(%voting_id, user_id = %dialog.user_id, "parsing dialog ids failed");
+                continue;
+            };
+
+            let content = format!(
+                "Reminder: you haven't submitted your vote in **{}** yet. Your voting dialog: https://discord.com/channels/@me/{}/{}",
+                name, channel_id, message_id
+            );
+
+            match data_clone
+                .discord_client
+                .create_message(Id::new(channel_id))
+                .content(&content)
+            {
+                Ok(request) => {
+                    if let Err(err) = request.await {
+                        tracing::error!(%voting_id, user_id = %dialog.user_id, error = ?err, "sending reminder failed");
+                    }
+                }
+                Err(err) => {
+                    tracing::error!(%voting_id, error = ?err, "reminder content failed");
+                }
+            }
+        }
+    });
+
+    Ok((
+        StatusCode::OK,
+        ephemeral_response(&format!("Reminding {} voters.", count)),
+    ))
+}
+
+// Completes the voting once `deadline_unix` is reached, unless the deadline was changed meanwhile.
+// Replaces the completion scheduled for a previous deadline of the voting.
+fn schedule_deadline(data: &Arc<AppState>, voting_id: &str, deadline_unix: u64) {
+    let task_data = data.clone();
+    let task_voting_id = voting_id.to_string();
+
+    // held until the task is registered, so a deadline which already passed can't finish first
+    let mut scheduled = data
+        .scheduled_deadlines
+        .lock()
+        .expect("scheduled deadlines lock poisoned");
+
+    let task = data.task_tracker.spawn(async move {
+        let data = task_data;
+        let voting_id = task_voting_id;
+
+        let wait = deadline_unix.saturating_sub(util::unix_timestamp());
+        tokio::time::sleep(Duration::from_secs(wait)).await;
+
+        // still cancellable while it waits, the entry is kept until it completes
+        while data.is_read_only() {
+            tokio::time::sleep(READ_ONLY_POLL_INTERVAL).await;
+        }
+
+        // the completion is not cancelled once it started, a newer deadline keeps its entry
+        {
+            let mut scheduled = data
+                .scheduled_deadlines
+                .lock()
+                .expect("scheduled deadlines lock poisoned");
+            if scheduled
+                .get(&voting_id)
+                .is_some_and(|(scheduled_unix, _)| *scheduled_unix == deadline_unix)
+            {
+                scheduled.remove(&voting_id);
+            }
+        }
+
+        let voting = match data.db.get_open_voting(&voting_id).await {
+            Ok(VotingState::Open(v)) => v,
+            Ok(_) => return,
+            Err(err) => {
+                tracing::error!(%voting_id, error = ?err, "db get voting failed");
+                return;
+            }
+        };
+
+        if voting.deadline_unix != Some(deadline_unix) {
+            return;
+        }
+
+        tracing::info!(%voting_id, "completing voting at its deadline");
+
+        // the creator may complete it at the same time, only one of them publishes the results.
+        // The pending completion is kept on failure, see `retry_pending_completions`.
+        if let Err(err) = complete_voting(&data, &voting_id, db::SYSTEM_USER_ID).await {
+            tracing::error!(%voting_id, error = ?err, "completing voting at its deadline failed");
+        }
+    });
+
+    if let Some((_, previous)) =
+        scheduled.insert(voting_id.to_string(), (deadline_unix, task.abort_handle()))
+    {
+        previous.abort();
+    }
+}
+
+// Schedules the completion of every active voting with a deadline, should be called on start.
+// Deadlines which passed while the bot was down complete right away.
+pub async fn schedule_pending_deadlines(data: &Arc<AppState>) {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let res = data
+        .db
+        .for_each_voting(move |voting| {
+            if let (false, false, Some(deadline_unix)) =
+                (voting.is_deleted, voting.is_completed, voting.deadline_unix)
+            {
+                let _ = tx.send((voting.id, deadline_unix));
+            }
+            ControlFlow::Continue(())
+        })
+        .await;
+
+    if let Err(err) = res {
+        tracing::error!(error = ?err, "listing votings with a deadline failed");
+        return;
+    }
+
+    for (voting_id, deadline_unix) in rx.try_iter() {
+        schedule_deadline(data, &voting_id, deadline_unix);
+    }
+}
+
+fn spawn_clean_voting_dialogs(voting: Voting, data_clone: Arc<AppState>, message: String) {
+    let data = data_clone.clone();
+    data.task_tracker.spawn(async move {
+        if let Ok(dialogs) = data_clone.db.get_voting_dialogs(voting.id.as_str()).await {
+            let content = format!("{}: {}", message, voting.name);
+            let total = dialogs.len();
+            let mut failed = 0;
+
+            for dialog in dialogs {
+                // in channel dialogs are ephemeral, there is no message to update
+                if !dialog.message_id.is_empty() {
+                    if let Err(err) = clear_dialog_message(
+                        &data_clone.discord_client,
+                        &dialog.channel_id,
+                        &dialog.message_id,
+                        &content,
+                    )
+                    .await
+                    {
+                        tracing::error!(voting_id = %voting.id, user_id = %dialog.user_id, error = ?err, "updating dialog message failed");
+                        failed += 1;
+
+                        // the dialog row is only dropped once the retry is recorded
+                        let cleanup = FailedCleanup {
+                            voting_id: dialog.voting_id.clone(),
+                            user_id: dialog.user_id.clone(),
+                            channel_id: dialog.channel_id.clone(),
+                            message_id: dialog.message_id.clone(),
+                            content: content.clone(),
+                            attempts: 1,
+                            retry_unix: util::unix_timestamp() + cleanup_backoff_secs(1),
+                        };
+                        if let Err(err) = data_clone.db.save_failed_cleanup(cleanup).await {
+                            tracing::error!(voting_id = %voting.id, error = ?err, "saving failed cleanup into db failed");
+                            continue;
+                        }
+                    }
+                }
+
+                if let Err(err) = data_clone
+                    .db
+                    .delete_voting_dialog(&dialog.voting_id, &dialog.user_id)
+                    .await
+                {
+                    tracing::error!(error = ?err, "deleting voting dialog from db failed")
+                }
+            }
+
+            if failed > 0 {
+                tracing::warn!(voting_id = %voting.id, failed, total, "some voting dialogs were not cleaned up, they will be retried");
+            }
+        }
+
+        // result pages stay browsable and ballots downloadable after the voting is closed
+        if let Err(err) = data_clone
+            .db
+            .delete_custom_ids_except(
+                &voting.id,
+                vec![
+                    Action::ResultNext,
+                    Action::ResultPrevious,
+                    Action::DownloadBallots,
+                ],
+            )
+            .await
+        {
+            tracing::debug!("deleting custom ids from db failed: {:?}", err);
+        }
+
+        match data_clone.db.repair_custom_id_index(&voting.id).await {
+            Ok(0) => {}
+            Ok(pruned) => {
+                tracing::warn!(voting_id = %voting.id, pruned, "pruned orphaned custom id index entries")
+            }
+            Err(err) => {
+                tracing::error!(voting_id = %voting.id, error = ?err, "repairing custom id index failed")
+            }
+        }
+    });
+}
+
+// Replaces the dm dialog message, e.g. once the voting is closed
+async fn clear_dialog_message(
+    discord_client: &twilight_http::Client,
+    channel_id: &str,
+    message_id: &str,
+    content: &str,
+) -> Result<(), InteractionError> {
+    let (Ok(channel_id), Ok(message_id)) = (channel_id.parse::<u64>(), message_id.parse::<u64>())
+    else {
+        tracing::error!(%channel_id, %message_id, "parsing dialog message ids failed");
+        return Err(InteractionError::InternalServerError);
+    };
+
+    update_message(
+        discord_client,
+        Id::new(channel_id),
+        Id::new(message_id),
+        Some(content),
+        Some(&Vec::new()),
+        Some(&Vec::new()),
+    )
+    .await
+}
+
+fn cleanup_backoff_secs(attempts: u32) -> u64 {
+    CLEANUP_RETRY_BASE_SECS << attempts.saturating_sub(1).min(MAX_CLEANUP_ATTEMPTS)
+}
+
+async fn handle_dm_vote(
+    data: &Arc<AppState>,
+    interaction: &Interaction,
+    voting: Voting,
+    voting_id: &str,
+) -> InteractionResult {
+    let Some(user_id) = interaction.author() else {
+        tracing::error!(%voting_id, data = ?interaction.data, "user id not found");
+        return Err(InteractionError::InternalServerError);
+    };
+
+    // this can happen with lingering dialogs while completing or deleting voting
+    let VotingState::Open(voting) = VotingState::from(voting) else {
+        return ack_response();
+    };
+
+    let voting_dialog = match data
+        .db
+        .get_voting_dialog(voting_id, &user_id.id.to_string())
+        .await
+    {
+        Ok(v) => v,
+        Err(db::DbError::NotFound) => {
+            return ack_response();
+        }
+        Err(err) => {
+            tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "getting voting dialog from db failed");
+            return Err(InteractionError::InternalServerError);
+        }
+    };
+
+    // double click on the vote button, the ballot was already sent
+    if voting_dialog.submitted {
+        return ack_response();
+    }
+
+    if let Err(err) = validate_ballot(&voting, &voting_dialog.ballot) {
+        return Ok((StatusCode::OK, ephemeral_response(&err)));
+    }
+
+    // every question of a survey is checked before anything is submitted
+    let mut question_ballots = Vec::new();
+    for (i, question) in voting.questions.iter().enumerate() {
+        let question_ballot = voting_dialog
+            .question_ballot(i + 1)
+            .cloned()
+            .unwrap_or_default();
+        if let Some(view) = voting.question_view(i + 1) {
+            if let Err(err) = validate_ballot(&view, &question_ballot) {
+                return Ok((
+                    StatusCode::OK,
+                    ephemeral_response(&format!("Question \"{}\": {}", question.name, err)),
+                ));
+            }
+        }
+
+        let ballot: HashMap<String, i32> = question
+            .choices
+            .iter()
+            .cloned()
+            .zip(question_ballot)
+            .collect();
+        question_ballots.push((question.dd_voting_id.as_str(), ballot));
+    }
+
+    // the further questions go first, the pending vote below only guards the ballot of the
+    // voting itself. A failure leaves the dialog open, submitting again replaces the ballots.
+    for (dd_voting_id, ballot) in &question_ballots {
+        if let Err(err) = submit_weighted_vote(
+            data,
+            dd_voting_id,
+            &user_id.id.to_string(),
+            voting_dialog.weight,
+            voting.max_voter_weight(),
+            ballot,
+        )
+        .await
+        {
+            tracing::error!(%voting_id, %dd_voting_id, error = ?err, data = ?interaction.data, "voting on question failed");
+            return Err(err.into());
+        }
+    }
+
+    // unranked choices are sent with rank 0, which DD counts as tied last
+    let mut ballot = HashMap::new();
+
+    // the dialog ballot is indexed by the original choices, whatever order they were displayed in
+    for (name, value) in voting.choices.iter().zip(voting_dialog.ballot.iter()) {
+        ballot.insert(name.clone(), *value);
+    }
+
+    // the pending vote is replayed on startup if we crash before the dialog is updated
+    data.db
+        .save_pending_vote(PendingVote {
+            voting_id: voting_id.to_string(),
+            user_id: user_id.id.to_string(),
+            ballot: ballot.clone(),
+            weight: voting_dialog.weight,
+        })
+        .await
+        .map_err(|err| {
+            tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "saving pending vote into db failed");
+            InteractionError::InternalServerError
+        })?;
+
+    // dm interactions have no member, the weight was taken from the member roles when the dialog was opened
+    if let Err(err) = submit_weighted_vote(
+        data,
+        voting_id,
+        &user_id.id.to_string(),
+        voting_dialog.weight,
+        voting.max_voter_weight(),
+        &ballot,
+    )
+    .await
+    {
+        tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "voting failed");
+
+        // the backend answered, so the ballot was surely not recorded and must not be replayed
+        if !matches!(err, ApiError::Client(_) | ApiError::InternalServerError(_)) {
+            if let Err(err) = data
+                .db
+                .delete_pending_vote(voting_id, &user_id.id.to_string())
+                .await
+            {
+                tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "deleting pending vote from db failed");
+            }
+        }
+
+        return Err(err.into());
+    }
+
+    // the dialog is kept (marked as submitted) so the vote can be undone within the window
+    data.db
+        .set_voting_dialog_submitted(
+            voting_id,
+            &user_id.id.to_string(),
+            true,
+            util::unix_timestamp(),
+        )
+        .await
+        .map_err(|err| {
+            tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "marking voting dialog as submitted failed");
+            InteractionError::InternalServerError
+        })?;
+
+    let custom_uuid = util::generate_random_custom_uuid();
+    let mut custom_ids = vec![(
+        custom_uuid.clone(),
+        CustomID {
+            action: Action::UndoVote,
+            voting_id: voting_id.to_string(),
+            user_id: Some(user_id.id.to_string()),
+            page: None,
+            index: None,
+        },
+    )];
+
+    let undo_btn = Button {
+        custom_id: Some(custom_uuid),
+        disabled: false,
+        emoji: None,
+        label: Some("Undo".to_string()),
+        style: ButtonStyle::Secondary,
+        url: None,
+    };
+    let mut btns = vec![Component::Button(undo_btn)];
+
+    // anonymous votings don't collect comments
+    if !voting.anonymous {
+        let custom_uuid = util::generate_random_custom_uuid();
+        custom_ids.push((
+            custom_uuid.clone(),
+            CustomID {
+                action: Action::AddComment,
+                voting_id: voting_id.to_string(),
+                user_id: Some(user_id.id.to_string()),
+                page: None,
+                index: None,
+            },
+        ));
+        btns.push(Component::Button(Button {
+            custom_id: Some(custom_uuid),
+            disabled: false,
+            emoji: None,
+            label: Some("Add Comment".to_string()),
+            style: ButtonStyle::Secondary,
+            url: None,
+        }));
+    }
+
+    data.db
+        .bulk_save_custom_ids(custom_ids)
+        .await
+        .map_err(|err| {
+            tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "bulk saving custom ids into db failed");
+            InteractionError::InternalServerError
+        })?;
+
+    let components = vec![Component::ActionRow(ActionRow { components: btns })];
+
+    let response = update_dialog(
+        data,
+        interaction,
+        Some(
+            format!(
+                "Thank you for voting! Your vote has been successfully submitted. You can undo it within {} seconds.",
+                UNDO_VOTE_WINDOW_SECS
+            )
+            .as_str(),
+        ),
+        &[],
+        &components,
+    )
+    .await?;
+
+    if let Err(err) = data
+        .db
+        .delete_pending_vote(voting_id, &user_id.id.to_string())
+        .await
+    {
+        tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "deleting pending vote from db failed");
+    }
+
+    schedule_vote_count_edit(data, &voting);
+
+    Ok(response)
+}
+
+// Edits the vote count of the announcement in the background, at most once per
+// `VOTE_COUNT_EDIT_INTERVAL`. Votes arriving in between are picked up by the one pending edit,
+// which reads the count when it runs.
+fn schedule_vote_count_edit(data: &Arc<AppState>, voting: &Voting) {
+    if !voting.show_vote_count || voting.simple || voting.message_id.is_empty() {
+        return;
+    }
+
+    let delay = {
+        let mut edits = data
+            .vote_count_edits
+            .lock()
+            .expect("vote count edits lock poisoned");
+        let edit = edits.entry(voting.id.clone()).or_default();
+        if edit.pending {
+            return;
+        }
+        edit.pending = true;
+        edit.last_edit
+            .map(|last_edit| {
+                (last_edit + VOTE_COUNT_EDIT_INTERVAL).saturating_duration_since(Instant::now())
+            })
+            .unwrap_or_default()
+    };
+
+    let data_clone = data.clone();
+    let voting_id = voting.id.clone();
+    data.task_tracker.spawn(async move {
+        let data = data_clone;
+        tokio::time::sleep(delay).await;
+
+        if let Some(edit) = data
+            .vote_count_edits
+            .lock()
+            .expect("vote count edits lock poisoned")
+            .get_mut(&voting_id)
+        {
+            edit.pending = false;
+            edit.last_edit = Some(Instant::now());
+        }
+
+        let voting = match data.db.get_open_voting(&voting_id).await {
+            Ok(VotingState::Open(voting)) => voting,
+            // the closed announcement has no vote count anymore
+            Ok(_) => return,
+            Err(err) => {
+                tracing::error!(%voting_id, error = ?err, "getting voting from db failed");
+                return;
+            }
+        };
+
+        if let Err(err) = rerender_channel_announcement(&data, &voting).await {
+            tracing::error!(%voting_id, error = ?err, "updating vote count of announcement failed");
+        }
+    });
+}
+
+// DD counts one ballot per voter, a weighted ballot is submitted once for each weight unit.
+// The first copy is the voter itself, the others are suffixed with their number.
+pub fn weighted_voter_ids(user_id: &str, weight: u32) -> Vec<String> {
+    (1..=weight.max(1))
+        .map(|n| match n {
+            1 => user_id.to_string(),
+            n => format!("{}-{}", user_id, n),
+        })
+        .collect()
+}
+
+// Submits every copy of the ballot concurrently, resubmitting a copy just replaces it.
+// The copies above `weight` up to `max_weight` are unvoted, they are left from a ballot submitted
+// with a higher weight before, e.g. when the voter lost a weighted role in between.
+async fn submit_weighted_vote(
+    data: &Arc<AppState>,
+    voting_id: &str,
+    user_id: &str,
+    weight: u32,
+    max_weight: u32,
+    ballot: &HashMap<String, i32>,
+) -> Result<(), ApiError> {
+    let weight = weight.max(1);
+    let mut requests = tokio::task::JoinSet::new();
+
+    for voter_id in weighted_voter_ids(user_id, weight) {
+        let data = data.clone();
+        let voting_id = voting_id.to_string();
+        let ballot = ballot.clone();
+        requests.spawn(async move {
+            data.dd_client
+                .vote(&voting_id, &voter_id, ballot)
+                .await
+                .map(|_| ())
+        });
+    }
+
+    let stale_voter_ids = weighted_voter_ids(user_id, max_weight)
+        .into_iter()
+        .skip(weight as usize);
+    for voter_id in stale_voter_ids {
+        let data = data.clone();
+        let voting_id = voting_id.to_string();
+        requests.spawn(async move {
+            match data.dd_client.unvote(&voting_id, &voter_id).await {
+                // the copy was never submitted
+                Ok(_) | Err(ApiError::NotFound) => Ok(()),
+                Err(err) => Err(err),
+            }
+        });
+    }
+
+    while let Some(result) = requests.join_next().await {
+        match result {
+            Ok(result) => result?,
+            // the requests are not cancelled, so this is a panic of one of them
+            Err(err) => std::panic::resume_unwind(err.into_panic()),
+        }
+    }
+
+    Ok(())
+}
+
+// Checks the ballot ranks fit the voting, returns the message to show to the voter otherwise
+pub fn validate_ballot(voting: &Voting, ballot: &[i32]) -> Result<(), String> {
+    if ballot.len() != voting.choices.len() {
+        return Err("Your ballot does not match the voting choices.".to_string());
+    }
+
+    let rank_limit = voting.rank_limit();
+    if ballot
+        .iter()
+        .any(|&rank| rank < 0 || rank as usize > rank_limit)
+    {
+        return Err(format!(
+            "You can only rank choices from 1 to {}.",
+            rank_limit
+        ));
+    }
+
+    if voting.require_full_ranking && ballot.contains(&0) {
+        return Err("Please rank all choices before submitting.".to_string());
+    }
+
+    Ok(())
+}
+
+async fn handle_undo_vote(
+    data: &Arc<AppState>,
+    interaction: &Interaction,
+    voting: Voting,
+    voting_id: &str,
+) -> InteractionResult {
+    let Some(user) = interaction.author() else {
+        tracing::error!(%voting_id, data = ?interaction.data, "user id not found");
+        return Err(InteractionError::InternalServerError);
+    };
+
+    // votes can not be undone once the voting is closed
+    if voting.is_deleted || voting.is_completed {
+        return ack_response();
+    }
+
+    let voting_dialog = match data
+        .db
+        .get_voting_dialog(voting_id, &user.id.to_string())
+        .await
+    {
+        Ok(v) => v,
+        Err(db::DbError::NotFound) => {
+            return ack_response();
+        }
+        Err(err) => {
+            tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "getting voting dialog from db failed");
+            return Err(InteractionError::InternalServerError);
+        }
+    };
+
+    if !voting_dialog.submitted {
+        return ack_response();
+    }
+
+    if util::unix_timestamp().saturating_sub(voting_dialog.submitted_unix) > UNDO_VOTE_WINDOW_SECS {
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response("The undo window has expired, your vote stays submitted."),
+        ));
+    }
+
+    // the further questions of a survey were submitted along with the voting
+    let dd_voting_ids =
+        std::iter::once(voting_id).chain(voting.questions.iter().map(|q| q.dd_voting_id.as_str()));
+    for dd_voting_id in dd_voting_ids {
+        for voter_id in weighted_voter_ids(&user.id.to_string(), voting_dialog.weight) {
+            data.dd_client
+                .unvote(dd_voting_id, &voter_id)
+                .await
+                .map_err(|err| {
+                    tracing::error!(%voting_id, %dd_voting_id, error = ?err, data = ?interaction.data, "unvoting failed");
+                    InteractionError::from(err)
+                })?;
+        }
+    }
+
+    data.db
+        .set_voting_dialog_submitted(voting_id, &user.id.to_string(), false, 0)
+        .await
+        .map_err(|err| {
+            tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "reverting submitted voting dialog failed");
+            InteractionError::InternalServerError
+        })?;
+
+    schedule_vote_count_edit(data, &voting);
+
+    let question = voting_dialog.question;
+    let Some(ballot) = voting_dialog.question_ballot(question).cloned() else {
+        tracing::error!(%voting_id, question, data = ?interaction.data, "question ballot not found");
+        return Err(InteractionError::InternalServerError);
+    };
+
+    let mut voting = voting;
+    if voting_dialog.display_order.is_some() {
+        voting.display_order = voting_dialog.display_order;
+    }
+
+    let (title, components, custom_ids) =
+        create_vote_components(voting_id, voting, question, 1, ballot, &user.id.to_string());
+    data.db
+        .bulk_save_custom_ids(custom_ids)
+        .await
+        .map_err(|err| {
+            tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "bulk saving custom ids into db failed");
+            InteractionError::InternalServerError
+        })?;
+
+    update_dialog(data, interaction, Some(""), &title, &components).await
+}
+
+async fn handle_vote_select(
+    data: &Arc<AppState>,
+    interaction: &Interaction,
+    voting: Voting,
+    command: &MessageComponentInteractionData,
+    custom_id: &CustomID,
+) -> InteractionResult {
+    let voting_id = &custom_id.voting_id;
+    let Some(index) = custom_id.index else {
+        tracing::error!(%voting_id, data = ?interaction.data, "index not found");
+        return Err(InteractionError::InternalServerError);
+    };
+
+    let Some(user_id) = interaction.author() else {
+        tracing::error!(%voting_id, data = ?interaction.data, "user id not found");
+        return Err(InteractionError::InternalServerError);
+    };
+
+    let Some(vote) = command.values.first() else {
+        tracing::error!(%voting_id, data = ?interaction.data, "vote not found");
+        return Err(InteractionError::InternalServerError);
+    };
+
+    let vote = vote.parse::<i32>().map_err(|err| {
+        tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "parsing vote failed");
+        InteractionError::InternalServerError
+    })?;
+
+    // this can happen with lingering dialogs while completing or deleting voting
+    let VotingState::Open(voting) = VotingState::from(voting) else {
+        return ack_response();
+    };
+
+    match data
+        .db
+        .vote_voting_dialog(voting_id, &user_id.id.to_string(), vote, index)
+        .await
+    {
+        Ok(()) => ack_response(),
+        // the select only offers valid ranks, this is a crafted interaction
+        Err(db::DbError::InvalidRank) => {
+            tracing::warn!(%voting_id, %vote, data = ?interaction.data, "rejected invalid rank");
+            Ok((
+                StatusCode::OK,
+                ephemeral_response(&format!(
+                    "You can only rank choices from 1 to {}.",
+                    voting.rank_limit()
+                )),
+            ))
+        }
+        Err(err) => {
+            tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "updating vote in db failed");
+            Err(InteractionError::InternalServerError)
+        }
+    }
+}
+
+async fn handle_vote_channel(
+    data: &Arc<AppState>,
+    interaction: &Interaction,
+    voting: Voting,
+    voting_id: &str,
+) -> InteractionResult {
+    // this can happen with lingering dialogs while completing or deleting voting
+    let VotingState::Open(voting) = VotingState::from(voting) else {
+        return ack_response();
+    };
+
+    let Some(ref member) = interaction.member else {
+        tracing::error!(%voting_id, data = ?interaction.data, "member not found");
+        return Err(InteractionError::InternalServerError);
+    };
+
+    let Some(ref user) = member.user else {
+        tracing::error!(%voting_id, data = ?interaction.data, "user id not found");
+        return Err(InteractionError::InternalServerError);
+    };
+
+    // member roles are part of the guild interaction payload
+    if let Some(ref allowed_role_id) = voting.allowed_role_id {
+        if !member
+            .roles
+            .iter()
+            .any(|role_id| role_id.to_string() == *allowed_role_id)
+        {
+            return Ok((
+                StatusCode::OK,
+                ephemeral_response("You don't have permission to vote in this poll."),
+            ));
+        }
+    }
+
+    // users who already opened a dialog can finish it, or vote again within the undo window
+    if voting.dialogs_locked {
+        match data.db.dialog_exists(voting_id, &user.id.to_string()).await {
+            Ok(true) => {}
+            Ok(false) => {
+                return Ok((
+                    StatusCode::OK,
+                    ephemeral_response("This voting is closed to new voters."),
+                ));
+            }
+            Err(err) => {
+                tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "checking voting dialog in db failed");
+                return Err(InteractionError::InternalServerError);
+            }
+        }
+    }
+
+    // dm interactions carry no member, so the weight is kept on the dialog
+    let weight = voting.voter_weight(&member.roles);
+
+    // shuffled per voter, seeded so the order stays the same on every page of the dialog
+    let display_order = voting.shuffle.then(|| {
+        util::stable_permutation(&format!("{}-{}", voting_id, user.id), voting.choices.len())
+    });
+
+    let mut voting = voting;
+    if display_order.is_some() {
+        voting.display_order = display_order.clone();
+    }
+
+    let in_channel = voting.in_channel;
+    let ballot: Vec<i32> = vec![0; voting.choices.len()];
+    let (title, components, custom_ids) = create_vote_components(
+        voting_id,
+        voting,
+        0,
+        1,
+        ballot.clone(),
+        &user.id.to_string(),
+    );
+
+    // the custom ids are saved with the slot, and released with it if anything below fails,
+    // so the user can click vote again
+    // a submitted dialog only lingers for the undo window, voting again replaces it
+    let slot = match data
+        .db
+        .open_dialog_with_custom_ids(voting_id, &user.id.to_string(), custom_ids)
+        .await
+    {
+        Ok(slot) => slot,
+        Err(db::DbError::AlreadyExists) => {
+            return Ok((StatusCode::OK, ephemeral_response("You already have voting dialog open or it is being sent to you. If that is not the case, please contact support.")));
+        }
+        Err(err) => {
+            tracing::error!(%voting_id, error = ?err, data = ?interaction.data, "saving voting dialog into db failed");
+            return Err(InteractionError::InternalServerError);
+        }
+    };
+
+    let slot = slot.with_weight(weight);
+
+    if in_channel {
+        return in_channel_dialog(slot, ballot, display_order, title, components).await;
+    }
+
+    // the dm is sent after replying, so a slow discord api doesn't leave the user waiting
+    let dialog = PreparedDialog {
+        slot,
+        ballot,
+        display_order,
+        embeds: title,
+        components,
+    };
+    data.task_tracker.spawn(send_dm_dialog(
+        data.clone(),
+        interaction.clone(),
+        voting_id.to_string(),
+        user.id,
+        dialog,
+    ));
+
+    let response = Json(InteractionResponse {
+        kind: InteractionResponseType::ChannelMessageWithSource,
+        data: Some(InteractionResponseData {
+            content: Some("You will receive dm with voting dialog".to_string()),
+            flags: Some(MessageFlags::EPHEMERAL),
+            ..Default::default()
+        }),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+// Voting dialog of a user waiting to be sent, the slot is committed once it is delivered
+struct PreparedDialog {
+    slot: DialogSlot,
+    ballot: Vec<i32>,
+    display_order: Option<Vec<usize>>,
+    embeds: Vec<Embed>,
+    components: Vec<Component>,
+}
+
+// Sends the voting dialog to the user dm and updates the ephemeral vote reply with the outcome
+async fn send_dm_dialog(
+    data: Arc<AppState>,
+    interaction: Interaction,
+    voting_id: String,
+    user_id: Id<UserMarker>,
+    dialog: PreparedDialog,
+) {
+    let (content, embeds, components) = match deliver_dm_dialog(&data, &voting_id, user_id, dialog)
+        .await
+    {
+        Ok(reply) => reply,
+        Err(InteractionError::DmUnavailable) => {
+            (DM_UNAVAILABLE_MESSAGE.to_string(), Vec::new(), Vec::new())
+        }
+        Err(InteractionError::BotUser) => (BOT_USER_MESSAGE.to_string(), Vec::new(), Vec::new()),
+        // the slot was released, the user can click vote again
+        Err(_) => (
+            "Sending the voting dialog failed, please click vote again.".to_string(),
+            Vec::new(),
+            Vec::new(),
+        ),
+    };
+
+    let client = data.discord_client.interaction(interaction.application_id);
+    let update = client
+        .update_response(&interaction.token)
+        .content(Some(&content))
+        .and_then(|update| update.embeds(Some(&embeds)))
+        .and_then(|update| update.components(Some(&components)));
+
+    match update {
+        Ok(update) => {
+            if let Err(err) = update.await {
+                tracing::error!(%voting_id, error = ?err, "updating vote reply failed");
+            }
+        }
+        Err(err) => {
+            tracing::error!(%voting_id, error = ?err, "building vote reply update failed");
+        }
+    }
+}
+
+// Returns the content, embeds and components of the vote reply once the dialog is delivered.
+// When the user doesn't accept dms the dialog itself is shown in the reply.
+async fn deliver_dm_dialog(
+    data: &Arc<AppState>,
+    voting_id: &str,
+    user_id: Id<UserMarker>,
+    dialog: PreparedDialog,
+) -> Result<(String, Vec<Embed>, Vec<Component>), InteractionError> {
+    let PreparedDialog {
+        slot,
+        ballot,
+        display_order,
+        embeds,
+        components,
+    } = dialog;
+
+    let (channel_id, message_id) = match send_dm_dialog_message(
+        data,
+        voting_id,
+        user_id,
+        &embeds,
+        &components,
+    )
+    .await
+    {
+        Ok(Some(ids)) => ids,
+        Ok(None) => {
+            tracing::info!(%voting_id, "dms are disabled, sending the voting dialog in channel");
+            return in_reply_dialog(slot, ballot, display_order, embeds, components).await;
+        }
+        Err(err) => {
+            if let Err(err) = slot.release().await {
+                tracing::error!(%voting_id, error = ?err, "releasing dialog slot failed");
+            }
+            return Err(err);
+        }
+    };
+
+    slot.commit(
+        ballot,
+        display_order,
+        message_id.to_string(),
+        channel_id.to_string(),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(%voting_id, error = ?err, "saving voting dialog into db failed");
+        InteractionError::InternalServerError
+    })?;
+
+    Ok((
+        format!(
+            "Your voting dialog is ready: https://discord.com/channels/@me/{}/{}",
+            channel_id, message_id
+        ),
+        Vec::new(),
+        Vec::new(),
+    ))
+}
+
+// Sends the voting dialog to the user dm and returns the channel and message ids,
+// None when the user doesn't accept dms
+async fn send_dm_dialog_message(
+    data: &Arc<AppState>,
+    voting_id: &str,
+    user_id: Id<UserMarker>,
+    embeds: &[Embed],
+    components: &[Component],
+) -> Result<Option<(Id<ChannelMarker>, Id<MessageMarker>)>, InteractionError> {
+    let dm_channel = match open_dm_channel(data, user_id, DM_CHANNEL_BACKGROUND_BUDGET).await {
+        Ok(dm_channel) => dm_channel,
+        Err(DmChannelError::Disabled) => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+
+    match data
+        .discord_client
+        .create_message(dm_channel.id)
+        .embeds(embeds)
+        .map_err(|err| {
+            tracing::error!(%voting_id, error = ?err, "embeds failed");
+            InteractionError::InternalServerError
+        })?
+        .components(components)
+        .map_err(|err| {
+            tracing::error!(%voting_id, error = ?err, "components failed");
+            InteractionError::InternalServerError
+        })?
+        .await
+    {
+        Ok(message) => {
+            let message = message.model().await.map_err(|err| {
+                tracing::error!(%voting_id, error = ?err, "getting message model failed");
+                InteractionError::InternalServerError
+            })?;
+            Ok(Some((dm_channel.id, message.id)))
+        }
+        // the dm channel can be created even when the user does not accept dms
+        Err(err) if is_dm_disabled(&err) => Ok(None),
+        Err(err) => {
+            tracing::error!(%voting_id, error = ?err, "creating dm message failed");
+            Err(InteractionError::InternalServerError)
+        }
+    }
+}
+
+// Dialog shown in the vote reply instead of the dm, it has no message ids
+async fn in_reply_dialog(
+    slot: DialogSlot,
+    ballot: Vec<i32>,
+    display_order: Option<Vec<usize>>,
+    embeds: Vec<Embed>,
+    components: Vec<Component>,
+) -> Result<(String, Vec<Embed>, Vec<Component>), InteractionError> {
+    slot.commit(ballot, display_order, String::new(), String::new())
+        .await
+        .map_err(|err| {
+            tracing::error!(error = ?err, "saving voting dialog into db failed");
+            InteractionError::InternalServerError
+        })?;
+
+    Ok((DM_DISABLED_MESSAGE.to_string(), embeds, components))
+}
+
+// Sends the voting dialog as an ephemeral reply in the channel instead of a dm.
+// The dialog has no message ids, its pages are updated through the component interactions.
+async fn in_channel_dialog(
+    slot: DialogSlot,
+    ballot: Vec<i32>,
+    display_order: Option<Vec<usize>>,
+    embeds: Vec<Embed>,
+    components: Vec<Component>,
+) -> InteractionResult {
+    slot.commit(ballot, display_order, String::new(), String::new())
+        .await
+        .map_err(|err| {
+            tracing::error!(error = ?err, "saving voting dialog into db failed");
+            InteractionError::InternalServerError
+        })?;
+
+    let response = Json(InteractionResponse {
+        kind: InteractionResponseType::ChannelMessageWithSource,
+        data: Some(InteractionResponseData {
+            embeds: Some(embeds),
+            components: Some(components),
+            flags: Some(MessageFlags::EPHEMERAL),
+            ..Default::default()
+        }),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+// Discord answers with 403 (code 50007) when the user does not accept dms from the bot
+fn is_dm_disabled(err: &twilight_http::Error) -> bool {
+    match err.kind() {
+        twilight_http::error::ErrorType::Response { status, error, .. } => {
+            status.get() == 403
+                || matches!(
+                    error,
+                    twilight_http::api_error::ApiError::General(general) if general.code == 50007
+                )
+        }
+        _ => false,
+    }
+}
+
+enum DmChannelError {
+    // the user is the bot itself, it can't open a dm with itself
+    OwnAccount,
+    // the user does not accept dms from the bot
+    Disabled,
+    // discord kept failing or timing out on every attempt
+    Unavailable,
+    Other,
+}
+
+impl From<DmChannelError> for InteractionError {
+    fn from(err: DmChannelError) -> Self {
+        match err {
+            DmChannelError::OwnAccount => InteractionError::BotUser,
+            DmChannelError::Disabled | DmChannelError::Unavailable => {
+                InteractionError::DmUnavailable
+            }
+            DmChannelError::Other => InteractionError::InternalServerError,
+        }
+    }
+}
+
+// Opens the dm channel with the user, retrying timeouts, server errors and rate limits
+// until `budget` is used up
+async fn open_dm_channel(
+    data: &AppState,
+    user_id: Id<UserMarker>,
+    budget: Duration,
+) -> Result<Channel, DmChannelError> {
+    // e.g. interactions relayed by a proxy or a webhook integration as the bot
+    if data.is_bot_user(user_id) {
+        tracing::warn!(%user_id, "not opening a dm channel with the bot itself");
+        return Err(DmChannelError::OwnAccount);
+    }
+
+    let discord_client = &data.discord_client;
+    let deadline = tokio::time::Instant::now() + budget;
+    for attempt in 1..=DM_CHANNEL_ATTEMPTS {
+        if attempt > 1 {
+            let retry_at = tokio::time::Instant::now() + DM_CHANNEL_RETRY_DELAY * (attempt - 1);
+            if retry_at >= deadline {
+                break;
+            }
+            tokio::time::sleep_until(retry_at).await;
+        }
+
+        let attempt_deadline = deadline.min(tokio::time::Instant::now() + DM_CHANNEL_TIMEOUT);
+        let request = discord_client.create_private_channel(user_id).into_future();
+        match tokio::time::timeout_at(attempt_deadline, request).await {
+            Ok(Ok(response)) => {
+                return response.model().await.map_err(|err| {
+                    tracing::error!(%user_id, error = ?err, "getting dm channel model failed");
+                    DmChannelError::Other
+                });
+            }
+            Ok(Err(err)) if is_dm_disabled(&err) => return Err(DmChannelError::Disabled),
+            Ok(Err(err)) if is_retryable(&err) => {
+                tracing::warn!(%user_id, attempt, error = ?err, "creating dm channel failed");
+            }
+            Ok(Err(err)) => {
+                tracing::error!(%user_id, error = ?err, "creating dm channel failed");
+                return Err(DmChannelError::Other);
+            }
+            Err(_) => {
+                tracing::warn!(%user_id, attempt, "creating dm channel timed out");
+            }
+        }
+    }
+
+    tracing::error!(%user_id, "creating dm channel failed within its time budget");
+    Err(DmChannelError::Unavailable)
+}
+
+// Adds the reactions in order, since discord lists them in the order they were added and
+// they all count against the same rate limit, sending them concurrently would not be faster.
+// Rate limited reactions are retried after the wait discord asks for. A reaction which still
+// fails is logged and skipped, voters can add it themselves, so the voting stays usable.
+// Returns how many reactions were added.
+pub async fn add_reactions(
+    discord_client: &twilight_http::Client,
+    channel_id: Id<ChannelMarker>,
+    message_id: Id<MessageMarker>,
+    emojis: &[String],
+) -> usize {
+    let mut added = 0;
+
+    for emoji in emojis {
+        for attempt in 1..=REACTION_ATTEMPTS {
+            let res = discord_client
+                .create_reaction(
+                    channel_id,
+                    message_id,
+                    &RequestReactionType::Unicode { name: emoji },
+                )
+                .await;
+
+            match res {
+                Ok(_) => {
+                    added += 1;
+                    break;
+                }
+                Err(err) => match rate_limit_delay(&err) {
+                    Some(delay) if attempt < REACTION_ATTEMPTS => {
+                        tracing::warn!(%message_id, %emoji, attempt, ?delay, "adding reaction was rate limited");
+                        tokio::time::sleep(delay).await;
+                    }
+                    _ => {
+                        tracing::error!(%message_id, %emoji, error = ?err, "adding reaction failed");
+                        break;
+                    }
+                },
+            }
+        }
+    }
+
+    added
+}
+
+// How long to wait before sending a rate limited request again, `None` for other errors.
+// The wait is the retry_after of the 429 body, which matches its Retry-After header.
+fn rate_limit_delay(err: &twilight_http::Error) -> Option<Duration> {
+    let twilight_http::error::ErrorType::Response { error, status, .. } = err.kind() else {
+        return None;
+    };
+    if status.get() != 429 {
+        return None;
+    }
+
+    let delay = match error {
+        twilight_http::api_error::ApiError::Ratelimited(ratelimited)
+            if ratelimited.retry_after.is_finite() && ratelimited.retry_after >= 0.0 =>
+        {
+            Duration::from_secs_f64(
+                ratelimited
+                    .retry_after
+                    .min(MAX_REACTION_RETRY_DELAY.as_secs_f64()),
+            )
+        }
+        _ => REACTION_RETRY_DELAY,
+    };
+
+    Some(delay)
+}
+
+// Server errors, rate limits and network failures can succeed when sent again
+fn is_retryable(err: &twilight_http::Error) -> bool {
+    match err.kind() {
+        twilight_http::error::ErrorType::Response { status, .. } => {
+            status.is_server_error() || status.get() == 429
+        }
+        twilight_http::error::ErrorType::RequestError
+        | twilight_http::error::ErrorType::RequestTimedOut => true,
+        _ => false,
+    }
+}
+
+// Updates the voting dialog the component belongs to, dm dialogs are edited by their message id
+// and in channel dialogs, which are ephemeral, through the interaction response.
+async fn update_dialog(
+    data: &Arc<AppState>,
+    interaction: &Interaction,
+    content: Option<&str>,
+    embeds: &[Embed],
+    components: &[Component],
+) -> InteractionResult {
+    if !interaction.is_dm() {
+        let response = Json(InteractionResponse {
+            kind: InteractionResponseType::UpdateMessage,
+            data: Some(InteractionResponseData {
+                content: content.map(str::to_string),
+                embeds: Some(embeds.to_vec()),
+                components: Some(components.to_vec()),
+                ..Default::default()
+            }),
+        });
+
+        return Ok((StatusCode::OK, response));
+    }
+
+    let Some(ref channel) = interaction.channel else {
+        tracing::error!(data = ?interaction.data, "channel not found");
+        return Err(InteractionError::InternalServerError);
+    };
+
+    let Some(ref message) = interaction.message else {
+        tracing::error!(data = ?interaction.data, "message not found");
+        return Err(InteractionError::InternalServerError);
+    };
+
+    update_message(
+        &data.discord_client,
+        channel.id,
+        message.id,
+        content,
+        Some(embeds),
+        Some(components),
+    )
+    .await?;
+
+    ack_response()
+}
+
+// Dialog page of question `question` of the voting, `ballot` is the ballot of that question
+fn create_vote_components(
+    voting_id: &str,
+    voting: Voting,
+    question: usize,
+    page: usize,
+    ballot: Vec<i32>,
+    user_id: &str,
+) -> (Vec<Embed>, Vec<Component>, Vec<(String, CustomID)>) {
+    let question_count = voting.question_count();
+    // further questions are ranked like the voting itself
+    let voting = voting.question_view(question).unwrap_or(voting);
+
+    let page_size = 4;
+    let total_pages = voting.choices.len().div_ceil(page_size);
+    let start = (page - 1) * page_size;
+    let end = usize::min(start + page_size, voting.choices.len());
+
+    // pages follow the display order, while custom ids and the ballot keep the original indices
+    let display_indices = voting.display_indices();
+
+    let embed_title = if voting.choices.len() > page_size {
+        format!("Voting Choices - Page {} of {}", page, total_pages)
+    } else {
+        "Voting Choices".to_string()
+    };
+    // surveys name the question, the voters rank several of them in the same dialog
+    let embed_title = if question_count > 1 {
+        format!(
+            "Question {} of {}: {} - {}",
+            question + 1,
+            question_count,
+            voting.name,
+            embed_title
+        )
+    } else {
+        embed_title
+    };
+
+    let page_indices = &display_indices[start..end];
+    let mut title = EmbedBuilder::new().title(embed_title);
+
+    // a few long choices would overflow the description, each of them gets its own field instead
+    if page_indices
+        .iter()
+        .any(|&index| voting.choices[index].chars().count() > LONG_CHOICE_LENGTH)
+    {
+        for (i, &index) in page_indices.iter().enumerate() {
+            title = title.field(
+                EmbedFieldBuilder::new(format!("Choice {}", start + i + 1), &voting.choices[index])
+                    .build(),
+            );
+        }
+    } else {
+        let paginated_choices = page_indices
+            .iter()
+            .enumerate()
+            .map(|(i, &index)| format!("**{}**: {}", start + i + 1, voting.choices[index]))
+            .collect::<Vec<_>>()
+            .join("\n");
+        title = title.description(paginated_choices);
+    }
+
+    let title = title.build();
+
+    let options: Vec<SelectMenuOption> = (1..=voting.rank_limit())
+        .map(|i| SelectMenuOption {
+            default: false,
+            description: None,
+            emoji: None,
+            label: i.to_string(),
+            value: i.to_string(),
+        })
+        .collect();
+
+    let mut custom_ids: Vec<(String, CustomID)> = Vec::new();
+
+    let mut components: Vec<Component> = display_indices[start..end]
+        .iter()
+        .map(|&index| {
+            let placeholder = match ballot[index] {
+                0 => "Select".to_string(),
+                _ => ballot[index].to_string(),
+            };
+
+            let custom_uuid = util::generate_random_custom_uuid();
+            let custom_id = CustomID {
+                action: Action::VoteSelect,
+                voting_id: voting_id.to_string(),
+                user_id: Some(user_id.to_string()),
+                page: None,
+                index: Some(index),
+            };
+
+            custom_ids.push((custom_uuid.clone(), custom_id));
+
+            // every rank of the choice shows its description
+            let description = voting.choice_description(index).map(str::to_string);
+            let options = options
+                .iter()
+                .cloned()
+                .map(|option| SelectMenuOption {
+                    description: description.clone(),
+                    ..option
+                })
+                .collect();
+
+            Component::ActionRow(ActionRow {
+                components: Vec::from([Component::SelectMenu(
+                    twilight_model::channel::message::component::SelectMenu {
+                        custom_id: custom_uuid,
+                        disabled: false,
+                        max_values: Some(1),
+                        min_values: Some(1),
+                        options,
+                        placeholder: Some(placeholder),
+                    },
+                )]),
+            })
+        })
+        .collect();
+
+    let mut btns = Vec::new();
+
+    if page == 1 && question > 0 {
+        let custom_uuid = util::generate_random_custom_uuid();
+        custom_ids.push((
+            custom_uuid.clone(),
+            CustomID {
+                action: Action::PreviousQuestion,
+                voting_id: voting_id.to_string(),
+                user_id: Some(user_id.to_string()),
+                page: None,
+                index: None,
+            },
+        ));
+
+        btns.push(Component::Button(Button {
+            custom_id: Some(custom_uuid),
+            disabled: false,
+            emoji: None,
+            label: Some("Previous Question".to_string()),
+            style: ButtonStyle::Secondary,
+            url: None,
+        }));
+    }
+
+    if page > 1 {
+        let custom_uuid = util::generate_random_custom_uuid();
+        custom_ids.push((
+            custom_uuid.clone(),
+            CustomID {
+                action: Action::VotePrevious,
+                voting_id: voting_id.to_string(),
+                user_id: Some(user_id.to_string()),
+                page: Some(page - 1),
+                index: None,
+            },
+        ));
+
+        btns.push(Component::Button(Button {
+            custom_id: Some(custom_uuid),
+            disabled: false,
+            emoji: None,
+            label: Some("Previous".to_string()),
+            style: ButtonStyle::Secondary,
+            url: None,
+        }));
+    }
+
+    if total_pages > page {
+        let custom_uuid = util::generate_random_custom_uuid();
+        custom_ids.push((
+            custom_uuid.clone(),
+            CustomID {
+                action: Action::VoteNext,
+                voting_id: voting_id.to_string(),
+                user_id: Some(user_id.to_string()),
+                page: Some(page + 1),
+                index: None,
+            },
+        ));
+
+        btns.push(Component::Button(Button {
+            custom_id: Some(custom_uuid),
+            disabled: false,
+            emoji: None,
+            label: Some("Next".to_string()),
+            style: ButtonStyle::Secondary,
+            url: None,
+        }))
+    }
+
+    let custom_uuid = util::generate_random_custom_uuid();
+    custom_ids.push((
+        custom_uuid.clone(),
+        CustomID {
+            action: Action::ResetBallot,
+            voting_id: voting_id.to_string(),
+            user_id: Some(user_id.to_string()),
+            page: None,
+            index: None,
+        },
+    ));
+    btns.push(Component::Button(Button {
+        custom_id: Some(custom_uuid),
+        disabled: false,
+        emoji: None,
+        label: Some("Reset".to_string()),
+        style: ButtonStyle::Secondary,
+        url: None,
+    }));
+
+    if page == total_pages && question + 1 < question_count {
+        let custom_uuid = util::generate_random_custom_uuid();
+        custom_ids.push((
+            custom_uuid.clone(),
+            CustomID {
+                action: Action::NextQuestion,
+                voting_id: voting_id.to_string(),
+                user_id: Some(user_id.to_string()),
+                page: None,
+                index: None,
+            },
+        ));
+        btns.push(Component::Button(Button {
+            custom_id: Some(custom_uuid),
+            disabled: false,
+            emoji: None,
+            label: Some("Next Question".to_string()),
+            style: ButtonStyle::Primary,
+            url: None,
+        }))
+    } else if page == total_pages {
+        let custom_uuid = util::generate_random_custom_uuid();
+        custom_ids.push((
+            custom_uuid.clone(),
+            CustomID {
+                action: Action::VoteFromDM,
+                voting_id: voting_id.to_string(),
+                user_id: Some(user_id.to_string()),
+                page: None,
+                index: None,
+            },
+        ));
+        btns.push(Component::Button(Button {
+            custom_id: Some(custom_uuid),
+            disabled: false,
+            emoji: None,
+            label: Some("Vote".to_string()),
+            style: ButtonStyle::Primary,
+            url: None,
+        }))
+    }
+
+    components.push(Component::ActionRow(ActionRow { components: btns }));
+
+    (vec![title], components, custom_ids)
+}
+
+// Embeds ranking the choices of the further questions of a survey, shown below the results.
+// A question whose results can't be fetched is left out, the voting results are still shown.
+async fn create_question_result_embeds(data: &Arc<AppState>, voting: &Voting) -> Vec<Embed> {
+    let mut embeds = Vec::new();
+
+    for question in &voting.questions {
+        let results = match data
+            .dd_client
+            .get_voting_results_duels(&question.dd_voting_id)
+            .await
+        {
+            Ok(results) => results,
+            Err(err) => {
+                tracing::error!(voting_id = %voting.id, dd_voting_id = %question.dd_voting_id, error = ?err, "getting question results failed");
+                continue;
+            }
+        };
+
+        let description = if results.results.is_empty() {
+            "No votes were cast.".to_string()
+        } else {
+            results
+                .results
+                .iter()
+                .enumerate()
+                .map(|(i, result)| {
+                    format!(
+                        "**{}**: {} ({:.2}%)",
+                        i + 1,
+                        result.choice,
+                        result.percentage
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+
+        embeds.push(
+            EmbedBuilder::new()
+                .title(question.name.clone())
+                .description(description)
+                .build(),
+        );
+    }
+
+    embeds
+}
+
+// Custom ids of the result pagination buttons. Every page reuses them with the new target page,
+// so paging doesn't store new custom ids.
+struct ResultPageIds {
+    previous: String,
+    next: String,
+}
+
+impl ResultPageIds {
+    fn new() -> Self {
+        ResultPageIds {
+            previous: util::generate_random_custom_uuid(),
+            next: util::generate_random_custom_uuid(),
+        }
+    }
+
+    // Picks the ids already stored for the voting, a missing one is generated
+    fn from_custom_ids(custom_ids: &[(String, CustomID)]) -> Self {
+        let mut page_ids = ResultPageIds::new();
+        for (uuid, custom_id) in custom_ids {
+            match custom_id.action {
+                Action::ResultPrevious => page_ids.previous = uuid.clone(),
+                Action::ResultNext => page_ids.next = uuid.clone(),
+                _ => {}
+            }
+        }
+        page_ids
+    }
+}
+
+// Renders the results of a completed voting.
+// The ranking and the head-to-head breakdown are paged separately, ranking pages come first.
+// Small votings fit on a single page, which shows both sections without pagination buttons.
+fn create_results_components(
+    voting_id: &str,
+    voting: &Voting,
+    results: &VotingResults,
+    comments: &[(String, String)],
+    page: usize,
+    page_ids: &ResultPageIds,
+) -> (Vec<Embed>, Vec<Component>, Vec<(String, CustomID)>) {
+    let page_size = 10;
+
+    // without votes there is nothing to rank or break down, simple votings list every choice with 0 votes
+    let no_votes = results.results.is_empty()
+        || (voting.simple && results.results.iter().all(|result| result.wins == 0));
+    if no_votes {
+        let embed = EmbedBuilder::new()
+            .title(voting.name.clone())
+            .description("No votes were cast.")
+            .color(NO_VOTES_COLOR)
+            .build();
+        return (vec![embed], Vec::new(), Vec::new());
+    }
+
+    let description = if results.tie {
+        match break_tie(voting, results) {
+            Some(winner) => format!(
+                "Tie broken by {}: {}",
+                tie_break_name(voting.tie_break),
+                winner.choice
+            ),
+            None => "Its a tie!".to_string(),
+        }
+    } else if voting.simple {
+        "Voting results were counted from the reactions. The choices are ranked by votes."
+            .to_string()
+    } else {
+        "Voting results were calculated using Shultze method. The users are ranked by winning percentages."
+            .to_string()
+    };
+
+    let mut duel_messages = Vec::new();
+    if let Some(ref duels) = results.duels {
+        if !results.tie {
+            for duel in duels {
+                let message = if duel.left.strength == duel.right.strength {
+                    format!(
+                        "**{}** and **{}** are tied",
+                        duel.left.choice, duel.right.choice
+                    )
+                } else {
+                    let (left, right) = if duel.left.strength > duel.right.strength {
+                        (&duel.left, &duel.right)
+                    } else {
+                        (&duel.right, &duel.left)
+                    };
+
+                    format!(
+                        "**{}** defeats **{}** by ({} - {}) = {} votes",
+                        left.choice,
+                        right.choice,
+                        left.strength,
+                        right.strength,
+                        left.strength - right.strength
+                    )
+                };
+                duel_messages.push(message);
+            }
+        }
+    }
+
+    let ranking_pages = usize::max(results.results.len().div_ceil(page_size), 1);
+    let duel_pages = duel_messages.len().div_ceil(page_size);
+    let total_pages = if ranking_pages == 1 && duel_pages <= 1 {
+        1
+    } else {
+        ranking_pages + duel_pages
+    };
+
+    let ranking_embed = |section_page: usize| {
+        let start = (section_page - 1) * page_size;
+        let end = usize::min(start + page_size, results.results.len());
+
+        let title = if ranking_pages > 1 {
+            format!(
+                "{} - Page {} of {}",
+                voting.name, section_page, ranking_pages
+            )
+        } else {
+            voting.name.clone()
+        };
+
+        let mut embed = EmbedBuilder::new()
+            .title(title)
+            .description(description.as_str());
+        for result in &results.results[start..end] {
+            let field_text = if voting.simple {
+                format!(
+                    "Votes: {}, Percentage: {:.2}%",
+                    result.wins, result.percentage
+                )
+            } else {
+                let first_choices = voting
+                    .first_choice_counts
+                    .as_ref()
+                    .map(|counts| {
+                        let count = usize::try_from(result.index)
+                            .ok()
+                            .and_then(|index| counts.get(&index))
+                            .copied()
+                            .unwrap_or(0);
+                        format!(" · {} first-choice votes", count)
+                    })
+                    .unwrap_or_default();
+
+                format!(
+                    "Wins: {}, Percentage: {:.2}%{}",
+                    result.wins, result.percentage, first_choices
+                )
+            };
+            embed = embed.field(EmbedFieldBuilder::new(&result.choice, field_text).build());
+        }
+
+        embed.build()
+    };
+
+    let duels_embed = |section_page: usize| {
+        let start = (section_page - 1) * page_size;
+        let end = usize::min(start + page_size, duel_messages.len());
+
+        let title = if duel_pages > 1 {
+            format!("Result breakdown - Page {} of {}", section_page, duel_pages)
+        } else {
+            "Result breakdown".to_string()
+        };
+
+        let mut embed = EmbedBuilder::new().title(title);
+        for message in &duel_messages[start..end] {
+            embed = embed.field(EmbedFieldBuilder::new("", message).build());
+        }
+
+        embed.build()
+    };
+
+    let page = usize::min(usize::max(page, 1), total_pages);
+    let mut embeds = Vec::new();
+    if total_pages == 1 {
+        embeds.push(ranking_embed(1));
+        if duel_pages == 1 {
+            embeds.push(duels_embed(1));
+        }
+    } else if page <= ranking_pages {
+        embeds.push(ranking_embed(page));
+    } else {
+        embeds.push(duels_embed(page - ranking_pages));
+    }
+
+    // comments name their voters, so they are never shown for anonymous votings
+    if page == 1 && !voting.anonymous && !comments.is_empty() {
+        embeds.push(comments_embed(comments));
+    }
+
+    let mut custom_ids: Vec<(String, CustomID)> = Vec::new();
+    let mut btns = Vec::new();
+
+    if page > 1 {
+        let custom_uuid = page_ids.previous.clone();
+        custom_ids.push((
+            custom_uuid.clone(),
+            CustomID {
+                action: Action::ResultPrevious,
+                voting_id: voting_id.to_string(),
+                user_id: None,
+                page: Some(page - 1),
+                index: None,
+            },
+        ));
+
+        btns.push(Component::Button(Button {
+            custom_id: Some(custom_uuid),
+            disabled: false,
+            emoji: None,
+            label: Some("Previous".to_string()),
+            style: ButtonStyle::Secondary,
+            url: None,
+        }));
+    }
+
+    if total_pages > page {
+        let custom_uuid = page_ids.next.clone();
+        custom_ids.push((
+            custom_uuid.clone(),
+            CustomID {
+                action: Action::ResultNext,
+                voting_id: voting_id.to_string(),
+                user_id: None,
+                page: Some(page + 1),
+                index: None,
+            },
+        ));
+
+        btns.push(Component::Button(Button {
+            custom_id: Some(custom_uuid),
+            disabled: false,
+            emoji: None,
+            label: Some("Next".to_string()),
+            style: ButtonStyle::Secondary,
+            url: None,
+        }));
+    }
+
+    let mut components = Vec::new();
+    if !btns.is_empty() {
+        components.push(Component::ActionRow(ActionRow { components: btns }));
+    }
+
+    (embeds, components, custom_ids)
+}
+
+fn comments_embed(comments: &[(String, String)]) -> Embed {
+    let mut lines: Vec<String> = comments
+        .iter()
+        .take(MAX_LISTED_COMMENTS)
+        .map(|(user_id, comment)| format!("<@{}>: {}", user_id, comment))
+        .collect();
+    if comments.len() > MAX_LISTED_COMMENTS {
+        lines.push(format!(
+            "…and {} more",
+            comments.len() - MAX_LISTED_COMMENTS
+        ));
+    }
+
+    EmbedBuilder::new()
+        .title("Comments")
+        .description(lines.join("\n"))
+        .build()
+}
+
+async fn handle_slash_voting(
+    data: &Arc<AppState>,
+    command: &CommandData,
+    interaction: &Interaction,
+) -> InteractionResult {
+    let Some(member) = interaction.member.as_ref() else {
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response("Voting can only be started from a public channel."),
+        ));
+    };
+
+    let Some(option) = &command.options.first() else {
+        tracing::error!(data = ?interaction, "option not found");
+        return Err(InteractionError::InternalServerError);
+    };
+
+    let CommandOptionValue::String(ref name) = &option.value else {
+        tracing::error!(data = ?interaction, "name not found");
+        return Err(InteractionError::InternalServerError);
+    };
+
+    // choices are only split when asked for, a | may as well be part of the choice
+    let with_descriptions = command.options.iter().any(|option| {
+        option.name == "choice_descriptions" && option.value == CommandOptionValue::Boolean(true)
+    });
+
+    let (mut choices, mut choice_descriptions): (Vec<String>, Vec<String>) = command
+        .options
+        .iter()
+        .skip(1)
+        .filter(|option| option.name.starts_with("choice"))
+        .filter_map(|option| match &option.value {
+            CommandOptionValue::String(choice) => {
+                Some(split_choice_description(choice, with_descriptions))
+            }
+            _ => None,
+        })
+        .unzip();
+
+    let template_name = command
+        .options
+        .iter()
+        .find_map(|option| match &option.value {
+            CommandOptionValue::String(name) if option.name == "template" => {
+                Some(name.trim().to_string())
+            }
+            _ => None,
+        });
+
+    // the choices given with the command win over the template
+    if let (Some(template_name), Some(guild_id)) = (template_name, interaction.guild_id) {
+        if choices.is_empty() {
+            let template = match data
+                .db
+                .get_template(&guild_id.to_string(), &template_name)
+                .await
+            {
+                Ok(template) => template,
+                Err(db::DbError::NotFound) => {
+                    return Ok((
+                        StatusCode::OK,
+                        ephemeral_response(&format!("Template \"{}\" not found.", template_name)),
+                    ));
+                }
+                Err(err) => {
+                    tracing::error!(%guild_id, error = ?err, "getting voting template from db failed");
+                    return Err(InteractionError::InternalServerError);
+                }
+            };
+
+            (choices, choice_descriptions) = template
+                .choices
+                .iter()
+                .map(|choice| split_choice_description(choice, with_descriptions))
+                .unzip();
+        }
+    }
+
+    // votings without descriptions are stored as before
+    let choice_descriptions = if choice_descriptions.iter().all(String::is_empty) {
+        Vec::new()
+    } else {
+        choice_descriptions
+    };
+
+    let max_ranks = command
+        .options
+        .iter()
+        .find_map(|option| match option.value {
+            CommandOptionValue::Integer(max_ranks) if option.name == "max_ranks" => {
+                u32::try_from(max_ranks).ok()
+            }
+            _ => None,
+        });
+
+    let allowed_role_id = command
+        .options
+        .iter()
+        .find_map(|option| match option.value {
+            CommandOptionValue::Role(role_id) if option.name == "allowed_role" => {
+                Some(role_id.to_string())
+            }
+            _ => None,
+        });
+
+    let completion_template = command
+        .options
+        .iter()
+        .find_map(|option| match &option.value {
+            CommandOptionValue::String(template) if option.name == "completion_template" => {
+                Some(template.clone())
+            }
+            _ => None,
+        });
+
+    let image_url = command
+        .options
+        .iter()
+        .find_map(|option| match &option.value {
+            CommandOptionValue::String(url) if option.name == "image_url" => Some(url.clone()),
+            _ => None,
+        });
+
+    let vote_button_label = command
+        .options
+        .iter()
+        .find_map(|option| match &option.value {
+            CommandOptionValue::String(label) if option.name == "vote_button_label" => {
+                Some(label.trim().to_string())
+            }
+            _ => None,
+        });
+
+    let vote_button_emoji = command
+        .options
+        .iter()
+        .find_map(|option| match &option.value {
+            CommandOptionValue::String(emoji) if option.name == "vote_button_emoji" => {
+                Some(emoji.trim().to_string())
+            }
+            _ => None,
+        });
+
+    let tie_break = command
+        .options
+        .iter()
+        .find_map(|option| match &option.value {
+            CommandOptionValue::String(tie_break) if option.name == "tie_break" => {
+                match tie_break.as_str() {
+                    "random" => Some(TieBreak::Random),
+                    "earliest_choice" => Some(TieBreak::EarliestChoice),
+                    "first_choice_count" => Some(TieBreak::FirstChoiceCount),
+                    _ => None,
+                }
+            }
+            _ => None,
+        })
+        .unwrap_or_default();
+
+    let role_weights = command
+        .options
+        .iter()
+        .find_map(|option| match &option.value {
+            CommandOptionValue::String(weights) if option.name == "role_weights" => {
+                Some(parse_role_weights(weights))
+            }
+            _ => None,
+        });
+
+    let role_weights = match role_weights {
+        Some(Some(role_weights)) => role_weights,
+        Some(None) => {
+            return Ok((
+                StatusCode::OK,
+                ephemeral_response(&format!(
+                    "Role weights must be role:weight pairs, e.g. @Council:3, with weights from 1 to {}.",
+                    MAX_ROLE_WEIGHT
+                )),
+            ));
+        }
+        None => Vec::new(),
+    };
+
+    let questions = command
+        .options
+        .iter()
+        .find_map(|option| match &option.value {
+            CommandOptionValue::String(questions) if option.name == "questions" => {
+                Some(parse_questions(questions))
+            }
+            _ => None,
+        });
+
+    let questions = match questions {
+        Some(Some(questions)) => questions,
+        Some(None) => {
+            return Ok((
+                StatusCode::OK,
+                ephemeral_response(&format!(
+                    "Questions must look like Question: choice, choice | Question: choice, choice, with at most {} questions of 2 to {} different choices.",
+                    MAX_QUESTIONS, MAX_QUESTION_CHOICES
+                )),
+            ));
+        }
+        None => Vec::new(),
+    };
+
+    let completion_mentions = command.options.iter().any(|option| {
+        option.name == "completion_mentions" && option.value == CommandOptionValue::Boolean(true)
+    });
+
+    let show_vote_count = command.options.iter().any(|option| {
+        option.name == "show_vote_count" && option.value == CommandOptionValue::Boolean(true)
+    });
+
+    let in_channel = command.options.iter().any(|option| {
+        option.name == "in_channel" && option.value == CommandOptionValue::Boolean(true)
+    });
+
+    let shuffle = command.options.iter().any(|option| {
+        option.name == "shuffle" && option.value == CommandOptionValue::Boolean(true)
+    });
+
+    let private_results = command.options.iter().any(|option| {
+        option.name == "private_results" && option.value == CommandOptionValue::Boolean(true)
+    });
+
+    let guild_config = interaction_guild_config(data, interaction).await?;
+
+    // an explicit `anonymous: false` wins over the guild default
+    let anonymous = command
+        .options
+        .iter()
+        .find_map(|option| match option.value {
+            CommandOptionValue::Boolean(anonymous) if option.name == "anonymous" => Some(anonymous),
+            _ => None,
+        })
+        .unwrap_or(guild_config.anonymous_by_default);
+
+    let chart = command
+        .options
+        .iter()
+        .any(|option| option.name == "chart" && option.value == CommandOptionValue::Boolean(true));
+
+    let require_full_ranking = command.options.iter().any(|option| {
+        option.name == "require_full_ranking" && option.value == CommandOptionValue::Boolean(true)
+    });
+
+    let pin_result = command.options.iter().any(|option| {
+        option.name == "pin_result" && option.value == CommandOptionValue::Boolean(true)
+    });
+
+    let simple = command
+        .options
+        .iter()
+        .any(|option| option.name == "simple" && option.value == CommandOptionValue::Boolean(true));
+
+    let deadline_unix = command
+        .options
+        .iter()
+        .find_map(|option| match option.value {
+            CommandOptionValue::Integer(hours) if option.name == "duration_hours" => {
+                u64::try_from(hours).ok()
+            }
+            _ => None,
+        })
+        .map(|hours| util::unix_timestamp() + hours * 60 * 60);
+
+    // a single choice can't be ranked, whatever the configured minimum
+    let min_choices = guild_config
+        .min_choices
+        .unwrap_or(data.config.min_choices)
+        .max(2);
+    if choices.len() < min_choices {
+        let message = match guild_config.min_choices {
+            Some(_) if min_choices > 2 => {
+                format!("This server requires at least {} choices.", min_choices)
+            }
+            _ => format!("Voting must have at least {} choices.", min_choices),
+        };
+        return Ok((StatusCode::OK, ephemeral_response(&message)));
+    }
+
+    if let Some(position) = choices.iter().position(|choice| choice.trim().is_empty()) {
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response(&format!("Choice {} is empty.", position + 1)),
+        ));
+    }
+
+    // the same choice twice splits its votes, the DD backend also keys the ballot by choice
+    let mut seen = HashSet::new();
+    if let Some(duplicate) = choices
+        .iter()
+        .find(|choice| !seen.insert(choice.trim().to_lowercase()))
+    {
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response(&format!(
+                "Choice \"{}\" is listed more than once.",
+                duplicate.trim()
+            )),
+        ));
+    }
+
+    if let Some(max_choices) = guild_config.max_choices {
+        if choices.len() > max_choices {
+            return Ok((
+                StatusCode::OK,
+                ephemeral_response(&format!(
+                    "This server allows at most {} choices.",
+                    max_choices
+                )),
+            ));
+        }
+    } else if let Some(max_choices) = data.config.max_choices {
+        if choices.len() > max_choices {
+            return Ok((
+                StatusCode::OK,
+                ephemeral_response(&format!("Voting can have at most {} choices.", max_choices)),
+            ));
+        }
+    }
+
+    if let Some((position, choice)) = choices
+        .iter()
+        .enumerate()
+        .find(|(_, choice)| choice.chars().count() > MAX_CHOICE_LENGTH)
+    {
+        let preview: String = choice.chars().take(40).collect();
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response(&format!(
+                "Choice {} (\"{}…\") is longer than {} characters.",
+                position + 1,
+                preview,
+                MAX_CHOICE_LENGTH
+            )),
+        ));
+    }
+
+    if simple && choices.len() > SIMPLE_VOTING_MAX_CHOICES {
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response(&format!(
+                "Simple voting can have at most {} choices.",
+                SIMPLE_VOTING_MAX_CHOICES
+            )),
+        ));
+    }
+
+    // reactions can only vote on the choices of the voting itself
+    if simple && !questions.is_empty() {
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response("Simple voting can't have further questions."),
+        ));
+    }
+
+    // voters can't rank every choice when they may only rank the top ones
+    if require_full_ranking && max_ranks.is_some() {
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response("Requiring a full ranking can't be combined with max ranks."),
+        ));
+    }
+
+    if image_url.as_deref().is_some_and(|url| !is_http_url(url)) {
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response("The image url must be an http or https link."),
+        ));
+    }
+
+    if vote_button_label
+        .as_deref()
+        .is_some_and(|label| label.is_empty() || label.chars().count() > MAX_BUTTON_LABEL_LENGTH)
+    {
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response(&format!(
+                "The vote button label must have 1 to {} characters.",
+                MAX_BUTTON_LABEL_LENGTH
+            )),
+        ));
+    }
+
+    if vote_button_emoji
+        .as_deref()
+        .is_some_and(|emoji| !is_unicode_emoji(emoji))
+    {
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response("The vote button emoji must be a single emoji."),
+        ));
+    }
+
+    let Some(ref user) = member.user else {
+        tracing::error!(data = ?interaction, "user id not found");
+        return Err(InteractionError::InternalServerError);
+    };
+
+    let voting = Voting {
+        id: String::new(),
+        name: name.to_string(),
+        choices,
+        is_completed: false,
+        is_deleted: false,
+        message_id: String::new(),
+        channel_id: String::new(),
+        creator_message_id: String::new(),
+        creator_dm_channel_id: String::new(),
+        creator_user_id: String::new(),
+        guild_id: String::new(),
+        display_order: None,
+        max_ranks,
+        require_full_ranking,
+        allowed_role_id,
+        completion_template,
+        completion_mentions,
+        show_vote_count,
+        simple,
+        in_channel,
+        shuffle,
+        deadline_unix,
+        first_choice_counts: None,
+        image_url,
+        tie_break,
+        private_results,
+        reminded_unix: None,
+        role_weights,
+        anonymous,
+        chart,
+        pin_result,
+        dialogs_locked: false,
+        questions,
+        choice_descriptions,
+        vote_button_label,
+        vote_button_emoji,
+        version: 0,
+    };
+
+    start_voting(data, interaction, user.id, voting).await
+}
+
+// Whether the voting was started in the guild of the interaction. Votings saved before the guild
+// was recorded belong to no guild.
+fn is_guild_voting(voting: &Voting, interaction: &Interaction) -> bool {
+    interaction
+        .guild_id
+        .is_some_and(|guild_id| voting.guild_id == guild_id.to_string())
+}
+
+// Config of the guild the interaction comes from, interactions outside of guilds get the defaults
+async fn interaction_guild_config(
+    data: &Arc<AppState>,
+    interaction: &Interaction,
+) -> Result<GuildConfig, InteractionError> {
+    let Some(guild_id) = interaction.guild_id else {
+        return Ok(GuildConfig::default());
+    };
+
+    data.db
+        .get_guild_config(&guild_id.to_string())
+        .await
+        .map_err(|err| {
+            tracing::error!(%guild_id, error = ?err, "getting guild config from db failed");
+            InteractionError::InternalServerError
+        })
+}
+
+// Creates the voting from its settings and posts the creator dm and the announcement.
+// The id, the creator and the message ids of `voting` are set here.
+async fn start_voting(
+    data: &Arc<AppState>,
+    interaction: &Interaction,
+    user_id: Id<UserMarker>,
+    mut voting: Voting,
+) -> InteractionResult {
+    let active_votings = data
+        .db
+        .count_active_votings_by_creator(&user_id.to_string())
+        .await
+        .map_err(|err| {
+            tracing::error!(data = ?interaction, error = ?err, "counting active votings failed");
+            InteractionError::InternalServerError
+        })?;
+
+    let max_active_votings = interaction_guild_config(data, interaction)
+        .await?
+        .max_active_votings_per_creator
+        .unwrap_or(data.config.max_active_votings_per_creator);
+
+    if active_votings >= max_active_votings {
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response(&format!(
+                "You already have {} active votings. Complete or delete some of them before creating a new one.",
+                active_votings
+            )),
+        ));
+    }
+
+    // simple votings are tallied from reactions, they don't need the DD backend
+    let voting_id = if voting.simple {
+        util::generate_voting_id()
+    } else {
+        data.dd_client
+            .create_voting(voting.choices.clone())
+            .await
+            .map_err(|err| {
+                tracing::error!(data= ?interaction, error = ?err, "creating voting failed");
+                InteractionError::from(err)
+            })?
+            .id
+    };
+
+    tracing::Span::current().record("voting_id", voting_id.as_str());
+
+    // the id is used in db keys and custom ids, a malformed one would corrupt the indices
+    if !db::is_valid_voting_id(&voting_id) {
+        tracing::error!(data = ?interaction, %voting_id, "invalid voting id");
+        return Err(InteractionError::InternalServerError);
+    }
+
+    for question in voting.questions.iter_mut() {
+        question.dd_voting_id = data
+            .dd_client
+            .create_voting(question.choices.clone())
+            .await
+            .map_err(|err| {
+                tracing::error!(data = ?interaction, %voting_id, error = ?err, "creating question voting failed");
+                InteractionError::from(err)
+            })?
+            .id;
+    }
+
+    // the voting is saved before posting any message, so a failure below leaves a record to clean up
+    voting.id = voting_id;
+    voting.creator_user_id = user_id.to_string();
+    voting.guild_id = interaction
+        .guild_id
+        .map(|guild_id| guild_id.to_string())
+        .unwrap_or_default();
+
+    data.db.save_voting(voting.clone()).await.map_err(|err| {
+        tracing::error!(data = ?interaction, error = ?err, "saving voting into db failed");
+        InteractionError::InternalServerError
+    })?;
+
+    let dm_channel = open_dm_channel(data, user_id, DM_CHANNEL_INLINE_BUDGET).await?;
+
+    let creator_embed = EmbedBuilder::new()
+        .title(format!("Voting Created: {}", voting.name))
+        .description("Your voting has been successfully created. The results will be published once the voting is completed.");
+    let mut creator_embed = with_list_fields(creator_embed, "Choices", &voting.choices);
+    for question in &voting.questions {
+        creator_embed = with_list_fields(creator_embed, &question.name, &question.choices);
+    }
+    let embeds = vec![with_deadline_field(
+        creator_embed.build(),
+        voting.deadline_unix,
+    )];
+
+    let mut custom_ids = Vec::new();
+    let components = create_creator_components(&voting, &mut custom_ids);
+
+    let creator_message_id =
+        create_message(&data.discord_client, dm_channel.id, &embeds, &components)
+            .await?
+            .id
+            .to_string();
+
+    let (embeds, components) = if voting.simple {
+        create_simple_announcement_components(&voting)
+    } else {
+        let custom_uuid = util::generate_random_custom_uuid();
+        let custom_id = CustomID {
+            action: Action::VoteFromChannel,
+            voting_id: voting.id.clone(),
+            user_id: None,
+            page: None,
+            index: None,
+        };
+        custom_ids.push((custom_uuid.clone(), custom_id));
+
+        create_announcement_components(&voting, custom_uuid, 0)
+    };
+
+    data.db.bulk_save_custom_ids(custom_ids).await .map_err(|err| {
+        tracing::error!(data = ?interaction, error = ?err, "bulk saving custom ids into db failed");
+        InteractionError::InternalServerError
+    })?;
+
+    let Some(ref channel) = interaction.channel else {
+        tracing::error!(data = ?interaction, "channel not found");
+        return Err(InteractionError::InternalServerError);
+    };
+
+    let message = create_message(&data.discord_client, channel.id, &embeds, &components).await?;
+
+    if voting.simple {
+        let emojis: Vec<String> = (0..voting.choices.len()).map(choice_emoji).collect();
+        let added = add_reactions(
+            &data.discord_client,
+            message.channel_id,
+            message.id,
+            &emojis,
+        )
+        .await;
+        if added < emojis.len() {
+            tracing::warn!(voting_id = %voting.id, added, total = emojis.len(), "not every choice reaction was added");
+        }
+    }
+
+    data.db
+        .set_voting_message_ids(
+            &voting.id,
+            &message.id.to_string(),
+            &message.channel_id.to_string(),
+            &creator_message_id,
+            &dm_channel.id.to_string(),
+        )
+        .await
+        .map_err(|err| {
+            tracing::error!(data = ?interaction, error = ?err, "setting voting message ids in db failed");
+            InteractionError::InternalServerError
+        })?;
+
+    if let Some(deadline_unix) = voting.deadline_unix {
+        schedule_deadline(data, &voting.id, deadline_unix);
+    }
+
+    ack_response()
+}
+
+// Buttons of the creator dm message, their custom ids are appended to `custom_ids`
+fn create_creator_components(
+    voting: &Voting,
+    custom_ids: &mut Vec<(String, CustomID)>,
+) -> Vec<Component> {
+    let custom_uuid = util::generate_random_custom_uuid();
+    custom_ids.push((
+        custom_uuid.clone(),
+        CustomID {
+            action: Action::Complete,
+            voting_id: voting.id.clone(),
+            user_id: None,
+            page: None,
+            index: None,
+        },
+    ));
+
+    let complete_btn = Button {
+        custom_id: Some(custom_uuid),
+        disabled: false,
+        emoji: None,
+        label: Some("Complete Voting".to_string()),
+        style: ButtonStyle::Primary,
+        url: None,
+    };
+
+    let custom_uuid = util::generate_random_custom_uuid();
+    custom_ids.push((
+        custom_uuid.clone(),
+        CustomID {
+            action: Action::Delete,
+            voting_id: voting.id.clone(),
+            user_id: None,
+            page: None,
+            index: None,
+        },
+    ));
+    let delete_btn = Button {
+        custom_id: Some(custom_uuid),
+        disabled: false,
+        emoji: None,
+        label: Some("Delete Voting".to_string()),
+        style: ButtonStyle::Danger,
+        url: None,
+    };
+
+    let mut creator_btns = vec![
+        Component::Button(complete_btn),
+        Component::Button(delete_btn),
+    ];
+
+    // the reactions are added in the original order, so simple votings can't be sorted
+    if !voting.simple {
+        let custom_uuid = util::generate_random_custom_uuid();
+        custom_ids.push((
+            custom_uuid.clone(),
+            CustomID {
+                action: Action::SortChoices,
+                voting_id: voting.id.clone(),
+                user_id: None,
+                page: None,
+                index: None,
+            },
+        ));
+        creator_btns.push(Component::Button(Button {
+            custom_id: Some(custom_uuid),
+            disabled: false,
+            emoji: None,
+            label: Some("Sort A–Z".to_string()),
+            style: ButtonStyle::Secondary,
+            url: None,
+        }));
+    }
+
+    // simple votings have no dialogs, so there is nobody to remind
+    if !voting.simple {
+        let custom_uuid = util::generate_random_custom_uuid();
+        custom_ids.push((
+            custom_uuid.clone(),
+            CustomID {
+                action: Action::RemindNonVoters,
+                voting_id: voting.id.clone(),
+                user_id: None,
+                page: None,
+                index: None,
+            },
+        ));
+        creator_btns.push(Component::Button(Button {
+            custom_id: Some(custom_uuid),
+            disabled: false,
+            emoji: None,
+            label: Some("Remind Non-Voters".to_string()),
+            style: ButtonStyle::Secondary,
+            url: None,
+        }));
+    }
+
+    let custom_uuid = util::generate_random_custom_uuid();
+    custom_ids.push((
+        custom_uuid.clone(),
+        CustomID {
+            action: Action::ViewAudit,
+            voting_id: voting.id.clone(),
+            user_id: None,
+            page: None,
+            index: None,
+        },
+    ));
+    creator_btns.push(Component::Button(Button {
+        custom_id: Some(custom_uuid),
+        disabled: false,
+        emoji: None,
+        label: Some("Audit Log".to_string()),
+        style: ButtonStyle::Secondary,
+        url: None,
+    }));
+
+    let custom_uuid = util::generate_random_custom_uuid();
+    custom_ids.push((
+        custom_uuid.clone(),
+        CustomID {
+            action: Action::EditName,
+            voting_id: voting.id.clone(),
+            user_id: None,
+            page: None,
+            index: None,
+        },
+    ));
+
+    // a row holds at most five buttons, the first one is full
+    let mut edit_btns = vec![Component::Button(Button {
+        custom_id: Some(custom_uuid),
+        disabled: false,
+        emoji: None,
+        label: Some("Edit Name".to_string()),
+        style: ButtonStyle::Secondary,
+        url: None,
+    })];
+
+    let custom_uuid = util::generate_random_custom_uuid();
+    custom_ids.push((
+        custom_uuid.clone(),
+        CustomID {
+            action: Action::Refresh,
+            voting_id: voting.id.clone(),
+            user_id: None,
+            page: None,
+            index: None,
+        },
+    ));
+    edit_btns.push(Component::Button(Button {
+        custom_id: Some(custom_uuid),
+        disabled: false,
+        emoji: None,
+        label: Some("Refresh".to_string()),
+        style: ButtonStyle::Secondary,
+        url: None,
+    }));
+
+    // simple votings have no dialogs to lock
+    if !voting.simple {
+        let custom_uuid = util::generate_random_custom_uuid();
+        custom_ids.push((
+            custom_uuid.clone(),
+            CustomID {
+                action: Action::LockDialogs,
+                voting_id: voting.id.clone(),
+                user_id: None,
+                page: None,
+                index: None,
+            },
+        ));
+        let label = if voting.dialogs_locked {
+            "Unlock New Voters"
+        } else {
+            "Lock New Voters"
+        };
+        edit_btns.push(Component::Button(Button {
+            custom_id: Some(custom_uuid),
+            disabled: false,
+            emoji: None,
+            label: Some(label.to_string()),
+            style: ButtonStyle::Secondary,
+            url: None,
+        }));
+    }
+
+    if voting.deadline_unix.is_some() {
+        for (action, label) in [
+            (Action::ExtendDeadline, "Extend +1h"),
+            (Action::ClearDeadline, "Cancel Deadline"),
+        ] {
+            let custom_uuid = util::generate_random_custom_uuid();
+            custom_ids.push((
+                custom_uuid.clone(),
+                CustomID {
+                    action,
+                    voting_id: voting.id.clone(),
+                    user_id: None,
+                    page: None,
+                    index: None,
+                },
+            ));
+            edit_btns.push(Component::Button(Button {
+                custom_id: Some(custom_uuid),
+                disabled: false,
+                emoji: None,
+                label: Some(label.to_string()),
+                style: ButtonStyle::Secondary,
+                url: None,
+            }));
+        }
+    }
+
+    vec![
+        Component::ActionRow(ActionRow {
+            components: creator_btns,
+        }),
+        Component::ActionRow(ActionRow {
+            components: edit_btns,
+        }),
+    ]
+}
+
+// Replaces the deadline field of the creator or announcement embed, the field is removed when
+// there is no deadline. The time left is shown next to the date, discord keeps it counting down.
+fn with_deadline_field(mut embed: Embed, deadline_unix: Option<u64>) -> Embed {
+    embed.fields.retain(|field| field.name != "Deadline");
+    if let Some(deadline_unix) = deadline_unix {
+        let value = format!(
+            "<t:{}:f> ({})",
+            deadline_unix,
+            util::discord_relative_timestamp(deadline_unix)
+        );
+        embed
+            .fields
+            .push(EmbedFieldBuilder::new("Deadline", value).build());
+    }
+
+    embed
+}
+
+// Copy of the components which can't be interacted with anymore, shown on closed votings.
+// Primary buttons and select menus read "Voting closed", the rest keep their labels.
+pub fn disable_components(components: &[Component]) -> Vec<Component> {
+    components
+        .iter()
+        .map(|component| match component {
+            Component::ActionRow(row) => Component::ActionRow(ActionRow {
+                components: disable_components(&row.components),
+            }),
+            Component::Button(button) => {
+                let label = if button.style == ButtonStyle::Primary {
+                    Some("Voting closed".to_string())
+                } else {
+                    button.label.clone()
+                };
+
+                Component::Button(Button {
+                    disabled: true,
+                    label,
+                    ..button.clone()
+                })
+            }
+            Component::SelectMenu(menu) => {
+                Component::SelectMenu(twilight_model::channel::message::component::SelectMenu {
+                    disabled: true,
+                    placeholder: Some("Voting closed".to_string()),
+                    ..menu.clone()
+                })
+            }
+            other => other.clone(),
+        })
+        .collect()
+}
+
+// Row with the button sending the recorded ballots, shown in the creator dm after completion
+fn create_download_ballots_component(
+    voting: &Voting,
+    custom_ids: &mut Vec<(String, CustomID)>,
+) -> Component {
+    let custom_uuid = util::generate_random_custom_uuid();
+    custom_ids.push((
+        custom_uuid.clone(),
+        CustomID {
+            action: Action::DownloadBallots,
+            voting_id: voting.id.clone(),
+            user_id: None,
+            page: None,
+            index: None,
+        },
+    ));
+
+    Component::ActionRow(ActionRow {
+        components: vec![Component::Button(Button {
+            custom_id: Some(custom_uuid),
+            disabled: false,
+            emoji: None,
+            label: Some("Download Ballots".to_string()),
+            style: ButtonStyle::Secondary,
+            url: None,
+        })],
+    })
+}
+
+// Components left on the messages of a closed voting, depending on `Config::keep_closed_components`
+fn closed_components(data: &Arc<AppState>, components: Vec<Component>) -> Vec<Component> {
+    if data.config.keep_closed_components {
+        disable_components(&components)
+    } else {
+        Vec::new()
+    }
+}
+
+// Channel message announcing the voting, choices are listed in the display order.
+// The vote count is only shown when the voting has `show_vote_count` set.
+fn create_announcement_components(
+    voting: &Voting,
+    vote_custom_uuid: String,
+    vote_count: usize,
+) -> (Vec<Embed>, Vec<Component>) {
+    let choices: Vec<&str> = voting
+        .display_indices()
+        .into_iter()
+        .map(|i| voting.choices[i].as_str())
+        .collect();
+
+    let embed = EmbedBuilder::new()
+        .title(format!(
+            "Created a voting with name:{}, id: {} and choices: {:?}",
+            voting.name, voting.id, choices
+        ))
+        .description(
+            "Click vote button when you are ready to vote. The voting will be done in dm.",
+        );
+    let embed = with_list_fields(embed, "Choices", &choices);
+    let mut embed = with_deadline_field(
+        with_image(embed, voting.image_url.as_deref()).build(),
+        voting.deadline_unix,
+    );
+    if voting.show_vote_count {
+        embed
+            .fields
+            .push(EmbedFieldBuilder::new("Votes", vote_count_line(vote_count)).build());
+    }
+    let embeds = vec![embed];
+
+    let vote_btn = Button {
+        custom_id: Some(vote_custom_uuid),
+        disabled: false,
+        emoji: voting
+            .vote_button_emoji
+            .clone()
+            .map(|name| ReactionType::Unicode { name }),
+        label: Some(
+            voting
+                .vote_button_label
+                .clone()
+                .unwrap_or_else(|| "Vote".to_string()),
+        ),
+        style: ButtonStyle::Primary,
+        url: None,
+    };
+
+    let components = vec![Component::ActionRow(ActionRow {
+        components: Vec::from([Component::Button(vote_btn)]),
+    })];
+
+    (embeds, components)
+}
+
+// Channel message announcing a simple voting, members vote by reacting with the choice emoji
+fn create_simple_announcement_components(voting: &Voting) -> (Vec<Embed>, Vec<Component>) {
+    let choices: Vec<String> = voting
+        .choices
+        .iter()
+        .enumerate()
+        .map(|(i, choice)| format!("{} {}", choice_emoji(i), choice))
+        .collect();
+
+    let embed = EmbedBuilder::new()
+        .title(format!(
+            "Created a voting with name:{}, id: {}",
+            voting.name, voting.id
+        ))
+        .description("React with the emoji of your choice to vote.");
+    let embed = with_list_fields(embed, "Choices", &choices);
+    let embeds = vec![with_deadline_field(
+        with_image(embed, voting.image_url.as_deref()).build(),
+        voting.deadline_unix,
+    )];
+
+    (embeds, Vec::new())
+}
+
+fn vote_count_line(count: usize) -> String {
+    match count {
+        1 => "1 person has voted".to_string(),
+        count => format!("{} people have voted", count),
+    }
+}
+
+// Rough check for a unicode emoji, custom emoji names and plain text are rejected by discord
+fn is_unicode_emoji(emoji: &str) -> bool {
+    let count = emoji.chars().count();
+    count > 0
+        && count <= MAX_BUTTON_EMOJI_CHARS
+        && emoji.chars().any(|c| !c.is_ascii())
+        && !emoji
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_ascii_alphabetic())
+}
+
+// Sets the banner image of the voting on the announcement embed
+fn with_image(embed: EmbedBuilder, image_url: Option<&str>) -> EmbedBuilder {
+    match image_url.map(ImageSource::url) {
+        Some(Ok(source)) => embed.image(source),
+        Some(Err(err)) => {
+            tracing::warn!(error = ?err, "invalid voting image url");
+            embed
+        }
+        None => embed,
+    }
+}
+
+// Adds the lines as fields named `name`, one line per row. Lines which don't fit into the value
+// limit of a field continue in further fields, a line too long for a field on its own is cut.
+pub fn with_list_fields<T: AsRef<str>>(
+    mut embed: EmbedBuilder,
+    name: &str,
+    lines: &[T],
+) -> EmbedBuilder {
+    let mut values = vec![String::new()];
+    for line in lines {
+        let line: String = line
+            .as_ref()
+            .chars()
+            .take(EMBED_FIELD_VALUE_MAX_LENGTH)
+            .collect();
+        let value = values.last_mut().expect("values start with a field");
+        if value.is_empty() {
+            *value = line;
+        } else if value.chars().count() + 1 + line.chars().count() <= EMBED_FIELD_VALUE_MAX_LENGTH {
+            value.push('\n');
+            value.push_str(&line);
+        } else {
+            values.push(line);
+        }
+    }
+
+    for (i, value) in values.into_iter().enumerate() {
+        let name = match i {
+            0 => name.to_string(),
+            _ => format!("{} (continued)", name),
+        };
+        embed = embed.field(EmbedFieldBuilder::new(name, value));
+    }
+    embed
+}
+
+// Splits "choice | description" into the choice and its description, truncated to the
+// select option limit. Choices without a description get an empty one, and so does every
+// choice when `with_description` is false. The choice is trimmed either way.
+pub fn split_choice_description(choice: &str, with_description: bool) -> (String, String) {
+    match choice.split_once('|').filter(|_| with_description) {
+        Some((choice, description)) => (
+            choice.trim().to_string(),
+            description
+                .trim()
+                .chars()
+                .take(MAX_CHOICE_DESCRIPTION_LENGTH)
+                .collect(),
+        ),
+        None => (choice.trim().to_string(), String::new()),
+    }
+}
+
+// Parses "role:weight" pairs separated by commas or spaces, the role can be a mention or an id.
+// Returns None when a pair is malformed or its weight is out of range.
+pub fn parse_role_weights(weights: &str) -> Option<Vec<(String, u32)>> {
+    weights
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (role, weight) = pair.rsplit_once(':')?;
+            let role = role
+                .strip_prefix("<@&")
+                .and_then(|role| role.strip_suffix('>'))
+                .unwrap_or(role);
+            if role.is_empty() || !role.chars().all(|c| c.is_ascii_digit()) {
+                return None;
+            }
+
+            let weight = weight.parse::<u32>().ok()?;
+            (1..=MAX_ROLE_WEIGHT)
+                .contains(&weight)
+                .then(|| (role.to_string(), weight))
+        })
+        .collect()
+}
+
+// Parses the further questions of a survey, given as `Question: choice, choice | Question: ...`.
+// `None` if a question has no name, fewer than 2 or more than `MAX_QUESTION_CHOICES` choices,
+// an empty or repeated choice, or there are more than `MAX_QUESTIONS` questions.
+pub fn parse_questions(questions: &str) -> Option<Vec<Question>> {
+    let questions: Vec<Question> = questions
+        .split('|')
+        .filter(|question| !question.trim().is_empty())
+        .map(|question| {
+            let (name, choices) = question.split_once(':')?;
+            let name = name.trim();
+            let choices: Vec<String> = choices
+                .split(',')
+                .map(|choice| choice.trim().to_string())
+                .collect();
+
+            let mut seen = HashSet::new();
+            let valid = !name.is_empty()
+                && (2..=MAX_QUESTION_CHOICES).contains(&choices.len())
+                && choices.iter().all(|choice| {
+                    !choice.is_empty()
+                        && choice.chars().count() <= MAX_CHOICE_LENGTH
+                        && seen.insert(choice.to_lowercase())
+                });
+
+            valid.then(|| Question {
+                dd_voting_id: String::new(),
+                name: name.to_string(),
+                choices,
+            })
+        })
+        .collect::<Option<_>>()?;
+
+    (questions.len() <= MAX_QUESTIONS).then_some(questions)
+}
+
+// Whether the url is an absolute http or https link
+fn is_http_url(url: &str) -> bool {
+    reqwest::Url::parse(url)
+        .map(|url| matches!(url.scheme(), "http" | "https") && url.has_host())
+        .unwrap_or(false)
+}
+
+// Reassigns a voting to another user and sends them the creator dm with the management buttons.
+// Only guild administrators can transfer, e.g. when the creator left the server.
+async fn handle_voting_transfer(
+    data: &Arc<AppState>,
+    command: &CommandData,
+    interaction: &Interaction,
+) -> InteractionResult {
+    let Some(member) = interaction.member.as_ref() else {
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response("Votings can only be transferred from a server channel."),
+        ));
+    };
+
+    let is_admin = member
+        .permissions
+        .is_some_and(|permissions| permissions.contains(Permissions::ADMINISTRATOR));
+    if !is_admin {
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response("Only server administrators can transfer votings."),
+        ));
+    }
+
+    let voting_id = command
+        .options
+        .iter()
+        .find_map(|option| match &option.value {
+            CommandOptionValue::String(voting_id) if option.name == "voting_id" => {
+                Some(voting_id.clone())
+            }
+            _ => None,
+        });
+
+    let new_owner = command
+        .options
+        .iter()
+        .find_map(|option| match option.value {
+            CommandOptionValue::User(user_id) if option.name == "new_owner" => Some(user_id),
+            _ => None,
+        });
+
+    let (Some(voting_id), Some(new_owner)) = (voting_id, new_owner) else {
+        tracing::error!(data = ?interaction, "transfer options not found");
+        return Err(InteractionError::InternalServerError);
+    };
+
+    let Some(ref user) = member.user else {
+        tracing::error!(data = ?interaction, "user id not found");
+        return Err(InteractionError::InternalServerError);
+    };
+
+    // votings of other guilds are reported as missing, their ids are not confirmed
+    match data.db.get_open_voting(&voting_id).await {
+        Ok(VotingState::Open(v)) if is_guild_voting(&v, interaction) => {}
+        Ok(VotingState::Open(_) | VotingState::NotFound) => {
+            return Ok((StatusCode::OK, ephemeral_response("Voting not found.")));
+        }
+        Ok(VotingState::Completed | VotingState::Deleted) => {
+            return Ok((
+                StatusCode::OK,
+                ephemeral_response("Only active votings can be transferred."),
+            ));
+        }
+        Err(err) => {
+            tracing::error!(%voting_id, error = ?err, data = ?interaction, "db get voting failed");
+            return Err(InteractionError::InternalServerError);
+        }
+    };
+
+    // the dm is opened first, so a new owner who can't be reached doesn't take over the voting.
+    // The dm privacy hint is meant for the user who clicked, not for the new owner.
+    let dm_channel = open_dm_channel(data, new_owner, DM_CHANNEL_INLINE_BUDGET)
+        .await
+        .map_err(|err| match err {
+            DmChannelError::OwnAccount => InteractionError::BotUser,
+            _ => {
+                tracing::error!(%voting_id, data = ?interaction, "opening dm channel of the new owner failed");
+                InteractionError::InternalServerError
+            }
+        })?;
+
+    let voting = data
+        .db
+        .set_voting_creator(&voting_id, &new_owner.to_string(), &user.id.to_string())
+        .await
+        .map_err(|err| {
+            tracing::error!(%voting_id, error = ?err, data = ?interaction, "setting voting creator in db failed");
+            InteractionError::InternalServerError
+        })?;
+
+    let embed = EmbedBuilder::new()
+        .title(format!("Voting Transferred: {}", voting.name))
+        .description("You are now the owner of this voting. The results will be published once the voting is completed.");
+    let embeds = vec![with_deadline_field(
+        with_list_fields(embed, "Choices", &voting.choices).build(),
+        voting.deadline_unix,
+    )];
+
+    let mut custom_ids = Vec::new();
+    let components = create_creator_components(&voting, &mut custom_ids);
+
+    data.db.bulk_save_custom_ids(custom_ids).await.map_err(|err| {
+        tracing::error!(%voting_id, error = ?err, data = ?interaction, "bulk saving custom ids into db failed");
+        InteractionError::InternalServerError
+    })?;
+
+    let creator_message =
+        create_message(&data.discord_client, dm_channel.id, &embeds, &components).await?;
+
+    data.db
+        .set_voting_message_ids(
+            &voting_id,
+            &voting.message_id,
+            &voting.channel_id,
+            &creator_message.id.to_string(),
+            &dm_channel.id.to_string(),
+        )
+        .await
+        .map_err(|err| {
+            tracing::error!(%voting_id, error = ?err, data = ?interaction, "setting voting message ids in db failed");
+            InteractionError::InternalServerError
+        })?;
+
+    // the previous owner may have left, so failing to update their dm does not fail the transfer
+    if let (Ok(old_channel_id), Ok(old_message_id)) = (
+        voting.creator_dm_channel_id.parse::<u64>(),
+        voting.creator_message_id.parse::<u64>(),
+    ) {
+        if let Err(err) = update_message(
+            &data.discord_client,
+            Id::new(old_channel_id),
+            Id::new(old_message_id),
+            Some(format!("Voting transferred: {}", voting.name).as_str()),
+            Some(&Vec::new()),
+            Some(&Vec::new()),
+        )
+        .await
+        {
+            tracing::error!(%voting_id, error = ?err, "updating previous creator message failed");
+        }
+    }
+
+    Ok((
+        StatusCode::OK,
+        ephemeral_response(&format!(
+            "Voting {} was transferred to <@{}>.",
+            voting.name, new_owner
+        )),
+    ))
+}
+
+// Starts a fresh voting with the name, choices and settings of a past one
+async fn handle_voting_clone(
+    data: &Arc<AppState>,
+    command: &CommandData,
+    interaction: &Interaction,
+) -> InteractionResult {
+    let Some(member) = interaction.member.as_ref() else {
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response("Voting can only be started from a public channel."),
+        ));
+    };
+
+    let Some(ref user) = member.user else {
+        tracing::error!(data = ?interaction, "user id not found");
+        return Err(InteractionError::InternalServerError);
+    };
 
-            custom_ids.push((custom_uuid.clone(), custom_id));
+    let Some(voting_id) = command
+        .options
+        .iter()
+        .find_map(|option| match &option.value {
+            CommandOptionValue::String(voting_id) if option.name == "voting_id" => {
+                Some(voting_id.clone())
+            }
+            _ => None,
+        })
+    else {
+        tracing::error!(data = ?interaction, "voting id option not found");
+        return Err(InteractionError::InternalServerError);
+    };
 
-            Component::ActionRow(ActionRow {
-                components: Vec::from([Component::SelectMenu(
-                    twilight_model::channel::message::component::SelectMenu {
-                        custom_id: custom_uuid,
-                        disabled: false,
-                        max_values: Some(1),
-                        min_values: Some(1),
-                        options: options.clone(),
-                        placeholder: Some(placeholder),
-                    },
-                )]),
+    // the role settings only make sense in the guild of the source voting, other guilds can't
+    // learn about it either
+    let source = match data.db.get_voting(&voting_id).await {
+        Ok(v) if is_guild_voting(&v, interaction) => v,
+        Ok(_) | Err(db::DbError::NotFound) => {
+            return Ok((StatusCode::OK, ephemeral_response("Voting not found.")));
+        }
+        Err(err) => {
+            tracing::error!(%voting_id, error = ?err, data = ?interaction, "db get voting failed");
+            return Err(InteractionError::InternalServerError);
+        }
+    };
+
+    // only the settings are copied, the state and the messages belong to the source voting
+    let voting = Voting {
+        id: String::new(),
+        name: source.name,
+        choices: source.choices,
+        is_completed: false,
+        is_deleted: false,
+        message_id: String::new(),
+        channel_id: String::new(),
+        creator_message_id: String::new(),
+        creator_dm_channel_id: String::new(),
+        creator_user_id: String::new(),
+        guild_id: String::new(),
+        display_order: None,
+        max_ranks: source.max_ranks,
+        require_full_ranking: source.require_full_ranking,
+        allowed_role_id: source.allowed_role_id,
+        completion_template: source.completion_template,
+        completion_mentions: source.completion_mentions,
+        show_vote_count: source.show_vote_count,
+        simple: source.simple,
+        in_channel: source.in_channel,
+        shuffle: source.shuffle,
+        // the deadline of the source has most likely passed
+        deadline_unix: None,
+        first_choice_counts: None,
+        image_url: source.image_url,
+        tie_break: source.tie_break,
+        private_results: source.private_results,
+        reminded_unix: None,
+        role_weights: source.role_weights,
+        anonymous: source.anonymous,
+        chart: source.chart,
+        pin_result: source.pin_result,
+        dialogs_locked: false,
+        // the questions get new DD votings when the clone is started
+        questions: source
+            .questions
+            .into_iter()
+            .map(|question| Question {
+                dd_voting_id: String::new(),
+                ..question
             })
-        })
-        .collect();
+            .collect(),
+        choice_descriptions: source.choice_descriptions,
+        vote_button_label: source.vote_button_label,
+        vote_button_emoji: source.vote_button_emoji,
+        version: 0,
+    };
 
-    let mut btns = Vec::new();
+    start_voting(data, interaction, user.id, voting).await
+}
 
-    if page > 1 {
-        let custom_uuid = util::generate_random_custom_uuid();
-        custom_ids.push((
-            custom_uuid.clone(),
-            CustomID {
-                action: Action::VotePrevious,
-                voting_id: voting_id.to_string(),
-                user_id: None,
-                page: Some(page - 1),
-                index: None,
-            },
-        ));
+// Lists the voting dialogs the user has not submitted yet, linking to their dm messages
+async fn handle_my_votes(data: &Arc<AppState>, interaction: &Interaction) -> InteractionResult {
+    let Some(user) = interaction.author() else {
+        tracing::error!(data = ?interaction, "user not found");
+        return Err(InteractionError::InternalServerError);
+    };
 
-        btns.push(Component::Button(Button {
-            custom_id: Some(custom_uuid),
-            disabled: false,
-            emoji: None,
-            label: Some("Previous".to_string()),
-            style: ButtonStyle::Secondary,
-            url: None,
-        }));
+    let dialogs = data
+        .db
+        .get_dialogs_for_user(&user.id.to_string())
+        .await
+        .map_err(|err| {
+            tracing::error!(data = ?interaction, error = ?err, "getting user dialogs from db failed");
+            InteractionError::InternalServerError
+        })?;
+
+    let mut lines = Vec::new();
+    for dialog in dialogs {
+        // placeholder and in channel dialogs do not have a dm message
+        if dialog.submitted || dialog.message_id.is_empty() {
+            continue;
+        }
+
+        let voting = match data.db.get_open_voting(&dialog.voting_id).await {
+            Ok(VotingState::Open(v)) => v,
+            Ok(_) => continue,
+            Err(err) => {
+                tracing::error!(voting_id = %dialog.voting_id, error = ?err, "db get voting failed");
+                continue;
+            }
+        };
+
+        lines.push(format!(
+            "[{}](https://discord.com/channels/@me/{}/{})",
+            voting.name, dialog.channel_id, dialog.message_id
+        ));
     }
 
-    if total_pages > page {
-        let custom_uuid = util::generate_random_custom_uuid();
-        custom_ids.push((
-            custom_uuid.clone(),
-            CustomID {
-                action: Action::VoteNext,
-                voting_id: voting_id.to_string(),
-                user_id: None,
-                page: Some(page + 1),
-                index: None,
-            },
+    if lines.is_empty() {
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response("You have no open voting dialogs."),
         ));
+    }
 
-        btns.push(Component::Button(Button {
-            custom_id: Some(custom_uuid),
-            disabled: false,
-            emoji: None,
-            label: Some("Next".to_string()),
-            style: ButtonStyle::Secondary,
-            url: None,
-        }))
+    let embed = EmbedBuilder::new()
+        .title("Your open votings")
+        .description(lines.join("\n"))
+        .build();
+
+    Ok((
+        StatusCode::OK,
+        Json(InteractionResponse {
+            kind: InteractionResponseType::ChannelMessageWithSource,
+            data: Some(InteractionResponseData {
+                embeds: Some(vec![embed]),
+                flags: Some(MessageFlags::EPHEMERAL),
+                ..Default::default()
+            }),
+        }),
+    ))
+}
+
+// Replies with the health of the bot dependencies, each check is bounded by a timeout
+async fn handle_ping(data: &Arc<AppState>) -> InteractionResult {
+    let db_check =
+        check_dependency(async { data.db.ping().await.map_err(|err| format!("{:?}", err)) });
+
+    let dd_check = check_dependency(async {
+        // any api response means the backend is reachable, the voting is not expected to exist
+        match data.dd_client.get_voting(STATUS_CHECK_VOTING_ID).await {
+            Ok(_) | Err(ApiError::NotFound) => Ok(()),
+            Err(err) => Err(err.to_string()),
+        }
+    });
+
+    let (db_status, dd_status) = tokio::join!(db_check, dd_check);
+
+    let embed = EmbedBuilder::new()
+        .title("Status")
+        .field(EmbedFieldBuilder::new("Database", db_status))
+        .field(EmbedFieldBuilder::new("Direct Decisions API", dd_status))
+        .build();
+
+    let pong = Json(InteractionResponse {
+        kind: InteractionResponseType::ChannelMessageWithSource,
+        data: Some(InteractionResponseData {
+            content: Some("pong".to_string()),
+            embeds: Some(vec![embed]),
+            flags: Some(MessageFlags::EPHEMERAL),
+            ..Default::default()
+        }),
+    });
+
+    Ok((StatusCode::OK, pong))
+}
+
+async fn check_dependency<F>(check: F) -> String
+where
+    F: Future<Output = Result<(), String>>,
+{
+    let start = Instant::now();
+    match tokio::time::timeout(STATUS_CHECK_TIMEOUT, check).await {
+        Ok(Ok(())) => format!("✅ {} ms", start.elapsed().as_millis()),
+        Ok(Err(err)) => format!("❌ {}", err),
+        Err(_) => format!("❌ timed out after {} s", STATUS_CHECK_TIMEOUT.as_secs()),
     }
+}
 
-    if page == total_pages {
-        let custom_uuid = util::generate_random_custom_uuid();
-        custom_ids.push((
-            custom_uuid.clone(),
-            CustomID {
-                action: Action::VoteFromDM,
-                voting_id: voting_id.to_string(),
-                user_id: None,
-                page: None,
-                index: None,
-            },
+// Shows the stored JSON of a voting to the server administrators, even when it fails to deserialize
+async fn handle_voting_debug(
+    data: &Arc<AppState>,
+    command: &CommandData,
+    interaction: &Interaction,
+) -> InteractionResult {
+    let is_admin = interaction.member.as_ref().is_some_and(|member| {
+        member
+            .permissions
+            .is_some_and(|permissions| permissions.contains(Permissions::ADMINISTRATOR))
+    });
+    if !is_admin {
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response("Only server administrators can debug votings."),
         ));
-        btns.push(Component::Button(Button {
-            custom_id: Some(custom_uuid),
-            disabled: false,
-            emoji: None,
-            label: Some("Vote".to_string()),
-            style: ButtonStyle::Primary,
-            url: None,
-        }))
     }
 
-    if !btns.is_empty() {
-        components.push(Component::ActionRow(ActionRow { components: btns }));
+    let Some(voting_id) = command
+        .options
+        .iter()
+        .find_map(|option| match &option.value {
+            CommandOptionValue::String(voting_id) if option.name == "voting_id" => {
+                Some(voting_id.clone())
+            }
+            _ => None,
+        })
+    else {
+        tracing::error!(data = ?interaction, "voting id option not found");
+        return Err(InteractionError::InternalServerError);
+    };
+
+    let raw = match data.db.get_voting_raw(&voting_id).await {
+        Ok(raw) => raw,
+        Err(db::DbError::NotFound) => {
+            return Ok((StatusCode::OK, ephemeral_response("Voting not found.")));
+        }
+        Err(err) => {
+            tracing::error!(%voting_id, error = ?err, data = ?interaction, "db get raw voting failed");
+            return Err(InteractionError::InternalServerError);
+        }
+    };
+
+    // the raw voting may not deserialize as `Voting`, that's what it is shown for, so the guild
+    // is read from the json. Votings of other guilds are answered like missing ones.
+    let voting_guild_id = serde_json::from_str::<serde_json::Value>(&raw)
+        .ok()
+        .and_then(|raw| raw.get("guild_id")?.as_str().map(str::to_string));
+    let is_own_guild = interaction
+        .guild_id
+        .is_some_and(|guild_id| voting_guild_id == Some(guild_id.to_string()));
+    if !is_own_guild {
+        return Ok((StatusCode::OK, ephemeral_response("Voting not found.")));
     }
 
-    (vec![title], components, custom_ids)
+    // discord messages are limited to 2000 characters, the code block takes a few of them
+    let mut raw = raw.replace("```", "'''");
+    if raw.chars().count() > DEBUG_VOTING_MAX_CHARS {
+        raw = raw.chars().take(DEBUG_VOTING_MAX_CHARS).collect();
+        raw.push('…');
+    }
+
+    Ok((
+        StatusCode::OK,
+        ephemeral_response(&format!("```json\n{}\n```", raw)),
+    ))
 }
 
-async fn handle_slash_voting(
+// Shows the voting settings of the guild to its administrators, updating the given ones first
+async fn handle_voting_config(
     data: &Arc<AppState>,
     command: &CommandData,
     interaction: &Interaction,
 ) -> InteractionResult {
-    let Some(member) = interaction.member.as_ref() else {
+    let Some(guild_id) = interaction.guild_id else {
         return Ok((
             StatusCode::OK,
-            ephemeral_response("Voting can only be started from a public channel."),
+            ephemeral_response("Voting settings can only be changed in a server."),
         ));
     };
 
-    let Some(option) = &command.options.first() else {
-        tracing::error!(data = ?interaction, "option not found");
-        return Err(InteractionError::InternalServerError);
-    };
+    let is_admin = interaction.member.as_ref().is_some_and(|member| {
+        member
+            .permissions
+            .is_some_and(|permissions| permissions.contains(Permissions::ADMINISTRATOR))
+    });
+    if !is_admin {
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response("Only server administrators can change the voting settings."),
+        ));
+    }
 
-    let CommandOptionValue::String(ref name) = &option.value else {
-        tracing::error!(data = ?interaction, "name not found");
-        return Err(InteractionError::InternalServerError);
+    let mut config = interaction_guild_config(data, interaction).await?;
+
+    // the reset goes first, so a setting given along with it is kept
+    for option in &command.options {
+        if let ("reset", CommandOptionValue::String(setting)) =
+            (option.name.as_str(), &option.value)
+        {
+            match setting.as_str() {
+                "anonymous_by_default" => config.anonymous_by_default = false,
+                "min_choices" => config.min_choices = None,
+                "max_choices" => config.max_choices = None,
+                "max_active_votings" => config.max_active_votings_per_creator = None,
+                _ => {}
+            }
+        }
+    }
+
+    for option in &command.options {
+        match (option.name.as_str(), &option.value) {
+            ("anonymous_by_default", CommandOptionValue::Boolean(anonymous)) => {
+                config.anonymous_by_default = *anonymous;
+            }
+            ("min_choices", CommandOptionValue::Integer(min_choices)) => {
+                config.min_choices = usize::try_from(*min_choices).ok();
+            }
+            ("max_choices", CommandOptionValue::Integer(max_choices)) => {
+                config.max_choices = usize::try_from(*max_choices).ok();
+            }
+            ("max_active_votings", CommandOptionValue::Integer(max_active)) => {
+                config.max_active_votings_per_creator = usize::try_from(*max_active).ok();
+            }
+            _ => {}
+        }
+    }
+
+    // checked against the defaults of the bot as well, a server setting may be left unset
+    let min_choices = config.min_choices.unwrap_or(data.config.min_choices).max(2);
+    let max_choices = config.max_choices.or(data.config.max_choices);
+    if let Some(max_choices) = max_choices.filter(|max_choices| *max_choices < min_choices) {
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response(&format!(
+                "Min choices ({}) can not be more than max choices ({}).",
+                min_choices, max_choices
+            )),
+        ));
+    }
+
+    if !command.options.is_empty() {
+        data.db
+            .set_guild_config(&guild_id.to_string(), config.clone())
+            .await
+            .map_err(|err| {
+                tracing::error!(%guild_id, error = ?err, "saving guild config into db failed");
+                InteractionError::InternalServerError
+            })?;
+    }
+
+    let max_choices = match max_choices {
+        Some(max_choices) => max_choices.to_string(),
+        None => "no limit".to_string(),
     };
+    let max_active = config
+        .max_active_votings_per_creator
+        .unwrap_or(data.config.max_active_votings_per_creator);
 
-    let choices: Vec<String> = command
-        .options
-        .iter()
-        .skip(1)
-        .filter_map(|option| match &option.value {
-            CommandOptionValue::String(choice) => Some(choice.clone()),
-            _ => None,
-        })
-        .collect();
+    Ok((
+        StatusCode::OK,
+        ephemeral_response(&format!(
+            "Anonymous by default: {}\nMin choices: {}\nMax choices: {}\nMax active votings per member: {}",
+            if config.anonymous_by_default {
+                "yes"
+            } else {
+                "no"
+            },
+            min_choices,
+            max_choices,
+            max_active
+        )),
+    ))
+}
 
-    if choices.len() < 2 {
-        tracing::error!(data = ?interaction, "voting must have at least 2 choices");
+// Saves, deletes or shows a voting template of the server, for administrators only.
+// Without a name the templates of the server are listed.
+async fn handle_voting_template(
+    data: &Arc<AppState>,
+    command: &CommandData,
+    interaction: &Interaction,
+) -> InteractionResult {
+    let Some(guild_id) = interaction.guild_id else {
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response("Voting templates can only be managed in a server."),
+        ));
+    };
+
+    let is_admin = interaction.member.as_ref().is_some_and(|member| {
+        member
+            .permissions
+            .is_some_and(|permissions| permissions.contains(Permissions::ADMINISTRATOR))
+    });
+    if !is_admin {
         return Ok((
             StatusCode::OK,
-            ephemeral_response("Voting must have at least 2 choices."),
+            ephemeral_response("Only server administrators can manage voting templates."),
         ));
     }
 
-    let voting = data
-        .dd_client
-        .create_voting(choices.clone())
-        .await
-        .map_err(|err| {
-            tracing::error!(data= ?interaction, error = ?err, "creating voting failed");
+    let guild_id = guild_id.to_string();
+    let mut name = None;
+    let mut choices = None;
+    let mut delete = false;
+    for option in &command.options {
+        match (option.name.as_str(), &option.value) {
+            ("name", CommandOptionValue::String(value)) => name = Some(value.trim().to_string()),
+            ("choices", CommandOptionValue::String(value)) => choices = Some(value.clone()),
+            ("delete", CommandOptionValue::Boolean(value)) => delete = *value,
+            _ => {}
+        }
+    }
+
+    let Some(name) = name.filter(|name| !name.is_empty()) else {
+        let templates = data.db.list_templates(&guild_id).await.map_err(|err| {
+            tracing::error!(%guild_id, error = ?err, "listing voting templates from db failed");
             InteractionError::InternalServerError
         })?;
 
-    let Some(ref user) = member.user else {
-        tracing::error!(data = ?interaction, "user id not found");
-        return Err(InteractionError::InternalServerError);
+        if templates.is_empty() {
+            return Ok((
+                StatusCode::OK,
+                ephemeral_response("This server has no voting templates."),
+            ));
+        }
+
+        let lines: Vec<String> = templates
+            .iter()
+            .map(|template| format!("**{}**: {}", template.name, template.choices.join(", ")))
+            .collect();
+        return Ok((StatusCode::OK, ephemeral_response(&lines.join("\n"))));
+    };
+
+    if delete {
+        return match data.db.delete_template(&guild_id, &name).await {
+            Ok(()) => Ok((
+                StatusCode::OK,
+                ephemeral_response(&format!("Template \"{}\" deleted.", name)),
+            )),
+            Err(db::DbError::NotFound) => Ok((
+                StatusCode::OK,
+                ephemeral_response(&format!("Template \"{}\" not found.", name)),
+            )),
+            Err(err) => {
+                tracing::error!(%guild_id, error = ?err, "deleting voting template from db failed");
+                Err(InteractionError::InternalServerError)
+            }
+        };
+    }
+
+    let Some(choices) = choices else {
+        return match data.db.get_template(&guild_id, &name).await {
+            Ok(template) => Ok((
+                StatusCode::OK,
+                ephemeral_response(&format!(
+                    "**{}**: {}",
+                    template.name,
+                    template.choices.join(", ")
+                )),
+            )),
+            Err(db::DbError::NotFound) => Ok((
+                StatusCode::OK,
+                ephemeral_response(&format!("Template \"{}\" not found.", name)),
+            )),
+            Err(err) => {
+                tracing::error!(%guild_id, error = ?err, "getting voting template from db failed");
+                Err(InteractionError::InternalServerError)
+            }
+        };
     };
 
-    let dm_channel = data
-        .discord_client
-        .create_private_channel(user.id)
-        .await
-        .map_err(|err| {
-            tracing::error!(data = ?interaction, error = ?err, "creating dm channel failed");
-            InteractionError::InternalServerError
-        })?
-        .model()
+    // the choices are checked like any other choices once a voting is created from the template
+    let choices: Vec<String> = choices
+        .split(',')
+        .map(str::trim)
+        .filter(|choice| !choice.is_empty())
+        .map(str::to_string)
+        .collect();
+    if choices.len() < 2 {
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response(
+                "A template needs at least 2 choices separated by commas, e.g. Approve, Reject, Abstain.",
+            ),
+        ));
+    }
+
+    let templates = data.db.list_templates(&guild_id).await.map_err(|err| {
+        tracing::error!(%guild_id, error = ?err, "listing voting templates from db failed");
+        InteractionError::InternalServerError
+    })?;
+    if templates.len() >= MAX_GUILD_TEMPLATES && !templates.iter().any(|t| t.name == name) {
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response(&format!(
+                "A server can have at most {} voting templates.",
+                MAX_GUILD_TEMPLATES
+            )),
+        ));
+    }
+
+    data.db
+        .save_template(&guild_id, &name, choices.clone())
         .await
         .map_err(|err| {
-            tracing::error!(data = ?interaction, error = ?err, "getting dm channel model failed");
+            tracing::error!(%guild_id, error = ?err, "saving voting template into db failed");
             InteractionError::InternalServerError
         })?;
 
-    let embeds = vec![EmbedBuilder::new()
-        .title(format!("Voting Created: {}", name))
-        .description("Your voting has been successfully created. The results will be published once the voting is completed.")
-        .field(EmbedFieldBuilder::new("Choices", choices.join("\n")))
-        .build()];
-
-    let mut custom_ids = Vec::new();
-    let custom_uuid = util::generate_random_custom_uuid();
-    custom_ids.push((
-        custom_uuid.clone(),
-        CustomID {
-            action: Action::Complete,
-            voting_id: voting.id.clone(),
-            user_id: None,
-            page: None,
-            index: None,
-        },
-    ));
+    Ok((
+        StatusCode::OK,
+        ephemeral_response(&format!(
+            "Template \"{}\" saved with choices: {}",
+            name,
+            choices.join(", ")
+        )),
+    ))
+}
 
-    let complete_btn = Button {
-        custom_id: Some(custom_uuid),
-        disabled: false,
-        emoji: None,
-        label: Some("Complete Voting".to_string()),
-        style: ButtonStyle::Primary,
-        url: None,
+// Lists the votings of the guild to its administrators, page by page
+async fn handle_votings(data: &Arc<AppState>, interaction: &Interaction) -> InteractionResult {
+    let is_admin = interaction.member.as_ref().is_some_and(|member| {
+        member
+            .permissions
+            .is_some_and(|permissions| permissions.contains(Permissions::ADMINISTRATOR))
+    });
+    let Some(guild_id) = interaction.guild_id.filter(|_| is_admin) else {
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response("Only server administrators can list votings."),
+        ));
     };
 
-    let custom_uuid = util::generate_random_custom_uuid();
-    custom_ids.push((
-        custom_uuid.clone(),
-        CustomID {
-            action: Action::Delete,
-            voting_id: voting.id.clone(),
-            user_id: None,
-            page: None,
-            index: None,
-        },
-    ));
-    let delete_btn = Button {
-        custom_id: Some(custom_uuid),
-        disabled: false,
-        emoji: None,
-        label: Some("Delete Voting".to_string()),
-        style: ButtonStyle::Danger,
-        url: None,
+    let listing = VotingsListing {
+        guild_id: guild_id.to_string(),
+        cursors: Vec::new(),
+        expires_unix: 0,
     };
+    let (embeds, components) = create_votings_page_components(data, interaction, listing).await?;
 
-    let components = vec![Component::ActionRow(ActionRow {
-        components: Vec::from([
-            Component::Button(complete_btn),
-            Component::Button(delete_btn),
-        ]),
-    })];
-
-    let creator_message_id =
-        create_message(&data.discord_client, dm_channel.id, &embeds, &components)
-            .await?
-            .id
-            .to_string();
-
-    let embeds = vec![EmbedBuilder::new()
-        .title(format!(
-            "Created a voting with name:{}, id: {} and choices: {:?}",
-            name, voting.id, voting.choices
-        ))
-        .description("Click vote button when you are ready to vote. The voting will be done in dm.")
-        .field(EmbedFieldBuilder::new("Choices", choices.join("\n")))
-        .build()];
+    Ok((
+        StatusCode::OK,
+        Json(InteractionResponse {
+            kind: InteractionResponseType::ChannelMessageWithSource,
+            data: Some(InteractionResponseData {
+                embeds: Some(embeds),
+                components: Some(components),
+                flags: Some(MessageFlags::EPHEMERAL),
+                ..Default::default()
+            }),
+        }),
+    ))
+}
 
-    let custom_uuid = util::generate_random_custom_uuid();
-    let custom_id = CustomID {
-        action: Action::VoteFromChannel,
-        voting_id: voting.id.clone(),
-        user_id: None,
-        page: None,
-        index: None,
+async fn handle_votings_page(
+    data: &Arc<AppState>,
+    interaction: &Interaction,
+    custom_uuid: &str,
+) -> InteractionResult {
+    let listing = match data.db.get_votings_listing(custom_uuid).await {
+        Ok(listing) => Some(listing),
+        Err(db::DbError::NotFound) => None,
+        Err(err) => {
+            tracing::error!(error = ?err, data = ?interaction.data, "getting votings listing from db failed");
+            return Err(InteractionError::InternalServerError);
+        }
     };
 
-    let vote_btn = Button {
-        custom_id: Some(custom_uuid.clone()),
-        disabled: false,
-        emoji: None,
-        label: Some("Vote".to_string()),
-        style: ButtonStyle::Primary,
-        url: None,
+    let listing = listing.filter(|listing| {
+        listing.expires_unix >= util::unix_timestamp()
+            && interaction
+                .guild_id
+                .is_some_and(|guild_id| listing.guild_id == guild_id.to_string())
+    });
+    let Some(listing) = listing else {
+        return Ok((
+            StatusCode::OK,
+            ephemeral_response("This list has expired, please run /votings again."),
+        ));
     };
 
-    custom_ids.push((custom_uuid, custom_id));
+    let (embeds, components) = create_votings_page_components(data, interaction, listing).await?;
 
-    data.db.bulk_save_custom_ids(custom_ids).await .map_err(|err| {
-        tracing::error!(data = ?interaction, error = ?err, "bulk saving custom ids into db failed");
-        InteractionError::InternalServerError
-    })?;
+    Ok((
+        StatusCode::OK,
+        Json(InteractionResponse {
+            kind: InteractionResponseType::UpdateMessage,
+            data: Some(InteractionResponseData {
+                embeds: Some(embeds),
+                components: Some(components),
+                ..Default::default()
+            }),
+        }),
+    ))
+}
 
-    let components = vec![Component::ActionRow(ActionRow {
-        components: Vec::from([Component::Button(vote_btn)]),
-    })];
+// Votings embed for the page of `listing`. The page buttons carry the cursors of their page,
+// so only the votings of the page are read.
+async fn create_votings_page_components(
+    data: &Arc<AppState>,
+    interaction: &Interaction,
+    listing: VotingsListing,
+) -> Result<(Vec<Embed>, Vec<Component>), InteractionError> {
+    let (votings, next) = data
+        .db
+        .get_guild_votings(
+            &listing.guild_id,
+            listing.cursors.last().map(String::as_str),
+            VOTINGS_PAGE_SIZE,
+        )
+        .await
+        .map_err(|err| {
+            tracing::error!(error = ?err, data = ?interaction.data, "getting guild votings from db failed");
+            InteractionError::InternalServerError
+        })?;
 
-    let Some(ref channel) = interaction.channel else {
-        tracing::error!(data = ?interaction, "channel not found");
-        return Err(InteractionError::InternalServerError);
+    let description = if votings.is_empty() {
+        "No votings found.".to_string()
+    } else {
+        votings
+            .iter()
+            .map(|voting| {
+                let state = if voting.is_completed {
+                    "Completed"
+                } else {
+                    "Active"
+                };
+                format!(
+                    "`{}` {} by <@{}> ({})",
+                    voting.id, voting.name, voting.creator_user_id, state
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
     };
 
-    let message = create_message(&data.discord_client, channel.id, &embeds, &components).await?;
+    let embeds = vec![EmbedBuilder::new()
+        .title(format!("Votings - Page {}", listing.page()))
+        .description(description)
+        .build()];
+
+    let expires_unix = util::unix_timestamp() + VOTINGS_LISTING_TTL_SECS;
+    let mut listings: Vec<(String, VotingsListing)> = Vec::new();
+    let mut btns = Vec::new();
+
+    let mut pages = Vec::new();
+    if !listing.cursors.is_empty() {
+        let mut cursors = listing.cursors.clone();
+        cursors.pop();
+        pages.push(("Previous", cursors));
+    }
+    if let Some(next) = next {
+        let mut cursors = listing.cursors.clone();
+        cursors.push(next);
+        pages.push(("Next", cursors));
+    }
+
+    for (label, cursors) in pages {
+        let custom_uuid = util::generate_random_custom_uuid();
+        btns.push(Component::Button(Button {
+            custom_id: Some(format!("{}{}", VOTINGS_LISTING_PREFIX, custom_uuid)),
+            disabled: false,
+            emoji: None,
+            label: Some(label.to_string()),
+            style: ButtonStyle::Secondary,
+            url: None,
+        }));
+        listings.push((
+            custom_uuid,
+            VotingsListing {
+                guild_id: listing.guild_id.clone(),
+                cursors,
+                expires_unix,
+            },
+        ));
+    }
 
     data.db
-        .save_voting(Voting {
-            id: voting.id.clone(),
-            name: name.to_string(),
-            choices: choices.clone(),
-            is_completed: false,
-            is_deleted: false,
-            message_id: message.id.to_string(),
-            channel_id: message.channel_id.to_string(),
-            creator_message_id,
-            creator_dm_channel_id: dm_channel.id.to_string(),
-        })
+        .save_votings_listings(listings)
         .await
         .map_err(|err| {
-            tracing::error!(data = ?interaction, error = ?err, "saving voting into db failed");
+            tracing::error!(error = ?err, data = ?interaction.data, "saving votings listings into db failed");
             InteractionError::InternalServerError
         })?;
 
-    ack_response()
-}
-
-fn handle_ping() -> InteractionResult {
-    let pong = Json(InteractionResponse {
-        kind: InteractionResponseType::ChannelMessageWithSource,
-        data: Some(InteractionResponseData {
-            content: Some("pong".to_string()),
-            ..Default::default()
-        }),
-    });
+    let mut components = Vec::new();
+    if !btns.is_empty() {
+        components.push(Component::ActionRow(ActionRow { components: btns }));
+    }
 
-    Ok((StatusCode::OK, pong))
+    Ok((embeds, components))
 }
 
 fn ephemeral_response(message: &str) -> Json<InteractionResponse> {
@@ -1087,6 +6693,24 @@ fn ephemeral_response(message: &str) -> Json<InteractionResponse> {
     })
 }
 
+const INTERNAL_ERROR_MESSAGE: &str = "Ouch, something went wrong. Please try again later.";
+
+// Ephemeral internal error response naming the correlation id, and the support url when configured
+pub fn internal_error_response(
+    correlation_id: &str,
+    support_url: Option<&str>,
+) -> Json<InteractionResponse> {
+    let mut message = format!("{} Reference: `{}`", INTERNAL_ERROR_MESSAGE, correlation_id);
+    if let Some(support_url) = support_url {
+        message.push_str(&format!(
+            "\nIf this keeps happening, contact support: {}",
+            support_url
+        ));
+    }
+
+    ephemeral_response(&message)
+}
+
 fn ack_response() -> InteractionResult {
     Ok((
         StatusCode::OK,
@@ -1101,17 +6725,57 @@ fn ack_response() -> InteractionResult {
 pub enum InteractionError {
     Status(StatusCode),
     InternalServerError,
+    // the voting does not exist on the DD backend
+    VotingNotFound,
+    // the DD backend is rate limiting the bot
+    RateLimited,
+    // the DD backend rejected the request
+    InvalidRequest,
+    // a dm channel with the user could not be opened
+    DmUnavailable,
+    // the user is the bot itself
+    BotUser,
 }
 
 impl IntoResponse for InteractionError {
     fn into_response(self) -> Response {
-        match self {
-            InteractionError::Status(status) => (status, "").into_response(),
-            InteractionError::InternalServerError => (
-                StatusCode::OK,
-                ephemeral_response("Ouch, something went wrong. Please try again later."),
-            )
-                .into_response(),
+        let message = match self {
+            // discord only checks the status, the body tells proxies and logs what was rejected
+            InteractionError::Status(status) => {
+                let error = match status {
+                    StatusCode::BAD_REQUEST => "malformed interaction body",
+                    StatusCode::UNAUTHORIZED => "invalid request signature",
+                    StatusCode::PAYLOAD_TOO_LARGE => "interaction body too large",
+                    StatusCode::UNSUPPORTED_MEDIA_TYPE => {
+                        "interaction body must be application/json"
+                    }
+                    _ => status.canonical_reason().unwrap_or("request failed"),
+                };
+                return (status, Json(serde_json::json!({ "error": error }))).into_response();
+            }
+            InteractionError::InternalServerError => INTERNAL_ERROR_MESSAGE,
+            InteractionError::VotingNotFound => "This voting does not exist anymore.",
+            InteractionError::RateLimited => {
+                "Too many requests at the moment. Please try again in a minute."
+            }
+            InteractionError::InvalidRequest => {
+                "The voting service rejected the request. Please check your input and try again."
+            }
+            InteractionError::DmUnavailable => DM_UNAVAILABLE_MESSAGE,
+            InteractionError::BotUser => BOT_USER_MESSAGE,
+        };
+
+        (StatusCode::OK, ephemeral_response(message)).into_response()
+    }
+}
+
+impl From<ApiError> for InteractionError {
+    fn from(err: ApiError) -> Self {
+        match err {
+            ApiError::NotFound => InteractionError::VotingNotFound,
+            ApiError::TooManyRequests => InteractionError::RateLimited,
+            ApiError::BadRequest(_) => InteractionError::InvalidRequest,
+            _ => InteractionError::InternalServerError,
         }
     }
 }
@@ -1124,7 +6788,29 @@ async fn update_message(
     embeds: Option<&[Embed]>,
     components: Option<&[Component]>,
 ) -> Result<(), InteractionError> {
-    discord_client
+    update_message_with_mentions(
+        discord_client,
+        channel_id,
+        message_id,
+        content,
+        embeds,
+        components,
+        None,
+    )
+    .await
+}
+
+// Same as `update_message`, but overrides the client default allowed mentions when provided
+async fn update_message_with_mentions(
+    discord_client: &twilight_http::Client,
+    channel_id: Id<ChannelMarker>,
+    message_id: Id<MessageMarker>,
+    content: Option<&str>,
+    embeds: Option<&[Embed]>,
+    components: Option<&[Component]>,
+    allowed_mentions: Option<&AllowedMentions>,
+) -> Result<(), InteractionError> {
+    let mut request = discord_client
         .update_message(channel_id, message_id)
         .content(content)
         .map_err(|err| {
@@ -1140,14 +6826,147 @@ async fn update_message(
         .map_err(|err| {
             tracing::error!(error = ?err, "components failed");
             InteractionError::InternalServerError
-        })?
-        .await
-        .map_err(|err| {
-            tracing::error!(error = ?err, "updating message failed");
-            InteractionError::InternalServerError
         })?;
 
-    Ok(())
+    if allowed_mentions.is_some() {
+        request = request.allowed_mentions(allowed_mentions);
+    }
+
+    match request.await {
+        Ok(_) => Ok(()),
+        // e.g. a moderator deleted the message, there is nothing left to update
+        Err(err) if is_unknown_message(&err) => {
+            tracing::info!(%channel_id, %message_id, "message to update no longer exists, skipping");
+            Ok(())
+        }
+        Err(err) => {
+            tracing::error!(error = ?err, "updating message failed");
+            Err(InteractionError::InternalServerError)
+        }
+    }
+}
+
+// Discord answers with code 10008 when the message was deleted
+fn is_unknown_message(err: &twilight_http::Error) -> bool {
+    matches!(
+        err.kind(),
+        twilight_http::error::ErrorType::Response {
+            error: twilight_http::api_error::ApiError::General(general),
+            ..
+        } if general.code == 10008
+    )
+}
+
+// The summary is an extra on top of the edited announcement, failing to post or pin it is only logged
+async fn post_pinned_summary(
+    data: &Arc<AppState>,
+    voting_id: &str,
+    channel_id: Id<ChannelMarker>,
+    content: &str,
+    allowed_mentions: &AllowedMentions,
+) {
+    let request = match data
+        .discord_client
+        .create_message(channel_id)
+        .content(content)
+    {
+        Ok(request) => request.allowed_mentions(Some(allowed_mentions)),
+        Err(err) => {
+            tracing::error!(%voting_id, error = ?err, "summary content failed");
+            return;
+        }
+    };
+
+    let message = match request.await {
+        Ok(response) => match response.model().await {
+            Ok(message) => message,
+            Err(err) => {
+                tracing::error!(%voting_id, error = ?err, "getting summary message model failed");
+                return;
+            }
+        },
+        Err(err) => {
+            tracing::error!(%voting_id, error = ?err, "posting summary message failed");
+            return;
+        }
+    };
+
+    if let Err(err) = data.discord_client.create_pin(channel_id, message.id).await {
+        if is_pin_limit_reached(&err) {
+            tracing::warn!(%voting_id, %channel_id, "channel reached the pin limit, summary left unpinned");
+        } else {
+            tracing::error!(%voting_id, error = ?err, "pinning summary message failed");
+        }
+    }
+}
+
+// Discord answers with code 30003 when the channel already has the maximum of 50 pins
+fn is_pin_limit_reached(err: &twilight_http::Error) -> bool {
+    matches!(
+        err.kind(),
+        twilight_http::error::ErrorType::Response {
+            error: twilight_http::api_error::ApiError::General(general),
+            ..
+        } if general.code == 30003
+    )
+}
+
+// The chart and the json results are extras on top of the results embeds, failing to attach them
+// is only logged. They are attached in a single edit, as every edit replaces the attachments.
+#[cfg_attr(not(feature = "chart"), allow(unused_variables))]
+async fn attach_results(
+    data: &Arc<AppState>,
+    voting: &Voting,
+    channel_id: Id<ChannelMarker>,
+    message_id: Id<MessageMarker>,
+    results: &VotingResults,
+    artifact: &ResultsArtifact,
+) {
+    let voting_id = voting.id.as_str();
+    let mut attachments = Vec::new();
+
+    #[cfg(feature = "chart")]
+    if voting.chart {
+        let png = chart::render_results_chart(results);
+        if !png.is_empty() {
+            attachments.push(twilight_model::http::attachment::Attachment::from_bytes(
+                chart::CHART_FILENAME.to_string(),
+                png,
+                attachments.len() as u64,
+            ));
+        }
+    }
+
+    if data.config.attach_results_json {
+        match serde_json::to_vec_pretty(artifact) {
+            Ok(json) => attachments.push(twilight_model::http::attachment::Attachment::from_bytes(
+                RESULTS_ARTIFACT_FILENAME.to_string(),
+                json,
+                attachments.len() as u64,
+            )),
+            Err(err) => tracing::error!(%voting_id, error = ?err, "serializing results failed"),
+        }
+    }
+
+    if attachments.is_empty() {
+        return;
+    }
+
+    let request = match data
+        .discord_client
+        .update_message(channel_id, message_id)
+        .attachments(&attachments)
+    {
+        Ok(request) => request,
+        Err(err) => {
+            tracing::error!(%voting_id, error = ?err, "results attachments failed");
+            return;
+        }
+    };
+
+    if let Err(err) = request.await {
+        tracing::error!(%voting_id, error = ?err, "attaching results failed");
+    }
 }
 
 async fn create_message(
@@ -1,5 +1,10 @@
-use axum::{routing::post, Router};
+use axum::{
+    extract::DefaultBodyLimit,
+    routing::{get, post},
+    Router,
+};
 use ddclient_rs::Client;
+use std::collections::HashMap;
 use std::time::Duration;
 use tokio::time;
 use tracing_panic::panic_hook;
@@ -7,6 +12,19 @@ use tracing_subscriber::{filter::EnvFilter, fmt::Subscriber};
 use twilight_http::Client as DiscordClient;
 
 const MAX_CHOICES: usize = 32;
+// How often abandoned voting dialogs are looked for
+const STALE_DIALOG_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+// How often dialog messages which failed to update on close are looked for
+const FAILED_CLEANUP_RETRY_INTERVAL: Duration = Duration::from_secs(60);
+// Interaction tokens are valid for 15 minutes, discord does not redeliver after that
+const INTERACTION_DEDUP_SECS: u64 = 15 * 60;
+
+// locale -> (name, description) of the voting command
+const VOTING_COMMAND_LOCALIZATIONS: &[(&str, &str, &str)] = &[
+    ("de", "abstimmung", "Eine Abstimmung erstellen"),
+    ("es-ES", "votacion", "Crear una votación"),
+    ("fr", "vote", "Créer un vote"),
+];
 
 #[tokio::main]
 async fn main() {
@@ -19,7 +37,48 @@ async fn main() {
 
     std::panic::set_hook(Box::new(panic_hook));
 
-    let db = dd_discord::db::new();
+    let mut db_options = dd_discord::db::DbOptions::default();
+    if let Ok(cache_bytes) = std::env::var("DB_CACHE_BYTES") {
+        db_options.cache_bytes = Some(
+            cache_bytes
+                .parse()
+                .expect("DB_CACHE_BYTES must be a number"),
+        );
+    }
+    if let Ok(durability) = std::env::var("DB_DURABILITY") {
+        db_options.durability = match durability.as_str() {
+            "immediate" => redb::Durability::Immediate,
+            "eventual" => redb::Durability::Eventual,
+            _ => panic!("DB_DURABILITY must be either immediate or eventual"),
+        };
+    }
+    if let Ok(table_prefix) = std::env::var("DB_TABLE_PREFIX") {
+        db_options.table_prefix = table_prefix;
+    }
+
+    // redb locks the file, a second instance refuses to run instead of overwriting the votings
+    let mut db = match dd_discord::db::new_with_path(dd_discord::db::DB_PATH, db_options) {
+        Ok(db) => db,
+        Err(dd_discord::db::DbError::DatabaseInUse(err)) => {
+            panic!("another instance is using the database: {}", err)
+        }
+        Err(err) => panic!("opening the database failed: {:?}", err),
+    };
+    if std::env::var("COMPACT_ON_START").as_deref() == Ok("1") {
+        db = compact_db(db).await;
+    }
+
+    if let Err(err) = db.self_check().await {
+        panic!("the database is corrupted: {:?}", err);
+    }
+
+    match db.run_migrations().await {
+        Ok(applied) if !applied.is_empty() => {
+            tracing::info!(?applied, "applied database migrations")
+        }
+        Ok(_) => {}
+        Err(err) => panic!("migrating the database failed: {:?}", err),
+    }
     let bot_token = std::env::var("BOT_TOKEN").expect("BOT_TOKEN env variable not set");
     let dd_token = std::env::var("DD_TOKEN").expect("DD_TOKEN env variable not set");
     let dd_api_url = std::env::var("DD_API_URL").expect("API_URL env variable not set");
@@ -28,6 +87,63 @@ async fn main() {
     let discord_public_key =
         std::env::var("DISCORD_PUBLIC_KEY").expect("DISCORD_PUBLIC_KEY env variable not set");
 
+    let mut config = dd_discord::Config::default();
+    if let Ok(max) = std::env::var("MAX_ACTIVE_VOTINGS_PER_CREATOR") {
+        config.max_active_votings_per_creator = max
+            .parse()
+            .expect("MAX_ACTIVE_VOTINGS_PER_CREATOR must be a number");
+    }
+    if std::env::var("KEEP_CLOSED_COMPONENTS").as_deref() == Ok("1") {
+        config.keep_closed_components = true;
+    }
+    if let Ok(min_choices) = std::env::var("MIN_CHOICES_PER_VOTING") {
+        config.min_choices = min_choices
+            .parse()
+            .expect("MIN_CHOICES_PER_VOTING must be a number");
+    }
+    if let Ok(max_choices) = std::env::var("MAX_CHOICES_PER_VOTING") {
+        config.max_choices = Some(
+            max_choices
+                .parse()
+                .expect("MAX_CHOICES_PER_VOTING must be a number"),
+        );
+    }
+    if config
+        .max_choices
+        .is_some_and(|max_choices| max_choices < config.min_choices)
+    {
+        panic!("MAX_CHOICES_PER_VOTING must not be less than MIN_CHOICES_PER_VOTING");
+    }
+    if let Ok(max_age) = std::env::var("DIALOG_MAX_AGE_SECS") {
+        config.dialog_max_age_secs = max_age
+            .parse()
+            .expect("DIALOG_MAX_AGE_SECS must be a number");
+    }
+    config.results_api_secret = std::env::var("RESULTS_API_SECRET")
+        .ok()
+        .filter(|secret| !secret.is_empty());
+    config.admin_api_secret = std::env::var("ADMIN_API_SECRET")
+        .ok()
+        .filter(|secret| !secret.is_empty());
+    if std::env::var("ATTACH_RESULTS_JSON").as_deref() == Ok("1") {
+        config.attach_results_json = true;
+    }
+    config.interaction_dedup_secs = match std::env::var("INTERACTION_DEDUP_SECS") {
+        Ok(secs) => secs
+            .parse()
+            .expect("INTERACTION_DEDUP_SECS must be a number"),
+        Err(_) => INTERACTION_DEDUP_SECS,
+    };
+    config.support_url = std::env::var("SUPPORT_URL")
+        .ok()
+        .filter(|url| !url.is_empty());
+    config.voting_command = Some(dd_discord::VotingCommandRegistration {
+        bot_token: bot_token.clone(),
+        register_url: discord_register_url.clone(),
+        max_choices: MAX_CHOICES,
+        localizations: voting_command_localizations(),
+    });
+
     let discord_client = DiscordClient::new(bot_token.clone());
     let dd_client = Client::builder(dd_token).api_url(dd_api_url).build();
 
@@ -36,13 +152,63 @@ async fn main() {
         .unwrap();
     tracing::info!("listening on {}", listener.local_addr().unwrap());
 
-    let app_state = dd_discord::new_app_state(db, discord_client, dd_client, discord_public_key);
+    let app_state =
+        dd_discord::new_app_state(db, discord_client, dd_client, discord_public_key, config);
+
+    dd_discord::fetch_bot_user_id(&app_state).await;
+    dd_discord::replay_pending_votes(&app_state).await;
+    dd_discord::reconcile_pending_completions(&app_state).await;
+    dd_discord::schedule_pending_deadlines(&app_state).await;
+
+    let expiry_state = app_state.clone();
+    tokio::spawn(async move {
+        let mut interval = time::interval(STALE_DIALOG_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            dd_discord::expire_stale_dialogs(&expiry_state).await;
+            dd_discord::expire_votings_listings(&expiry_state).await;
+        }
+    });
+
+    let cleanup_state = app_state.clone();
+    tokio::spawn(async move {
+        let mut interval = time::interval(FAILED_CLEANUP_RETRY_INTERVAL);
+        loop {
+            interval.tick().await;
+            dd_discord::retry_failed_cleanups(&cleanup_state).await;
+            dd_discord::retry_pending_completions(&cleanup_state).await;
+        }
+    });
 
     let app = Router::new()
-        .route("/", post(dd_discord::handle_interaction))
+        .route(
+            "/",
+            post(dd_discord::handle_interaction).layer(DefaultBodyLimit::max(
+                dd_discord::MAX_INTERACTION_BODY_BYTES,
+            )),
+        )
+        .route(
+            "/votings/:id/results",
+            get(dd_discord::handle_voting_results),
+        )
+        .route("/admin/register", post(dd_discord::handle_admin_register))
+        .route("/admin/read-only", post(dd_discord::handle_admin_read_only))
         .with_state(app_state.clone());
 
-    dd_discord::util::register_voting_command(&bot_token, &discord_register_url, MAX_CHOICES).await;
+    dd_discord::util::register_voting_command(
+        &bot_token,
+        &discord_register_url,
+        MAX_CHOICES,
+        &voting_command_localizations(),
+    )
+    .await;
+    dd_discord::util::register_my_votes_command(&bot_token, &discord_register_url).await;
+    dd_discord::util::register_voting_transfer_command(&bot_token, &discord_register_url).await;
+    dd_discord::util::register_voting_clone_command(&bot_token, &discord_register_url).await;
+    dd_discord::util::register_votings_command(&bot_token, &discord_register_url).await;
+    dd_discord::util::register_voting_debug_command(&bot_token, &discord_register_url).await;
+    dd_discord::util::register_voting_config_command(&bot_token, &discord_register_url).await;
+    dd_discord::util::register_voting_template_command(&bot_token, &discord_register_url).await;
 
     axum::serve(listener, app)
         .with_graceful_shutdown(async move {
@@ -53,6 +219,9 @@ async fn main() {
 
             app_state.task_tracker.close();
 
+            let cancelled = app_state.cancel_all_deadlines();
+            tracing::info!(cancelled, "cancelled scheduled deadlines");
+
             match time::timeout(Duration::from_secs(10), app_state.task_tracker.wait()).await {
                 Ok(_) => tracing::info!("All tasks finished cleanly."),
                 Err(_) => tracing::info!("Timed out waiting for tasks to finish."),
@@ -61,3 +230,41 @@ async fn main() {
         .await
         .unwrap();
 }
+
+// Compacts the database before it is shared with the handlers, on a blocking task
+async fn compact_db(mut db: dd_discord::db::Db) -> dd_discord::db::Db {
+    tokio::task::spawn_blocking(move || {
+        let file_size = || {
+            std::fs::metadata(dd_discord::db::DB_PATH)
+                .map(|m| m.len())
+                .unwrap_or_default()
+        };
+
+        let size_before = file_size();
+        match db.compact() {
+            Ok(compacted) => tracing::info!(
+                compacted,
+                size_before,
+                size_after = file_size(),
+                "database compaction finished"
+            ),
+            Err(err) => tracing::error!(error = ?err, "database compaction failed"),
+        }
+
+        db
+    })
+    .await
+    .expect("database compaction task failed")
+}
+
+fn voting_command_localizations() -> HashMap<String, (String, String)> {
+    VOTING_COMMAND_LOCALIZATIONS
+        .iter()
+        .map(|(locale, name, description)| {
+            (
+                locale.to_string(),
+                (name.to_string(), description.to_string()),
+            )
+        })
+        .collect()
+}
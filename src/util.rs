@@ -1,17 +1,81 @@
 use ed25519_dalek::{Signature, VerifyingKey};
 use http::HeaderMap;
 use reqwest::Method;
-use twilight_model::application::command::CommandType;
-use twilight_util::builder::command::{CommandBuilder, StringBuilder};
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+use twilight_model::application::command::{Command, CommandType};
+use twilight_model::guild::Permissions;
+use twilight_util::builder::command::{
+    BooleanBuilder, CommandBuilder, IntegerBuilder, RoleBuilder, StringBuilder, UserBuilder,
+};
 use uuid::Uuid;
 
+// Longest duration the voting command accepts, a month
+pub const MAX_VOTING_DURATION_HOURS: i64 = 24 * 30;
+
 // Register voting command to the bot
 // This will overwrite the existing command if changed
 // Panics if the request fails, which is fine because the bot should not work without the command
-pub async fn register_voting_command(token: &str, api_url: &str, max_choices: usize) {
-    let mut cmd = CommandBuilder::new("voting", "Create a voting", CommandType::ChatInput)
+pub async fn register_voting_command(
+    token: &str,
+    api_url: &str,
+    max_choices: usize,
+    localizations: &HashMap<String, (String, String)>,
+) {
+    register_command(
+        token,
+        api_url,
+        build_voting_command(max_choices, localizations),
+    )
+    .await;
+}
+
+// Same as `register_voting_command` but returns the discord response status instead of panicking
+pub async fn try_register_voting_command(
+    token: &str,
+    api_url: &str,
+    max_choices: usize,
+    localizations: &HashMap<String, (String, String)>,
+) -> Result<reqwest::StatusCode, reqwest::Error> {
+    send_command(
+        token,
+        api_url,
+        &build_voting_command(max_choices, localizations),
+    )
+    .await
+}
+
+// Voting command with the translated name and description for every locale in `localizations`,
+// given as locale -> (name, description). Other locales get the default english strings.
+pub fn build_voting_command(
+    max_choices: usize,
+    localizations: &HashMap<String, (String, String)>,
+) -> Command {
+    let mut cmd = CommandBuilder::new("voting", "Create a voting", CommandType::ChatInput);
+
+    if !localizations.is_empty() {
+        cmd = cmd
+            .name_localizations(
+                localizations
+                    .iter()
+                    .map(|(locale, (name, _))| (locale.clone(), name.clone())),
+            )
+            .description_localizations(
+                localizations
+                    .iter()
+                    .map(|(locale, (_, description))| (locale.clone(), description.clone())),
+            );
+    }
+
+    cmd = cmd
         .option(StringBuilder::new("name", "The reason of the voting").required(true))
-        .option(StringBuilder::new("choice1", "The first choice").required(true));
+        .option(
+            StringBuilder::new(
+                "choice1",
+                "The first choice, with choice_descriptions e.g. Kant | Critique of Pure Reason",
+            )
+            .required(false),
+        );
 
     for i in 2..=max_choices {
         cmd = cmd.option(
@@ -20,16 +84,327 @@ pub async fn register_voting_command(token: &str, api_url: &str, max_choices: us
         );
     }
 
+    cmd = cmd.option(
+        StringBuilder::new(
+            "template",
+            "Template of this server to take the choices from, when no choices are given",
+        )
+        .required(false),
+    );
+
+    cmd = cmd.option(
+        BooleanBuilder::new(
+            "choice_descriptions",
+            "Split each choice at | into the choice and a description shown to voters",
+        )
+        .required(false),
+    );
+
+    cmd = cmd.option(
+        IntegerBuilder::new("max_ranks", "How many top choices each voter ranks")
+            .min_value(1)
+            .max_value(max_choices as i64)
+            .required(false),
+    );
+
+    cmd = cmd.option(
+        BooleanBuilder::new(
+            "require_full_ranking",
+            "Voters must rank every choice, otherwise unranked choices are tied last",
+        )
+        .required(false),
+    );
+
+    cmd = cmd.option(
+        RoleBuilder::new("allowed_role", "Only members with this role can vote").required(false),
+    );
+
+    cmd = cmd.option(
+        StringBuilder::new(
+            "role_weights",
+            "Extra voting weight per role, e.g. @Council:3 @Members:2",
+        )
+        .required(false),
+    );
+
+    cmd = cmd.option(
+        StringBuilder::new(
+            "questions",
+            "Further questions ranked in the same dialog, e.g. Venue: Park, Hall | Food: Pizza, Tacos",
+        )
+        .required(false),
+    );
+
+    cmd = cmd.option(
+        StringBuilder::new(
+            "completion_template",
+            "Completion message, supports {name}, {winner} and {runner_up}",
+        )
+        .required(false),
+    );
+
+    cmd = cmd.option(
+        StringBuilder::new(
+            "tie_break",
+            "How the winner is picked when the top choices are tied",
+        )
+        .choices([
+            ("None", "none"),
+            ("Random draw", "random"),
+            ("Earliest choice", "earliest_choice"),
+            ("Most first-choice votes", "first_choice_count"),
+        ])
+        .required(false),
+    );
+
+    cmd = cmd.option(
+        StringBuilder::new("image_url", "Banner image shown in the voting announcement")
+            .required(false),
+    );
+
+    cmd = cmd.option(
+        StringBuilder::new(
+            "vote_button_label",
+            "Label of the vote button in the announcement, e.g. Rank the finalists",
+        )
+        .required(false),
+    );
+
+    cmd = cmd.option(
+        StringBuilder::new("vote_button_emoji", "Emoji shown on the vote button").required(false),
+    );
+
+    cmd = cmd.option(
+        BooleanBuilder::new(
+            "show_vote_count",
+            "Show how many people have voted in the announcement",
+        )
+        .required(false),
+    );
+
+    cmd = cmd.option(
+        BooleanBuilder::new(
+            "completion_mentions",
+            "Allow the completion message to ping mentioned users and roles",
+        )
+        .required(false),
+    );
+
+    cmd = cmd.option(
+        BooleanBuilder::new(
+            "simple",
+            "Single choice voting with emoji reactions instead of ranking in dm",
+        )
+        .required(false),
+    );
+
+    cmd = cmd.option(
+        BooleanBuilder::new(
+            "in_channel",
+            "Show the voting dialog in the channel instead of sending a dm",
+        )
+        .required(false),
+    );
+
+    cmd = cmd.option(
+        BooleanBuilder::new(
+            "shuffle",
+            "Show the choices in a different order to every voter",
+        )
+        .required(false),
+    );
+
+    cmd = cmd.option(
+        BooleanBuilder::new("private_results", "Only show the results to the creator")
+            .required(false),
+    );
+
+    cmd = cmd.option(
+        BooleanBuilder::new(
+            "anonymous",
+            "Don't ask voters for comments or show them with the results",
+        )
+        .required(false),
+    );
+
+    cmd = cmd.option(
+        BooleanBuilder::new(
+            "pin_result",
+            "Post and pin a summary of the results when the voting is completed",
+        )
+        .required(false),
+    );
+
+    #[cfg(feature = "chart")]
+    {
+        cmd = cmd.option(
+            BooleanBuilder::new("chart", "Attach a bar chart of the results on completion")
+                .required(false),
+        );
+    }
+
+    cmd = cmd.option(
+        IntegerBuilder::new(
+            "duration_hours",
+            "Complete the voting automatically after this many hours",
+        )
+        .min_value(1)
+        .max_value(MAX_VOTING_DURATION_HOURS)
+        .required(false),
+    );
+
+    cmd.build()
+}
+
+// Register my-votes command to the bot, listing the open voting dialogs of the user
+pub async fn register_my_votes_command(token: &str, api_url: &str) {
+    let cmd = CommandBuilder::new(
+        "my-votes",
+        "List your open voting dialogs",
+        CommandType::ChatInput,
+    );
+
+    register_command(token, api_url, cmd.build()).await;
+}
+
+// Register voting-transfer command to the bot, hidden from members who are not administrators
+pub async fn register_voting_transfer_command(token: &str, api_url: &str) {
+    let cmd = CommandBuilder::new(
+        "voting-transfer",
+        "Transfer a voting to another owner",
+        CommandType::ChatInput,
+    )
+    .default_member_permissions(Permissions::ADMINISTRATOR)
+    .option(StringBuilder::new("voting_id", "The id of the voting").required(true))
+    .option(UserBuilder::new("new_owner", "The new owner of the voting").required(true));
+
+    register_command(token, api_url, cmd.build()).await;
+}
+
+pub async fn register_voting_clone_command(token: &str, api_url: &str) {
+    let cmd = CommandBuilder::new(
+        "voting-clone",
+        "Start a new voting with the choices of a past one",
+        CommandType::ChatInput,
+    )
+    .option(StringBuilder::new("voting_id", "The id of the voting to clone").required(true));
+
+    register_command(token, api_url, cmd.build()).await;
+}
+
+pub async fn register_votings_command(token: &str, api_url: &str) {
+    let cmd = CommandBuilder::new("votings", "List all votings", CommandType::ChatInput)
+        .default_member_permissions(Permissions::ADMINISTRATOR);
+
+    register_command(token, api_url, cmd.build()).await;
+}
+
+// Register voting-debug command to the bot, hidden from members who are not administrators
+pub async fn register_voting_debug_command(token: &str, api_url: &str) {
+    let cmd = CommandBuilder::new(
+        "voting-debug",
+        "Show the stored data of a voting",
+        CommandType::ChatInput,
+    )
+    .default_member_permissions(Permissions::ADMINISTRATOR)
+    .option(StringBuilder::new("voting_id", "The id of the voting").required(true));
+
+    register_command(token, api_url, cmd.build()).await;
+}
+
+// Register voting-config command to the bot, hidden from members who are not administrators.
+// Options which are not given keep their current value, `reset` clears the override of a setting.
+pub async fn register_voting_config_command(token: &str, api_url: &str) {
+    let cmd = CommandBuilder::new(
+        "voting-config",
+        "Show or change the voting settings of this server",
+        CommandType::ChatInput,
+    )
+    .default_member_permissions(Permissions::ADMINISTRATOR)
+    .option(
+        BooleanBuilder::new(
+            "anonymous_by_default",
+            "Votings are anonymous unless the creator says otherwise",
+        )
+        .required(false),
+    )
+    .option(
+        IntegerBuilder::new("min_choices", "Fewest choices a voting can have")
+            .min_value(2)
+            .required(false),
+    )
+    .option(
+        IntegerBuilder::new("max_choices", "Most choices a voting can have")
+            .min_value(2)
+            .required(false),
+    )
+    .option(
+        IntegerBuilder::new(
+            "max_active_votings",
+            "How many active votings a single member can have",
+        )
+        .min_value(1)
+        .required(false),
+    )
+    .option(
+        StringBuilder::new(
+            "reset",
+            "Go back to the default of the bot for this setting",
+        )
+        .choices([
+            ("Anonymous by default", "anonymous_by_default"),
+            ("Min choices", "min_choices"),
+            ("Max choices", "max_choices"),
+            ("Max active votings", "max_active_votings"),
+        ])
+        .required(false),
+    );
+
+    register_command(token, api_url, cmd.build()).await;
+}
+
+// Register voting-template command to the bot, hidden from members who are not administrators.
+// Saves the template when choices are given, without a name the templates are listed.
+pub async fn register_voting_template_command(token: &str, api_url: &str) {
+    let cmd = CommandBuilder::new(
+        "voting-template",
+        "Show, save or delete the voting templates of this server",
+        CommandType::ChatInput,
+    )
+    .default_member_permissions(Permissions::ADMINISTRATOR)
+    .option(StringBuilder::new("name", "The name of the template").required(false))
+    .option(
+        StringBuilder::new(
+            "choices",
+            "Choices separated by commas, e.g. Approve, Reject, Abstain",
+        )
+        .required(false),
+    )
+    .option(BooleanBuilder::new("delete", "Delete the template").required(false));
+
+    register_command(token, api_url, cmd.build()).await;
+}
+
+async fn register_command(token: &str, api_url: &str, cmd: Command) {
+    let status = send_command(token, api_url, &cmd).await.unwrap();
+
+    tracing::info!("register {} command: {}", cmd.name, status);
+}
+
+async fn send_command(
+    token: &str,
+    api_url: &str,
+    cmd: &Command,
+) -> Result<reqwest::StatusCode, reqwest::Error> {
     let client = reqwest::Client::new();
     let resp = client
         .request(Method::POST, api_url)
         .header("Authorization", format!("Bot {}", token))
-        .json(&cmd.build())
+        .json(cmd)
         .send()
-        .await
-        .unwrap();
+        .await?;
 
-    tracing::info!("register voting comand: {}", resp.status());
+    Ok(resp.status())
 }
 
 // verify the signature of a request
@@ -64,3 +439,54 @@ pub fn verify_signature(headers: HeaderMap, body: String, public_key: &str) -> R
 pub fn generate_random_custom_uuid() -> String {
     Uuid::new_v4().to_string()
 }
+
+// Id of a voting which is not created on the DD backend, a uuid without hyphens
+// since they delimit the db keys
+pub fn generate_voting_id() -> String {
+    Uuid::new_v4().simple().to_string()
+}
+
+// Current unix time in seconds
+pub fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+// Discord markup showing the time relative to now, e.g. "in 2 hours", clients keep it updated
+pub fn discord_relative_timestamp(unix: u64) -> String {
+    format!("<t:{}:R>", unix)
+}
+
+// Permutation of 0..n which is always the same for the same seed, e.g. a user id.
+// Fisher–Yates driven by xorshift over an FNV-1a hash of the seed, it doesn't need to be secure.
+pub fn stable_permutation(seed: &str, n: usize) -> Vec<usize> {
+    let mut state = seed.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    // xorshift gets stuck on zero
+    if state == 0 {
+        state = 1;
+    }
+
+    let mut order: Vec<usize> = (0..n).collect();
+    for i in (1..n).rev() {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        order.swap(i, (state % (i as u64 + 1)) as usize);
+    }
+
+    order
+}
+
+// Inverse of a permutation, maps each value back to its position: inverted[order[i]] == i
+pub fn invert_permutation(order: &[usize]) -> Vec<usize> {
+    let mut inverted = vec![0; order.len()];
+    for (position, &value) in order.iter().enumerate() {
+        inverted[value] = position;
+    }
+
+    inverted
+}
@@ -0,0 +1,35 @@
+#![cfg(feature = "chart")]
+
+use dd_discord::chart::render_results_chart;
+use ddclient_rs::VotingResults;
+use serde_json::json;
+
+const PNG_MAGIC: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+#[test]
+fn render_results_chart_test() {
+    let results: VotingResults = serde_json::from_value(json!({
+        "tie": false,
+        "results": [
+            {"choice": "Spinoza", "index": 0, "wins": 2, "percentage": 100.0, "strength": 2, "advantage": 1},
+            {"choice": "Kant", "index": 1, "wins": 1, "percentage": 50.0, "strength": 1, "advantage": 0},
+            {"choice": "Hume", "index": 2, "wins": 0, "percentage": 0.0, "strength": 0, "advantage": 0},
+        ],
+        "duels": null,
+    }))
+    .unwrap();
+
+    let png = render_results_chart(&results);
+    assert!(png.len() > PNG_MAGIC.len());
+    assert_eq!(png[..PNG_MAGIC.len()], PNG_MAGIC);
+
+    // votings without results still get an image
+    let empty: VotingResults = serde_json::from_value(json!({
+        "tie": false,
+        "results": [],
+        "duels": null,
+    }))
+    .unwrap();
+    let png = render_results_chart(&empty);
+    assert_eq!(png[..PNG_MAGIC.len()], PNG_MAGIC);
+}
@@ -1,8 +1,5 @@
-use std::sync::Arc;
-
-use dd_discord::db::Db;
+use dd_discord::db::{Db, DbOptions};
 use rand::Rng;
-use redb::Database;
 
 pub struct DropDb {
     name: String,
@@ -15,7 +12,11 @@ impl Drop for DropDb {
 }
 
 pub fn create_test_db() -> (DropDb, Db) {
+    create_test_db_with_options(DbOptions::default())
+}
+
+pub fn create_test_db_with_options(options: DbOptions) -> (DropDb, Db) {
     let name = format!("test-{}.redb", rand::thread_rng().gen::<u32>());
-    let db = Database::create(name.clone()).expect("failed to create database");
-    (DropDb { name }, Db { db: Arc::new(db) })
+    let db = dd_discord::db::new_with_path(&name, options).expect("failed to create database");
+    (DropDb { name }, db)
 }
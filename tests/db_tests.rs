@@ -1,14 +1,23 @@
 mod common;
-use common::create_test_db;
-use dd_discord::db::{Action, CustomID, DbError, Voting};
+use common::{create_test_db, create_test_db_with_options};
+use dd_discord::db::{
+    Action, AuditEvent, AuditEventKind, CustomID, DbError, DbOptions, FailedCleanup, GuildConfig,
+    PendingVote, Question, VoteDialog, Voting, VotingBallots, VotingState, VotingTemplate,
+    VotingsListing, CUSTOM_IDS_CHUNK_SIZE, MAX_BULK_CUSTOM_IDS, SYSTEM_USER_ID,
+};
 use dd_discord::util;
 use hex::encode;
 use rand::Rng;
+use redb::{Durability, TableDefinition};
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
 #[tokio::test]
 async fn save_voting() {
     let (_drop_db, db) = create_test_db();
-    let votings = vec![
+    let votings = [
         Voting {
             id: "84ee17be18185a077db2".to_string(),
             name: "voting1".to_string(),
@@ -19,6 +28,7 @@ async fn save_voting() {
             channel_id: "channel_id".to_string(),
             creator_message_id: "creator_message_id".to_string(),
             creator_dm_channel_id: "creator_dm_channel_id".to_string(),
+            ..Default::default()
         },
         Voting {
             id: "84ee17be18185a077db3".to_string(),
@@ -30,6 +40,7 @@ async fn save_voting() {
             channel_id: "channel_id".to_string(),
             creator_message_id: "creator_message_id".to_string(),
             creator_dm_channel_id: "creator_dm_channel_id".to_string(),
+            ..Default::default()
         },
         Voting {
             id: "84ee17be18185a077db4".to_string(),
@@ -41,6 +52,7 @@ async fn save_voting() {
             channel_id: "channel_id".to_string(),
             creator_message_id: "creator_message_id".to_string(),
             creator_dm_channel_id: "creator_dm_channel_id".to_string(),
+            ..Default::default()
         },
     ];
 
@@ -60,124 +72,221 @@ async fn save_voting() {
 }
 
 #[tokio::test]
-async fn voting_not_found() {
-    let (_drop_db, db) = create_test_db();
-    let voting_id = "84ee17be18185a077db2";
-
-    let err = db
-        .get_voting(voting_id)
-        .await
-        .expect_err("voting should not exist");
+async fn custom_db_options() {
+    let (_drop_db, db) = create_test_db_with_options(DbOptions {
+        cache_bytes: Some(4 * 1024 * 1024),
+        durability: Durability::Eventual,
+        ..Default::default()
+    });
 
-    assert_eq!(err, DbError::NotFound);
-}
-
-#[tokio::test]
-async fn voting_already_exists() {
-    let (_drop_db, db) = create_test_db();
-    let voting_id = "84ee17be18185a077db2";
     let voting = Voting {
-        id: voting_id.to_string(),
+        id: "84ee17be18185a077db2".to_string(),
         name: "voting1".to_string(),
         choices: vec!["choice1".to_string(), "choice2".to_string()],
-        is_completed: false,
-        is_deleted: false,
-        message_id: "message_id".to_string(),
-        channel_id: "channel_id".to_string(),
-        creator_message_id: "creator_message_id".to_string(),
-        creator_dm_channel_id: "creator_dm_channel_id".to_string(),
+        creator_user_id: "creator".to_string(),
+        ..Default::default()
     };
-
     db.save_voting(voting.clone())
         .await
         .expect("failed to save voting");
 
-    let err = db
-        .save_voting(voting)
+    db.save_voting_dialog(
+        voting.id.clone(),
+        "user".to_string(),
+        vec![0, 0],
+        "message-id".to_string(),
+        "channel-id".to_string(),
+        false,
+    )
+    .await
+    .expect("failed to save voting dialog");
+    db.delete_voting_dialog(&voting.id, "user")
         .await
-        .expect_err("voting should already exist");
+        .expect("failed to delete voting dialog");
 
-    assert_eq!(err, DbError::AlreadyExists);
+    assert_eq!(db.get_voting(&voting.id).await.unwrap(), voting);
+    assert_eq!(
+        db.get_voting_dialog(&voting.id, "user").await,
+        Err(DbError::NotFound)
+    );
+}
+
+#[test]
+fn database_already_open() {
+    let path = format!("test-{}.redb", rand::thread_rng().gen::<u32>());
+    let db = dd_discord::db::new_with_path(&path, DbOptions::default())
+        .expect("failed to create database");
+
+    let second = dd_discord::db::new_with_path(&path, DbOptions::default());
+    assert!(matches!(second, Err(DbError::DatabaseInUse(_))));
+
+    drop(db);
+    std::fs::remove_file(path).expect("failed to remove test database");
 }
 
 #[tokio::test]
-async fn complete_voting() {
-    let (_drop_db, db) = create_test_db();
-    let voting_id = "84ee17be18185a077db2";
-    let voting = Voting {
-        id: voting_id.to_string(),
-        name: "voting1".to_string(),
+async fn table_prefix() {
+    let path = format!("test-{}.redb", rand::thread_rng().gen::<u32>());
+    let open = |prefix: &str| {
+        dd_discord::db::new_with_path(
+            &path,
+            DbOptions {
+                table_prefix: prefix.to_string(),
+                ..Default::default()
+            },
+        )
+        .expect("failed to create database")
+    };
+
+    let voting = |name: &str| Voting {
+        id: "84ee17be18185a077db4".to_string(),
+        name: name.to_string(),
         choices: vec!["choice1".to_string(), "choice2".to_string()],
-        is_completed: false,
-        is_deleted: false,
-        message_id: "message_id".to_string(),
-        channel_id: "channel_id".to_string(),
-        creator_message_id: "creator_message_id".to_string(),
-        creator_dm_channel_id: "creator_dm_channel_id".to_string(),
+        creator_user_id: "creator".to_string(),
+        ..Default::default()
     };
 
-    db.save_voting(voting.clone())
+    // the file can only be open once, so the instances take turns
+    let dev = open("dev_");
+    dev.save_voting(voting("dev"))
         .await
         .expect("failed to save voting");
-
-    let v = db
-        .get_voting(voting_id)
+    drop(dev);
+
+    let staging = open("staging_");
+    assert!(matches!(
+        staging.get_voting("84ee17be18185a077db4").await,
+        Err(DbError::NotFound)
+    ));
+    // the same id is free under another prefix
+    staging
+        .save_voting(voting("staging"))
         .await
-        .expect("failed to get voting");
-
-    assert_eq!(v.is_completed, false);
+        .expect("failed to save voting");
+    drop(staging);
 
-    db.complete_voting(voting_id)
+    let dev = open("dev_");
+    let saved = dev
+        .get_voting("84ee17be18185a077db4")
         .await
-        .expect("failed to complete voting");
+        .expect("failed to get voting");
+    assert_eq!(saved.name, "dev");
+    drop(dev);
 
-    let v = db
-        .get_voting(voting_id)
+    let staging = open("staging_");
+    let saved = staging
+        .get_voting("84ee17be18185a077db4")
         .await
         .expect("failed to get voting");
+    assert_eq!(saved.name, "staging");
+    drop(staging);
 
-    assert_eq!(v.is_completed, true);
+    std::fs::remove_file(path).expect("failed to remove test database");
 }
 
 #[tokio::test]
-async fn complete_voting_errors() {
+async fn voting_image_url() {
     let (_drop_db, db) = create_test_db();
-    let voting_id = "84ee17be18185a077db2";
+    let voting = Voting {
+        id: "84ee17be18185a077db5".to_string(),
+        name: "voting".to_string(),
+        choices: vec!["choice1".to_string(), "choice2".to_string()],
+        image_url: Some("https://example.com/banner.png".to_string()),
+        ..Default::default()
+    };
+    db.save_voting(voting.clone()).await.unwrap();
+
+    let saved = db.get_voting(&voting.id).await.unwrap();
+    assert_eq!(
+        saved.image_url.as_deref(),
+        Some("https://example.com/banner.png")
+    );
+    assert_eq!(saved, voting);
+
+    // votings saved before the image was added have none
+    let mut json = serde_json::to_value(&voting).unwrap();
+    json.as_object_mut().unwrap().remove("image_url");
+    let old = Voting::try_from(json.to_string().as_str()).unwrap();
+    assert_eq!(old.image_url, None);
+}
 
-    let err = db
-        .complete_voting(voting_id)
-        .await
-        .expect_err("voting should not exist");
+#[tokio::test]
+async fn voting_role_weights() {
+    let (_drop_db, db) = create_test_db();
+    let voting = Voting {
+        id: "84ee17be18185a077db5".to_string(),
+        name: "voting".to_string(),
+        choices: vec!["choice1".to_string(), "choice2".to_string()],
+        role_weights: vec![("111".to_string(), 2), ("222".to_string(), 5)],
+        ..Default::default()
+    };
+    db.save_voting(voting.clone()).await.unwrap();
 
-    assert_eq!(err, DbError::NotFound);
+    let saved = db.get_voting(&voting.id).await.unwrap();
+    assert_eq!(saved, voting);
 
+    // the highest weighted role counts, members without one count once
+    assert_eq!(saved.voter_weight(&["111", "222", "333"]), 5);
+    assert_eq!(saved.voter_weight(&["111"]), 2);
+    assert_eq!(saved.voter_weight(&["333"]), 1);
+    assert_eq!(saved.voter_weight::<&str>(&[]), 1);
+
+    // dialogs saved before weights were added count once
+    db.save_voting_dialog(
+        voting.id.clone(),
+        "user".to_string(),
+        vec![0, 0],
+        String::new(),
+        String::new(),
+        false,
+    )
+    .await
+    .unwrap();
+    let dialog = db.get_voting_dialog(&voting.id, "user").await.unwrap();
+    let mut json = serde_json::to_value(&dialog).unwrap();
+    json.as_object_mut().unwrap().remove("weight");
+    let old = VoteDialog::try_from(json.to_string().as_str()).unwrap();
+    assert_eq!(old.weight, 1);
+}
+
+#[tokio::test]
+async fn get_voting_raw() {
+    let (_drop_db, db) = create_test_db();
     let voting = Voting {
-        id: voting_id.to_string(),
-        name: "voting1".to_string(),
+        id: "84ee17be18185a077db5".to_string(),
+        name: "voting".to_string(),
         choices: vec!["choice1".to_string(), "choice2".to_string()],
-        is_completed: false,
-        is_deleted: true,
-        message_id: "message_id".to_string(),
-        channel_id: "channel_id".to_string(),
-        creator_message_id: "creator_message_id".to_string(),
-        creator_dm_channel_id: "creator_dm_channel_id".to_string(),
+        ..Default::default()
     };
+    db.save_voting(voting.clone()).await.unwrap();
+
+    let raw = db.get_voting_raw(&voting.id).await.unwrap();
+    assert_eq!(raw, String::from(&voting));
+
+    // malformed json is returned verbatim while the voting can not be read
+    let malformed = r#"{"id":"broken","name":42,"#;
+    let voting_table: TableDefinition<&str, &str> = TableDefinition::new("voting");
+    let write_txn = db.db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(voting_table).unwrap();
+        table.insert("broken", malformed).unwrap();
+    }
+    write_txn.commit().unwrap();
 
-    db.save_voting(voting.clone())
-        .await
-        .expect("failed to save voting");
+    assert!(db.get_voting("broken").await.is_err());
+    assert_eq!(db.get_voting_raw("broken").await.unwrap(), malformed);
 
-    db.complete_voting(voting_id)
-        .await
-        .expect_err("voting should be deleted");
+    assert_eq!(db.get_voting_raw("missing").await, Err(DbError::NotFound));
 }
 
 #[tokio::test]
-async fn delete_voting() {
+async fn ping() {
     let (_drop_db, db) = create_test_db();
-    let voting_id = "84ee17be18185a077db2";
-    let voting = Voting {
-        id: voting_id.to_string(),
+
+    db.ping().await.expect("fresh db should be reachable");
+
+    db.save_voting(Voting {
+        id: "84ee17be18185a077db2".to_string(),
         name: "voting1".to_string(),
         choices: vec!["choice1".to_string(), "choice2".to_string()],
         is_completed: false,
@@ -186,226 +295,475 @@ async fn delete_voting() {
         channel_id: "channel_id".to_string(),
         creator_message_id: "creator_message_id".to_string(),
         creator_dm_channel_id: "creator_dm_channel_id".to_string(),
-    };
+        ..Default::default()
+    })
+    .await
+    .expect("failed to save voting");
 
-    db.save_voting(voting.clone())
-        .await
-        .expect("failed to save voting");
+    db.ping().await.expect("db should be reachable");
+}
 
-    let v = db
-        .get_voting(voting_id)
+#[tokio::test]
+async fn self_check() {
+    let (_drop_db, db) = create_test_db();
+
+    db.self_check()
         .await
-        .expect("failed to get voting");
+        .expect("fresh db should pass the check");
 
-    assert_eq!(v.is_deleted, false);
+    db.save_voting(Voting {
+        id: "84ee17be18185a077db2".to_string(),
+        choices: vec!["choice1".to_string(), "choice2".to_string()],
+        ..Default::default()
+    })
+    .await
+    .expect("failed to save voting");
+    db.self_check().await.expect("db should pass the check");
+
+    // a table written with other key and value types can't be opened anymore
+    let guild_config_table: TableDefinition<u64, u64> = TableDefinition::new("guild_config");
+    let write_txn = db.db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(guild_config_table).unwrap();
+        table.insert(1, 2).unwrap();
+    }
+    write_txn.commit().unwrap();
 
-    db.delete_voting(voting_id)
-        .await
-        .expect("failed to delete voting");
+    let Err(DbError::Other(message)) = db.self_check().await else {
+        panic!("corrupted table should fail the check");
+    };
+    assert!(message.contains("guild_config"));
+}
 
-    let v = db
-        .get_voting(voting_id)
+#[tokio::test]
+async fn for_each_voting() {
+    let (_drop_db, db) = create_test_db();
+
+    for i in 0..500 {
+        db.save_voting(Voting {
+            id: format!("voting-{}", i),
+            name: "voting".to_string(),
+            choices: vec!["choice1".to_string(), "choice2".to_string()],
+            ..Default::default()
+        })
         .await
-        .expect("failed to get voting");
+        .expect("failed to save voting");
+    }
 
-    assert_eq!(v.is_deleted, true);
+    let count = Arc::new(AtomicUsize::new(0));
+    let counter = count.clone();
+    db.for_each_voting(move |_| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        ControlFlow::Continue(())
+    })
+    .await
+    .expect("failed to iterate votings");
+    assert_eq!(count.load(Ordering::SeqCst), 500);
+
+    let count = Arc::new(AtomicUsize::new(0));
+    let counter = count.clone();
+    db.for_each_voting(move |_| {
+        if counter.fetch_add(1, Ordering::SeqCst) + 1 == 10 {
+            return ControlFlow::Break(());
+        }
+        ControlFlow::Continue(())
+    })
+    .await
+    .expect("failed to iterate votings");
+    assert_eq!(count.load(Ordering::SeqCst), 10);
 }
 
 #[tokio::test]
-async fn test_update_vote() {
+async fn get_votings_page() {
     let (_drop_db, db) = create_test_db();
-    let voting_id = "voting-id";
-    let user_id = "user-id";
-    let ballot = vec![0, 0, 0];
 
-    db.save_voting_dialog(
-        voting_id.to_string(),
-        user_id.to_string(),
-        ballot.clone(),
-        "message_id".to_string(),
-        "channel-id".to_string(),
-        false,
-    )
-    .await
-    .expect("failed to save voting dialog");
+    let (votings, cursor) = db.get_votings_page(None, 25, false).await.unwrap();
+    assert!(votings.is_empty());
+    assert_eq!(cursor, None);
 
-    let mut dialog = db
-        .get_voting_dialog(voting_id, user_id)
+    for i in 0..60 {
+        db.save_voting(Voting {
+            id: format!("voting{:02}", i),
+            name: format!("voting {}", i),
+            choices: vec!["choice1".to_string(), "choice2".to_string()],
+            ..Default::default()
+        })
         .await
-        .expect("failed to get voting dialog");
+        .unwrap();
+    }
 
-    assert_eq!(&dialog.ballot, &ballot);
+    let mut seen = Vec::new();
+    let mut pages = 0;
+    let mut cursor = None;
+    loop {
+        let (votings, next) = db
+            .get_votings_page(cursor.as_deref(), 25, false)
+            .await
+            .unwrap();
+        pages += 1;
+        seen.extend(votings.into_iter().map(|v| v.id));
+        if next.is_none() {
+            break;
+        }
+        cursor = next;
+    }
 
-    db.vote_voting_dialog(voting_id, user_id, 1, 0)
-        .await
-        .expect("failed to update vote");
+    assert_eq!(pages, 3);
+    let expected: Vec<String> = (0..60).map(|i| format!("voting{:02}", i)).collect();
+    assert_eq!(seen, expected);
 
-    let updated_dialog = db
-        .get_voting_dialog(voting_id, user_id)
+    // deleted votings are skipped unless asked for
+    db.delete_voting("voting25", "creator").await.unwrap();
+    let (votings, cursor) = db
+        .get_votings_page(Some("voting24"), 25, false)
         .await
-        .expect("failed to get voting dialog");
-
-    dialog.ballot = vec![1, 0, 0];
+        .unwrap();
+    assert_eq!(votings.first().unwrap().id, "voting26");
+    assert_eq!(votings.len(), 25);
+    assert_eq!(cursor, Some("voting50".to_string()));
 
-    assert_eq!(dialog, updated_dialog);
+    let (votings, _) = db
+        .get_votings_page(Some("voting24"), 25, true)
+        .await
+        .unwrap();
+    assert_eq!(votings.first().unwrap().id, "voting25");
+    assert!(votings.first().unwrap().is_deleted);
 }
 
 #[tokio::test]
-async fn test_update_vote_index_out_of_range() {
+async fn get_guild_votings() {
     let (_drop_db, db) = create_test_db();
-    let voting_id = "voting-id";
-    let user_id = "user-id";
-    let ballot = vec![0, 0, 0];
 
-    db.save_voting_dialog(
-        voting_id.to_string(),
-        user_id.to_string(),
-        ballot.clone(),
-        "message_id".to_string(),
-        "channel-id".to_string(),
-        false,
-    )
-    .await
-    .expect("failed to save voting dialog");
+    let (votings, cursor) = db.get_guild_votings("guild1", None, 10).await.unwrap();
+    assert!(votings.is_empty());
+    assert_eq!(cursor, None);
 
-    let err = db
-        .vote_voting_dialog(voting_id, user_id, 1, 3)
+    for i in 0..25 {
+        let guild_id = if i % 2 == 0 { "guild1" } else { "guild2" };
+        db.save_voting(Voting {
+            id: format!("voting{:02}", i),
+            name: format!("voting {}", i),
+            choices: vec!["choice1".to_string(), "choice2".to_string()],
+            guild_id: guild_id.to_string(),
+            ..Default::default()
+        })
         .await
-        .expect_err("should not be able to update vote");
-
-    assert_eq!(err, DbError::IndexOutOfRange);
+        .unwrap();
+    }
+    // votings without a guild are not indexed
+    db.save_voting(Voting {
+        id: "voting99".to_string(),
+        name: "voting 99".to_string(),
+        choices: vec!["choice1".to_string(), "choice2".to_string()],
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let (votings, cursor) = db.get_guild_votings("guild1", None, 10).await.unwrap();
+    let ids: Vec<String> = votings.into_iter().map(|v| v.id).collect();
+    let expected: Vec<String> = (0..20)
+        .step_by(2)
+        .map(|i| format!("voting{:02}", i))
+        .collect();
+    assert_eq!(ids, expected);
+    assert_eq!(cursor, Some("voting18".to_string()));
+
+    // deleted votings are skipped
+    db.delete_voting("voting22", "creator").await.unwrap();
+    let (votings, cursor) = db
+        .get_guild_votings("guild1", cursor.as_deref(), 10)
+        .await
+        .unwrap();
+    let ids: Vec<String> = votings.into_iter().map(|v| v.id).collect();
+    assert_eq!(ids, vec!["voting20", "voting24"]);
+    assert_eq!(cursor, None);
+
+    let (votings, _) = db.get_guild_votings("guild2", None, 20).await.unwrap();
+    assert_eq!(votings.len(), 12);
+    assert!(votings.iter().all(|v| v.guild_id == "guild2"));
 }
 
 #[tokio::test]
-async fn test_update_vote_voting_dialog_not_found() {
+async fn votings_listings() {
     let (_drop_db, db) = create_test_db();
-    let voting_id = "voting-id";
-    let user_id = "user-id";
 
-    let err = db
-        .vote_voting_dialog(voting_id, user_id, 1, 0)
-        .await
-        .expect_err("should not be able to update vote");
+    assert_eq!(
+        db.get_votings_listing("uuid1").await.unwrap_err(),
+        DbError::NotFound
+    );
+    assert_eq!(db.delete_expired_votings_listings(100).await.unwrap(), 0);
 
-    assert_eq!(err, DbError::NotFound);
+    let listing = |cursors: Vec<&str>, expires_unix| VotingsListing {
+        guild_id: "guild1".to_string(),
+        cursors: cursors.into_iter().map(String::from).collect(),
+        expires_unix,
+    };
+    db.save_votings_listings(vec![
+        ("uuid1".to_string(), listing(vec![], 50)),
+        (
+            "uuid2".to_string(),
+            listing(vec!["voting09", "voting19"], 150),
+        ),
+    ])
+    .await
+    .unwrap();
+
+    let saved = db.get_votings_listing("uuid2").await.unwrap();
+    assert_eq!(saved, listing(vec!["voting09", "voting19"], 150));
+    assert_eq!(saved.page(), 3);
+
+    assert_eq!(db.delete_expired_votings_listings(100).await.unwrap(), 1);
+    assert_eq!(
+        db.get_votings_listing("uuid1").await.unwrap_err(),
+        DbError::NotFound
+    );
+    assert!(db.get_votings_listing("uuid2").await.is_ok());
+}
 
-    db.save_voting_dialog(
-        voting_id.to_string(),
-        user_id.to_string(),
-        vec![0, 0, 0],
-        "message-id".to_string(),
-        "channel-id".to_string(),
-        false,
-    )
+#[tokio::test]
+async fn set_voting_message_ids() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id = "84ee17be18185a077db2";
+
+    db.save_voting(Voting {
+        id: voting_id.to_string(),
+        name: "voting1".to_string(),
+        choices: vec!["choice1".to_string(), "choice2".to_string()],
+        creator_user_id: "creator".to_string(),
+        ..Default::default()
+    })
     .await
-    .expect("failed to save voting dialog");
+    .expect("failed to save voting");
 
-    let err = db
-        .vote_voting_dialog(voting_id, "", 1, 0)
+    let v = db
+        .set_voting_message_ids(
+            voting_id,
+            "message_id",
+            "channel_id",
+            "creator_message_id",
+            "creator_dm_channel_id",
+        )
         .await
-        .expect_err("should not be able to update vote");
+        .expect("failed to set voting message ids");
+    assert_eq!(v.message_id, "message_id");
+
+    let v = db
+        .get_voting(voting_id)
+        .await
+        .expect("failed to get voting");
+    assert_eq!(v.message_id, "message_id");
+    assert_eq!(v.channel_id, "channel_id");
+    assert_eq!(v.creator_message_id, "creator_message_id");
+    assert_eq!(v.creator_dm_channel_id, "creator_dm_channel_id");
+    assert_eq!(v.creator_user_id, "creator");
 
+    let err = db
+        .set_voting_message_ids("missing", "a", "b", "c", "d")
+        .await
+        .expect_err("voting should not exist");
     assert_eq!(err, DbError::NotFound);
 }
 
 #[tokio::test]
-async fn test_save_voting_dialog() {
+async fn set_voting_deadline() {
     let (_drop_db, db) = create_test_db();
-    let voting_id = "voting-id";
-    let user_id = "user-id";
-    let ballot = vec![0, 0, 0];
+    let voting_id = "84ee17be18185a077db2";
 
-    db.save_voting_dialog(
-        voting_id.to_string(),
-        user_id.to_string(),
-        ballot.clone(),
-        "message_id".to_string(),
-        "channel-id".to_string(),
-        true,
-    )
+    assert_eq!(
+        db.set_voting_deadline(voting_id, Some(1_700_000_000)).await,
+        Err(DbError::NotFound)
+    );
+
+    db.save_voting(Voting {
+        id: voting_id.to_string(),
+        ..Default::default()
+    })
     .await
-    .expect("failed to save voting dialog");
+    .expect("failed to save voting");
 
-    let dialog = db
-        .get_voting_dialog(voting_id, user_id)
+    let voting = db
+        .set_voting_deadline(voting_id, Some(1_700_000_000))
         .await
-        .expect("failed to get voting dialog");
+        .expect("failed to set deadline");
+    assert_eq!(voting.deadline_unix, Some(1_700_000_000));
+    assert_eq!(db.get_voting(voting_id).await.unwrap(), voting);
 
-    assert_eq!(dialog.voting_id, voting_id);
-    assert_eq!(dialog.user_id, user_id);
-    assert_eq!(dialog.ballot, ballot);
+    let voting = db
+        .set_voting_deadline(voting_id, None)
+        .await
+        .expect("failed to clear deadline");
+    assert_eq!(voting.deadline_unix, None);
+    assert_eq!(db.get_voting(voting_id).await.unwrap().deadline_unix, None);
+
+    // a closed voting keeps the deadline it was completed with
+    db.set_voting_deadline(voting_id, Some(1_700_000_000))
+        .await
+        .unwrap();
+    db.complete_open_voting(voting_id, "creator").await.unwrap();
+    assert_eq!(
+        db.set_voting_deadline(voting_id, Some(1_700_003_600)).await,
+        Err(DbError::NotFound)
+    );
+    assert_eq!(
+        db.get_voting(voting_id).await.unwrap().deadline_unix,
+        Some(1_700_000_000)
+    );
 }
 
 #[tokio::test]
-async fn test_get_voting_dialog_not_found() {
+async fn set_voting_dialogs_locked() {
     let (_drop_db, db) = create_test_db();
-    let voting_id = "voting-id";
-    let user_id = "user-id";
+    let voting_id = "84ee17be18185a077db2";
 
-    let err = db
-        .get_voting_dialog(voting_id, user_id)
+    assert_eq!(
+        db.set_voting_dialogs_locked(voting_id, true).await,
+        Err(DbError::NotFound)
+    );
+
+    db.save_voting(Voting {
+        id: voting_id.to_string(),
+        ..Default::default()
+    })
+    .await
+    .expect("failed to save voting");
+
+    let voting = db
+        .set_voting_dialogs_locked(voting_id, true)
         .await
-        .expect_err("voting dialog should not exist");
+        .expect("failed to lock dialogs");
+    assert!(voting.dialogs_locked);
+    assert_eq!(db.get_voting(voting_id).await.unwrap(), voting);
 
-    assert_eq!(err, DbError::NotFound);
+    let voting = db
+        .set_voting_dialogs_locked(voting_id, false)
+        .await
+        .expect("failed to unlock dialogs");
+    assert!(!voting.dialogs_locked);
+
+    db.complete_open_voting(voting_id, "creator").await.unwrap();
+    assert_eq!(
+        db.set_voting_dialogs_locked(voting_id, true).await,
+        Err(DbError::NotFound)
+    );
 }
 
 #[tokio::test]
-async fn test_delete_voting_dialog() {
+async fn set_voting_creator() {
     let (_drop_db, db) = create_test_db();
-    let voting_id = "voting-id";
-    let user_id = "user-id";
-    let ballot = vec![0, 0, 0];
+    let voting_id = "84ee17be18185a077db2";
 
-    db.delete_voting_dialog(voting_id, user_id)
+    db.save_voting(Voting {
+        id: voting_id.to_string(),
+        name: "voting1".to_string(),
+        choices: vec!["choice1".to_string(), "choice2".to_string()],
+        creator_user_id: "creator".to_string(),
+        ..Default::default()
+    })
+    .await
+    .expect("failed to save voting");
+
+    let v = db
+        .set_voting_creator(voting_id, "new-creator", "admin")
         .await
-        .expect("failed to delete voting dialog");
+        .expect("failed to set voting creator");
+    assert_eq!(v.creator_user_id, "new-creator");
 
-    db.save_voting_dialog(
-        voting_id.to_string(),
-        user_id.to_string(),
-        ballot.clone(),
-        "message_id".to_string(),
-        "channel-id".to_string(),
-        false,
-    )
-    .await
-    .expect("failed to save voting dialog");
+    // the transfer is audited with the user who made it
+    let events = db.get_audit(voting_id).await.unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, AuditEventKind::Transferred);
+    assert_eq!(events[0].user_id, "admin");
 
-    let dialog = db
-        .get_voting_dialog(voting_id, user_id)
+    let v = db
+        .get_voting(voting_id)
         .await
-        .expect("failed to get voting dialog");
+        .expect("failed to get voting");
+    assert_eq!(v.creator_user_id, "new-creator");
+
+    // the voting counts towards the limit of the new creator only
+    assert_eq!(
+        db.count_active_votings_by_creator("creator").await.unwrap(),
+        0
+    );
+    assert_eq!(
+        db.count_active_votings_by_creator("new-creator")
+            .await
+            .unwrap(),
+        1
+    );
 
-    assert_eq!(dialog.voting_id, voting_id);
-    assert_eq!(dialog.user_id, user_id);
-    assert_eq!(dialog.ballot, ballot);
+    let err = db
+        .set_voting_creator("missing", "new-creator", "admin")
+        .await
+        .expect_err("voting should not exist");
+    assert_eq!(err, DbError::NotFound);
+}
 
-    db.delete_voting_dialog(voting_id, user_id)
+#[tokio::test]
+async fn update_voting_checked() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id = "84ee17be18185a077db2";
+
+    db.save_voting(Voting {
+        id: voting_id.to_string(),
+        name: "voting1".to_string(),
+        choices: vec!["choice1".to_string(), "choice2".to_string()],
+        creator_user_id: "creator".to_string(),
+        ..Default::default()
+    })
+    .await
+    .expect("failed to save voting");
+
+    let read = db.get_voting(voting_id).await.unwrap();
+    assert_eq!(read.version, 0);
+
+    let v = db
+        .update_voting_checked(voting_id, read.version, |voting| {
+            voting.name = "renamed".to_string();
+        })
         .await
-        .expect("failed to delete voting dialog");
+        .expect("failed to update voting");
+    assert_eq!(v.name, "renamed");
+    assert_eq!(v.version, 1);
 
+    // a second writer still holding the first read is rejected instead of clobbering the rename
     let err = db
-        .get_voting_dialog(voting_id, user_id)
+        .update_voting_checked(voting_id, read.version, |voting| {
+            voting.choices.push("choice3".to_string());
+        })
         .await
-        .expect_err("voting dialog should not exist");
+        .expect_err("stale write should conflict");
+    assert_eq!(err, DbError::VersionConflict);
+
+    let v = db.get_voting(voting_id).await.unwrap();
+    assert_eq!(v.name, "renamed");
+    assert_eq!(v.choices.len(), 2);
 
+    // other writes bump the version too
+    db.complete_open_voting(voting_id, "creator").await.unwrap();
+    let err = db
+        .update_voting_checked(voting_id, 1, |_| {})
+        .await
+        .expect_err("write after completion should conflict");
+    assert_eq!(err, DbError::VersionConflict);
+
+    let err = db
+        .update_voting_checked("missing", 0, |_| {})
+        .await
+        .expect_err("voting should not exist");
     assert_eq!(err, DbError::NotFound);
 }
 
 #[tokio::test]
-async fn test_get_voting_dialogs() {
-    let (_drop_db, db) = create_test_db();
-    let voting_id1 = "84ee17be18185a077db2";
-    let voting_id2 = "84ee17be18185a077db3";
-    let ballot = vec![0, 0, 0];
+async fn compact() {
+    let (_drop_db, mut db) = create_test_db();
 
-    for _ in 0..100 {
-        let user_id = generate_random_hex_string(20);
+    for i in 0..500 {
         db.save_voting_dialog(
-            voting_id1.to_string(),
-            user_id.to_string(),
-            ballot.clone(),
+            format!("voting-{}", i),
+            "user".to_string(),
+            vec![0; 32],
             "message_id".to_string(),
             "channel_id".to_string(),
             false,
@@ -414,144 +772,2306 @@ async fn test_get_voting_dialogs() {
         .expect("failed to save voting dialog");
     }
 
-    for _ in 0..10 {
-        let user_id = generate_random_hex_string(20);
-        db.save_voting_dialog(
-            voting_id2.to_string(),
-            user_id.to_string(),
-            ballot.clone(),
-            "message_id".to_string(),
-            "channel_id".to_string(),
-            false,
-        )
-        .await
-        .expect("failed to save voting dialog");
+    for i in 0..500 {
+        db.delete_voting_dialog(&format!("voting-{}", i), "user")
+            .await
+            .expect("failed to delete voting dialog");
     }
 
-    let dialogs = db
-        .get_voting_dialogs(voting_id1)
+    let compacted = tokio::task::spawn_blocking(move || db.compact())
         .await
-        .expect("failed to get voting dialogs");
+        .unwrap()
+        .expect("failed to compact database");
+    assert!(compacted);
+}
 
-    assert_eq!(dialogs.len(), 100);
+#[tokio::test]
+async fn compact_shared_db() {
+    let (_drop_db, mut db) = create_test_db();
+    let _shared = db.db.clone();
 
-    let dialogs = db
-        .get_voting_dialogs(voting_id2)
+    db.compact()
+        .expect_err("shared database should not be compacted");
+}
+
+#[tokio::test]
+async fn voting_not_found() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id = "84ee17be18185a077db2";
+
+    let err = db
+        .get_voting(voting_id)
         .await
-        .expect("failed to get voting dialogs");
+        .expect_err("voting should not exist");
+
+    assert_eq!(err, DbError::NotFound);
+}
+
+#[tokio::test]
+async fn voting_already_exists() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id = "84ee17be18185a077db2";
+    let voting = Voting {
+        id: voting_id.to_string(),
+        name: "voting1".to_string(),
+        choices: vec!["choice1".to_string(), "choice2".to_string()],
+        is_completed: false,
+        is_deleted: false,
+        message_id: "message_id".to_string(),
+        channel_id: "channel_id".to_string(),
+        creator_message_id: "creator_message_id".to_string(),
+        creator_dm_channel_id: "creator_dm_channel_id".to_string(),
+        ..Default::default()
+    };
+
+    db.save_voting(voting.clone())
+        .await
+        .expect("failed to save voting");
+
+    let err = db
+        .save_voting(voting)
+        .await
+        .expect_err("voting should already exist");
+
+    assert_eq!(err, DbError::AlreadyExists);
+}
+
+#[tokio::test]
+async fn complete_voting() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id = "84ee17be18185a077db2";
+    let voting = Voting {
+        id: voting_id.to_string(),
+        name: "voting1".to_string(),
+        choices: vec!["choice1".to_string(), "choice2".to_string()],
+        is_completed: false,
+        is_deleted: false,
+        message_id: "message_id".to_string(),
+        channel_id: "channel_id".to_string(),
+        creator_message_id: "creator_message_id".to_string(),
+        creator_dm_channel_id: "creator_dm_channel_id".to_string(),
+        ..Default::default()
+    };
+
+    db.save_voting(voting.clone())
+        .await
+        .expect("failed to save voting");
+
+    let v = db
+        .get_voting(voting_id)
+        .await
+        .expect("failed to get voting");
+
+    assert!(!v.is_completed);
+
+    db.complete_open_voting(voting_id, "creator")
+        .await
+        .expect("failed to complete voting");
+
+    let v = db
+        .get_voting(voting_id)
+        .await
+        .expect("failed to get voting");
+
+    assert!(v.is_completed);
+}
+
+#[tokio::test]
+async fn complete_open_voting() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id = "84ee17be18185a077db2";
+
+    assert_eq!(
+        db.complete_open_voting(voting_id, "creator").await,
+        Err(DbError::NotFound)
+    );
+
+    let voting = Voting {
+        id: voting_id.to_string(),
+        name: "voting1".to_string(),
+        choices: vec!["choice1".to_string(), "choice2".to_string()],
+        ..Default::default()
+    };
+    db.save_voting(voting.clone()).await.unwrap();
+
+    // the first call completes the voting, the second one finds it completed
+    let completed = db.complete_open_voting(voting_id, "creator").await.unwrap();
+    assert!(completed.is_completed);
+    assert_eq!(completed.version, voting.version + 1);
+    assert_eq!(db.get_voting(voting_id).await.unwrap(), completed);
+
+    assert_eq!(
+        db.complete_open_voting(voting_id, "creator").await,
+        Err(DbError::AlreadyCompleted)
+    );
+    assert_eq!(db.get_voting(voting_id).await.unwrap(), completed);
+
+    // the completion is pending until the results are published
+    assert_eq!(
+        db.get_pending_completions().await.unwrap(),
+        vec![voting_id.to_string()]
+    );
+
+    let deleted_id = "84ee17be18185a077db3";
+    db.save_voting(Voting {
+        id: deleted_id.to_string(),
+        ..voting
+    })
+    .await
+    .unwrap();
+    db.delete_voting(deleted_id, "creator").await.unwrap();
+    assert_eq!(
+        db.complete_open_voting(deleted_id, "creator").await,
+        Err(DbError::NotFound)
+    );
+}
+
+#[tokio::test]
+async fn complete_voting_errors() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id = "84ee17be18185a077db2";
+
+    let err = db
+        .complete_open_voting(voting_id, "creator")
+        .await
+        .expect_err("voting should not exist");
+
+    assert_eq!(err, DbError::NotFound);
+
+    let voting = Voting {
+        id: voting_id.to_string(),
+        name: "voting1".to_string(),
+        choices: vec!["choice1".to_string(), "choice2".to_string()],
+        is_completed: false,
+        is_deleted: true,
+        message_id: "message_id".to_string(),
+        channel_id: "channel_id".to_string(),
+        creator_message_id: "creator_message_id".to_string(),
+        creator_dm_channel_id: "creator_dm_channel_id".to_string(),
+        ..Default::default()
+    };
+
+    db.save_voting(voting.clone())
+        .await
+        .expect("failed to save voting");
+
+    db.complete_open_voting(voting_id, "creator")
+        .await
+        .expect_err("voting should be deleted");
+}
+
+#[tokio::test]
+async fn test_pending_completions() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id = "84ee17be18185a077db2";
+    let voting = Voting {
+        id: voting_id.to_string(),
+        name: "voting1".to_string(),
+        choices: vec!["choice1".to_string(), "choice2".to_string()],
+        is_completed: false,
+        is_deleted: false,
+        message_id: "message_id".to_string(),
+        channel_id: "channel_id".to_string(),
+        creator_message_id: "creator_message_id".to_string(),
+        creator_dm_channel_id: "creator_dm_channel_id".to_string(),
+        ..Default::default()
+    };
+
+    db.save_voting(voting).await.expect("failed to save voting");
+
+    let pending = db
+        .get_pending_completions()
+        .await
+        .expect("failed to get pending completions");
+    assert!(pending.is_empty());
+
+    db.complete_open_voting(voting_id, "creator")
+        .await
+        .expect("failed to complete voting");
+    db.save_pending_completion("84ee17be18185a077db3")
+        .await
+        .expect("failed to save pending completion");
+    db.save_pending_completion("84ee17be18185a077db4")
+        .await
+        .expect("failed to save pending completion");
+    db.delete_pending_completion("84ee17be18185a077db4")
+        .await
+        .expect("failed to delete pending completion");
+
+    let pending = db
+        .get_pending_completions()
+        .await
+        .expect("failed to get pending completions");
+    assert_eq!(
+        pending,
+        vec![voting_id.to_string(), "84ee17be18185a077db3".to_string()]
+    );
+
+    // completions which just started are left to the caller publishing them
+    let started_before = util::unix_timestamp() - 60;
+    assert!(db
+        .get_pending_completions_before(started_before)
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        db.get_pending_completions_before(u64::MAX).await.unwrap(),
+        pending
+    );
+
+    // the completions stay pending until their results are published
+    db.delete_pending_completion(voting_id)
+        .await
+        .expect("failed to delete pending completion");
+    let pending = db
+        .get_pending_completions()
+        .await
+        .expect("failed to get pending completions");
+    assert_eq!(pending, vec!["84ee17be18185a077db3".to_string()]);
+}
+
+#[tokio::test]
+async fn test_pending_votes() {
+    let (_drop_db, db) = create_test_db();
+
+    let pending = db
+        .get_pending_votes()
+        .await
+        .expect("failed to get pending votes");
+    assert!(pending.is_empty());
+
+    let vote = PendingVote {
+        voting_id: "voting-id".to_string(),
+        user_id: "user-id".to_string(),
+        ballot: HashMap::from([("choice1".to_string(), 1), ("choice2".to_string(), 2)]),
+        weight: 1,
+    };
+    db.save_pending_vote(vote.clone())
+        .await
+        .expect("failed to save pending vote");
+
+    // saving again replaces the ballot of the voter
+    let vote = PendingVote {
+        ballot: HashMap::from([("choice1".to_string(), 2), ("choice2".to_string(), 1)]),
+        ..vote
+    };
+    db.save_pending_vote(vote.clone())
+        .await
+        .expect("failed to save pending vote");
+
+    let pending = db
+        .get_pending_votes()
+        .await
+        .expect("failed to get pending votes");
+    assert_eq!(pending, vec![vote]);
+
+    db.delete_pending_vote("voting-id", "user-id")
+        .await
+        .expect("failed to delete pending vote");
+
+    let pending = db
+        .get_pending_votes()
+        .await
+        .expect("failed to get pending votes");
+    assert!(pending.is_empty());
+}
+
+#[tokio::test]
+async fn delete_voting() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id = "84ee17be18185a077db2";
+    let voting = Voting {
+        id: voting_id.to_string(),
+        name: "voting1".to_string(),
+        choices: vec!["choice1".to_string(), "choice2".to_string()],
+        is_completed: false,
+        is_deleted: false,
+        message_id: "message_id".to_string(),
+        channel_id: "channel_id".to_string(),
+        creator_message_id: "creator_message_id".to_string(),
+        creator_dm_channel_id: "creator_dm_channel_id".to_string(),
+        ..Default::default()
+    };
+
+    db.save_voting(voting.clone())
+        .await
+        .expect("failed to save voting");
+
+    let v = db
+        .get_voting(voting_id)
+        .await
+        .expect("failed to get voting");
+
+    assert!(!v.is_deleted);
+
+    db.delete_voting(voting_id, "creator")
+        .await
+        .expect("failed to delete voting");
+
+    let v = db
+        .get_voting(voting_id)
+        .await
+        .expect("failed to get voting");
+
+    assert!(v.is_deleted);
+}
+
+#[tokio::test]
+async fn audit_log() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id = "84ee17be18185a077db2";
+
+    assert!(db.get_audit(voting_id).await.unwrap().is_empty());
+
+    db.save_voting(Voting {
+        id: voting_id.to_string(),
+        creator_user_id: "creator".to_string(),
+        ..Default::default()
+    })
+    .await
+    .expect("failed to save voting");
+    db.save_voting(Voting {
+        id: "84ee17be18185a077db3".to_string(),
+        creator_user_id: "other".to_string(),
+        ..Default::default()
+    })
+    .await
+    .expect("failed to save voting");
+
+    // events of the same second keep the order they were appended in
+    for user_id in ["admin-1", "admin-2"] {
+        db.append_audit(
+            voting_id,
+            AuditEvent {
+                kind: AuditEventKind::Completed,
+                user_id: user_id.to_string(),
+                timestamp_unix: 1_700_000_000,
+            },
+        )
+        .await
+        .expect("failed to append audit");
+    }
+
+    db.complete_open_voting(voting_id, SYSTEM_USER_ID)
+        .await
+        .unwrap();
+    db.delete_voting(voting_id, "admin-3").await.unwrap();
+
+    let events: Vec<(AuditEventKind, String)> = db
+        .get_audit(voting_id)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|event| (event.kind, event.user_id))
+        .collect();
+    assert_eq!(
+        events,
+        vec![
+            (AuditEventKind::Completed, "admin-1".to_string()),
+            (AuditEventKind::Completed, "admin-2".to_string()),
+            (AuditEventKind::Created, "creator".to_string()),
+            (AuditEventKind::Completed, SYSTEM_USER_ID.to_string()),
+            (AuditEventKind::Deleted, "admin-3".to_string()),
+        ]
+    );
+
+    let other_events = db.get_audit("84ee17be18185a077db3").await.unwrap();
+    assert_eq!(other_events.len(), 1);
+    assert_eq!(other_events[0].kind, AuditEventKind::Created);
+    assert_eq!(other_events[0].user_id, "other");
+}
+
+#[tokio::test]
+async fn count_active_votings_by_creator() {
+    let (_drop_db, db) = create_test_db();
+
+    let count = db
+        .count_active_votings_by_creator("creator1")
+        .await
+        .expect("failed to count votings");
+    assert_eq!(count, 0);
+
+    for (id, creator) in [
+        ("84ee17be18185a077db2", "creator1"),
+        ("84ee17be18185a077db3", "creator1"),
+        ("84ee17be18185a077db4", "creator1"),
+        ("84ee17be18185a077db5", "creator10"),
+        ("84ee17be18185a077db6", ""),
+    ] {
+        db.save_voting(Voting {
+            id: id.to_string(),
+            name: "voting".to_string(),
+            choices: vec!["choice1".to_string(), "choice2".to_string()],
+            creator_user_id: creator.to_string(),
+            ..Default::default()
+        })
+        .await
+        .expect("failed to save voting");
+    }
+
+    let count = db
+        .count_active_votings_by_creator("creator1")
+        .await
+        .expect("failed to count votings");
+    assert_eq!(count, 3);
+
+    db.complete_open_voting("84ee17be18185a077db2", "creator")
+        .await
+        .expect("failed to complete voting");
+    db.delete_voting("84ee17be18185a077db3", "creator")
+        .await
+        .expect("failed to delete voting");
+
+    let count = db
+        .count_active_votings_by_creator("creator1")
+        .await
+        .expect("failed to count votings");
+    assert_eq!(count, 1);
+
+    let count = db
+        .count_active_votings_by_creator("creator10")
+        .await
+        .expect("failed to count votings");
+    assert_eq!(count, 1);
+}
+
+#[tokio::test]
+async fn test_update_vote() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id = "voting-id";
+    let user_id = "user-id";
+    let ballot = vec![0, 0, 0];
+
+    db.save_voting_dialog(
+        voting_id.to_string(),
+        user_id.to_string(),
+        ballot.clone(),
+        "message_id".to_string(),
+        "channel-id".to_string(),
+        false,
+    )
+    .await
+    .expect("failed to save voting dialog");
+
+    let mut dialog = db
+        .get_voting_dialog(voting_id, user_id)
+        .await
+        .expect("failed to get voting dialog");
+
+    assert_eq!(&dialog.ballot, &ballot);
+
+    db.vote_voting_dialog(voting_id, user_id, 1, 0)
+        .await
+        .expect("failed to update vote");
+
+    let updated_dialog = db
+        .get_voting_dialog(voting_id, user_id)
+        .await
+        .expect("failed to get voting dialog");
+
+    dialog.ballot = vec![1, 0, 0];
+
+    assert_eq!(dialog, updated_dialog);
+}
+
+#[tokio::test]
+async fn test_update_vote_index_out_of_range() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id = "voting-id";
+    let user_id = "user-id";
+    let ballot = vec![0, 0, 0];
+
+    db.save_voting_dialog(
+        voting_id.to_string(),
+        user_id.to_string(),
+        ballot.clone(),
+        "message_id".to_string(),
+        "channel-id".to_string(),
+        false,
+    )
+    .await
+    .expect("failed to save voting dialog");
+
+    let err = db
+        .vote_voting_dialog(voting_id, user_id, 1, 3)
+        .await
+        .expect_err("should not be able to update vote");
+
+    assert_eq!(err, DbError::IndexOutOfRange);
+}
+
+#[tokio::test]
+async fn exists_checks() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id = "84ee17be18185a077db2";
+
+    // the tables do not exist yet
+    assert_eq!(db.voting_exists(voting_id).await, Ok(false));
+    assert_eq!(db.dialog_exists(voting_id, "user").await, Ok(false));
+
+    db.save_voting(Voting {
+        id: voting_id.to_string(),
+        ..Default::default()
+    })
+    .await
+    .expect("failed to save voting");
+    db.save_voting_dialog(
+        voting_id.to_string(),
+        "user".to_string(),
+        vec![0, 0],
+        "message-id".to_string(),
+        "channel-id".to_string(),
+        false,
+    )
+    .await
+    .expect("failed to save voting dialog");
+
+    assert_eq!(db.voting_exists(voting_id).await, Ok(true));
+    assert_eq!(db.voting_exists("84ee17be18185a077db3").await, Ok(false));
+    assert_eq!(db.dialog_exists(voting_id, "user").await, Ok(true));
+    assert_eq!(db.dialog_exists(voting_id, "other").await, Ok(false));
+    assert_eq!(
+        db.dialog_exists("84ee17be18185a077db3", "user").await,
+        Ok(false)
+    );
+
+    // deleted votings are kept, closed dialogs are not
+    db.delete_voting(voting_id, "creator").await.unwrap();
+    db.delete_voting_dialog(voting_id, "user").await.unwrap();
+    assert_eq!(db.voting_exists(voting_id).await, Ok(true));
+    assert_eq!(db.dialog_exists(voting_id, "user").await, Ok(false));
+}
+
+#[tokio::test]
+async fn test_batch_get_votings() {
+    let (_drop_db, db) = create_test_db();
+
+    let ids = vec![
+        "84ee17be18185a077db2".to_string(),
+        "84ee17be18185a077db3".to_string(),
+        "84ee17be18185a077db4".to_string(),
+    ];
+
+    // the table does not exist yet
+    let res = db.batch_get_votings(&ids).await.unwrap();
+    assert_eq!(res.len(), 3);
+    assert!(res.iter().all(|(_, v)| *v == Err(DbError::NotFound)));
+
+    for id in [&ids[0], &ids[2]] {
+        db.save_voting(Voting {
+            id: id.clone(),
+            name: format!("voting {}", id),
+            ..Default::default()
+        })
+        .await
+        .expect("failed to save voting");
+    }
+
+    let res = db.batch_get_votings(&ids).await.unwrap();
+    assert_eq!(
+        res.iter().map(|(id, _)| id.clone()).collect::<Vec<_>>(),
+        ids
+    );
+    assert_eq!(
+        res[0].1.as_ref().map(|v| v.name.clone()),
+        Ok(format!("voting {}", ids[0]))
+    );
+    assert_eq!(res[1].1, Err(DbError::NotFound));
+    assert_eq!(
+        res[2].1.as_ref().map(|v| v.name.clone()),
+        Ok(format!("voting {}", ids[2]))
+    );
+
+    assert!(db.batch_get_votings(&[]).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_guild_config() {
+    let (_drop_db, db) = create_test_db();
+    let guild_id = "1187313045127581796";
+
+    // the table does not exist yet
+    assert_eq!(
+        db.get_guild_config(guild_id).await,
+        Ok(GuildConfig::default())
+    );
+
+    let config = GuildConfig {
+        anonymous_by_default: true,
+        max_choices: Some(5),
+        max_active_votings_per_creator: None,
+    };
+    db.set_guild_config(guild_id, config.clone())
+        .await
+        .expect("failed to save guild config");
+
+    assert_eq!(db.get_guild_config(guild_id).await, Ok(config.clone()));
+    assert_eq!(
+        db.get_guild_config("1187313045127581797").await,
+        Ok(GuildConfig::default())
+    );
+
+    let config = GuildConfig {
+        max_active_votings_per_creator: Some(3),
+        ..config
+    };
+    db.set_guild_config(guild_id, config.clone())
+        .await
+        .expect("failed to update guild config");
+    assert_eq!(db.get_guild_config(guild_id).await, Ok(config));
+}
+
+#[tokio::test]
+async fn test_update_vote_invalid_rank() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id = "voting-id";
+    let user_id = "user-id";
+
+    db.save_voting_dialog(
+        voting_id.to_string(),
+        user_id.to_string(),
+        vec![0, 0, 0],
+        "message_id".to_string(),
+        "channel-id".to_string(),
+        false,
+    )
+    .await
+    .expect("failed to save voting dialog");
+
+    for rank in [0, -1, 4, i32::MAX] {
+        let err = db
+            .vote_voting_dialog(voting_id, user_id, rank, 0)
+            .await
+            .expect_err("should not be able to store rank");
+
+        assert_eq!(err, DbError::InvalidRank);
+    }
+
+    db.vote_voting_dialog(voting_id, user_id, 3, 1)
+        .await
+        .expect("failed to update vote");
+
+    let dialog = db
+        .get_voting_dialog(voting_id, user_id)
+        .await
+        .expect("failed to get voting dialog");
+
+    assert_eq!(dialog.ballot, vec![0, 3, 0]);
+}
+
+#[tokio::test]
+async fn test_set_dialog_ballot() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id = "voting-id";
+    let user_id = "user-id";
+
+    let err = db
+        .set_dialog_ballot(voting_id, user_id, vec![1, 2, 3])
+        .await
+        .expect_err("dialog should not exist");
+    assert_eq!(err, DbError::NotFound);
+
+    db.save_voting_dialog(
+        voting_id.to_string(),
+        user_id.to_string(),
+        vec![0, 0, 0],
+        "message_id".to_string(),
+        "channel-id".to_string(),
+        false,
+    )
+    .await
+    .expect("failed to save voting dialog");
+
+    db.set_dialog_ballot(voting_id, user_id, vec![2, 0, 1])
+        .await
+        .expect("failed to set ballot");
+
+    let dialog = db
+        .get_voting_dialog(voting_id, user_id)
+        .await
+        .expect("failed to get voting dialog");
+    assert_eq!(dialog.ballot, vec![2, 0, 1]);
+    assert_eq!(dialog.message_id, "message_id");
+}
+
+#[tokio::test]
+async fn test_update_dialog_message() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id = "voting-id";
+    let user_id = "user-id";
+
+    let err = db
+        .update_dialog_message(voting_id, user_id, "message-id", "channel-id")
+        .await
+        .expect_err("dialog should not exist");
+    assert_eq!(err, DbError::NotFound);
+
+    db.save_voting_dialog(
+        voting_id.to_string(),
+        user_id.to_string(),
+        vec![0, 0, 0],
+        "message-id".to_string(),
+        "channel-id".to_string(),
+        false,
+    )
+    .await
+    .expect("failed to save voting dialog");
+    db.set_dialog_ballot(voting_id, user_id, vec![2, 0, 1])
+        .await
+        .expect("failed to set ballot");
+
+    db.update_dialog_message(voting_id, user_id, "new-message-id", "new-channel-id")
+        .await
+        .expect("failed to update dialog message");
+
+    let dialog = db
+        .get_voting_dialog(voting_id, user_id)
+        .await
+        .expect("failed to get voting dialog");
+    assert_eq!(dialog.message_id, "new-message-id");
+    assert_eq!(dialog.channel_id, "new-channel-id");
+    assert_eq!(dialog.ballot, vec![2, 0, 1]);
+    assert!(!dialog.submitted);
+
+    // the message index follows the re-posted message
+    let dialog = db
+        .get_voting_dialog_by_message_id("new-message-id")
+        .await
+        .expect("new message should be indexed");
+    assert_eq!(dialog.user_id, user_id);
+    let err = db
+        .get_voting_dialog_by_message_id("message-id")
+        .await
+        .expect_err("old message should not be indexed");
+    assert_eq!(err, DbError::NotFound);
+}
+
+#[tokio::test]
+async fn test_set_dialog_comment() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id = "voting-id";
+    let user_id = "user-id";
+
+    let err = db
+        .set_dialog_comment(voting_id, user_id, Some("comment".to_string()))
+        .await
+        .expect_err("dialog should not exist");
+    assert_eq!(err, DbError::NotFound);
+
+    db.save_voting_dialog(
+        voting_id.to_string(),
+        user_id.to_string(),
+        vec![1, 2],
+        "message-id".to_string(),
+        "channel-id".to_string(),
+        false,
+    )
+    .await
+    .expect("failed to save voting dialog");
+
+    let dialog = db.get_voting_dialog(voting_id, user_id).await.unwrap();
+    assert_eq!(dialog.comment, None);
+
+    db.set_dialog_comment(voting_id, user_id, Some("Kant settled it.".to_string()))
+        .await
+        .expect("failed to set comment");
+    let dialog = db.get_voting_dialog(voting_id, user_id).await.unwrap();
+    assert_eq!(dialog.comment.as_deref(), Some("Kant settled it."));
+    assert_eq!(dialog.ballot, vec![1, 2]);
+
+    db.set_dialog_comment(voting_id, user_id, None)
+        .await
+        .expect("failed to remove comment");
+    let dialog = db.get_voting_dialog(voting_id, user_id).await.unwrap();
+    assert_eq!(dialog.comment, None);
+
+    // dialogs saved before comments were added have none
+    let mut json = serde_json::to_value(&dialog).unwrap();
+    json.as_object_mut().unwrap().remove("comment");
+    let old = VoteDialog::try_from(json.to_string().as_str()).unwrap();
+    assert_eq!(old.comment, None);
+}
+
+#[tokio::test]
+async fn test_get_dialog_render_state() {
+    let (_drop_db, db) = create_test_db();
+    let voting = Voting {
+        id: "voting-id".to_string(),
+        name: "voting".to_string(),
+        choices: vec![
+            "choice1".to_string(),
+            "choice2".to_string(),
+            "choice3".to_string(),
+        ],
+        ..Default::default()
+    };
+    let user_id = "user-id";
+
+    let err = db
+        .get_dialog_render_state(&voting.id, user_id)
+        .await
+        .expect_err("voting should not exist");
+    assert_eq!(err, DbError::NotFound);
+
+    db.save_voting(voting.clone()).await.unwrap();
+
+    let err = db
+        .get_dialog_render_state(&voting.id, user_id)
+        .await
+        .expect_err("dialog should not exist");
+    assert_eq!(err, DbError::NotFound);
+
+    db.save_voting_dialog(
+        voting.id.clone(),
+        user_id.to_string(),
+        vec![0, 0, 0],
+        "message-id".to_string(),
+        "channel-id".to_string(),
+        false,
+    )
+    .await
+    .expect("failed to save voting dialog");
+    db.set_dialog_ballot(&voting.id, user_id, vec![2, 0, 1])
+        .await
+        .expect("failed to set ballot");
+
+    let (state_voting, dialog) = db
+        .get_dialog_render_state(&voting.id, user_id)
+        .await
+        .expect("failed to get render state");
+    assert_eq!(state_voting, db.get_voting(&voting.id).await.unwrap());
+    assert_eq!(
+        dialog,
+        db.get_voting_dialog(&voting.id, user_id).await.unwrap()
+    );
+    assert_eq!(dialog.voting_id, state_voting.id);
+    assert_eq!(dialog.ballot.len(), state_voting.choices.len());
+    assert_eq!(dialog.ballot, vec![2, 0, 1]);
+}
+
+#[tokio::test]
+async fn test_set_dialog_ballot_length_mismatch() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id = "voting-id";
+    let user_id = "user-id";
+
+    db.save_voting_dialog(
+        voting_id.to_string(),
+        user_id.to_string(),
+        vec![0, 0, 0],
+        "message_id".to_string(),
+        "channel-id".to_string(),
+        false,
+    )
+    .await
+    .expect("failed to save voting dialog");
+
+    for ballot in [vec![1, 2], vec![1, 2, 3, 4], vec![]] {
+        let err = db
+            .set_dialog_ballot(voting_id, user_id, ballot)
+            .await
+            .expect_err("should not be able to store ballot");
+        assert_eq!(err, DbError::IndexOutOfRange);
+    }
+
+    let err = db
+        .set_dialog_ballot(voting_id, user_id, vec![1, 4, 0])
+        .await
+        .expect_err("should not be able to store rank");
+    assert_eq!(err, DbError::InvalidRank);
+
+    let dialog = db
+        .get_voting_dialog(voting_id, user_id)
+        .await
+        .expect("failed to get voting dialog");
+    assert_eq!(dialog.ballot, vec![0, 0, 0]);
+}
+
+#[tokio::test]
+async fn test_update_vote_voting_dialog_not_found() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id = "voting-id";
+    let user_id = "user-id";
+
+    let err = db
+        .vote_voting_dialog(voting_id, user_id, 1, 0)
+        .await
+        .expect_err("should not be able to update vote");
+
+    assert_eq!(err, DbError::NotFound);
+
+    db.save_voting_dialog(
+        voting_id.to_string(),
+        user_id.to_string(),
+        vec![0, 0, 0],
+        "message-id".to_string(),
+        "channel-id".to_string(),
+        false,
+    )
+    .await
+    .expect("failed to save voting dialog");
+
+    let err = db
+        .vote_voting_dialog(voting_id, "", 1, 0)
+        .await
+        .expect_err("should not be able to update vote");
+
+    assert_eq!(err, DbError::NotFound);
+}
+
+#[tokio::test]
+async fn test_save_voting_dialog() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id = "voting-id";
+    let user_id = "user-id";
+    let ballot = vec![0, 0, 0];
+
+    db.save_voting_dialog(
+        voting_id.to_string(),
+        user_id.to_string(),
+        ballot.clone(),
+        "message_id".to_string(),
+        "channel-id".to_string(),
+        true,
+    )
+    .await
+    .expect("failed to save voting dialog");
+
+    let dialog = db
+        .get_voting_dialog(voting_id, user_id)
+        .await
+        .expect("failed to get voting dialog");
+
+    assert_eq!(dialog.voting_id, voting_id);
+    assert_eq!(dialog.user_id, user_id);
+    assert_eq!(dialog.ballot, ballot);
+}
+
+#[tokio::test]
+async fn test_get_voting_dialog_not_found() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id = "voting-id";
+    let user_id = "user-id";
+
+    let err = db
+        .get_voting_dialog(voting_id, user_id)
+        .await
+        .expect_err("voting dialog should not exist");
+
+    assert_eq!(err, DbError::NotFound);
+}
+
+#[tokio::test]
+async fn test_delete_voting_dialog() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id = "voting-id";
+    let user_id = "user-id";
+    let ballot = vec![0, 0, 0];
+
+    db.delete_voting_dialog(voting_id, user_id)
+        .await
+        .expect("failed to delete voting dialog");
+
+    db.save_voting_dialog(
+        voting_id.to_string(),
+        user_id.to_string(),
+        ballot.clone(),
+        "message_id".to_string(),
+        "channel-id".to_string(),
+        false,
+    )
+    .await
+    .expect("failed to save voting dialog");
+
+    let dialog = db
+        .get_voting_dialog(voting_id, user_id)
+        .await
+        .expect("failed to get voting dialog");
+
+    assert_eq!(dialog.voting_id, voting_id);
+    assert_eq!(dialog.user_id, user_id);
+    assert_eq!(dialog.ballot, ballot);
+
+    db.delete_voting_dialog(voting_id, user_id)
+        .await
+        .expect("failed to delete voting dialog");
+
+    let err = db
+        .get_voting_dialog(voting_id, user_id)
+        .await
+        .expect_err("voting dialog should not exist");
+
+    assert_eq!(err, DbError::NotFound);
+}
+
+#[tokio::test]
+async fn test_claim_dialog_slot_commit() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id = "voting-id";
+    let user_id = "user-id";
+
+    let slot = db
+        .claim_dialog_slot(voting_id, user_id, false)
+        .await
+        .expect("failed to claim dialog slot");
+
+    // the placeholder blocks a second claim while the slot is held
+    let err = db
+        .claim_dialog_slot(voting_id, user_id, false)
+        .await
+        .err()
+        .expect("slot should be claimed");
+    assert_eq!(err, DbError::AlreadyExists);
+
+    slot.commit(
+        vec![0, 0],
+        None,
+        "message-id".to_string(),
+        "channel-id".to_string(),
+    )
+    .await
+    .expect("failed to commit dialog slot");
+
+    let dialog = db
+        .get_voting_dialog(voting_id, user_id)
+        .await
+        .expect("committed dialog should exist");
+    assert_eq!(dialog.ballot, vec![0, 0]);
+    assert_eq!(dialog.message_id, "message-id");
+    assert_eq!(dialog.channel_id, "channel-id");
+    assert!(!dialog.placeholder);
+
+    let dialog = db
+        .get_voting_dialog_by_message_id("message-id")
+        .await
+        .expect("committed dialog should be indexed by message id");
+    assert_eq!(dialog.user_id, user_id);
+}
+
+#[tokio::test]
+async fn test_claim_dialog_slot_rollback() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id = "voting-id";
+    let user_id = "user-id";
+
+    let slot = db
+        .claim_dialog_slot(voting_id, user_id, false)
+        .await
+        .expect("failed to claim dialog slot");
+
+    let dialog = db
+        .get_voting_dialog(voting_id, user_id)
+        .await
+        .expect("placeholder dialog should exist");
+    assert!(dialog.placeholder);
+
+    slot.release().await.expect("failed to release dialog slot");
+
+    let err = db
+        .get_voting_dialog(voting_id, user_id)
+        .await
+        .expect_err("placeholder should be removed");
+    assert_eq!(err, DbError::NotFound);
+    assert!(db.get_dialogs_for_user(user_id).await.unwrap().is_empty());
+
+    // the slot can be claimed again after a rollback
+    let slot = db
+        .claim_dialog_slot(voting_id, user_id, false)
+        .await
+        .expect("failed to claim dialog slot again");
+
+    // a slot dropped without being released is rolled back in the background
+    drop(slot);
+    let mut removed = false;
+    for _ in 0..50 {
+        if matches!(
+            db.get_voting_dialog(voting_id, user_id).await,
+            Err(DbError::NotFound)
+        ) {
+            removed = true;
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    assert!(removed, "dropped placeholder should be removed");
+
+    // a late rollback leaves a dialog which replaced the placeholder alone
+    let slot = db
+        .claim_dialog_slot(voting_id, user_id, false)
+        .await
+        .expect("failed to claim dialog slot again");
+    db.save_voting_dialog(
+        voting_id.to_string(),
+        user_id.to_string(),
+        vec![0, 0],
+        "message-id".to_string(),
+        "channel-id".to_string(),
+        true,
+    )
+    .await
+    .expect("failed to save voting dialog");
+
+    slot.release().await.expect("failed to release dialog slot");
+
+    let dialog = db
+        .get_voting_dialog(voting_id, user_id)
+        .await
+        .expect("newer dialog should be kept");
+    assert_eq!(dialog.message_id, "message-id");
+    assert!(!dialog.placeholder);
+}
+
+#[tokio::test]
+async fn test_open_dialog_with_custom_ids() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id = "voting-id";
+    let user_id = "user-id";
+
+    let custom_ids = |uuids: [&str; 2]| {
+        uuids
+            .iter()
+            .enumerate()
+            .map(|(i, uuid)| {
+                (
+                    uuid.to_string(),
+                    CustomID {
+                        action: Action::VoteSelect,
+                        voting_id: voting_id.to_string(),
+                        user_id: Some(user_id.to_string()),
+                        page: Some(1),
+                        index: Some(i),
+                    },
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let slot = db
+        .open_dialog_with_custom_ids(voting_id, user_id, custom_ids(["uuid-1", "uuid-2"]))
+        .await
+        .expect("failed to open dialog");
+
+    db.get_voting_dialog(voting_id, user_id)
+        .await
+        .expect("placeholder dialog should exist");
+    assert_eq!(db.get_custom_ids(voting_id).await.unwrap().len(), 2);
+
+    // a taken slot saves none of the custom ids
+    let err = db
+        .open_dialog_with_custom_ids(voting_id, user_id, custom_ids(["uuid-3", "uuid-4"]))
+        .await
+        .err()
+        .expect("slot should be claimed");
+    assert_eq!(err, DbError::AlreadyExists);
+    assert_eq!(db.get_custom_id("uuid-3").await, Err(DbError::NotFound));
+    assert_eq!(db.get_custom_ids(voting_id).await.unwrap().len(), 2);
+
+    // a failure before committing, e.g. the dm could not be sent, rolls back both
+    slot.release().await.expect("failed to release dialog slot");
+
+    let err = db
+        .get_voting_dialog(voting_id, user_id)
+        .await
+        .expect_err("placeholder should be removed");
+    assert_eq!(err, DbError::NotFound);
+    assert_eq!(db.get_custom_id("uuid-1").await, Err(DbError::NotFound));
+    assert!(db.get_custom_ids(voting_id).await.unwrap().is_empty());
+
+    // committed dialogs keep their custom ids
+    let slot = db
+        .open_dialog_with_custom_ids(voting_id, user_id, custom_ids(["uuid-1", "uuid-2"]))
+        .await
+        .expect("failed to open dialog again");
+    slot.commit(
+        vec![0, 0],
+        None,
+        "message-id".to_string(),
+        "channel-id".to_string(),
+    )
+    .await
+    .expect("failed to commit dialog slot");
+
+    db.get_voting_dialog(voting_id, user_id)
+        .await
+        .expect("committed dialog should exist");
+    assert_eq!(db.get_custom_ids(voting_id).await.unwrap().len(), 2);
+
+    // a submitted dialog is replaced by a new placeholder
+    db.set_voting_dialog_submitted(voting_id, user_id, true, 1)
+        .await
+        .expect("failed to submit dialog");
+    let submitted_ids = [
+        ("undo-uuid", Action::UndoVote),
+        ("comment-uuid", Action::AddComment),
+    ]
+    .into_iter()
+    .map(|(uuid, action)| {
+        (
+            uuid.to_string(),
+            CustomID {
+                action,
+                voting_id: voting_id.to_string(),
+                user_id: Some(user_id.to_string()),
+                page: None,
+                index: None,
+            },
+        )
+    })
+    .collect();
+    db.bulk_save_custom_ids(submitted_ids)
+        .await
+        .expect("failed to save submitted dialog custom ids");
+
+    db.open_dialog_with_custom_ids(voting_id, user_id, custom_ids(["uuid-3", "uuid-4"]))
+        .await
+        .expect("failed to replace submitted dialog");
+
+    let dialog = db.get_voting_dialog(voting_id, user_id).await.unwrap();
+    assert!(!dialog.submitted);
+    assert!(dialog.message_id.is_empty());
+
+    // the undo and comment buttons of the replaced dialog are gone with it
+    assert_eq!(db.get_custom_id("undo-uuid").await, Err(DbError::NotFound));
+    assert_eq!(
+        db.get_custom_id("comment-uuid").await,
+        Err(DbError::NotFound)
+    );
+    assert_eq!(db.get_custom_ids(voting_id).await.unwrap().len(), 4);
+}
+
+#[tokio::test]
+async fn test_get_voting_dialog_by_message_id() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id = "voting-id";
+
+    let err = db
+        .get_voting_dialog_by_message_id("message-id-1")
+        .await
+        .expect_err("voting dialog should not exist");
+    assert_eq!(err, DbError::NotFound);
+
+    for (user_id, message_id) in [("user-id-1", "message-id-1"), ("user-id-2", "message-id-2")] {
+        db.save_voting_dialog(
+            voting_id.to_string(),
+            user_id.to_string(),
+            vec![0, 0, 0],
+            message_id.to_string(),
+            "channel-id".to_string(),
+            false,
+        )
+        .await
+        .expect("failed to save voting dialog");
+    }
+
+    let dialog = db
+        .get_voting_dialog_by_message_id("message-id-2")
+        .await
+        .expect("failed to get voting dialog");
+    assert_eq!(dialog.voting_id, voting_id);
+    assert_eq!(dialog.user_id, "user-id-2");
+
+    // overwriting the dialog with a new dm message moves the index
+    db.save_voting_dialog(
+        voting_id.to_string(),
+        "user-id-2".to_string(),
+        vec![1, 2, 3],
+        "message-id-3".to_string(),
+        "channel-id".to_string(),
+        true,
+    )
+    .await
+    .expect("failed to save voting dialog");
+
+    let err = db
+        .get_voting_dialog_by_message_id("message-id-2")
+        .await
+        .expect_err("old message should not be indexed");
+    assert_eq!(err, DbError::NotFound);
+
+    let dialog = db
+        .get_voting_dialog_by_message_id("message-id-3")
+        .await
+        .expect("failed to get voting dialog");
+    assert_eq!(dialog.user_id, "user-id-2");
+    assert_eq!(dialog.ballot, vec![1, 2, 3]);
+}
+
+#[tokio::test]
+async fn test_delete_voting_dialog_cleans_message_index() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id = "voting-id";
+    let user_id = "user-id";
+
+    db.save_voting_dialog(
+        voting_id.to_string(),
+        user_id.to_string(),
+        vec![0, 0, 0],
+        "message-id".to_string(),
+        "channel-id".to_string(),
+        false,
+    )
+    .await
+    .expect("failed to save voting dialog");
+
+    db.get_voting_dialog_by_message_id("message-id")
+        .await
+        .expect("failed to get voting dialog");
+
+    db.delete_voting_dialog(voting_id, user_id)
+        .await
+        .expect("failed to delete voting dialog");
+
+    let err = db
+        .get_voting_dialog_by_message_id("message-id")
+        .await
+        .expect_err("deleted voting dialog should not be indexed");
+    assert_eq!(err, DbError::NotFound);
+}
+
+#[tokio::test]
+async fn test_get_dialogs_for_user() {
+    let (_drop_db, db) = create_test_db();
+
+    let dialogs = db
+        .get_dialogs_for_user("user1")
+        .await
+        .expect("failed to get user dialogs");
+    assert!(dialogs.is_empty());
+
+    for (voting_id, user_id) in [
+        ("voting1", "user1"),
+        ("voting2", "user1"),
+        ("voting1", "user10"),
+        ("voting3", "user2"),
+    ] {
+        db.save_voting_dialog(
+            voting_id.to_string(),
+            user_id.to_string(),
+            vec![0, 0],
+            format!("message-{}-{}", voting_id, user_id),
+            "channel-id".to_string(),
+            false,
+        )
+        .await
+        .expect("failed to save voting dialog");
+    }
+
+    let dialogs = db
+        .get_dialogs_for_user("user1")
+        .await
+        .expect("failed to get user dialogs");
+    let voting_ids: Vec<&str> = dialogs.iter().map(|d| d.voting_id.as_str()).collect();
+    assert_eq!(voting_ids, vec!["voting1", "voting2"]);
+    assert!(dialogs.iter().all(|d| d.user_id == "user1"));
+
+    db.delete_voting_dialog("voting1", "user1")
+        .await
+        .expect("failed to delete voting dialog");
+
+    let dialogs = db
+        .get_dialogs_for_user("user1")
+        .await
+        .expect("failed to get user dialogs");
+    assert_eq!(dialogs.len(), 1);
+    assert_eq!(dialogs[0].voting_id, "voting2");
+}
+
+#[tokio::test]
+async fn test_get_voting_dialogs() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id1 = "84ee17be18185a077db2";
+    let voting_id2 = "84ee17be18185a077db3";
+    let ballot = vec![0, 0, 0];
+
+    for _ in 0..100 {
+        let user_id = generate_random_hex_string(20);
+        db.save_voting_dialog(
+            voting_id1.to_string(),
+            user_id.to_string(),
+            ballot.clone(),
+            "message_id".to_string(),
+            "channel_id".to_string(),
+            false,
+        )
+        .await
+        .expect("failed to save voting dialog");
+    }
+
+    for _ in 0..10 {
+        let user_id = generate_random_hex_string(20);
+        db.save_voting_dialog(
+            voting_id2.to_string(),
+            user_id.to_string(),
+            ballot.clone(),
+            "message_id".to_string(),
+            "channel_id".to_string(),
+            false,
+        )
+        .await
+        .expect("failed to save voting dialog");
+    }
+
+    let dialogs = db
+        .get_voting_dialogs(voting_id1)
+        .await
+        .expect("failed to get voting dialogs");
+
+    assert_eq!(dialogs.len(), 100);
+
+    let dialogs = db
+        .get_voting_dialogs(voting_id2)
+        .await
+        .expect("failed to get voting dialogs");
+
+    assert_eq!(dialogs.len(), 10);
+}
+
+#[tokio::test]
+async fn test_get_or_create_dialog() {
+    let (_drop_db, db) = create_test_db();
+
+    let (dialog, created) = db.get_or_create_dialog("voting", "1", 3).await.unwrap();
+    assert!(created);
+    assert_eq!(dialog.voting_id, "voting");
+    assert_eq!(dialog.user_id, "1");
+    assert_eq!(dialog.ballot, vec![0, 0, 0]);
+    assert!(!dialog.submitted);
+    assert_eq!(db.get_voting_dialog("voting", "1").await.unwrap(), dialog);
+
+    db.set_dialog_ballot("voting", "1", vec![2, 1, 0])
+        .await
+        .unwrap();
+
+    // the existing dialog is returned as it is, the choice count only seeds new ballots
+    let (dialog, created) = db.get_or_create_dialog("voting", "1", 5).await.unwrap();
+    assert!(!created);
+    assert_eq!(dialog.ballot, vec![2, 1, 0]);
+
+    // other voters get their own dialog
+    let (dialog, created) = db.get_or_create_dialog("voting", "2", 3).await.unwrap();
+    assert!(created);
+    assert_eq!(dialog.user_id, "2");
+}
+
+#[test]
+fn test_is_valid_voting_id() {
+    assert!(dd_discord::db::is_valid_voting_id("4712947128794"));
+    assert!(dd_discord::db::is_valid_voting_id(
+        &util::generate_voting_id()
+    ));
+    assert!(!dd_discord::db::is_valid_voting_id(""));
+    assert!(!dd_discord::db::is_valid_voting_id("4712947-128794"));
+}
+
+#[tokio::test]
+async fn test_failed_cleanups() {
+    let (_drop_db, db) = create_test_db();
+
+    // fresh database without any failed cleanup
+    assert!(db
+        .get_due_failed_cleanups(u64::MAX)
+        .await
+        .unwrap()
+        .is_empty());
+
+    let cleanup = |user_id: &str, retry_unix: u64| FailedCleanup {
+        voting_id: "voting".to_string(),
+        user_id: user_id.to_string(),
+        channel_id: "1202277900045262001".to_string(),
+        message_id: "1202277900045263001".to_string(),
+        content: "Voting completed: voting".to_string(),
+        attempts: 1,
+        retry_unix,
+    };
+    db.save_failed_cleanup(cleanup("1", 100)).await.unwrap();
+    db.save_failed_cleanup(cleanup("2", 200)).await.unwrap();
+
+    let due = db.get_due_failed_cleanups(150).await.unwrap();
+    assert_eq!(due, vec![cleanup("1", 100)]);
+
+    // saving again replaces the cleanup of the dialog
+    db.save_failed_cleanup(FailedCleanup {
+        attempts: 2,
+        ..cleanup("1", 300)
+    })
+    .await
+    .unwrap();
+    assert_eq!(db.get_due_failed_cleanups(250).await.unwrap().len(), 1);
+
+    db.delete_failed_cleanup("voting", "2").await.unwrap();
+    let due = db.get_due_failed_cleanups(u64::MAX).await.unwrap();
+    assert_eq!(due.len(), 1);
+    assert_eq!(due[0].attempts, 2);
+}
+
+#[tokio::test]
+async fn test_get_submitted_dialogs() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id1 = "84ee17be18185a077db2";
+    let voting_id2 = "84ee17be18185a077db3";
+
+    let mut submitted = Vec::new();
+    for (voting_id, i) in (0..10)
+        .map(|i| (voting_id1, i))
+        .chain((0..4).map(|i| (voting_id2, i)))
+    {
+        let user_id = generate_random_hex_string(20);
+        db.save_voting_dialog(
+            voting_id.to_string(),
+            user_id.clone(),
+            vec![1, 2],
+            format!("message_id-{}-{}", voting_id, i),
+            "channel_id".to_string(),
+            false,
+        )
+        .await
+        .expect("failed to save voting dialog");
+
+        // every third dialog of the first voting is submitted, the rest are abandoned
+        if voting_id == voting_id1 && i % 3 == 0 {
+            db.set_voting_dialog_submitted(voting_id, &user_id, true, 1)
+                .await
+                .expect("failed to submit voting dialog");
+            submitted.push(user_id);
+        }
+    }
+
+    let dialogs = db
+        .get_submitted_dialogs(voting_id1)
+        .await
+        .expect("failed to get submitted dialogs");
+    assert!(dialogs.iter().all(|dialog| dialog.submitted));
+    assert!(dialogs.iter().all(|dialog| dialog.voting_id == voting_id1));
+
+    let mut user_ids: Vec<String> = dialogs.into_iter().map(|dialog| dialog.user_id).collect();
+    user_ids.sort();
+    submitted.sort();
+    assert_eq!(user_ids, submitted);
+    assert_eq!(db.count_submitted_dialogs(voting_id1).await.unwrap(), 4);
+
+    let dialogs = db
+        .get_submitted_dialogs(voting_id2)
+        .await
+        .expect("failed to get submitted dialogs");
+    assert!(dialogs.is_empty());
+    assert_eq!(db.count_submitted_dialogs(voting_id2).await.unwrap(), 0);
+
+    // an undone vote and a deleted dialog are no longer counted
+    db.set_voting_dialog_submitted(voting_id1, &submitted[0], false, 0)
+        .await
+        .expect("failed to undo voting dialog");
+    db.delete_voting_dialog(voting_id1, &submitted[1])
+        .await
+        .expect("failed to delete voting dialog");
+    assert_eq!(db.count_submitted_dialogs(voting_id1).await.unwrap(), 2);
+    assert_eq!(db.get_submitted_dialogs(voting_id1).await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_index_submitted_dialogs() {
+    let (_drop_db, db) = create_test_db();
+
+    // dialogs as written before the submitted dialogs were indexed
+    let dialog = |user_id: &str, submitted: bool| {
+        format!(
+            r#"{{"voting_id":"voting","user_id":"{}","ballot":[1,0],"message_id":"m-{}","channel_id":"c","submitted":{}}}"#,
+            user_id, user_id, submitted
+        )
+    };
+    let dialog_table: TableDefinition<&str, &str> = TableDefinition::new("voting_dialog");
+    let write_txn = db.db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(dialog_table).unwrap();
+        for (user_id, submitted) in [("voter1", true), ("voter2", false), ("voter3", true)] {
+            let key = format!("voting-{}", user_id);
+            table
+                .insert(key.as_str(), dialog(user_id, submitted).as_str())
+                .unwrap();
+        }
+    }
+    write_txn.commit().unwrap();
+
+    assert_eq!(db.count_submitted_dialogs("voting").await.unwrap(), 0);
+
+    assert_eq!(db.index_submitted_dialogs().await.unwrap(), Some(2));
+    assert_eq!(db.count_submitted_dialogs("voting").await.unwrap(), 2);
+
+    // the migration runs only once
+    assert_eq!(db.index_submitted_dialogs().await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_custom_id() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id = "84ee17be18185a077db2";
+    let user_id = "user_id";
+    let custom_id = "custom_id";
+
+    let err = db
+        .get_custom_id(custom_id)
+        .await
+        .expect_err("custom id should not exist");
+
+    assert_eq!(err, DbError::NotFound);
+
+    let custom_uuid = util::generate_random_custom_uuid();
+    let custom_id = CustomID {
+        action: Action::VoteFromChannel,
+        voting_id: voting_id.to_string(),
+        user_id: Some(user_id.to_string()),
+        page: None,
+        index: None,
+    };
+
+    db.bulk_save_custom_ids(vec![(custom_uuid.clone(), custom_id)])
+        .await
+        .expect("failed to save custom id");
+
+    let custom_id = db
+        .get_custom_id(&custom_uuid)
+        .await
+        .expect("failed to get custom id");
+
+    assert_eq!(custom_id.voting_id, voting_id);
+    assert_eq!(custom_id.user_id.unwrap(), user_id);
+
+    let custom_ids = db
+        .get_custom_ids(voting_id)
+        .await
+        .expect("failed to get custom ids");
+
+    assert_eq!(custom_ids.len(), 1);
+
+    let custom_uuid2 = util::generate_random_custom_uuid();
+    let custom_uuid3 = util::generate_random_custom_uuid();
+    let custom_id2 = CustomID {
+        action: Action::VoteFromChannel,
+        voting_id: voting_id.to_string(),
+        user_id: Some(user_id.to_string()),
+        page: None,
+        index: None,
+    };
+    let custom_id3 = CustomID {
+        action: Action::VoteFromChannel,
+        voting_id: voting_id.to_string(),
+        user_id: Some(user_id.to_string()),
+        page: None,
+        index: None,
+    };
+
+    // voting 2
+    let voting_id2 = "84ee17be18185a077db3".to_string();
+    let custom_uuid4 = util::generate_random_custom_uuid();
+    let custom_id4 = CustomID {
+        action: Action::VoteFromChannel,
+        voting_id: voting_id2.clone(),
+        user_id: Some(user_id.to_string()),
+        page: None,
+        index: None,
+    };
+
+    db.bulk_save_custom_ids(vec![
+        (custom_uuid2, custom_id2),
+        (custom_uuid3, custom_id3),
+        (custom_uuid4, custom_id4),
+    ])
+    .await
+    .expect("failed to save custom id");
+
+    let custom_ids = db
+        .get_custom_ids(voting_id)
+        .await
+        .expect("failed to get custom ids");
+
+    assert_eq!(custom_ids.len(), 3);
+
+    let custom_ids = db
+        .get_custom_ids(&voting_id2)
+        .await
+        .expect("failed to get custom ids");
+
+    assert_eq!(custom_ids.len(), 1);
+
+    db.delete_custom_ids(voting_id)
+        .await
+        .expect("failed to delete custom ids");
+
+    let custom_ids = db
+        .get_custom_ids(voting_id)
+        .await
+        .expect("failed to get custom ids");
+
+    assert_eq!(custom_ids.len(), 0);
+
+    let custom_ids = db
+        .get_custom_ids(&voting_id2)
+        .await
+        .expect("failed to get custom ids");
+
+    assert_eq!(custom_ids.len(), 1);
+}
+
+#[tokio::test]
+async fn test_bulk_save_custom_ids_chunked() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id = "84ee17be18185a077db2";
+
+    let count = CUSTOM_IDS_CHUNK_SIZE * 2 + 7;
+    let custom_ids: Vec<(String, CustomID)> = (0..count)
+        .map(|i| {
+            (
+                util::generate_random_custom_uuid(),
+                CustomID {
+                    action: Action::VoteSelect,
+                    voting_id: voting_id.to_string(),
+                    user_id: None,
+                    page: Some(1),
+                    index: Some(i),
+                },
+            )
+        })
+        .collect();
+
+    db.bulk_save_custom_ids(custom_ids.clone())
+        .await
+        .expect("failed to save custom ids");
+
+    // the index is written with every chunk
+    let saved = db
+        .get_custom_ids(voting_id)
+        .await
+        .expect("failed to get custom ids");
+    assert_eq!(saved.len(), count);
+
+    for (custom_uuid, custom_id) in custom_ids {
+        let saved = db
+            .get_custom_id(&custom_uuid)
+            .await
+            .expect("failed to get custom id");
+        assert_eq!(saved, custom_id);
+    }
+
+    let too_many: Vec<(String, CustomID)> = (0..=MAX_BULK_CUSTOM_IDS)
+        .map(|_| {
+            (
+                util::generate_random_custom_uuid(),
+                CustomID {
+                    action: Action::VoteNext,
+                    voting_id: "84ee17be18185a077db3".to_string(),
+                    user_id: None,
+                    page: None,
+                    index: None,
+                },
+            )
+        })
+        .collect();
+
+    let err = db
+        .bulk_save_custom_ids(too_many)
+        .await
+        .expect_err("too many custom ids should be rejected");
+    assert_eq!(err, DbError::TooManyItems);
+    assert!(db
+        .get_custom_ids("84ee17be18185a077db3")
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_list_stale_dialogs() {
+    let (_drop_db, db) = create_test_db();
+
+    assert!(db.list_stale_dialogs(u64::MAX).await.unwrap().is_empty());
+
+    let save = |voting_id: &str, user_id: &str| {
+        db.save_voting_dialog(
+            voting_id.to_string(),
+            user_id.to_string(),
+            vec![0, 0],
+            format!("{}-{}-message", voting_id, user_id),
+            "channel-id".to_string(),
+            false,
+        )
+    };
+
+    save("voting", "1").await.unwrap();
+    save("voting-2", "1").await.unwrap();
+    save("voting", "2").await.unwrap();
+    // submitted dialogs are kept for the undo window, they are not abandoned
+    db.set_voting_dialog_submitted("voting", "2", true, util::unix_timestamp())
+        .await
+        .unwrap();
+
+    // timestamps have a second resolution
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+    let cutoff = util::unix_timestamp();
+
+    save("voting", "3").await.unwrap();
+
+    // a dialog saved before the creation time was recorded has an unknown age
+    let dialog_table: TableDefinition<&str, &str> = TableDefinition::new("voting_dialog");
+    let write_txn = db.db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(dialog_table).unwrap();
+        table
+            .insert(
+                "voting-4",
+                r#"{"voting_id":"voting","user_id":"4","ballot":[0,0],"message_id":"m-4","channel_id":"c","submitted":false}"#,
+            )
+            .unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    let mut stale: Vec<(String, String)> = db
+        .list_stale_dialogs(cutoff)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|dialog| (dialog.voting_id, dialog.user_id))
+        .collect();
+    stale.sort();
+    assert_eq!(
+        stale,
+        vec![
+            ("voting".to_string(), "1".to_string()),
+            ("voting-2".to_string(), "1".to_string()),
+        ]
+    );
+
+    assert!(db.list_stale_dialogs(0).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_find_incomplete_dialogs() {
+    let (_drop_db, db) = create_test_db();
+
+    assert!(db.find_incomplete_dialogs().await.unwrap().is_empty());
+
+    db.save_voting_dialog(
+        "voting".to_string(),
+        "1".to_string(),
+        vec![0, 0],
+        "message-id".to_string(),
+        "channel-id".to_string(),
+        false,
+    )
+    .await
+    .unwrap();
+
+    // the placeholder saved before the dialog dm is sent, left behind by a crash
+    let slot = db.claim_dialog_slot("voting", "2", false).await.unwrap();
+    std::mem::forget(slot);
+
+    // an in-channel dialog is committed without message ids
+    let slot = db.claim_dialog_slot("voting", "3", false).await.unwrap();
+    slot.commit(vec![0, 0], None, String::new(), String::new())
+        .await
+        .unwrap();
+
+    let incomplete = db.find_incomplete_dialogs().await.unwrap();
+    assert_eq!(incomplete.len(), 1);
+    assert_eq!(incomplete[0].user_id, "2");
+    assert!(incomplete[0].placeholder);
+}
+
+#[tokio::test]
+async fn test_delete_custom_ids_for_user() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id = "84ee17be18185a077db2";
+
+    let custom_id = |user_id: Option<&str>| CustomID {
+        action: Action::VoteSelect,
+        voting_id: voting_id.to_string(),
+        user_id: user_id.map(str::to_string),
+        page: None,
+        index: Some(0),
+    };
+
+    db.bulk_save_custom_ids(vec![
+        ("user-1".to_string(), custom_id(Some("1"))),
+        ("user-2".to_string(), custom_id(Some("2"))),
+        ("shared".to_string(), custom_id(None)),
+    ])
+    .await
+    .unwrap();
+
+    db.delete_custom_ids_for_user(voting_id, "1").await.unwrap();
+
+    assert_eq!(
+        db.get_custom_id("user-1")
+            .await
+            .expect_err("should be deleted"),
+        DbError::NotFound
+    );
+    db.get_custom_id("user-2").await.expect("should be kept");
+    db.get_custom_id("shared").await.expect("should be kept");
+    assert_eq!(db.get_custom_ids(voting_id).await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_replace_custom_id() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id = "84ee17be18185a077db2";
+
+    let custom_id = |action: Action| CustomID {
+        action,
+        voting_id: voting_id.to_string(),
+        user_id: None,
+        page: None,
+        index: None,
+    };
+
+    db.bulk_save_custom_ids(vec![
+        ("vote".to_string(), custom_id(Action::VoteFromChannel)),
+        ("complete".to_string(), custom_id(Action::Complete)),
+    ])
+    .await
+    .unwrap();
+
+    db.replace_custom_id(
+        voting_id,
+        "vote",
+        "vote-2",
+        custom_id(Action::VoteFromChannel),
+    )
+    .await
+    .unwrap();
 
-    assert_eq!(dialogs.len(), 10);
+    assert_eq!(
+        db.get_custom_id("vote")
+            .await
+            .expect_err("should be replaced"),
+        DbError::NotFound
+    );
+    assert_eq!(
+        db.get_custom_id("vote-2").await.unwrap(),
+        custom_id(Action::VoteFromChannel)
+    );
+    db.get_custom_id("complete").await.expect("should be kept");
+
+    let mut uuids: Vec<String> = db
+        .get_custom_ids(voting_id)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(uuid, _)| uuid)
+        .collect();
+    uuids.sort();
+    assert_eq!(uuids, vec!["complete", "vote-2"]);
+
+    assert_eq!(
+        db.replace_custom_id(
+            voting_id,
+            "vote",
+            "vote-3",
+            custom_id(Action::VoteFromChannel)
+        )
+        .await,
+        Err(DbError::NotFound)
+    );
+    // a custom id of another voting is not replaced
+    assert_eq!(
+        db.replace_custom_id("voting", "complete", "vote-3", custom_id(Action::Complete))
+            .await,
+        Err(DbError::NotFound)
+    );
+    assert_eq!(
+        db.get_custom_id("vote-3")
+            .await
+            .expect_err("should not be saved"),
+        DbError::NotFound
+    );
 }
 
 #[tokio::test]
-async fn test_custom_id() {
+async fn test_count_voting_dialogs() {
     let (_drop_db, db) = create_test_db();
-    let voting_id = "84ee17be18185a077db2";
-    let user_id = "user_id";
-    let custom_id = "custom_id";
 
-    let err = db
-        .get_custom_id(custom_id)
-        .await
-        .expect_err("custom id should not exist");
+    assert_eq!(db.count_voting_dialogs("voting").await.unwrap(), 0);
+
+    // "voting-2" shares the "voting-" key prefix with "voting"
+    for (voting_id, users) in [("voting", 3), ("voting-2", 2)] {
+        for user in 0..users {
+            db.save_voting_dialog(
+                voting_id.to_string(),
+                format!("{}", 1000 + user),
+                vec![0, 0],
+                format!("{}-message-{}", voting_id, user),
+                "channel-id".to_string(),
+                false,
+            )
+            .await
+            .expect("failed to save voting dialog");
+        }
+    }
 
-    assert_eq!(err, DbError::NotFound);
+    assert_eq!(db.count_voting_dialogs("voting").await.unwrap(), 3);
+    assert_eq!(db.count_voting_dialogs("voting-2").await.unwrap(), 2);
+    assert_eq!(db.count_voting_dialogs("votin").await.unwrap(), 0);
+}
 
-    let custom_uuid = util::generate_random_custom_uuid();
-    let custom_id = CustomID {
-        action: Action::VoteFromChannel,
-        voting_id: voting_id.to_string(),
-        user_id: Some(user_id.to_string()),
-        page: None,
-        index: None,
+#[tokio::test]
+async fn test_voting_templates() {
+    let (_drop_db, db) = create_test_db();
+    let guild = "1187313045127581796";
+    let other_guild = "1187313045127581797";
+
+    // no template table yet
+    assert_eq!(db.list_templates(guild).await.unwrap(), vec![]);
+    assert_eq!(
+        db.get_template(guild, "review").await,
+        Err(DbError::NotFound)
+    );
+
+    let choices = |choices: &[&str]| -> Vec<String> {
+        choices.iter().map(|choice| choice.to_string()).collect()
     };
-
-    db.bulk_save_custom_ids(vec![(custom_uuid.clone(), custom_id)])
+    db.save_template(guild, "review", choices(&["Approve", "Reject", "Abstain"]))
         .await
-        .expect("failed to save custom id");
-
-    let custom_id = db
-        .get_custom_id(&custom_uuid)
+        .unwrap();
+    db.save_template(guild, "lunch", choices(&["Pizza", "Tacos"]))
         .await
-        .expect("failed to get custom id");
-
-    assert_eq!(custom_id.voting_id, voting_id);
-    assert_eq!(custom_id.user_id.unwrap(), user_id);
+        .unwrap();
+    db.save_template(other_guild, "review", choices(&["Yes", "No"]))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        db.get_template(guild, "review").await.unwrap(),
+        VotingTemplate {
+            name: "review".to_string(),
+            choices: choices(&["Approve", "Reject", "Abstain"]),
+        }
+    );
+
+    // saving again replaces the template
+    db.save_template(guild, "review", choices(&["Approve", "Reject"]))
+        .await
+        .unwrap();
 
-    let custom_ids = db
-        .get_custom_ids(voting_id)
+    // templates of other guilds are left out
+    let names: Vec<(String, Vec<String>)> = db
+        .list_templates(guild)
         .await
-        .expect("failed to get custom ids");
+        .unwrap()
+        .into_iter()
+        .map(|template| (template.name, template.choices))
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            ("lunch".to_string(), choices(&["Pizza", "Tacos"])),
+            ("review".to_string(), choices(&["Approve", "Reject"])),
+        ]
+    );
+
+    db.delete_template(guild, "lunch").await.unwrap();
+    assert_eq!(
+        db.delete_template(guild, "lunch").await,
+        Err(DbError::NotFound)
+    );
+    assert_eq!(db.list_templates(guild).await.unwrap().len(), 1);
+    assert_eq!(db.list_templates(other_guild).await.unwrap().len(), 1);
+}
 
-    assert_eq!(custom_ids.len(), 1);
+#[tokio::test]
+async fn test_get_open_voting() {
+    let (_drop_db, db) = create_test_db();
 
-    let custom_uuid2 = util::generate_random_custom_uuid();
-    let custom_uuid3 = util::generate_random_custom_uuid();
-    let custom_id2 = CustomID {
-        action: Action::VoteFromChannel,
-        voting_id: voting_id.to_string(),
-        user_id: Some(user_id.to_string()),
-        page: None,
-        index: None,
+    // no voting table yet
+    assert_eq!(
+        db.get_open_voting("open").await.unwrap(),
+        VotingState::NotFound
+    );
+
+    let open = Voting {
+        id: "open".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        ..Default::default()
     };
-    let custom_id3 = CustomID {
-        action: Action::VoteFromChannel,
-        voting_id: voting_id.to_string(),
-        user_id: Some(user_id.to_string()),
-        page: None,
-        index: None,
+    db.save_voting(open.clone()).await.unwrap();
+    db.save_voting(Voting {
+        id: "completed".to_string(),
+        is_completed: true,
+        ..open.clone()
+    })
+    .await
+    .unwrap();
+    db.save_voting(Voting {
+        id: "deleted".to_string(),
+        is_completed: true,
+        is_deleted: true,
+        ..open.clone()
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(
+        db.get_open_voting("open").await.unwrap(),
+        VotingState::Open(open)
+    );
+    assert_eq!(
+        db.get_open_voting("completed").await.unwrap(),
+        VotingState::Completed
+    );
+    assert_eq!(
+        db.get_open_voting("deleted").await.unwrap(),
+        VotingState::Deleted
+    );
+    assert_eq!(
+        db.get_open_voting("missing").await.unwrap(),
+        VotingState::NotFound
+    );
+}
+
+#[tokio::test]
+async fn test_get_voting_with_dialog_count() {
+    let (_drop_db, db) = create_test_db();
+
+    assert_eq!(
+        db.get_voting_with_dialog_count("voting").await,
+        Err(DbError::NotFound)
+    );
+
+    let voting = Voting {
+        id: "voting".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        ..Default::default()
     };
+    db.save_voting(voting.clone()).await.unwrap();
+
+    // no dialog was saved yet
+    assert_eq!(
+        db.get_voting_with_dialog_count("voting").await,
+        Ok((voting.clone(), 0))
+    );
+
+    // "voting-2" shares the "voting-" key prefix with "voting"
+    for (voting_id, users) in [("voting", 3), ("voting-2", 2)] {
+        for user in 0..users {
+            db.save_voting_dialog(
+                voting_id.to_string(),
+                format!("{}", 1000 + user),
+                vec![0, 0],
+                format!("{}-message-{}", voting_id, user),
+                "channel-id".to_string(),
+                false,
+            )
+            .await
+            .expect("failed to save voting dialog");
+        }
+    }
 
-    // voting 2
-    let voting_id2 = "84ee17be18185a077db3".to_string();
-    let custom_uuid4 = util::generate_random_custom_uuid();
-    let custom_id4 = CustomID {
+    assert_eq!(
+        db.get_voting_with_dialog_count("voting").await,
+        Ok((voting, 3))
+    );
+    assert_eq!(
+        db.get_voting_with_dialog_count("voting-2").await,
+        Err(DbError::NotFound)
+    );
+}
+
+#[tokio::test]
+async fn test_get_custom_id_with_voting() {
+    let (_drop_db, db) = create_test_db();
+    let voting = Voting {
+        id: "84ee17be18185a077db2".to_string(),
+        name: "voting1".to_string(),
+        choices: vec!["choice1".to_string(), "choice2".to_string()],
+        ..Default::default()
+    };
+    let custom_id = CustomID {
         action: Action::VoteFromChannel,
-        voting_id: voting_id2.clone(),
-        user_id: Some(user_id.to_string()),
+        voting_id: voting.id.clone(),
+        user_id: None,
         page: None,
         index: None,
     };
 
-    db.bulk_save_custom_ids(vec![
-        (custom_uuid2, custom_id2),
-        (custom_uuid3, custom_id3),
-        (custom_uuid4, custom_id4),
-    ])
-    .await
-    .expect("failed to save custom id");
-
-    let custom_ids = db
-        .get_custom_ids(voting_id)
+    let err = db
+        .get_custom_id_with_voting("custom-uuid")
         .await
-        .expect("failed to get custom ids");
+        .expect_err("custom id should not exist");
+    assert_eq!(err, DbError::NotFound);
 
-    assert_eq!(custom_ids.len(), 3);
+    // the custom id exists, but its voting does not
+    db.bulk_save_custom_ids(vec![("custom-uuid".to_string(), custom_id.clone())])
+        .await
+        .expect("failed to save custom ids");
 
-    let custom_ids = db
-        .get_custom_ids(&voting_id2)
+    let err = db
+        .get_custom_id_with_voting("custom-uuid")
         .await
-        .expect("failed to get custom ids");
+        .expect_err("voting should not exist");
+    assert_eq!(err, DbError::NotFound);
 
-    assert_eq!(custom_ids.len(), 1);
+    db.save_voting(voting.clone())
+        .await
+        .expect("failed to save voting");
 
-    db.delete_custom_ids(voting_id)
+    let (got_custom_id, got_voting) = db
+        .get_custom_id_with_voting("custom-uuid")
         .await
-        .expect("failed to delete custom ids");
+        .expect("failed to get custom id with voting");
+    assert_eq!(got_custom_id, custom_id);
+    assert_eq!(got_voting, voting);
 
-    let custom_ids = db
-        .get_custom_ids(voting_id)
+    let err = db
+        .get_custom_id_with_voting("other-uuid")
         .await
-        .expect("failed to get custom ids");
+        .expect_err("custom id should not exist");
+    assert_eq!(err, DbError::NotFound);
+}
 
-    assert_eq!(custom_ids.len(), 0);
+#[tokio::test]
+async fn test_repair_custom_id_index() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id = "voting-id";
 
-    let custom_ids = db
-        .get_custom_ids(&voting_id2)
+    let custom_ids: Vec<(String, CustomID)> = (0..3)
+        .map(|_| {
+            (
+                util::generate_random_custom_uuid(),
+                CustomID {
+                    action: Action::VoteFromChannel,
+                    voting_id: voting_id.to_string(),
+                    user_id: None,
+                    page: None,
+                    index: None,
+                },
+            )
+        })
+        .collect();
+    db.bulk_save_custom_ids(custom_ids.clone())
         .await
-        .expect("failed to get custom ids");
+        .expect("failed to save custom ids");
 
-    assert_eq!(custom_ids.len(), 1);
+    assert_eq!(db.repair_custom_id_index(voting_id).await, Ok(0));
+
+    // remove a custom id row behind the back of the index
+    let custom_id_table: TableDefinition<&str, &str> = TableDefinition::new("custom_id");
+    let write_txn = db.db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(custom_id_table).unwrap();
+        table.remove(custom_ids[0].0.as_str()).unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    assert_eq!(db.repair_custom_id_index(voting_id).await, Ok(1));
+    assert_eq!(db.repair_custom_id_index(voting_id).await, Ok(0));
+
+    let mut remaining = db.get_custom_ids(voting_id).await.unwrap();
+    remaining.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut expected = custom_ids[1..].to_vec();
+    expected.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(remaining, expected);
+
+    // the remaining custom ids are still deleted through the index
+    db.delete_custom_ids(voting_id).await.unwrap();
+    assert!(db.get_custom_ids(voting_id).await.unwrap().is_empty());
+    assert_eq!(db.repair_custom_id_index(voting_id).await, Ok(0));
 }
 
 #[tokio::test]
@@ -616,8 +3136,408 @@ async fn test_delete_custom_ids() {
     assert_eq!(custom_ids.len(), 0);
 }
 
+#[tokio::test]
+async fn test_delete_custom_ids_except() {
+    let (_drop_db, db) = create_test_db();
+    let voting_id = "voting-id1";
+
+    let mut custom_ids = Vec::new();
+    for action in [Action::Complete, Action::ResultNext, Action::ResultPrevious] {
+        custom_ids.push((
+            util::generate_random_custom_uuid(),
+            CustomID {
+                action,
+                voting_id: voting_id.to_string(),
+                user_id: None,
+                page: Some(1),
+                index: None,
+            },
+        ));
+    }
+
+    db.bulk_save_custom_ids(custom_ids)
+        .await
+        .expect("failed to save custom ids");
+
+    db.delete_custom_ids_except(voting_id, vec![Action::ResultNext])
+        .await
+        .expect("failed to delete custom ids");
+
+    let custom_ids = db
+        .get_custom_ids(voting_id)
+        .await
+        .expect("failed to get custom ids");
+
+    assert_eq!(custom_ids.len(), 1);
+    assert_eq!(custom_ids[0].1.action, Action::ResultNext);
+}
+
 fn generate_random_hex_string(length: usize) -> String {
     let mut rng = rand::thread_rng();
     let bytes: Vec<u8> = (0..length / 2).map(|_| rng.gen()).collect();
     encode(bytes)
 }
+
+#[tokio::test]
+async fn test_backfill_dialog_submitted() {
+    let (_drop_db, db) = create_test_db();
+
+    let open = Voting {
+        id: "open".to_string(),
+        name: "open".to_string(),
+        choices: vec!["choice1".to_string(), "choice2".to_string()],
+        ..Default::default()
+    };
+    let completed = Voting {
+        id: "completed".to_string(),
+        name: "completed".to_string(),
+        choices: vec!["choice1".to_string(), "choice2".to_string()],
+        is_completed: true,
+        ..Default::default()
+    };
+    db.save_voting(open).await.unwrap();
+    db.save_voting(completed).await.unwrap();
+
+    db.save_pending_vote(PendingVote {
+        voting_id: "open".to_string(),
+        user_id: "pending".to_string(),
+        ballot: HashMap::from([("choice1".to_string(), 1)]),
+        weight: 1,
+    })
+    .await
+    .unwrap();
+
+    // dialogs as written before the submitted flag existed
+    let legacy = |voting_id: &str, user_id: &str| {
+        format!(
+            r#"{{"voting_id":"{}","user_id":"{}","ballot":[1,0],"message_id":"m-{}","channel_id":"c"}}"#,
+            voting_id, user_id, user_id
+        )
+    };
+    let dialog_table: TableDefinition<&str, &str> = TableDefinition::new("voting_dialog");
+    let write_txn = db.db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(dialog_table).unwrap();
+        for (voting_id, user_id) in [
+            ("open", "voter"),
+            ("open", "pending"),
+            ("completed", "voter"),
+            ("gone", "voter"),
+        ] {
+            let key = format!("{}-{}", voting_id, user_id);
+            table
+                .insert(key.as_str(), legacy(voting_id, user_id).as_str())
+                .unwrap();
+        }
+    }
+    write_txn.commit().unwrap();
+
+    // a dialog which already has the flag is left as it is
+    db.save_voting_dialog(
+        "completed".to_string(),
+        "current".to_string(),
+        vec![1, 0],
+        "m-current".to_string(),
+        "c".to_string(),
+        false,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        db.run_migrations().await.unwrap(),
+        vec![
+            "backfill_dialog_submitted",
+            "delete_votings_page_custom_ids",
+            "move_voting_ballots",
+            "index_submitted_dialogs"
+        ]
+    );
+
+    let submitted = |voting_id: &'static str, user_id: &'static str| {
+        let db = &db;
+        async move {
+            db.get_voting_dialog(voting_id, user_id)
+                .await
+                .unwrap()
+                .submitted
+        }
+    };
+    assert!(!submitted("open", "voter").await);
+    assert!(submitted("open", "pending").await);
+    assert!(submitted("completed", "voter").await);
+    assert!(submitted("gone", "voter").await);
+    assert!(!submitted("completed", "current").await);
+    assert_eq!(db.count_submitted_dialogs("open").await.unwrap(), 1);
+    assert_eq!(db.count_submitted_dialogs("completed").await.unwrap(), 1);
+
+    let dialog = db.get_voting_dialog("open", "pending").await.unwrap();
+    assert_eq!(dialog.submitted_unix, 0);
+    assert_eq!(dialog.ballot, vec![1, 0]);
+
+    // the backfill runs only once
+    assert!(db.run_migrations().await.unwrap().is_empty());
+    assert_eq!(db.backfill_dialog_submitted().await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_delete_votings_page_custom_ids() {
+    let (_drop_db, db) = create_test_db();
+
+    db.save_voting(Voting {
+        id: "voting1".to_string(),
+        name: "voting1".to_string(),
+        choices: vec!["choice1".to_string(), "choice2".to_string()],
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    db.bulk_save_custom_ids(vec![(
+        "voting-uuid".to_string(),
+        CustomID {
+            action: Action::Complete,
+            voting_id: "voting1".to_string(),
+            user_id: None,
+            page: None,
+            index: None,
+        },
+    )])
+    .await
+    .unwrap();
+
+    // page buttons of the votings listing as written before `VotingsListing`
+    let custom_id_table: TableDefinition<&str, &str> = TableDefinition::new("custom_id");
+    let index_table: TableDefinition<&str, &str> = TableDefinition::new("voting_customid_index");
+    let write_txn = db.db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(custom_id_table).unwrap();
+        let mut index = write_txn.open_table(index_table).unwrap();
+        for (uuid, page) in [("page-uuid1", 1), ("page-uuid2", 2)] {
+            let custom_id = format!(
+                r#"{{"action":"VotingsPage","voting_id":"","page":{}}}"#,
+                page
+            );
+            table.insert(uuid, custom_id.as_str()).unwrap();
+            index.insert(format!("-{}", uuid).as_str(), uuid).unwrap();
+        }
+    }
+    write_txn.commit().unwrap();
+
+    assert_eq!(db.delete_votings_page_custom_ids().await.unwrap(), Some(2));
+
+    assert_eq!(
+        db.get_custom_id("page-uuid1").await.unwrap_err(),
+        DbError::NotFound
+    );
+    assert!(db.get_custom_ids("").await.unwrap().is_empty());
+    assert_eq!(db.get_custom_ids("voting1").await.unwrap().len(), 1);
+
+    // the migration runs only once
+    assert_eq!(db.delete_votings_page_custom_ids().await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn voting_ballots() {
+    let (_drop_db, db) = create_test_db();
+
+    assert_eq!(
+        db.get_voting_ballots("voting1").await.unwrap(),
+        VotingBallots::default()
+    );
+
+    let ballots = VotingBallots {
+        comments: vec![("user1".to_string(), "comment".to_string())],
+        ballots: vec![("user1".to_string(), vec![1, 2])],
+    };
+    db.set_voting_ballots("voting1", ballots.clone())
+        .await
+        .unwrap();
+    assert_eq!(db.get_voting_ballots("voting1").await.unwrap(), ballots);
+    assert_eq!(
+        db.get_voting_ballots("voting2").await.unwrap(),
+        VotingBallots::default()
+    );
+}
+
+#[tokio::test]
+async fn test_move_voting_ballots() {
+    let (_drop_db, db) = create_test_db();
+
+    // votings as written when the ballots and comments were recorded on them
+    let legacy = |id: &str, recorded: &str| {
+        format!(
+            r#"{{"id":"{}","name":"{}","choices":["a","b"],"is_completed":true,"is_deleted":false,"message_id":"","channel_id":"","creator_message_id":"","creator_dm_channel_id":"",{}}}"#,
+            id, id, recorded
+        )
+    };
+    let voting_table: TableDefinition<&str, &str> = TableDefinition::new("voting");
+    let write_txn = db.db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(voting_table).unwrap();
+        let recorded = legacy(
+            "recorded",
+            r#""comments":[["user1","comment"]],"ballots":[["user1",[1,2]]]"#,
+        );
+        table.insert("recorded", recorded.as_str()).unwrap();
+        let empty = legacy("empty", r#""comments":[],"ballots":[]"#);
+        table.insert("empty", empty.as_str()).unwrap();
+    }
+    write_txn.commit().unwrap();
+
+    db.save_voting(Voting {
+        id: "current".to_string(),
+        name: "current".to_string(),
+        choices: vec!["a".to_string(), "b".to_string()],
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(db.move_voting_ballots().await.unwrap(), Some(1));
+
+    assert_eq!(
+        db.get_voting_ballots("recorded").await.unwrap(),
+        VotingBallots {
+            comments: vec![("user1".to_string(), "comment".to_string())],
+            ballots: vec![("user1".to_string(), vec![1, 2])],
+        }
+    );
+    assert_eq!(
+        db.get_voting_ballots("empty").await.unwrap(),
+        VotingBallots::default()
+    );
+
+    // the votings are rewritten without them
+    for id in ["recorded", "empty"] {
+        let raw = db.get_voting_raw(id).await.unwrap();
+        assert!(!raw.contains("ballots"));
+        assert!(!raw.contains("comments"));
+        assert!(db.get_voting(id).await.unwrap().is_completed);
+    }
+
+    // the migration runs only once
+    assert_eq!(db.move_voting_ballots().await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_reset_dialog_ballot() {
+    let (_drop_db, db) = create_test_db();
+
+    assert_eq!(
+        db.reset_dialog_ballot("voting", "user").await,
+        Err(DbError::NotFound)
+    );
+
+    db.save_voting_dialog(
+        "voting".to_string(),
+        "user".to_string(),
+        vec![2, 0, 1],
+        "message".to_string(),
+        "channel".to_string(),
+        false,
+    )
+    .await
+    .unwrap();
+
+    db.reset_dialog_ballot("voting", "user").await.unwrap();
+
+    let dialog = db.get_voting_dialog("voting", "user").await.unwrap();
+    assert_eq!(dialog.ballot, vec![0, 0, 0]);
+    assert_eq!(dialog.message_id, "message");
+}
+
+#[tokio::test]
+async fn test_voting_choice_descriptions() {
+    let (_drop_db, db) = create_test_db();
+
+    let voting = Voting {
+        id: "voting".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        choice_descriptions: vec![String::new(), "Critique of Pure Reason".to_string()],
+        ..Default::default()
+    };
+    db.save_voting(voting.clone()).await.unwrap();
+
+    let saved = db.get_voting("voting").await.unwrap();
+    assert_eq!(saved, voting);
+    assert_eq!(saved.choice_description(0), None);
+    assert_eq!(saved.choice_description(1), Some("Critique of Pure Reason"));
+    assert_eq!(saved.choice_description(2), None);
+}
+
+#[tokio::test]
+async fn test_voting_vote_button() {
+    let (_drop_db, db) = create_test_db();
+
+    let voting = Voting {
+        id: "voting".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        vote_button_label: Some("Rank the finalists".to_string()),
+        vote_button_emoji: Some("🗳️".to_string()),
+        ..Default::default()
+    };
+    db.save_voting(voting.clone()).await.unwrap();
+    assert_eq!(db.get_voting("voting").await.unwrap(), voting);
+}
+
+#[tokio::test]
+async fn test_voting_questions() {
+    let (_drop_db, db) = create_test_db();
+
+    let voting = Voting {
+        id: "voting".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        questions: vec![Question {
+            dd_voting_id: "question".to_string(),
+            name: "Food".to_string(),
+            choices: vec![
+                "Pizza".to_string(),
+                "Tacos".to_string(),
+                "Sushi".to_string(),
+            ],
+        }],
+        ..Default::default()
+    };
+    db.save_voting(voting.clone()).await.unwrap();
+    assert_eq!(db.get_voting("voting").await.unwrap(), voting);
+
+    db.save_voting_dialog(
+        "voting".to_string(),
+        "user".to_string(),
+        vec![1, 2],
+        "message".to_string(),
+        "channel".to_string(),
+        false,
+    )
+    .await
+    .unwrap();
+
+    // questions can't be skipped
+    assert_eq!(
+        db.set_dialog_question("voting", "user", 2, 3).await,
+        Err(DbError::IndexOutOfRange)
+    );
+
+    let dialog = db
+        .set_dialog_question("voting", "user", 1, 3)
+        .await
+        .unwrap();
+    assert_eq!(dialog.question, 1);
+    assert_eq!(dialog.question_ballots, vec![vec![0, 0, 0]]);
+
+    db.vote_voting_dialog("voting", "user", 1, 2).await.unwrap();
+    db.set_dialog_question("voting", "user", 0, 2)
+        .await
+        .unwrap();
+    db.vote_voting_dialog("voting", "user", 2, 0).await.unwrap();
+
+    // going back to a question keeps its ballot
+    let dialog = db
+        .set_dialog_question("voting", "user", 1, 3)
+        .await
+        .unwrap();
+    assert_eq!(dialog.ballot, vec![2, 2]);
+    assert_eq!(dialog.question_ballots, vec![vec![0, 0, 1]]);
+    assert_eq!(dialog.question_ballot(1), Some(&vec![0, 0, 1]));
+    assert_eq!(dialog.question_ballot(2), None);
+}
@@ -1,25 +1,53 @@
 mod common;
-use axum::extract::State;
+use axum::extract::{Path, State};
+use axum::response::IntoResponse;
 use axum::Json;
 use common::create_test_db;
 use common::DropDb;
 use dd_discord::db::Action;
+use dd_discord::db::AuditEventKind;
 use dd_discord::db::CustomID;
+use dd_discord::db::GuildConfig;
+use dd_discord::db::PendingVote;
+use dd_discord::db::Question;
+use dd_discord::db::TieBreak;
+use dd_discord::db::VoteDialog;
+use dd_discord::db::VotingBallots;
 use dd_discord::util;
 use http::StatusCode;
 use serde_json::json;
+use twilight_model::channel::message::component::{
+    ActionRow, Button, ButtonStyle, Component, SelectMenu, TextInput, TextInputStyle,
+};
 use twilight_model::channel::message::MessageFlags;
 use twilight_model::http::interaction::InteractionResponse;
 use twilight_model::http::interaction::InteractionResponseData;
+use twilight_model::id::Id;
+use twilight_util::builder::embed::EmbedBuilder;
 
+use std::collections::HashMap;
 use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
+use tracing_test::traced_test;
 
-use dd_discord::{handle_interaction, InteractionError};
-use ddclient_rs::Voting;
+use dd_discord::{
+    ballots_csv, break_tie, disable_components, first_choice_counts, handle_admin_read_only,
+    handle_admin_register, handle_interaction, handle_voting_results, internal_error_response,
+    parse_questions, parse_role_weights, render_completion_template, result_summary,
+    split_choice_description, validate_ballot, weighted_voter_ids, with_list_fields,
+    InteractionError, ReadOnlyMode, RegisterResponse, ResultsArtifact, VotingCommandRegistration,
+    ADMIN_SECRET_HEADER, EMBED_FIELD_VALUE_MAX_LENGTH, MAX_CHOICE_DESCRIPTION_LENGTH,
+    MAX_CHOICE_LENGTH, MAX_INTERACTION_BODY_BYTES, MAX_QUESTIONS, MAX_ROLE_WEIGHT,
+    RESULTS_SECRET_HEADER,
+};
+use ddclient_rs::{Voting, VotingResults};
 use ed25519_dalek::{Signer, SigningKey};
-use httpmock::{Method::POST, MockServer};
+use httpmock::{
+    Method::{DELETE, GET, PATCH, POST, PUT},
+    MockServer,
+};
 use rand::rngs::OsRng;
 
 macro_rules! create_mock {
@@ -84,11 +112,41 @@ async fn handle_interaction_uknown_command() {
         &test,
         empty_mock_vec(),
         empty_mock_vec(),
-        internal_server_error_response(),
+        internal_server_error_response(&test),
         true
     );
 }
 
+#[tokio::test]
+async fn handle_interaction_support_url_test() {
+    let test = setup_test_env_with_config(
+        "unknown_comman.json",
+        dd_discord::Config {
+            support_url: Some("https://example.com/support".to_string()),
+            ..Default::default()
+        },
+    );
+    let body: serde_json::Value = serde_json::from_str(&test.body).unwrap();
+    let interaction_id = body["id"].as_str().unwrap();
+
+    let (status, Json(response)) = handle_interaction(
+        test.data.clone(),
+        test.headers.clone(),
+        test.body.to_string(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(status, http::StatusCode::OK);
+    let data = response.data.unwrap();
+    assert_eq!(data.flags, Some(MessageFlags::EPHEMERAL));
+    let content = data.content.unwrap();
+    assert!(content.contains(&format!("Reference: `{}`", interaction_id)));
+    assert!(
+        content.contains("If this keeps happening, contact support: https://example.com/support")
+    );
+}
+
 #[tokio::test]
 async fn handle_interaction_bad_signature() {
     let test = setup_test_env("slash_command.json");
@@ -96,6 +154,7 @@ async fn handle_interaction_bad_signature() {
     let mut headers = http::HeaderMap::new();
     headers.insert("X-Signature-Ed25519", "bad signature".parse().unwrap());
     headers.insert("X-Signature-Timestamp", "bad timestamp".parse().unwrap());
+    headers.insert("Content-Type", "application/json".parse().unwrap());
     let resp = handle_interaction(test.data.clone(), headers, test.body.to_string()).await;
 
     if let Err(InteractionError::Status(StatusCode::UNAUTHORIZED)) = resp {
@@ -105,6 +164,100 @@ async fn handle_interaction_bad_signature() {
 }
 
 #[tokio::test]
+#[traced_test]
+async fn handle_interaction_content_type_test() {
+    let test = setup_test_env("slash_command.json");
+
+    // a form encoded body is never parsed as json, nor is its signature checked
+    let body = "type=1&id=1201102103549050951".to_string();
+    for content_type in [Some("application/x-www-form-urlencoded"), None] {
+        let mut headers = signing_headers(&test.signing_key, &body);
+        match content_type {
+            Some(content_type) => {
+                headers.insert("Content-Type", content_type.parse().unwrap());
+            }
+            None => {
+                headers.remove("Content-Type");
+            }
+        }
+
+        let err = handle_interaction(test.data.clone(), headers, body.clone())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err,
+            InteractionError::Status(StatusCode::UNSUPPORTED_MEDIA_TYPE)
+        );
+
+        let response = err.into_response();
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let response_body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let response_body: serde_json::Value = serde_json::from_slice(&response_body).unwrap();
+        assert_eq!(
+            response_body,
+            json!({"error": "interaction body must be application/json"})
+        );
+    }
+    assert!(!logs_contain("parsing interaction from body failed"));
+
+    // parameters of the json content type are fine
+    let body = "{not json".to_string();
+    let mut headers = signing_headers(&test.signing_key, &body);
+    headers.insert(
+        "Content-Type",
+        "application/json; charset=utf-8".parse().unwrap(),
+    );
+    let resp = handle_interaction(test.data.clone(), headers, body).await;
+    assert_eq!(
+        resp.unwrap_err(),
+        InteractionError::Status(StatusCode::BAD_REQUEST)
+    );
+}
+
+#[tokio::test]
+async fn handle_interaction_error_body_test() {
+    let test = setup_test_env("slash_command.json");
+
+    let body = "{not json".to_string();
+    let headers = signing_headers(&test.signing_key, &body);
+    let bad_request = handle_interaction(test.data.clone(), headers, body)
+        .await
+        .unwrap_err();
+
+    let mut headers = http::HeaderMap::new();
+    headers.insert("X-Signature-Ed25519", "bad signature".parse().unwrap());
+    headers.insert("X-Signature-Timestamp", "bad timestamp".parse().unwrap());
+    headers.insert("Content-Type", "application/json".parse().unwrap());
+    let unauthorized = handle_interaction(test.data.clone(), headers, test.body.to_string())
+        .await
+        .unwrap_err();
+
+    for (err, expected_status, expected_error) in [
+        (
+            bad_request,
+            StatusCode::BAD_REQUEST,
+            "malformed interaction body",
+        ),
+        (
+            unauthorized,
+            StatusCode::UNAUTHORIZED,
+            "invalid request signature",
+        ),
+    ] {
+        let response = err.into_response();
+        assert_eq!(response.status(), expected_status);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body, json!({"error": expected_error}));
+    }
+}
+
+#[tokio::test]
+#[traced_test]
 async fn handle_slash_interaction() {
     let test = setup_test_env("slash_command.json");
 
@@ -123,13 +276,18 @@ async fn handle_slash_interaction() {
     let creator_message_id = "812746127846424";
     let message_id = "3589723985723";
 
-    let dd_client_happy_mocks = || -> Vec<(httpmock::Method, String, serde_json::Value)> {
-        vec![(
-            httpmock::Method::POST,
-            "/v1/votings".to_string(),
-            serde_json::json!(&voting),
-        )]
-    };
+    // every run creates a new voting, as the voting is saved before posting the messages
+    let dd_client_happy_mocks =
+        |voting_id: &str| -> Vec<(httpmock::Method, String, serde_json::Value)> {
+            vec![(
+                httpmock::Method::POST,
+                "/v1/votings".to_string(),
+                serde_json::json!(&Voting {
+                    id: voting_id.to_string(),
+                    choices: voting.choices.clone(),
+                }),
+            )]
+        };
 
     let discord_client_happy_mocks = || -> Vec<(httpmock::Method, String, serde_json::Value)> {
         vec![
@@ -209,7 +367,7 @@ async fn handle_slash_interaction() {
     run_test!(
       "happy path",
       &test,
-     dd_client_happy_mocks(),
+     dd_client_happy_mocks(&voting.id),
      discord_client_happy_mocks(),
       Ok((http::StatusCode::OK, Json(InteractionResponse{
         kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
@@ -226,18 +384,28 @@ async fn handle_slash_interaction() {
         is_deleted: false,
         creator_message_id: creator_message_id.to_string(),
         creator_dm_channel_id: dm_channel_id.to_string(),
+        creator_user_id: "399954205235871744".to_string(), // from slash_command.json
+        guild_id: "1187313045127581796".to_string(),       // from slash_command.json
+        version: 1,                                        // the message ids are set after saving
+        ..Default::default()
     };
 
     let got_voting = test.data.db.get_voting(&voting.id).await.unwrap();
     assert_eq!(got_voting, expected_voting);
 
+    // the interaction span carries the ids and the outcome of the request
+    assert!(logs_contain(&format!(
+        r#"interaction{{interaction_id=1201102103549050951 kind=ApplicationCommand voting_id="{}" outcome="ok" status=200}}"#,
+        voting.id
+    )));
+
     let custom_ids = test.data.db.get_custom_ids(&voting.id).await.unwrap();
-    assert_eq!(custom_ids.len(), 3);
+    assert_eq!(custom_ids.len(), 9);
 
     run_test!(
         "dd client create voting error",
         &test,
-        vec![(
+        [(
             POST,
             "/v1/votings".to_string(),
             json!({
@@ -245,30 +413,30 @@ async fn handle_slash_interaction() {
             })
         ),],
         empty_mock_vec(),
-        internal_server_error_response(),
+        internal_server_error_response(&test),
         true
     );
 
     run_test!(
         "discord client create private channel error",
         &test,
-        dd_client_happy_mocks(),
-        vec![(
+        dd_client_happy_mocks("4712947128795"),
+        [(
             POST,
             "/api/v10/users/@me/channels",
             json!({
               "error": "error",
             })
         ),],
-        internal_server_error_response(),
+        internal_server_error_response(&test),
         true
     );
 
     run_test!(
         "discord client create dm message error",
         &test,
-        dd_client_happy_mocks(),
-        vec![
+        dd_client_happy_mocks("4712947128796"),
+        [
             discord_client_happy_mocks().swap_remove(0),
             (
                 POST,
@@ -278,15 +446,15 @@ async fn handle_slash_interaction() {
                 })
             ),
         ],
-        internal_server_error_response(),
+        internal_server_error_response(&test),
         true
     );
 
     run_test!(
         "discord client create channel message error",
         &test,
-        dd_client_happy_mocks(),
-        vec![
+        dd_client_happy_mocks("4712947128797"),
+        [
             discord_client_happy_mocks().swap_remove(0),
             discord_client_happy_mocks().swap_remove(1),
             (
@@ -297,83 +465,277 @@ async fn handle_slash_interaction() {
                 })
             ),
         ],
-        internal_server_error_response(),
+        internal_server_error_response(&test),
         true
     );
+
+    // the voting record is kept without the message ids of the failed announcement
+    let orphan = test.data.db.get_voting("4712947128797").await.unwrap();
+    assert_eq!(orphan.message_id, "");
+    assert_eq!(orphan.channel_id, "");
+    assert_eq!(orphan.creator_user_id, "399954205235871744");
 }
 
-fn empty_mock_vec() -> Vec<(httpmock::Method, &'static str, serde_json::Value)> {
-    vec![]
+#[tokio::test]
+async fn handle_slash_voting_creator_limit_test() {
+    let test = setup_test_env_with_config(
+        "slash_command.json",
+        dd_discord::Config {
+            max_active_votings_per_creator: 1,
+            ..Default::default()
+        },
+    );
+
+    let creator_user_id = "399954205235871744"; // from slash_command.json
+
+    test.data
+        .db
+        .save_voting(dd_discord::db::Voting {
+            id: "4712947128794".to_string(),
+            name: "Existing voting".to_string(),
+            choices: vec!["a".to_string(), "b".to_string()],
+            creator_user_id: creator_user_id.to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    // the limit is checked before any voting is created on dd or discord
+    run_test!(
+        "creator has too many active votings",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        Ok((
+            http::StatusCode::OK,
+            Json(InteractionResponse {
+                kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    content: Some("You already have 1 active votings. Complete or delete some of them before creating a new one.".to_string()),
+                    flags: Some(MessageFlags::EPHEMERAL),
+                    ..Default::default()
+                }),
+            })
+        )),
+        true
+    );
 }
 
 #[tokio::test]
-async fn handle_vote_channel_test() {
-    let custom_uuid = "df4db2bc-9fd1-43fb-8e17-97170379159a";
+async fn handle_slash_voting_invalid_id_test() {
+    let test = setup_test_env("slash_command.json");
+
+    for (assertion, voting_id) in [
+        ("voting id with delimiter", "4712947-128794"),
+        ("empty voting id", ""),
+    ] {
+        // nothing is posted to discord, the voting is rejected right after dd created it
+        run_test!(
+            assertion,
+            &test,
+            [(
+                POST,
+                "/v1/votings".to_string(),
+                serde_json::json!(&Voting {
+                    id: voting_id.to_string(),
+                    choices: vec![
+                        "Spinoza".to_string(),
+                        "Kant".to_string(),
+                        "Nietzsche".to_string()
+                    ],
+                }),
+            )],
+            empty_mock_vec(),
+            internal_server_error_response(&test),
+            true
+        );
+    }
+
+    let (votings, _) = test.data.db.get_votings_page(None, 10, true).await.unwrap();
+    assert!(votings.is_empty());
+}
+
+#[tokio::test]
+async fn handle_simple_voting_test() {
+    let mut test = setup_test_env("slash_command.json");
+
     let dm_channel_id = "319674150115610528";
-    let user_id = "82198898841029460"; // vote_channel.json
+    let channel_id = "1187315505103638638"; // from slash_command.json
     let creator_message_id = "812746127846424";
+    let message_id = "3589723985723";
 
-    let voting = dd_discord::db::Voting {
-        id: "4712947128794".to_string(),
-        choices: vec![
-            "Spinoza".to_string(),
-            "Kant".to_string(),
-            "Nietzsche".to_string(),
+    let mut body: serde_json::Value = serde_json::from_str(&test.body).unwrap();
+    body["data"]["options"]
+        .as_array_mut()
+        .unwrap()
+        .push(json!({"name": "simple", "type": 5, "value": true}));
+    test.set_body(body.to_string());
+
+    // one reaction per choice is added to the announcement, the dd backend is not used
+    let mut reaction_mock = test.discord_server.mock(|when, then| {
+        when.method(PUT).path_contains(format!(
+            "/api/v10/channels/{}/messages/{}/reactions/",
+            channel_id, message_id
+        ));
+        then.status(204);
+    });
+
+    run_test!(
+        "create simple voting",
+        &test,
+        empty_mock_vec(),
+        [
+            (
+                POST,
+                "/api/v10/users/@me/channels".to_string(),
+                json!({
+                  "id": dm_channel_id,
+                  "type": 1,
+                  "last_message_id": null,
+                  "recipients": [],
+                  "application_id": null
+                }),
+            ),
+            (
+                POST,
+                format!("/api/v10/channels/{}/messages", dm_channel_id),
+                message_json(creator_message_id, dm_channel_id),
+            ),
+            (
+                POST,
+                format!("/api/v10/channels/{}/messages", channel_id),
+                message_json(message_id, channel_id),
+            ),
         ],
-        channel_id: "1187315505103638638".to_string(),
-        message_id: "3589723985723".to_string(),
-        name: "Who do you prefer?".to_string(),
-        is_completed: false,
-        is_deleted: false,
-        creator_message_id: creator_message_id.to_string(),
-        creator_dm_channel_id: dm_channel_id.to_string(),
-    };
+        ack(),
+        true
+    );
 
-    let test = setup_test_env("vote_channel.json");
+    reaction_mock.assert_hits(3);
+    reaction_mock.delete();
+
+    let (tx, rx) = std::sync::mpsc::channel();
     test.data
         .db
-        .save_voting(voting.clone())
+        .for_each_voting(move |voting| {
+            tx.send(voting).unwrap();
+            std::ops::ControlFlow::Break(())
+        })
         .await
-        .expect("Failed to save voting");
-    test.data
-        .db
-        .bulk_save_custom_ids(vec![
-            (
-                util::generate_random_custom_uuid(),
-                CustomID {
-                    action: Action::Complete,
-                    voting_id: voting.id.clone(),
-                    user_id: None,
-                    page: None,
-                    index: None,
-                },
-            ),
+        .unwrap();
+    let voting = rx.recv().unwrap();
+    assert!(voting.simple);
+    assert_eq!(voting.message_id, message_id);
+
+    // the announcement has no vote button, only the creator buttons are stored
+    let custom_ids = test.data.db.get_custom_ids(&voting.id).await.unwrap();
+    assert_eq!(custom_ids.len(), 5);
+    let (complete_uuid, _) = custom_ids
+        .into_iter()
+        .find(|(_, custom_id)| custom_id.action == Action::Complete)
+        .unwrap();
+
+    // the reactions of the bot are not counted
+    let mut message = message_json(message_id, channel_id);
+    message["reactions"] = json!([
+        {"count": 2, "me": true, "emoji": {"id": null, "name": "🇦"}},
+        {"count": 3, "me": true, "emoji": {"id": null, "name": "🇧"}},
+        {"count": 1, "me": true, "emoji": {"id": null, "name": "🇨"}},
+    ]);
+
+    test.set_body(dm_component_body(&complete_uuid, &[]));
+    let mut results_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH)
+            .path(format!(
+                "/api/v10/channels/{}/messages/{}",
+                channel_id, message_id
+            ))
+            .body_contains("Voting results were counted from the reactions.")
+            .body_contains(r#""name":"Kant","value":"Votes: 2, Percentage: 66.67%""#);
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json(message_id, channel_id));
+    });
+
+    run_test!(
+        "complete simple voting",
+        &test,
+        empty_mock_vec(),
+        [
             (
-                util::generate_random_custom_uuid(),
-                CustomID {
-                    action: Action::Delete,
-                    voting_id: voting.id.clone(),
-                    user_id: None,
-                    page: None,
-                    index: None,
-                },
+                GET,
+                format!("/api/v10/channels/{}/messages/{}", channel_id, message_id),
+                message
             ),
             (
-                custom_uuid.to_string(),
-                CustomID {
-                    action: Action::VoteFromChannel,
-                    voting_id: voting.id.clone(),
-                    user_id: None,
-                    page: None,
-                    index: None,
-                },
+                PATCH,
+                format!(
+                    "/api/v10/channels/{}/messages/{}",
+                    dm_channel_id, creator_message_id
+                ),
+                message_json(creator_message_id, dm_channel_id)
             ),
-        ])
-        .await
-        .expect("Failed to save custom ids");
+        ],
+        ack(),
+        true
+    );
 
-    let discord_client_happy_mocks = || -> Vec<(httpmock::Method, String, serde_json::Value)> {
-        vec![
+    results_mock.assert();
+    results_mock.delete();
+
+    assert!(
+        test.data
+            .db
+            .get_voting(&voting.id)
+            .await
+            .unwrap()
+            .is_completed
+    );
+}
+
+#[tokio::test]
+async fn handle_slash_voting_deadline_countdown_test() {
+    let mut test = setup_test_env("slash_command.json");
+
+    let dm_channel_id = "319674150115610528";
+    let channel_id = "1187315505103638638"; // from slash_command.json
+    let creator_message_id = "812746127846424";
+    let message_id = "3589723985723";
+
+    let mut body: serde_json::Value = serde_json::from_str(&test.body).unwrap();
+    body["data"]["options"]
+        .as_array_mut()
+        .unwrap()
+        .push(json!({"name": "duration_hours", "type": 4, "value": 2}));
+    test.set_body(body.to_string());
+
+    // the announcement counts down to the deadline
+    let mut announcement_mock = test.discord_server.mock(|when, then| {
+        when.method(POST)
+            .path(format!("/api/v10/channels/{}/messages", channel_id))
+            .body_contains(r#""name":"Deadline","value":"<t:"#)
+            .body_contains(r#":R>)""#);
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json(message_id, channel_id));
+    });
+
+    run_test!(
+        "create voting with deadline",
+        &test,
+        [(
+            POST,
+            "/v1/votings".to_string(),
+            serde_json::json!(&Voting {
+                id: "4712947128794".to_string(),
+                choices: vec![
+                    "Spinoza".to_string(),
+                    "Kant".to_string(),
+                    "Nietzsche".to_string()
+                ],
+            }),
+        )],
+        [
             (
                 POST,
                 "/api/v10/users/@me/channels".to_string(),
@@ -381,86 +743,7402 @@ async fn handle_vote_channel_test() {
                   "id": dm_channel_id,
                   "type": 1,
                   "last_message_id": null,
-                  "recipients": [
-                    {
-                      "username": "test",
-                      "discriminator": "9999",
-                      "id": user_id,
-                      "avatar": "33ecab261d4681afa4d85a04691c4a01"
-                    }
-                  ],
+                  "recipients": [],
                   "application_id": null
                 }),
             ),
             (
                 POST,
                 format!("/api/v10/channels/{}/messages", dm_channel_id),
-                json!({
-                            "attachments": [],
-                            "author": {
-                              "username": "test",
-                              "discriminator": "9999",
-                              "id": user_id,
-                              "avatar": "33ecab261d4681afa4d85a04691c4a01"
-                            },
-                            "channel_id": dm_channel_id,
-                            "content": "test",
-                            "edited_timestamp": null,
-                            "embeds": [],
-                            "flags": 0,
-                            "id": creator_message_id,
-                            "mention_everyone": false,
-                            "mention_roles": [],
-                            "mentions": [],
-                            "pinned": false,
-                            "timestamp": "2018-02-04T19:51:45.941000+00:00",
-                            "tts": false,
-                            "type": 0
-                }),
+                message_json(creator_message_id, dm_channel_id),
             ),
-        ]
-    };
+        ],
+        ack(),
+        true
+    );
 
-    let mocks = run_test!(
-      "happy path",
-      &test,
-     empty_mock_vec(),
-     discord_client_happy_mocks(),
-      Ok((http::StatusCode::OK, Json(InteractionResponse{
-        kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
-        data: Some(InteractionResponseData {
-            content: Some("You will receive dm with voting dialog".to_string()),
-            flags: Some(MessageFlags::EPHEMERAL),
-            ..Default::default()
-        }),
-      }))),
-    false);
+    announcement_mock.assert();
+    announcement_mock.delete();
 
-    let start = tokio::time::Instant::now();
-    let timeout_duration = Duration::from_secs(5);
+    let voting = test.data.db.get_voting("4712947128794").await.unwrap();
+    assert!(voting.deadline_unix.is_some());
+    assert_eq!(voting.message_id, message_id);
+}
 
-    let voting_dialog = loop {
-        match test.data.db.get_voting_dialog(&voting.id, user_id).await {
-            Ok(voting_dialog) => break voting_dialog,
-            Err(_) => {
-                if start.elapsed() > timeout_duration {
-                    panic!("get voting dialog timeout");
-                }
+#[tokio::test]
+async fn handle_slash_voting_vote_button_test() {
+    let mut test = setup_test_env("slash_command.json");
 
-                tokio::time::sleep(Duration::from_millis(100)).await;
-            }
-        }
-    };
+    let dm_channel_id = "319674150115610528";
+    let channel_id = "1187315505103638638"; // from slash_command.json
+    let creator_message_id = "812746127846424";
+    let message_id = "3589723985723";
 
-    assert_eq!(voting_dialog.voting_id, voting.id);
+    let mut body: serde_json::Value = serde_json::from_str(&test.body).unwrap();
+    let options = body["data"]["options"].as_array().unwrap().clone();
 
-    for mut mock in mocks {
-        mock.assert();
-        mock.delete();
-    }
+    let mut with_long_label = options.clone();
+    with_long_label.push(json!({"name": "vote_button_label", "type": 3, "value": "x".repeat(81)}));
+    body["data"]["options"] = json!(with_long_label);
+    test.set_body(body.to_string());
 
-    let custom_ids = test.data.db.get_custom_ids(&voting.id).await.unwrap();
-    assert_eq!(custom_ids.len(), 7);
+    run_test!(
+        "vote button label too long",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        Ok((
+            http::StatusCode::OK,
+            Json(InteractionResponse {
+                kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    content: Some("The vote button label must have 1 to 80 characters.".to_string()),
+                    flags: Some(MessageFlags::EPHEMERAL),
+                    ..Default::default()
+                }),
+            })
+        )),
+        true
+    );
+
+    let mut with_text_emoji = options.clone();
+    with_text_emoji.push(json!({"name": "vote_button_emoji", "type": 3, "value": ":ballot_box:"}));
+    body["data"]["options"] = json!(with_text_emoji);
+    test.set_body(body.to_string());
+
+    run_test!(
+        "vote button emoji not an emoji",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        Ok((
+            http::StatusCode::OK,
+            Json(InteractionResponse {
+                kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    content: Some("The vote button emoji must be a single emoji.".to_string()),
+                    flags: Some(MessageFlags::EPHEMERAL),
+                    ..Default::default()
+                }),
+            })
+        )),
+        true
+    );
+
+    let mut with_button = options.clone();
+    with_button
+        .push(json!({"name": "vote_button_label", "type": 3, "value": "Rank the finalists"}));
+    with_button.push(json!({"name": "vote_button_emoji", "type": 3, "value": "🗳️"}));
+    body["data"]["options"] = json!(with_button);
+    test.set_body(body.to_string());
+
+    // the announcement vote button carries the custom label and emoji
+    let mut announcement_mock = test.discord_server.mock(|when, then| {
+        when.method(POST)
+            .path(format!("/api/v10/channels/{}/messages", channel_id))
+            .body_contains(r#""label":"Rank the finalists""#)
+            .body_contains(r#""emoji":{"name":"🗳️"}"#);
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json(message_id, channel_id));
+    });
+
+    run_test!(
+        "create voting with custom vote button",
+        &test,
+        [(
+            POST,
+            "/v1/votings".to_string(),
+            serde_json::json!(&Voting {
+                id: "4712947128794".to_string(),
+                choices: vec![
+                    "Spinoza".to_string(),
+                    "Kant".to_string(),
+                    "Nietzsche".to_string()
+                ],
+            }),
+        )],
+        [
+            (
+                POST,
+                "/api/v10/users/@me/channels".to_string(),
+                json!({
+                  "id": dm_channel_id,
+                  "type": 1,
+                  "last_message_id": null,
+                  "recipients": [],
+                  "application_id": null
+                }),
+            ),
+            (
+                POST,
+                format!("/api/v10/channels/{}/messages", dm_channel_id),
+                message_json(creator_message_id, dm_channel_id),
+            ),
+        ],
+        ack(),
+        true
+    );
+
+    announcement_mock.assert();
+    announcement_mock.delete();
+
+    let voting = test.data.db.get_voting("4712947128794").await.unwrap();
+    assert_eq!(
+        voting.vote_button_label.as_deref(),
+        Some("Rank the finalists")
+    );
+    assert_eq!(voting.vote_button_emoji.as_deref(), Some("🗳️"));
+}
+
+#[tokio::test]
+async fn handle_duplicate_interaction_test() {
+    let test = setup_test_env_with_config(
+        "slash_command.json",
+        dd_discord::Config {
+            interaction_dedup_secs: 60,
+            ..Default::default()
+        },
+    );
+
+    let dm_channel_id = "319674150115610528";
+    let channel_id = "1187315505103638638"; // from slash_command.json
+
+    run_test!(
+        "create voting",
+        &test,
+        [(
+            POST,
+            "/v1/votings".to_string(),
+            serde_json::json!(&Voting {
+                id: "4712947128794".to_string(),
+                choices: vec![
+                    "Spinoza".to_string(),
+                    "Kant".to_string(),
+                    "Nietzsche".to_string()
+                ],
+            }),
+        )],
+        [
+            (
+                POST,
+                "/api/v10/users/@me/channels".to_string(),
+                json!({
+                  "id": dm_channel_id,
+                  "type": 1,
+                  "last_message_id": null,
+                  "recipients": [],
+                  "application_id": null
+                }),
+            ),
+            (
+                POST,
+                format!("/api/v10/channels/{}/messages", dm_channel_id),
+                message_json("812746127846424", dm_channel_id),
+            ),
+            (
+                POST,
+                format!("/api/v10/channels/{}/messages", channel_id),
+                message_json("3589723985723", channel_id),
+            ),
+        ],
+        ack(),
+        true
+    );
+
+    // the redelivery is acked without calling discord or the DD api again
+    run_test!(
+        "redelivered interaction",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        ack(),
+        true
+    );
+
+    let creator_id = "399954205235871744"; // from slash_command.json
+    assert_eq!(
+        test.data
+            .db
+            .count_active_votings_by_creator(creator_id)
+            .await
+            .unwrap(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn handle_simple_voting_rate_limited_reactions_test() {
+    let mut test = setup_test_env("slash_command.json");
+
+    let dm_channel_id = "319674150115610528";
+    let channel_id = "1187315505103638638"; // from slash_command.json
+    let creator_message_id = "812746127846424";
+    let message_id = "3589723985723";
+
+    let mut body: serde_json::Value = serde_json::from_str(&test.body).unwrap();
+    body["data"]["options"]
+        .as_array_mut()
+        .unwrap()
+        .push(json!({"name": "simple", "type": 5, "value": true}));
+    test.set_body(body.to_string());
+
+    let reactions_path = format!(
+        "/api/v10/channels/{}/messages/{}/reactions/",
+        channel_id, message_id
+    );
+    // the third reaction is rate limited once, it is added after the wait
+    static REACTIONS: AtomicUsize = AtomicUsize::new(0);
+    let mut rate_limited_mock = test.discord_server.mock(|when, then| {
+        when.method(PUT)
+            .path_contains(reactions_path.clone())
+            .matches(|_| REACTIONS.fetch_add(1, Ordering::SeqCst) == 2);
+        then.status(429)
+            .header("Content-Type", "application/json")
+            .json_body(json!({
+                "message": "You are being rate limited.",
+                "retry_after": 0.05,
+                "global": false
+            }));
+    });
+    let mut reaction_mock = test.discord_server.mock(|when, then| {
+        when.method(PUT).path_contains(reactions_path.clone());
+        then.status(204);
+    });
+
+    run_test!(
+        "create simple voting with rate limited reactions",
+        &test,
+        empty_mock_vec(),
+        [
+            (
+                POST,
+                "/api/v10/users/@me/channels".to_string(),
+                json!({
+                  "id": dm_channel_id,
+                  "type": 1,
+                  "last_message_id": null,
+                  "recipients": [],
+                  "application_id": null
+                }),
+            ),
+            (
+                POST,
+                format!("/api/v10/channels/{}/messages", dm_channel_id),
+                message_json(creator_message_id, dm_channel_id),
+            ),
+            (
+                POST,
+                format!("/api/v10/channels/{}/messages", channel_id),
+                message_json(message_id, channel_id),
+            ),
+        ],
+        ack(),
+        true
+    );
+
+    rate_limited_mock.assert_hits(1);
+    rate_limited_mock.delete();
+    reaction_mock.assert_hits(3);
+    reaction_mock.delete();
+}
+
+#[tokio::test]
+async fn handle_voting_transfer_test() {
+    let mut test = setup_test_env("slash_command.json");
+
+    let voting_id = "4712947128794";
+    let new_owner_id = "82198898841029461";
+    let old_dm_channel_id = "319674150115610528";
+    let old_creator_message_id = "812746127846424";
+    let new_dm_channel_id = "319674150115610529";
+    let new_creator_message_id = "812746127846425";
+
+    test.data
+        .db
+        .save_voting(dd_discord::db::Voting {
+            id: voting_id.to_string(),
+            name: "Who do you prefer?".to_string(),
+            choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+            channel_id: "1187315505103638638".to_string(),
+            message_id: "3589723985723".to_string(),
+            creator_message_id: old_creator_message_id.to_string(),
+            creator_dm_channel_id: old_dm_channel_id.to_string(),
+            creator_user_id: "399954205235871744".to_string(),
+            guild_id: "1187313045127581796".to_string(), // slash_command.json
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    test.data
+        .db
+        .save_voting(dd_discord::db::Voting {
+            id: "4712947128795".to_string(),
+            name: "Foreign voting".to_string(),
+            choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+            creator_user_id: "399954205235871744".to_string(),
+            guild_id: "1187313045127581797".to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    let mut body: serde_json::Value = serde_json::from_str(&test.body).unwrap();
+    body["data"]["name"] = json!("voting-transfer");
+    body["data"]["options"] = json!([
+        {"name": "voting_id", "type": 3, "value": voting_id},
+        {"name": "new_owner", "type": 6, "value": new_owner_id},
+    ]);
+    body["member"]["permissions"] = json!("0");
+    test.set_body(body.to_string());
+
+    run_test!(
+        "non admin",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        Ok((
+            http::StatusCode::OK,
+            Json(InteractionResponse {
+                kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    content: Some("Only server administrators can transfer votings.".to_string()),
+                    flags: Some(MessageFlags::EPHEMERAL),
+                    ..Default::default()
+                }),
+            })
+        )),
+        true
+    );
+
+    let voting = test.data.db.get_voting(voting_id).await.unwrap();
+    assert_eq!(voting.creator_user_id, "399954205235871744");
+
+    body["member"]["permissions"] = json!("8"); // administrator
+    body["data"]["options"][0]["value"] = json!("4712947128795");
+    test.set_body(body.to_string());
+
+    run_test!(
+        "voting of another guild",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        Ok((
+            http::StatusCode::OK,
+            Json(InteractionResponse {
+                kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    content: Some("Voting not found.".to_string()),
+                    flags: Some(MessageFlags::EPHEMERAL),
+                    ..Default::default()
+                }),
+            })
+        )),
+        true
+    );
+
+    let voting = test.data.db.get_voting("4712947128795").await.unwrap();
+    assert_eq!(voting.creator_user_id, "399954205235871744");
+
+    body["data"]["options"][0]["value"] = json!(voting_id);
+    test.set_body(body.to_string());
+
+    run_test!(
+        "admin",
+        &test,
+        empty_mock_vec(),
+        [
+            (
+                POST,
+                "/api/v10/users/@me/channels".to_string(),
+                json!({
+                  "id": new_dm_channel_id,
+                  "type": 1,
+                  "last_message_id": null,
+                  "recipients": [],
+                  "application_id": null
+                }),
+            ),
+            (
+                POST,
+                format!("/api/v10/channels/{}/messages", new_dm_channel_id),
+                message_json(new_creator_message_id, new_dm_channel_id),
+            ),
+            (
+                PATCH,
+                format!(
+                    "/api/v10/channels/{}/messages/{}",
+                    old_dm_channel_id, old_creator_message_id
+                ),
+                message_json(old_creator_message_id, old_dm_channel_id),
+            ),
+        ],
+        Ok((
+            http::StatusCode::OK,
+            Json(InteractionResponse {
+                kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    content: Some(format!(
+                        "Voting Who do you prefer? was transferred to <@{}>.",
+                        new_owner_id
+                    )),
+                    flags: Some(MessageFlags::EPHEMERAL),
+                    ..Default::default()
+                }),
+            })
+        )),
+        true
+    );
+
+    let voting = test.data.db.get_voting(voting_id).await.unwrap();
+    assert_eq!(voting.creator_user_id, new_owner_id);
+    assert_eq!(voting.creator_dm_channel_id, new_dm_channel_id);
+    assert_eq!(voting.creator_message_id, new_creator_message_id);
+    assert_eq!(voting.message_id, "3589723985723");
+
+    let events = test.data.db.get_audit(voting_id).await.unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, AuditEventKind::Transferred);
+    assert_eq!(events[0].user_id, "399954205235871744");
+}
+
+#[tokio::test]
+async fn handle_voting_transfer_slow_dm_test() {
+    let mut test = setup_test_env("slash_command.json");
+
+    let voting_id = "4712947128794";
+    let new_owner_id = "82198898841029461";
+
+    test.data
+        .db
+        .save_voting(dd_discord::db::Voting {
+            id: voting_id.to_string(),
+            name: "Who do you prefer?".to_string(),
+            choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+            creator_user_id: "399954205235871744".to_string(),
+            guild_id: "1187313045127581796".to_string(), // slash_command.json
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    let mut dm_channel_mock = test.discord_server.mock(|when, then| {
+        when.method(POST).path("/api/v10/users/@me/channels");
+        then.status(200)
+            .delay(Duration::from_secs(5))
+            .header("Content-Type", "application/json")
+            .json_body(json!({
+              "id": "319674150115610529",
+              "type": 1,
+              "last_message_id": null,
+              "recipients": [],
+              "application_id": null
+            }));
+    });
+
+    let mut body: serde_json::Value = serde_json::from_str(&test.body).unwrap();
+    body["data"]["name"] = json!("voting-transfer");
+    body["data"]["options"] = json!([
+        {"name": "voting_id", "type": 3, "value": voting_id},
+        {"name": "new_owner", "type": 6, "value": new_owner_id},
+    ]);
+    body["member"]["permissions"] = json!("8"); // administrator
+    test.set_body(body.to_string());
+
+    // the dm channel is given up before discord stops waiting for the response
+    let start = tokio::time::Instant::now();
+    let result = handle_interaction(
+        test.data.clone(),
+        test.headers.clone(),
+        test.body.to_string(),
+    )
+    .await;
+    assert!(start.elapsed() < Duration::from_secs(3));
+    assert!(result.is_err());
+
+    let voting = test.data.db.get_voting(voting_id).await.unwrap();
+    assert_eq!(voting.creator_user_id, "399954205235871744");
+
+    dm_channel_mock.delete();
+}
+
+#[tokio::test]
+async fn handle_slash_voting_dm_retry_test() {
+    let test = setup_test_env("slash_command.json");
+
+    let voting_id = "4712947128794";
+    let channel_id = "1187315505103638638"; // from slash_command.json
+    let dm_channel_id = "319674150115610528";
+
+    // the first attempt fails with a server error, the retry gets the channel
+    static ATTEMPTS: AtomicUsize = AtomicUsize::new(0);
+    let mut failing_mock = test.discord_server.mock(|when, then| {
+        when.method(POST)
+            .path("/api/v10/users/@me/channels")
+            .matches(|_| ATTEMPTS.fetch_add(1, Ordering::SeqCst) == 0);
+        then.status(500)
+            .header("Content-Type", "application/json")
+            .json_body(json!({"code": 0, "message": "500: Internal Server Error"}));
+    });
+    let mut dm_channel_mock = test.discord_server.mock(|when, then| {
+        when.method(POST)
+            .path("/api/v10/users/@me/channels")
+            .matches(|_| ATTEMPTS.load(Ordering::SeqCst) > 0);
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(json!({
+              "id": dm_channel_id,
+              "type": 1,
+              "last_message_id": null,
+              "recipients": [],
+              "application_id": null
+            }));
+    });
+
+    run_test!(
+        "dm channel retried",
+        &test,
+        [(
+            POST,
+            "/v1/votings".to_string(),
+            serde_json::json!(&Voting {
+                id: voting_id.to_string(),
+                choices: vec![
+                    "Spinoza".to_string(),
+                    "Kant".to_string(),
+                    "Nietzsche".to_string()
+                ],
+            }),
+        )],
+        [
+            (
+                POST,
+                format!("/api/v10/channels/{}/messages", dm_channel_id),
+                message_json("812746127846425", dm_channel_id),
+            ),
+            (
+                POST,
+                format!("/api/v10/channels/{}/messages", channel_id),
+                message_json("3589723985724", channel_id),
+            ),
+        ],
+        ack(),
+        true
+    );
+
+    failing_mock.assert_hits(1);
+    failing_mock.delete();
+    dm_channel_mock.assert_hits(1);
+    dm_channel_mock.delete();
+
+    let voting = test.data.db.get_voting(voting_id).await.unwrap();
+    assert_eq!(voting.creator_dm_channel_id, dm_channel_id);
+}
+
+#[tokio::test]
+async fn handle_slash_voting_dm_disabled_test() {
+    let test = setup_test_env("slash_command.json");
+
+    let mut dd_mock = create_mock!(
+        test.dd_server,
+        POST,
+        "/v1/votings",
+        serde_json::json!(&Voting {
+            id: "4712947128794".to_string(),
+            choices: vec![
+                "Spinoza".to_string(),
+                "Kant".to_string(),
+                "Nietzsche".to_string()
+            ],
+        })
+    );
+    // dm disabled is not retried
+    let mut dm_mock = test.discord_server.mock(|when, then| {
+        when.method(POST).path("/api/v10/users/@me/channels");
+        then.status(403)
+            .header("Content-Type", "application/json")
+            .json_body(json!({"code": 50007, "message": "Cannot send messages to this user"}));
+    });
+
+    let err = handle_interaction(
+        test.data.clone(),
+        test.headers.clone(),
+        test.body.to_string(),
+    )
+    .await
+    .expect_err("voting should fail");
+
+    dd_mock.assert();
+    dd_mock.delete();
+    dm_mock.assert_hits(1);
+    dm_mock.delete();
+
+    assert_eq!(err, InteractionError::DmUnavailable);
+
+    let response = err.into_response();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        body["data"]["content"],
+        json!("Couldn't open a DM — check your DM privacy settings.")
+    );
+    assert_eq!(body["data"]["flags"], json!(64));
+}
+
+#[tokio::test]
+async fn handle_slash_voting_bot_user_test() {
+    let test = setup_test_env("slash_command.json");
+    let creator_user_id = "399954205235871744"; // from slash_command.json
+
+    // the interaction is relayed as the bot itself
+    test.data
+        .set_bot_user_id(Id::new(creator_user_id.parse().unwrap()));
+
+    let mut dd_mock = create_mock!(
+        test.dd_server,
+        POST,
+        "/v1/votings",
+        serde_json::json!(&Voting {
+            id: "4712947128794".to_string(),
+            choices: vec![
+                "Spinoza".to_string(),
+                "Kant".to_string(),
+                "Nietzsche".to_string()
+            ],
+        })
+    );
+    let mut dm_mock = test.discord_server.mock(|when, then| {
+        when.method(POST).path("/api/v10/users/@me/channels");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(json!({}));
+    });
+
+    let err = handle_interaction(
+        test.data.clone(),
+        test.headers.clone(),
+        test.body.to_string(),
+    )
+    .await
+    .expect_err("voting should fail");
+
+    dd_mock.assert();
+    dd_mock.delete();
+    dm_mock.assert_hits(0);
+    dm_mock.delete();
+
+    assert_eq!(err, InteractionError::BotUser);
+
+    let response = err.into_response();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        body["data"]["content"],
+        json!("The bot can't send direct messages to itself.")
+    );
+    assert_eq!(body["data"]["flags"], json!(64));
+}
+
+#[tokio::test]
+async fn handle_voting_clone_test() {
+    let mut test = setup_test_env("slash_command.json");
+
+    let source_id = "4712947128794";
+    let clone_id = "4712947128795";
+    let creator_id = "399954205235871744"; // from slash_command.json
+    let channel_id = "1187315505103638638"; // from slash_command.json
+    let dm_channel_id = "319674150115610528";
+    let creator_message_id = "812746127846425";
+    let message_id = "3589723985724";
+
+    let source = dd_discord::db::Voting {
+        id: source_id.to_string(),
+        name: "Who do you prefer?".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        channel_id: channel_id.to_string(),
+        message_id: "3589723985723".to_string(),
+        creator_message_id: "812746127846424".to_string(),
+        creator_dm_channel_id: dm_channel_id.to_string(),
+        creator_user_id: creator_id.to_string(),
+        guild_id: "1187313045127581796".to_string(), // from slash_command.json
+        display_order: Some(vec![1, 0]),
+        max_ranks: Some(1),
+        ..Default::default()
+    };
+    test.data.db.save_voting(source.clone()).await.unwrap();
+    test.data
+        .db
+        .save_voting(dd_discord::db::Voting {
+            id: "4712947128796".to_string(),
+            guild_id: "1187313045127581797".to_string(),
+            allowed_role_id: Some("1187313045127581798".to_string()),
+            ..source.clone()
+        })
+        .await
+        .unwrap();
+    test.data
+        .db
+        .complete_open_voting(source_id, "creator")
+        .await
+        .unwrap();
+
+    let mut body: serde_json::Value = serde_json::from_str(&test.body).unwrap();
+    body["data"]["name"] = json!("voting-clone");
+    body["data"]["options"] = json!([{"name": "voting_id", "type": 3, "value": "unknown"}]);
+    test.set_body(body.to_string());
+
+    run_test!(
+        "unknown voting",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        Ok((
+            http::StatusCode::OK,
+            Json(InteractionResponse {
+                kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    content: Some("Voting not found.".to_string()),
+                    flags: Some(MessageFlags::EPHEMERAL),
+                    ..Default::default()
+                }),
+            })
+        )),
+        true
+    );
+
+    // votings of other guilds can't be cloned
+    body["data"]["options"] = json!([{"name": "voting_id", "type": 3, "value": "4712947128796"}]);
+    test.set_body(body.to_string());
+
+    run_test!(
+        "voting of another guild",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        Ok((
+            http::StatusCode::OK,
+            Json(InteractionResponse {
+                kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    content: Some("Voting not found.".to_string()),
+                    flags: Some(MessageFlags::EPHEMERAL),
+                    ..Default::default()
+                }),
+            })
+        )),
+        true
+    );
+
+    body["data"]["options"] = json!([{"name": "voting_id", "type": 3, "value": source_id}]);
+    test.set_body(body.to_string());
+
+    run_test!(
+        "clone completed voting",
+        &test,
+        [(
+            POST,
+            "/v1/votings".to_string(),
+            serde_json::json!(&Voting {
+                id: clone_id.to_string(),
+                choices: source.choices.clone(),
+            }),
+        )],
+        [
+            (
+                POST,
+                "/api/v10/users/@me/channels".to_string(),
+                json!({
+                  "id": dm_channel_id,
+                  "type": 1,
+                  "last_message_id": null,
+                  "recipients": [],
+                  "application_id": null
+                }),
+            ),
+            (
+                POST,
+                format!("/api/v10/channels/{}/messages", dm_channel_id),
+                message_json(creator_message_id, dm_channel_id),
+            ),
+            (
+                POST,
+                format!("/api/v10/channels/{}/messages", channel_id),
+                message_json(message_id, channel_id),
+            ),
+        ],
+        ack(),
+        true
+    );
+
+    let clone = test.data.db.get_voting(clone_id).await.unwrap();
+    assert_eq!(
+        clone,
+        dd_discord::db::Voting {
+            id: clone_id.to_string(),
+            name: source.name.clone(),
+            choices: source.choices.clone(),
+            channel_id: channel_id.to_string(),
+            message_id: message_id.to_string(),
+            creator_message_id: creator_message_id.to_string(),
+            creator_dm_channel_id: dm_channel_id.to_string(),
+            creator_user_id: creator_id.to_string(),
+            guild_id: "1187313045127581796".to_string(), // slash_command.json
+            max_ranks: Some(1),
+            version: 1, // the message ids are set after saving
+            ..Default::default()
+        }
+    );
+
+    // the source voting is left as it was
+    let source = test.data.db.get_voting(source_id).await.unwrap();
+    assert!(source.is_completed);
+    assert_eq!(source.message_id, "3589723985723");
+}
+
+#[tokio::test]
+async fn handle_slash_voting_choice_length_test() {
+    let mut test = setup_test_env("slash_command.json");
+
+    let mut body: serde_json::Value = serde_json::from_str(&test.body).unwrap();
+    body["data"]["options"][2]["value"] = json!("Kant ".repeat(MAX_CHOICE_LENGTH / 5 + 1));
+    test.set_body(body.to_string());
+
+    run_test!(
+        "over-length choice",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        Ok((
+            http::StatusCode::OK,
+            Json(InteractionResponse {
+                kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    content: Some(format!(
+                        "Choice 2 (\"{}…\") is longer than {} characters.",
+                        "Kant ".repeat(8),
+                        MAX_CHOICE_LENGTH
+                    )),
+                    flags: Some(MessageFlags::EPHEMERAL),
+                    ..Default::default()
+                }),
+            })
+        )),
+        true
+    );
+
+    let (votings, _) = test.data.db.get_votings_page(None, 10, true).await.unwrap();
+    assert!(votings.is_empty());
+}
+
+#[tokio::test]
+async fn handle_slash_voting_invalid_choices_test() {
+    let mut test = setup_test_env("slash_command.json");
+
+    for (assertion, choice, expected) in [
+        (
+            "duplicate choice",
+            " kant ",
+            "Choice \"kant\" is listed more than once.",
+        ),
+        ("empty choice", "   ", "Choice 3 is empty."),
+    ] {
+        let mut body: serde_json::Value = serde_json::from_str(&test.body).unwrap();
+        body["data"]["options"][3]["value"] = json!(choice);
+        test.set_body(body.to_string());
+
+        // no dd mocks, the voting is rejected before it is created
+        run_test!(
+            assertion,
+            &test,
+            empty_mock_vec(),
+            empty_mock_vec(),
+            Ok((
+                http::StatusCode::OK,
+                Json(InteractionResponse {
+                    kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                    data: Some(InteractionResponseData {
+                        content: Some(expected.to_string()),
+                        flags: Some(MessageFlags::EPHEMERAL),
+                        ..Default::default()
+                    }),
+                })
+            )),
+            true
+        );
+    }
+
+    let (votings, _) = test.data.db.get_votings_page(None, 10, true).await.unwrap();
+    assert!(votings.is_empty());
+}
+
+#[tokio::test]
+async fn handle_voting_config_test() {
+    let mut test = setup_test_env("slash_command.json");
+    let guild_id = "1187313045127581796"; // slash_command.json
+
+    let response = |content: &str| {
+        Ok((
+            http::StatusCode::OK,
+            Json(InteractionResponse {
+                kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    content: Some(content.to_string()),
+                    flags: Some(MessageFlags::EPHEMERAL),
+                    ..Default::default()
+                }),
+            }),
+        ))
+    };
+
+    let mut body: serde_json::Value = serde_json::from_str(&test.body).unwrap();
+    body["data"]["name"] = json!("voting-config");
+    body["data"]["options"] = json!([
+        {"name": "max_choices", "type": 4, "value": 5},
+        {"name": "max_active_votings", "type": 4, "value": 3},
+    ]);
+    body["member"]["permissions"] = json!("8"); // administrator
+    test.set_body(body.to_string());
+
+    run_test!(
+        "set max choices",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        response("Anonymous by default: no\nMin choices: 2\nMax choices: 5\nMax active votings per member: 3"),
+        true
+    );
+
+    // the max falls back to the default of the bot, no limit
+    body["data"]["options"] = json!([{"name": "reset", "type": 3, "value": "max_choices"}]);
+    test.set_body(body.to_string());
+
+    run_test!(
+        "reset max choices",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        response("Anonymous by default: no\nMin choices: 2\nMax choices: no limit\nMax active votings per member: 3"),
+        true
+    );
+
+    let config = test.data.db.get_guild_config(guild_id).await.unwrap();
+    assert_eq!(config.max_choices, None);
+    assert_eq!(config.max_active_votings_per_creator, Some(3));
+
+    // a setting given along with its reset is kept
+    body["data"]["options"] = json!([
+        {"name": "max_choices", "type": 4, "value": 4},
+        {"name": "reset", "type": 3, "value": "max_choices"},
+    ]);
+    test.set_body(body.to_string());
+
+    run_test!(
+        "reset and set max choices",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        response("Anonymous by default: no\nMin choices: 2\nMax choices: 4\nMax active votings per member: 3"),
+        true
+    );
+
+    // a min above the max is rejected and nothing is saved
+    body["data"]["options"] = json!([{"name": "min_choices", "type": 4, "value": 5}]);
+    test.set_body(body.to_string());
+
+    run_test!(
+        "min above max choices",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        response("Min choices (5) can not be more than max choices (4)."),
+        true
+    );
+
+    let config = test.data.db.get_guild_config(guild_id).await.unwrap();
+    assert_eq!(config.min_choices, None);
+    assert_eq!(config.max_choices, Some(4));
+}
+
+#[tokio::test]
+async fn handle_slash_voting_guild_config_test() {
+    let test = setup_test_env("slash_command.json");
+    let guild_id = "1187313045127581796"; // from slash_command.json
+
+    test.data
+        .db
+        .set_guild_config(
+            guild_id,
+            GuildConfig {
+                max_choices: Some(2),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Failed to save guild config");
+
+    // the command has 3 choices, more than the guild allows
+    run_test!(
+        "guild max choices",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        Ok((
+            http::StatusCode::OK,
+            Json(InteractionResponse {
+                kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    content: Some("This server allows at most 2 choices.".to_string()),
+                    flags: Some(MessageFlags::EPHEMERAL),
+                    ..Default::default()
+                }),
+            })
+        )),
+        true
+    );
+
+    let (votings, _) = test.data.db.get_votings_page(None, 10, true).await.unwrap();
+    assert!(votings.is_empty());
+}
+
+#[tokio::test]
+async fn handle_slash_voting_choice_bounds_test() {
+    let guild_id = "1187313045127581796"; // from slash_command.json
+    let channel_id = "1187315505103638638"; // from slash_command.json
+    let dm_channel_id = "319674150115610528";
+
+    let rejected = |content: &str| -> dd_discord::InteractionResult {
+        Ok((
+            http::StatusCode::OK,
+            Json(InteractionResponse {
+                kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    content: Some(content.to_string()),
+                    flags: Some(MessageFlags::EPHEMERAL),
+                    ..Default::default()
+                }),
+            }),
+        ))
+    };
+
+    // the command has 3 choices
+    let test = setup_test_env("slash_command.json");
+    test.data
+        .db
+        .set_guild_config(
+            guild_id,
+            GuildConfig {
+                min_choices: Some(4),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Failed to save guild config");
+
+    run_test!(
+        "guild min choices",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        rejected("This server requires at least 4 choices."),
+        true
+    );
+
+    // the global bounds apply when the guild has none
+    let test = setup_test_env_with_config(
+        "slash_command.json",
+        dd_discord::Config {
+            max_choices: Some(2),
+            ..Default::default()
+        },
+    );
+
+    run_test!(
+        "global max choices",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        rejected("Voting can have at most 2 choices."),
+        true
+    );
+
+    let (votings, _) = test.data.db.get_votings_page(None, 10, true).await.unwrap();
+    assert!(votings.is_empty());
+
+    // exactly at the bounds of the guild
+    test.data
+        .db
+        .set_guild_config(
+            guild_id,
+            GuildConfig {
+                min_choices: Some(3),
+                max_choices: Some(3),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Failed to save guild config");
+
+    run_test!(
+        "choices within bounds",
+        &test,
+        [(
+            POST,
+            "/v1/votings".to_string(),
+            serde_json::json!(&Voting {
+                id: "4712947128794".to_string(),
+                choices: vec![
+                    "Spinoza".to_string(),
+                    "Kant".to_string(),
+                    "Nietzsche".to_string()
+                ],
+            }),
+        )],
+        [
+            (
+                POST,
+                "/api/v10/users/@me/channels".to_string(),
+                json!({
+                  "id": dm_channel_id,
+                  "type": 1,
+                  "last_message_id": null,
+                  "recipients": [],
+                  "application_id": null
+                }),
+            ),
+            (
+                POST,
+                format!("/api/v10/channels/{}/messages", dm_channel_id),
+                message_json("812746127846425", dm_channel_id),
+            ),
+            (
+                POST,
+                format!("/api/v10/channels/{}/messages", channel_id),
+                message_json("3589723985724", channel_id),
+            ),
+        ],
+        ack(),
+        true
+    );
+
+    let voting = test.data.db.get_voting("4712947128794").await.unwrap();
+    assert_eq!(voting.choices.len(), 3);
+}
+
+#[tokio::test]
+async fn handle_vote_channel_long_choices_test() {
+    let custom_uuid = "df4db2bc-9fd1-43fb-8e17-97170379159a"; // vote_channel.json
+
+    let long_choice = "Spinoza, because of the Ethics demonstrated in geometrical order ".repeat(3);
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec![long_choice.clone(), "Kant".to_string()],
+        channel_id: "1187315505103638638".to_string(),
+        message_id: "3589723985723".to_string(),
+        name: "Who do you prefer?".to_string(),
+        in_channel: true,
+        ..Default::default()
+    };
+
+    let test = setup_test_env("vote_channel.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![(
+            custom_uuid.to_string(),
+            CustomID {
+                action: Action::VoteFromChannel,
+                voting_id: voting.id.clone(),
+                user_id: None,
+                page: None,
+                index: None,
+            },
+        )])
+        .await
+        .expect("Failed to save custom ids");
+
+    let (_, response) = handle_interaction(
+        test.data.clone(),
+        test.headers.clone(),
+        test.body.to_string(),
+    )
+    .await
+    .expect("vote channel failed");
+    let embeds = response
+        .0
+        .data
+        .and_then(|data| data.embeds)
+        .expect("response should carry the dialog");
+
+    // every choice of the page gets its own field instead of a line of the description
+    assert_eq!(embeds[0].description, None);
+    let fields: Vec<(&str, &str)> = embeds[0]
+        .fields
+        .iter()
+        .map(|field| (field.name.as_str(), field.value.as_str()))
+        .collect();
+    assert_eq!(
+        fields,
+        vec![("Choice 1", long_choice.as_str()), ("Choice 2", "Kant")]
+    );
+}
+
+#[tokio::test]
+async fn handle_slash_voting_image_test() {
+    let mut test = setup_test_env("slash_command.json");
+
+    let channel_id = "1187315505103638638"; // from slash_command.json
+    let dm_channel_id = "319674150115610528";
+    let image_url = "https://example.com/banner.png";
+
+    let mut body: serde_json::Value = serde_json::from_str(&test.body).unwrap();
+    let options = body["data"]["options"].as_array().unwrap().clone();
+
+    let mut with_image = options.clone();
+    with_image
+        .push(json!({"name": "image_url", "type": 3, "value": "ftp://example.com/banner.png"}));
+    body["data"]["options"] = json!(with_image);
+    test.set_body(body.to_string());
+
+    run_test!(
+        "non http image url",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        Ok((
+            http::StatusCode::OK,
+            Json(InteractionResponse {
+                kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    content: Some("The image url must be an http or https link.".to_string()),
+                    flags: Some(MessageFlags::EPHEMERAL),
+                    ..Default::default()
+                }),
+            })
+        )),
+        true
+    );
+
+    for (voting_id, image) in [("4712947128794", Some(image_url)), ("4712947128795", None)] {
+        let mut options = options.clone();
+        if let Some(image) = image {
+            options.push(json!({"name": "image_url", "type": 3, "value": image}));
+        }
+        body["data"]["options"] = json!(options);
+        test.set_body(body.to_string());
+
+        let mut announcement_mock = test.discord_server.mock(|when, then| {
+            let when = when
+                .method(POST)
+                .path(format!("/api/v10/channels/{}/messages", channel_id));
+            match image {
+                Some(image) => when.body_contains(format!(r#""image":{{"url":"{}"}}"#, image)),
+                None => when.matches(|req| {
+                    !String::from_utf8_lossy(req.body.as_deref().unwrap_or_default())
+                        .contains(r#""image""#)
+                }),
+            };
+            then.status(200)
+                .header("Content-Type", "application/json")
+                .json_body(message_json("3589723985724", channel_id));
+        });
+
+        run_test!(
+            "announcement image",
+            &test,
+            [(
+                POST,
+                "/v1/votings".to_string(),
+                serde_json::json!(&Voting {
+                    id: voting_id.to_string(),
+                    choices: vec![
+                        "Spinoza".to_string(),
+                        "Kant".to_string(),
+                        "Nietzsche".to_string()
+                    ],
+                }),
+            )],
+            [
+                (
+                    POST,
+                    "/api/v10/users/@me/channels".to_string(),
+                    json!({
+                      "id": dm_channel_id,
+                      "type": 1,
+                      "last_message_id": null,
+                      "recipients": [],
+                      "application_id": null
+                    }),
+                ),
+                (
+                    POST,
+                    format!("/api/v10/channels/{}/messages", dm_channel_id),
+                    message_json("812746127846425", dm_channel_id),
+                ),
+            ],
+            ack(),
+            true
+        );
+
+        announcement_mock.assert();
+        announcement_mock.delete();
+
+        let voting = test.data.db.get_voting(voting_id).await.unwrap();
+        assert_eq!(voting.image_url.as_deref(), image);
+    }
+}
+
+#[tokio::test]
+async fn handle_slash_voting_template_test() {
+    let mut test = setup_test_env("slash_command.json");
+
+    let guild_id = "1187313045127581796"; // from slash_command.json
+    let dm_channel_id = "319674150115610528";
+    let channel_id = "1187315505103638638"; // from slash_command.json
+
+    test.data
+        .db
+        .save_template(
+            guild_id,
+            "philosophers",
+            vec![
+                "Spinoza".to_string(),
+                "Kant | Critique of Pure Reason".to_string(),
+                "Nietzsche".to_string(),
+            ],
+        )
+        .await
+        .unwrap();
+
+    let mut body: serde_json::Value = serde_json::from_str(&test.body).unwrap();
+    body["data"]["options"] = json!([
+        {"name": "name", "type": 3, "value": "Who do you prefer?"},
+        {"name": "template", "type": 3, "value": "missing"},
+    ]);
+    test.set_body(body.to_string());
+
+    run_test!(
+        "unknown template",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        Ok((
+            http::StatusCode::OK,
+            Json(InteractionResponse {
+                kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    content: Some("Template \"missing\" not found.".to_string()),
+                    flags: Some(MessageFlags::EPHEMERAL),
+                    ..Default::default()
+                }),
+            })
+        )),
+        true
+    );
+
+    body["data"]["options"][1]["value"] = json!("philosophers");
+    body["data"]["options"]
+        .as_array_mut()
+        .unwrap()
+        .push(json!({"name": "choice_descriptions", "type": 5, "value": true}));
+    test.set_body(body.to_string());
+
+    // the choices and their descriptions come from the template
+    run_test!(
+        "voting from template",
+        &test,
+        [(
+            POST,
+            "/v1/votings".to_string(),
+            serde_json::json!(&Voting {
+                id: "4712947128794".to_string(),
+                choices: vec![
+                    "Spinoza".to_string(),
+                    "Kant".to_string(),
+                    "Nietzsche".to_string()
+                ],
+            }),
+        )],
+        [
+            (
+                POST,
+                "/api/v10/users/@me/channels".to_string(),
+                json!({
+                  "id": dm_channel_id,
+                  "type": 1,
+                  "last_message_id": null,
+                  "recipients": [],
+                  "application_id": null
+                }),
+            ),
+            (
+                POST,
+                format!("/api/v10/channels/{}/messages", dm_channel_id),
+                message_json("812746127846424", dm_channel_id),
+            ),
+            (
+                POST,
+                format!("/api/v10/channels/{}/messages", channel_id),
+                message_json("3589723985723", channel_id),
+            ),
+        ],
+        ack(),
+        true
+    );
+
+    let voting = test.data.db.get_voting("4712947128794").await.unwrap();
+    assert_eq!(voting.choices, vec!["Spinoza", "Kant", "Nietzsche"]);
+    assert_eq!(
+        voting.choice_description(1),
+        Some("Critique of Pure Reason")
+    );
+}
+
+#[tokio::test]
+async fn handle_voting_debug_test() {
+    let mut test = setup_test_env("slash_command.json");
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        name: "Who do you prefer?".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        guild_id: "1187313045127581796".to_string(), // slash_command.json
+        ..Default::default()
+    };
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .save_voting(dd_discord::db::Voting {
+            id: "4712947128795".to_string(),
+            name: "Foreign voting".to_string(),
+            choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+            guild_id: "1187313045127581797".to_string(),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to save voting");
+
+    let mut body: serde_json::Value = serde_json::from_str(&test.body).unwrap();
+    body["data"]["name"] = json!("voting-debug");
+    body["data"]["options"] = json!([{"name": "voting_id", "type": 3, "value": voting.id}]);
+    body["member"]["permissions"] = json!("0");
+    test.set_body(body.to_string());
+
+    let response = |content: String| {
+        Ok((
+            http::StatusCode::OK,
+            Json(InteractionResponse {
+                kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    content: Some(content),
+                    flags: Some(MessageFlags::EPHEMERAL),
+                    ..Default::default()
+                }),
+            }),
+        ))
+    };
+
+    run_test!(
+        "not an admin",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        response("Only server administrators can debug votings.".to_string()),
+        true
+    );
+
+    // administrator
+    body["member"]["permissions"] = json!("8");
+    test.set_body(body.to_string());
+
+    run_test!(
+        "stored voting",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        response(format!("```json\n{}\n```", String::from(&voting))),
+        true
+    );
+
+    body["data"]["options"] = json!([{"name": "voting_id", "type": 3, "value": "4712947128795"}]);
+    test.set_body(body.to_string());
+
+    run_test!(
+        "voting of another guild",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        response("Voting not found.".to_string()),
+        true
+    );
+
+    body["data"]["options"] = json!([{"name": "voting_id", "type": 3, "value": "missing"}]);
+    test.set_body(body.to_string());
+
+    run_test!(
+        "missing voting",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        response("Voting not found.".to_string()),
+        true
+    );
+}
+
+#[tokio::test]
+async fn handle_votings_test() {
+    let mut test = setup_test_env("slash_command.json");
+
+    for i in 0..12 {
+        test.data
+            .db
+            .save_voting(dd_discord::db::Voting {
+                id: format!("47129471287{:02}", i),
+                name: format!("voting {}", i),
+                choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+                creator_user_id: "399954205235871744".to_string(),
+                guild_id: "1187313045127581796".to_string(), // slash_command.json
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+    }
+    // votings of other guilds are not listed
+    test.data
+        .db
+        .save_voting(dd_discord::db::Voting {
+            id: "4712947128699".to_string(),
+            name: "foreign voting".to_string(),
+            choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+            creator_user_id: "399954205235871744".to_string(),
+            guild_id: "2287313045127581796".to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    // page buttons are clicked in the guild of the listing
+    let page_click_body = |custom_id: &str, guild_id: &str| {
+        let mut body: serde_json::Value =
+            serde_json::from_str(&dm_component_body(custom_id, &[])).unwrap();
+        body["guild_id"] = json!(guild_id);
+        body.to_string()
+    };
+    let response = |content: String| {
+        Ok((
+            http::StatusCode::OK,
+            Json(InteractionResponse {
+                kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    content: Some(content),
+                    flags: Some(MessageFlags::EPHEMERAL),
+                    ..Default::default()
+                }),
+            }),
+        ))
+    };
+
+    let mut body: serde_json::Value = serde_json::from_str(&test.body).unwrap();
+    body["data"]["name"] = json!("votings");
+    body["data"]["options"] = json!([]);
+    body["member"]["permissions"] = json!("0");
+    test.set_body(body.to_string());
+
+    run_test!(
+        "not an admin",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        Ok((
+            http::StatusCode::OK,
+            Json(InteractionResponse {
+                kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    content: Some("Only server administrators can list votings.".to_string()),
+                    flags: Some(MessageFlags::EPHEMERAL),
+                    ..Default::default()
+                }),
+            })
+        )),
+        true
+    );
+
+    body["member"]["permissions"] = json!("8"); // ADMINISTRATOR
+    test.set_body(body.to_string());
+
+    let (status, response) = handle_interaction(
+        test.data.clone(),
+        test.headers.clone(),
+        test.body.to_string(),
+    )
+    .await
+    .expect("listing votings should succeed");
+
+    assert_eq!(status, StatusCode::OK);
+    let data = response.0.data.expect("response data should be set");
+    assert_eq!(data.flags, Some(MessageFlags::EPHEMERAL));
+    let embeds = data.embeds.expect("embeds should be set");
+    assert_eq!(embeds[0].title.as_deref(), Some("Votings - Page 1"));
+    let description = embeds[0].description.clone().unwrap();
+    assert_eq!(description.lines().count(), 10);
+    assert!(description.starts_with("`4712947128700` voting 0 by <@399954205235871744> (Active)"));
+
+    let components = data.components.expect("components should be set");
+    let Component::ActionRow(ref row) = components[0] else {
+        panic!("expected action row");
+    };
+    assert_eq!(row.components.len(), 1);
+    let Component::Button(ref next) = row.components[0] else {
+        panic!("expected next button");
+    };
+    assert_eq!(next.label.as_deref(), Some("Next"));
+
+    let next_custom_id = next.custom_id.clone().unwrap();
+    assert!(next_custom_id.starts_with("votings-"));
+    assert!(test
+        .data
+        .db
+        .get_custom_id(&next_custom_id)
+        .await
+        .is_err_and(|err| err == dd_discord::db::DbError::NotFound));
+
+    test.set_body(page_click_body(&next_custom_id, "2287313045127581796"));
+    run_test!(
+        "page of another guild",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        response("This list has expired, please run /votings again.".to_string()),
+        true
+    );
+
+    test.set_body(page_click_body(
+        "votings-6a4ba7a4-0e0c-4b5e-8a5a-5dc6f25e3f4e",
+        "1187313045127581796",
+    ));
+    run_test!(
+        "unknown page",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        response("This list has expired, please run /votings again.".to_string()),
+        true
+    );
+
+    test.set_body(page_click_body(&next_custom_id, "1187313045127581796"));
+    let (status, response) = handle_interaction(
+        test.data.clone(),
+        test.headers.clone(),
+        test.body.to_string(),
+    )
+    .await
+    .expect("next page should succeed");
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        response.0.kind,
+        twilight_model::http::interaction::InteractionResponseType::UpdateMessage
+    );
+    let data = response.0.data.expect("response data should be set");
+    let embeds = data.embeds.expect("embeds should be set");
+    assert_eq!(embeds[0].title.as_deref(), Some("Votings - Page 2"));
+    assert_eq!(
+        embeds[0].description.as_deref(),
+        Some(
+            "`4712947128710` voting 10 by <@399954205235871744> (Active)\n\
+             `4712947128711` voting 11 by <@399954205235871744> (Active)"
+        )
+    );
+
+    let components = data.components.expect("components should be set");
+    let Component::ActionRow(ref row) = components[0] else {
+        panic!("expected action row");
+    };
+    assert_eq!(row.components.len(), 1);
+    let Component::Button(ref previous) = row.components[0] else {
+        panic!("expected previous button");
+    };
+    assert_eq!(previous.label.as_deref(), Some("Previous"));
+
+    test.set_body(page_click_body(
+        previous.custom_id.as_deref().unwrap(),
+        "1187313045127581796",
+    ));
+    let (_, response) = handle_interaction(
+        test.data.clone(),
+        test.headers.clone(),
+        test.body.to_string(),
+    )
+    .await
+    .expect("previous page should succeed");
+
+    let data = response.0.data.expect("response data should be set");
+    let embeds = data.embeds.expect("embeds should be set");
+    assert_eq!(embeds[0].title.as_deref(), Some("Votings - Page 1"));
+    let description = embeds[0].description.clone().unwrap();
+    assert_eq!(description.lines().count(), 10);
+    assert!(description.starts_with("`4712947128700` voting 0"));
+    assert!(!description.contains("foreign voting"));
+}
+
+fn empty_mock_vec() -> Vec<(httpmock::Method, &'static str, serde_json::Value)> {
+    vec![]
+}
+
+#[tokio::test]
+async fn handle_vote_channel_test() {
+    let custom_uuid = "df4db2bc-9fd1-43fb-8e17-97170379159a";
+    let dm_channel_id = "319674150115610528";
+    let user_id = "82198898841029460"; // vote_channel.json
+    let creator_message_id = "812746127846424";
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec![
+            "Spinoza".to_string(),
+            "Kant".to_string(),
+            "Nietzsche".to_string(),
+        ],
+        channel_id: "1187315505103638638".to_string(),
+        message_id: "3589723985723".to_string(),
+        name: "Who do you prefer?".to_string(),
+        is_completed: false,
+        is_deleted: false,
+        creator_message_id: creator_message_id.to_string(),
+        creator_dm_channel_id: dm_channel_id.to_string(),
+        ..Default::default()
+    };
+
+    let test = setup_test_env("vote_channel.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![
+            (
+                util::generate_random_custom_uuid(),
+                CustomID {
+                    action: Action::Complete,
+                    voting_id: voting.id.clone(),
+                    user_id: None,
+                    page: None,
+                    index: None,
+                },
+            ),
+            (
+                util::generate_random_custom_uuid(),
+                CustomID {
+                    action: Action::Delete,
+                    voting_id: voting.id.clone(),
+                    user_id: None,
+                    page: None,
+                    index: None,
+                },
+            ),
+            (
+                custom_uuid.to_string(),
+                CustomID {
+                    action: Action::VoteFromChannel,
+                    voting_id: voting.id.clone(),
+                    user_id: None,
+                    page: None,
+                    index: None,
+                },
+            ),
+        ])
+        .await
+        .expect("Failed to save custom ids");
+
+    let discord_client_happy_mocks = || -> Vec<(httpmock::Method, String, serde_json::Value)> {
+        vec![
+            (
+                POST,
+                "/api/v10/users/@me/channels".to_string(),
+                json!({
+                  "id": dm_channel_id,
+                  "type": 1,
+                  "last_message_id": null,
+                  "recipients": [
+                    {
+                      "username": "test",
+                      "discriminator": "9999",
+                      "id": user_id,
+                      "avatar": "33ecab261d4681afa4d85a04691c4a01"
+                    }
+                  ],
+                  "application_id": null
+                }),
+            ),
+            (
+                POST,
+                format!("/api/v10/channels/{}/messages", dm_channel_id),
+                json!({
+                            "attachments": [],
+                            "author": {
+                              "username": "test",
+                              "discriminator": "9999",
+                              "id": user_id,
+                              "avatar": "33ecab261d4681afa4d85a04691c4a01"
+                            },
+                            "channel_id": dm_channel_id,
+                            "content": "test",
+                            "edited_timestamp": null,
+                            "embeds": [],
+                            "flags": 0,
+                            "id": creator_message_id,
+                            "mention_everyone": false,
+                            "mention_roles": [],
+                            "mentions": [],
+                            "pinned": false,
+                            "timestamp": "2018-02-04T19:51:45.941000+00:00",
+                            "tts": false,
+                            "type": 0
+                }),
+            ),
+        ]
+    };
+
+    let mocks = run_test!(
+      "happy path",
+      &test,
+     empty_mock_vec(),
+     discord_client_happy_mocks(),
+      Ok((http::StatusCode::OK, Json(InteractionResponse{
+        kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+        data: Some(InteractionResponseData {
+            content: Some("You will receive dm with voting dialog".to_string()),
+            flags: Some(MessageFlags::EPHEMERAL),
+            ..Default::default()
+        }),
+      }))),
+    false);
+
+    let start = tokio::time::Instant::now();
+    let timeout_duration = Duration::from_secs(5);
+
+    let voting_dialog = loop {
+        match test.data.db.get_voting_dialog(&voting.id, user_id).await {
+            Ok(voting_dialog) => break voting_dialog,
+            Err(_) => {
+                if start.elapsed() > timeout_duration {
+                    panic!("get voting dialog timeout");
+                }
+
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        }
+    };
+
+    assert_eq!(voting_dialog.voting_id, voting.id);
+
+    test.wait_for_tasks().await;
+    for mut mock in mocks {
+        mock.assert();
+        mock.delete();
+    }
+
+    let custom_ids = test.data.db.get_custom_ids(&voting.id).await.unwrap();
+    assert_eq!(custom_ids.len(), 8);
+}
+
+#[tokio::test]
+async fn handle_vote_channel_dm_failure_test() {
+    let custom_uuid = "df4db2bc-9fd1-43fb-8e17-97170379159a"; // vote_channel.json
+    let dm_channel_id = "319674150115610528";
+    let dm_message_id = "812746127846424";
+    let user_id = "82198898841029460"; // vote_channel.json
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        channel_id: "1187315505103638638".to_string(),
+        message_id: "3589723985723".to_string(),
+        name: "Who do you prefer?".to_string(),
+        ..Default::default()
+    };
+
+    let test = setup_test_env("vote_channel.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![(
+            custom_uuid.to_string(),
+            CustomID {
+                action: Action::VoteFromChannel,
+                voting_id: voting.id.clone(),
+                user_id: None,
+                page: None,
+                index: None,
+            },
+        )])
+        .await
+        .expect("Failed to save custom ids");
+
+    let mut reply_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH)
+            .path(test.original_response_path())
+            .body_contains("Sending the voting dialog failed, please click vote again.");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json(dm_message_id, dm_channel_id));
+    });
+
+    let mocks = run_test!(
+        "dm channel creation fails",
+        &test,
+        empty_mock_vec(),
+        [(
+            POST,
+            "/api/v10/users/@me/channels",
+            json!({
+              "error": "error",
+            })
+        )],
+        Ok((
+            http::StatusCode::OK,
+            Json(InteractionResponse {
+                kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    content: Some("You will receive dm with voting dialog".to_string()),
+                    flags: Some(MessageFlags::EPHEMERAL),
+                    ..Default::default()
+                }),
+            })
+        )),
+        false
+    );
+
+    test.wait_for_tasks().await;
+    for mut mock in mocks {
+        mock.assert();
+        mock.delete();
+    }
+    reply_mock.assert();
+    reply_mock.delete();
+
+    // the placeholder dialog was rolled back, so it doesn't block the next click
+    let err = test
+        .data
+        .db
+        .get_voting_dialog(&voting.id, user_id)
+        .await
+        .expect_err("placeholder dialog should be removed");
+    assert_eq!(err, dd_discord::db::DbError::NotFound);
+
+    let mocks = run_test!(
+        "retry after failed dm",
+        &test,
+        empty_mock_vec(),
+        [
+            (
+                POST,
+                "/api/v10/users/@me/channels".to_string(),
+                json!({
+                  "id": dm_channel_id,
+                  "type": 1,
+                  "last_message_id": null,
+                  "recipients": [],
+                  "application_id": null
+                }),
+            ),
+            (
+                POST,
+                format!("/api/v10/channels/{}/messages", dm_channel_id),
+                message_json(dm_message_id, dm_channel_id),
+            ),
+        ],
+        Ok((
+            http::StatusCode::OK,
+            Json(InteractionResponse {
+                kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    content: Some("You will receive dm with voting dialog".to_string()),
+                    flags: Some(MessageFlags::EPHEMERAL),
+                    ..Default::default()
+                }),
+            })
+        )),
+        false
+    );
+
+    test.wait_for_tasks().await;
+    for mut mock in mocks {
+        mock.assert();
+        mock.delete();
+    }
+
+    let dialog = test
+        .data
+        .db
+        .get_voting_dialog(&voting.id, user_id)
+        .await
+        .expect("voting dialog should be saved");
+    assert_eq!(dialog.message_id, dm_message_id);
+    assert_eq!(dialog.channel_id, dm_channel_id);
+}
+
+#[tokio::test]
+async fn handle_vote_channel_dm_disabled_test() {
+    let custom_uuid = "df4db2bc-9fd1-43fb-8e17-97170379159a"; // vote_channel.json
+    let user_id = "82198898841029460"; // vote_channel.json
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        channel_id: "1187315505103638638".to_string(),
+        message_id: "3589723985723".to_string(),
+        name: "Who do you prefer?".to_string(),
+        ..Default::default()
+    };
+
+    let test = setup_test_env("vote_channel.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![(
+            custom_uuid.to_string(),
+            CustomID {
+                action: Action::VoteFromChannel,
+                voting_id: voting.id.clone(),
+                user_id: None,
+                page: None,
+                index: None,
+            },
+        )])
+        .await
+        .expect("Failed to save custom ids");
+
+    let mut dm_mock = test.discord_server.mock(|when, then| {
+        when.method(POST).path("/api/v10/users/@me/channels");
+        then.status(403)
+            .header("Content-Type", "application/json")
+            .json_body(json!({"code": 50007, "message": "Cannot send messages to this user"}));
+    });
+
+    // the dialog is shown in the ephemeral vote reply instead of a dm
+    let mut reply_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH)
+            .path(test.original_response_path())
+            .body_contains("We couldn't send you a dm")
+            .body_contains(r#""embeds":[{"#)
+            .body_contains(r#""components":[{"#);
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json("1202277900045262871", "1187315505103638638"));
+    });
+
+    let (status, response) = handle_interaction(
+        test.data.clone(),
+        test.headers.clone(),
+        test.body.to_string(),
+    )
+    .await
+    .expect("dm disabled should fall back to the channel");
+
+    assert_eq!(status, StatusCode::OK);
+    let data = response.0.data.expect("response data should be set");
+    assert_eq!(data.flags, Some(MessageFlags::EPHEMERAL));
+
+    test.wait_for_tasks().await;
+    dm_mock.assert();
+    dm_mock.delete();
+    reply_mock.assert();
+    reply_mock.delete();
+
+    // the dialog has no dm message, the vote dialog is still tracked for the user
+    let dialog = test
+        .data
+        .db
+        .get_voting_dialog(&voting.id, user_id)
+        .await
+        .expect("voting dialog should be saved");
+    assert_eq!(dialog.message_id, "");
+    assert_eq!(dialog.ballot, vec![0, 0]);
+}
+
+#[tokio::test]
+async fn handle_vote_channel_slow_dm_test() {
+    let custom_uuid = "df4db2bc-9fd1-43fb-8e17-97170379159a"; // vote_channel.json
+    let dm_channel_id = "319674150115610528";
+    let dm_message_id = "812746127846424";
+    let user_id = "82198898841029460"; // vote_channel.json
+    let dm_delay = Duration::from_secs(2);
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        channel_id: "1187315505103638638".to_string(),
+        message_id: "3589723985723".to_string(),
+        name: "Who do you prefer?".to_string(),
+        ..Default::default()
+    };
+
+    let test = setup_test_env("vote_channel.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![(
+            custom_uuid.to_string(),
+            CustomID {
+                action: Action::VoteFromChannel,
+                voting_id: voting.id.clone(),
+                user_id: None,
+                page: None,
+                index: None,
+            },
+        )])
+        .await
+        .expect("Failed to save custom ids");
+
+    let mut dm_channel_mock = test.discord_server.mock(|when, then| {
+        when.method(POST).path("/api/v10/users/@me/channels");
+        then.status(200)
+            .delay(dm_delay)
+            .header("Content-Type", "application/json")
+            .json_body(json!({
+              "id": dm_channel_id,
+              "type": 1,
+              "last_message_id": null,
+              "recipients": [],
+              "application_id": null
+            }));
+    });
+    let mut dm_message_mock = create_mock!(
+        test.discord_server,
+        POST,
+        format!("/api/v10/channels/{}/messages", dm_channel_id),
+        message_json(dm_message_id, dm_channel_id)
+    );
+    let mut reply_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH)
+            .path(test.original_response_path())
+            .body_contains(format!(
+                "https://discord.com/channels/@me/{}/{}",
+                dm_channel_id, dm_message_id
+            ));
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json(dm_message_id, dm_channel_id));
+    });
+
+    let start = tokio::time::Instant::now();
+    let (status, response) = handle_interaction(
+        test.data.clone(),
+        test.headers.clone(),
+        test.body.to_string(),
+    )
+    .await
+    .expect("vote should be acknowledged");
+
+    // the reply doesn't wait for the dm channel
+    assert!(start.elapsed() < dm_delay);
+    assert_eq!(status, StatusCode::OK);
+    let data = response.0.data.expect("response data should be set");
+    assert_eq!(data.flags, Some(MessageFlags::EPHEMERAL));
+    assert_eq!(
+        data.content.as_deref(),
+        Some("You will receive dm with voting dialog")
+    );
+
+    let dialog = test
+        .data
+        .db
+        .get_voting_dialog(&voting.id, user_id)
+        .await
+        .expect("placeholder dialog should be claimed");
+    assert_eq!(dialog.message_id, "");
+
+    test.wait_for_tasks().await;
+    dm_channel_mock.assert();
+    dm_channel_mock.delete();
+    dm_message_mock.assert();
+    dm_message_mock.delete();
+    reply_mock.assert();
+    reply_mock.delete();
+
+    let dialog = test
+        .data
+        .db
+        .get_voting_dialog(&voting.id, user_id)
+        .await
+        .expect("voting dialog should be saved");
+    assert_eq!(dialog.message_id, dm_message_id);
+    assert_eq!(dialog.channel_id, dm_channel_id);
+}
+
+#[tokio::test]
+async fn handle_shuffled_voting_test() {
+    let custom_uuid = "df4db2bc-9fd1-43fb-8e17-97170379159a"; // vote_channel.json
+    let user_ids = ["82198898841029460", "82198898841029461"];
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec![
+            "Spinoza".to_string(),
+            "Kant".to_string(),
+            "Nietzsche".to_string(),
+            "Hume".to_string(),
+        ],
+        channel_id: "1187315505103638638".to_string(),
+        message_id: "3589723985723".to_string(),
+        name: "Who do you prefer?".to_string(),
+        in_channel: true,
+        shuffle: true,
+        ..Default::default()
+    };
+
+    let mut test = setup_test_env("vote_channel.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![(
+            custom_uuid.to_string(),
+            CustomID {
+                action: Action::VoteFromChannel,
+                voting_id: voting.id.clone(),
+                user_id: None,
+                page: None,
+                index: None,
+            },
+        )])
+        .await
+        .expect("Failed to save custom ids");
+
+    let vote_channel_body: serde_json::Value = serde_json::from_str(&test.body).unwrap();
+
+    let mut orders = Vec::new();
+    for user_id in user_ids {
+        let mut body = vote_channel_body.clone();
+        body["member"]["user"]["id"] = json!(user_id);
+        test.set_body(body.to_string());
+
+        let (_, response) = handle_interaction(
+            test.data.clone(),
+            test.headers.clone(),
+            test.body.to_string(),
+        )
+        .await
+        .expect("vote channel failed");
+        let data = response.0.data.expect("response should carry the dialog");
+
+        let dialog = test
+            .data
+            .db
+            .get_voting_dialog(&voting.id, user_id)
+            .await
+            .expect("voting dialog should be saved");
+        let order = dialog.display_order.expect("dialog should be shuffled");
+        orders.push(order.clone());
+
+        // the first displayed choice is ranked first
+        let description = data.embeds.unwrap()[0].description.clone().unwrap();
+        let first_choice = &voting.choices[order[0]];
+        assert!(description.starts_with(&format!("**1**: {}", first_choice)));
+
+        let components = data.components.unwrap();
+        let Component::ActionRow(ref row) = components[0] else {
+            panic!("expected a select row");
+        };
+        let Component::SelectMenu(ref select) = row.components[0] else {
+            panic!("expected a select menu");
+        };
+        let Component::ActionRow(ref row) = components[components.len() - 1] else {
+            panic!("expected a button row");
+        };
+        let Component::Button(ref vote_btn) = row.components[row.components.len() - 1] else {
+            panic!("expected a vote button");
+        };
+
+        let mut body: serde_json::Value =
+            serde_json::from_str(&dm_component_body(&select.custom_id, &["1"])).unwrap();
+        body["user"]["id"] = json!(user_id);
+        test.set_body(body.to_string());
+        run_test!(
+            "select first displayed choice",
+            &test,
+            empty_mock_vec(),
+            empty_mock_vec(),
+            ack(),
+            true
+        );
+
+        let mut body: serde_json::Value = serde_json::from_str(&dm_component_body(
+            vote_btn.custom_id.as_ref().unwrap(),
+            &[],
+        ))
+        .unwrap();
+        body["user"]["id"] = json!(user_id);
+        test.set_body(body.to_string());
+
+        // the ballot sent to DD ranks the displayed choice, not the one at the same original index
+        let mut ballot_mock = test.dd_server.mock(|when, then| {
+            when.method(POST)
+                .path(format!("/v1/votings/{}/ballots/{}", voting.id, user_id))
+                .body_contains(format!(r#""{}":1"#, first_choice));
+            then.status(200)
+                .header("Content-Type", "application/json")
+                .json_body(json!({"revoted": false}));
+        });
+        let mut dialog_mock = test.discord_server.mock(|when, then| {
+            when.method(PATCH).path_contains("/api/v10/channels/");
+            then.status(200)
+                .header("Content-Type", "application/json")
+                .json_body(message_json("3589723985724", "319674150115610528"));
+        });
+
+        run_test!(
+            "vote",
+            &test,
+            empty_mock_vec(),
+            empty_mock_vec(),
+            ack(),
+            true
+        );
+
+        ballot_mock.assert();
+        ballot_mock.delete();
+        dialog_mock.assert();
+        dialog_mock.delete();
+    }
+
+    assert_ne!(orders[0], orders[1]);
+}
+
+#[tokio::test]
+async fn handle_dm_vote_role_weight_test() {
+    let custom_uuid = "df4db2bc-9fd1-43fb-8e17-97170379159a"; // vote_channel.json
+    let user_id = "82198898841029460"; // vote_channel.json
+    let role_id = "1187313045127581999";
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        channel_id: "1187315505103638638".to_string(),
+        message_id: "3589723985723".to_string(),
+        name: "Who do you prefer?".to_string(),
+        in_channel: true,
+        role_weights: vec![
+            ("1187313045127582000".to_string(), 2),
+            (role_id.to_string(), 3),
+        ],
+        ..Default::default()
+    };
+
+    let mut test = setup_test_env("vote_channel.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![(
+            custom_uuid.to_string(),
+            CustomID {
+                action: Action::VoteFromChannel,
+                voting_id: voting.id.clone(),
+                user_id: None,
+                page: None,
+                index: None,
+            },
+        )])
+        .await
+        .expect("Failed to save custom ids");
+
+    let mut body: serde_json::Value = serde_json::from_str(&test.body).unwrap();
+    body["member"]["roles"] = json!([role_id]);
+    test.set_body(body.to_string());
+
+    let (_, response) = handle_interaction(
+        test.data.clone(),
+        test.headers.clone(),
+        test.body.to_string(),
+    )
+    .await
+    .expect("vote channel failed");
+    let components = response
+        .0
+        .data
+        .and_then(|data| data.components)
+        .expect("response should carry the dialog");
+
+    // the weight is taken from the member roles, the dm vote below has no member
+    let dialog = test
+        .data
+        .db
+        .get_voting_dialog(&voting.id, user_id)
+        .await
+        .expect("voting dialog should be saved");
+    assert_eq!(dialog.weight, 3);
+
+    let Component::ActionRow(ref row) = components[components.len() - 1] else {
+        panic!("expected a button row");
+    };
+    let Component::Button(ref vote_btn) = row.components[row.components.len() - 1] else {
+        panic!("expected a vote button");
+    };
+    test.set_body(dm_component_body(vote_btn.custom_id.as_ref().unwrap(), &[]));
+
+    // the ballot is submitted once per weight
+    let mut ballot_mocks = Vec::new();
+    for voter_id in [
+        user_id.to_string(),
+        format!("{}-2", user_id),
+        format!("{}-3", user_id),
+    ] {
+        ballot_mocks.push(test.dd_server.mock(|when, then| {
+            when.method(POST)
+                .path(format!("/v1/votings/{}/ballots/{}", voting.id, voter_id));
+            then.status(200)
+                .header("Content-Type", "application/json")
+                .json_body(json!({"revoted": false}));
+        }));
+    }
+    let mut dialog_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH).path_contains("/api/v10/channels/");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json("3589723985724", "319674150115610528"));
+    });
+
+    run_test!(
+        "weighted vote",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        ack(),
+        true
+    );
+
+    for mut ballot_mock in ballot_mocks {
+        ballot_mock.assert();
+        ballot_mock.delete();
+    }
+    dialog_mock.assert();
+    dialog_mock.delete();
+
+    let dialog = test
+        .data
+        .db
+        .get_voting_dialog(&voting.id, user_id)
+        .await
+        .expect("voting dialog should be kept");
+    assert!(dialog.submitted);
+}
+
+#[tokio::test]
+async fn handle_lock_dialogs_test() {
+    let custom_uuid = "df4db2bc-9fd1-43fb-8e17-97170379159a"; // vote_channel.json
+    let lock_uuid = "5f0b1c2e-8d3a-4e6f-9b7c-1a2d3e4f5a6b";
+    let user_id = "82198898841029460"; // vote_channel.json
+    let new_user_id = "82198898841029461";
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        channel_id: "1187315505103638638".to_string(),
+        message_id: "3589723985723".to_string(),
+        name: "Who do you prefer?".to_string(),
+        in_channel: true,
+        ..Default::default()
+    };
+
+    let mut test = setup_test_env("vote_channel.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![
+            (
+                custom_uuid.to_string(),
+                CustomID {
+                    action: Action::VoteFromChannel,
+                    voting_id: voting.id.clone(),
+                    user_id: None,
+                    page: None,
+                    index: None,
+                },
+            ),
+            (
+                lock_uuid.to_string(),
+                CustomID {
+                    action: Action::LockDialogs,
+                    voting_id: voting.id.clone(),
+                    user_id: None,
+                    page: None,
+                    index: None,
+                },
+            ),
+        ])
+        .await
+        .expect("Failed to save custom ids");
+
+    // the dialog is opened before the voting is locked
+    let vote_channel_body = test.body.clone();
+    let (_, response) = handle_interaction(
+        test.data.clone(),
+        test.headers.clone(),
+        test.body.to_string(),
+    )
+    .await
+    .expect("vote channel failed");
+    let components = response
+        .0
+        .data
+        .and_then(|data| data.components)
+        .expect("response should carry the dialog");
+
+    test.set_body(dm_component_body(lock_uuid, &[]));
+    let (_, response) = handle_interaction(
+        test.data.clone(),
+        test.headers.clone(),
+        test.body.to_string(),
+    )
+    .await
+    .expect("locking dialogs failed");
+    let response = response.0;
+    assert_eq!(
+        response.kind,
+        twilight_model::http::interaction::InteractionResponseType::UpdateMessage
+    );
+    let creator_components = response
+        .data
+        .and_then(|data| data.components)
+        .expect("creator buttons should be updated");
+    let labels: Vec<String> = creator_components
+        .iter()
+        .flat_map(|component| match component {
+            Component::ActionRow(row) => row.components.clone(),
+            _ => Vec::new(),
+        })
+        .filter_map(|component| match component {
+            Component::Button(button) => button.label,
+            _ => None,
+        })
+        .collect();
+    assert!(labels.contains(&"Unlock New Voters".to_string()));
+    assert!(
+        test.data
+            .db
+            .get_voting(&voting.id)
+            .await
+            .unwrap()
+            .dialogs_locked
+    );
+
+    // a user without a dialog can't open one anymore
+    let mut body: serde_json::Value = serde_json::from_str(&vote_channel_body).unwrap();
+    body["member"]["user"]["id"] = json!(new_user_id);
+    test.set_body(body.to_string());
+
+    run_test!(
+        "new voter of a locked voting",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        Ok((
+            http::StatusCode::OK,
+            Json(InteractionResponse {
+                kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    content: Some("This voting is closed to new voters.".to_string()),
+                    flags: Some(MessageFlags::EPHEMERAL),
+                    ..Default::default()
+                }),
+            })
+        )),
+        true
+    );
+    let err = test
+        .data
+        .db
+        .get_voting_dialog(&voting.id, new_user_id)
+        .await
+        .expect_err("voting dialog should not be created");
+    assert_eq!(err, dd_discord::db::DbError::NotFound);
+
+    // the open dialog can still be submitted
+    let Component::ActionRow(ref row) = components[components.len() - 1] else {
+        panic!("expected a button row");
+    };
+    let Component::Button(ref vote_btn) = row.components[row.components.len() - 1] else {
+        panic!("expected a vote button");
+    };
+    test.set_body(dm_component_body(vote_btn.custom_id.as_ref().unwrap(), &[]));
+
+    let mut ballot_mock = test.dd_server.mock(|when, then| {
+        when.method(POST)
+            .path(format!("/v1/votings/{}/ballots/{}", voting.id, user_id));
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(json!({"revoted": false}));
+    });
+    let mut dialog_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH).path_contains("/api/v10/channels/");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json("3589723985724", "319674150115610528"));
+    });
+
+    run_test!(
+        "vote of an existing dialog",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        ack(),
+        true
+    );
+
+    ballot_mock.assert();
+    ballot_mock.delete();
+    dialog_mock.assert();
+    dialog_mock.delete();
+
+    let dialog = test
+        .data
+        .db
+        .get_voting_dialog(&voting.id, user_id)
+        .await
+        .expect("voting dialog should be kept");
+    assert!(dialog.submitted);
+}
+
+#[tokio::test]
+async fn handle_dm_vote_lowered_weight_test() {
+    let custom_uuid = "0c8a5a3e-6f3e-4d0c-9f69-1c2f3bd5e6a7"; // dm_component.json
+    let user_id = "82198898841029460"; // dm_component.json
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        name: "Who do you prefer?".to_string(),
+        role_weights: vec![("1187313045127581999".to_string(), 3)],
+        ..Default::default()
+    };
+
+    let test = setup_test_env("dm_component.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    // the dialog was opened without the weighted role
+    test.data
+        .db
+        .save_voting_dialog(
+            voting.id.clone(),
+            user_id.to_string(),
+            vec![1, 2],
+            "1202277900045262871".to_string(),
+            "319674150115610528".to_string(),
+            false,
+        )
+        .await
+        .expect("Failed to save voting dialog");
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![(
+            custom_uuid.to_string(),
+            CustomID {
+                action: Action::VoteFromDM,
+                voting_id: voting.id.clone(),
+                user_id: None,
+                page: None,
+                index: None,
+            },
+        )])
+        .await
+        .expect("Failed to save custom ids");
+
+    let ballot_path = |voter_id: &str| format!("/v1/votings/{}/ballots/{}", voting.id, voter_id);
+    let mut ballot_mock = test.dd_server.mock(|when, then| {
+        when.method(POST).path(ballot_path(user_id));
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(json!({"revoted": true}));
+    });
+    // copies of an earlier ballot with the role are removed, missing copies are fine
+    let mut unvote_mock = test.dd_server.mock(|when, then| {
+        when.method(DELETE)
+            .path(ballot_path(&format!("{}-2", user_id)));
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(json!({"code": 200, "message": "OK"}));
+    });
+    let mut missing_unvote_mock = test.dd_server.mock(|when, then| {
+        when.method(DELETE)
+            .path(ballot_path(&format!("{}-3", user_id)));
+        then.status(404)
+            .header("Content-Type", "application/json")
+            .json_body(json!({"code": 404, "message": "Not Found"}));
+    });
+    let mut dialog_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH).path_contains("/api/v10/channels/");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json("1202277900045262871", "319674150115610528"));
+    });
+
+    run_test!(
+        "lowered weight",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        ack(),
+        true
+    );
+
+    ballot_mock.assert();
+    ballot_mock.delete();
+    unvote_mock.assert();
+    unvote_mock.delete();
+    missing_unvote_mock.assert();
+    missing_unvote_mock.delete();
+    dialog_mock.delete();
+
+    let dialog = test
+        .data
+        .db
+        .get_voting_dialog(&voting.id, user_id)
+        .await
+        .expect("voting dialog should be kept");
+    assert!(dialog.submitted);
+}
+
+#[tokio::test]
+async fn handle_dm_vote_dd_errors_test() {
+    let custom_uuid = "0c8a5a3e-6f3e-4d0c-9f69-1c2f3bd5e6a7"; // dm_component.json
+    let user_id = "82198898841029460"; // dm_component.json
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        name: "Who do you prefer?".to_string(),
+        ..Default::default()
+    };
+
+    let test = setup_test_env("dm_component.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .save_voting_dialog(
+            voting.id.clone(),
+            user_id.to_string(),
+            vec![1, 2],
+            "1202277900045262871".to_string(),
+            "319674150115610528".to_string(),
+            false,
+        )
+        .await
+        .expect("Failed to save voting dialog");
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![(
+            custom_uuid.to_string(),
+            CustomID {
+                action: Action::VoteFromDM,
+                voting_id: voting.id.clone(),
+                user_id: None,
+                page: None,
+                index: None,
+            },
+        )])
+        .await
+        .expect("Failed to save custom ids");
+
+    let ballot_path = format!("/v1/votings/{}/ballots/{}", voting.id, user_id);
+
+    for (status, expected_error, expected_message) in [
+        (
+            404,
+            InteractionError::VotingNotFound,
+            "This voting does not exist anymore.",
+        ),
+        (
+            429,
+            InteractionError::RateLimited,
+            "Too many requests at the moment. Please try again in a minute.",
+        ),
+    ] {
+        let mut dd_mock = test.dd_server.mock(|when, then| {
+            when.method(POST).path(ballot_path.clone());
+            then.status(status)
+                .header("Content-Type", "application/json")
+                .json_body(json!({"code": status, "message": "error"}));
+        });
+
+        let err = handle_interaction(
+            test.data.clone(),
+            test.headers.clone(),
+            test.body.to_string(),
+        )
+        .await
+        .expect_err("voting should fail");
+
+        dd_mock.assert();
+        dd_mock.delete();
+
+        assert_eq!(err, expected_error);
+
+        let response = err.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["data"]["content"], json!(expected_message));
+        assert_eq!(body["data"]["flags"], json!(64));
+
+        // the backend answered, nothing is left to replay
+        let pending = test.data.db.get_pending_votes().await.unwrap();
+        assert!(pending.is_empty());
+    }
+}
+
+#[tokio::test]
+async fn handle_dm_vote_count_test() {
+    let custom_uuid = "0c8a5a3e-6f3e-4d0c-9f69-1c2f3bd5e6a7"; // dm_component.json
+    let user_id = "82198898841029460"; // dm_component.json
+    let dm_channel_id = "319674150115610528"; // dm_component.json
+    let channel_id = "1187315505103638638";
+    let message_id = "3589723985723";
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        name: "Who do you prefer?".to_string(),
+        channel_id: channel_id.to_string(),
+        message_id: message_id.to_string(),
+        show_vote_count: true,
+        ..Default::default()
+    };
+
+    let test = setup_test_env("dm_component.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .save_voting_dialog(
+            voting.id.clone(),
+            user_id.to_string(),
+            vec![1, 2],
+            "1202277900045262871".to_string(),
+            dm_channel_id.to_string(),
+            false,
+        )
+        .await
+        .expect("Failed to save voting dialog");
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![
+            (
+                custom_uuid.to_string(),
+                CustomID {
+                    action: Action::VoteFromDM,
+                    voting_id: voting.id.clone(),
+                    user_id: None,
+                    page: None,
+                    index: None,
+                },
+            ),
+            (
+                "df4db2bc-9fd1-43fb-8e17-97170379159a".to_string(),
+                CustomID {
+                    action: Action::VoteFromChannel,
+                    voting_id: voting.id.clone(),
+                    user_id: None,
+                    page: None,
+                    index: None,
+                },
+            ),
+        ])
+        .await
+        .expect("Failed to save custom ids");
+
+    let mut ballot_mock = test.dd_server.mock(|when, then| {
+        when.method(POST)
+            .path(format!("/v1/votings/{}/ballots/{}", voting.id, user_id));
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(json!({"revoted": false}));
+    });
+    let mut dialog_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH)
+            .path_contains(format!("/api/v10/channels/{}/", dm_channel_id));
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json("1202277900045262871", dm_channel_id));
+    });
+
+    // the announcement is edited in the background with the new count
+    let mut announcement_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH)
+            .path(format!(
+                "/api/v10/channels/{}/messages/{}",
+                channel_id, message_id
+            ))
+            .body_contains(r#""name":"Votes","value":"1 person has voted""#);
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json(message_id, channel_id));
+    });
+
+    run_test!(
+        "vote with count",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        ack(),
+        true
+    );
+    test.wait_for_tasks().await;
+
+    ballot_mock.assert();
+    ballot_mock.delete();
+    dialog_mock.assert();
+    dialog_mock.delete();
+    announcement_mock.assert();
+    announcement_mock.delete();
+}
+
+#[tokio::test]
+async fn handle_dm_vote_require_full_ranking_test() {
+    let custom_uuid = "0c8a5a3e-6f3e-4d0c-9f69-1c2f3bd5e6a7"; // dm_component.json
+    let user_id = "82198898841029460"; // dm_component.json
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        name: "Who do you prefer?".to_string(),
+        require_full_ranking: true,
+        ..Default::default()
+    };
+
+    let test = setup_test_env("dm_component.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .save_voting_dialog(
+            voting.id.clone(),
+            user_id.to_string(),
+            vec![1, 0],
+            "1202277900045262871".to_string(),
+            "319674150115610528".to_string(),
+            false,
+        )
+        .await
+        .expect("Failed to save voting dialog");
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![(
+            custom_uuid.to_string(),
+            CustomID {
+                action: Action::VoteFromDM,
+                voting_id: voting.id.clone(),
+                user_id: None,
+                page: None,
+                index: None,
+            },
+        )])
+        .await
+        .expect("Failed to save custom ids");
+
+    run_test!(
+        "partial ballot rejected",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        Ok((
+            http::StatusCode::OK,
+            Json(InteractionResponse {
+                kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    content: Some("Please rank all choices before submitting.".to_string()),
+                    flags: Some(MessageFlags::EPHEMERAL),
+                    ..Default::default()
+                }),
+            })
+        )),
+        true
+    );
+
+    let dialog = test
+        .data
+        .db
+        .get_voting_dialog(&voting.id, user_id)
+        .await
+        .expect("voting dialog should be kept");
+    assert!(!dialog.submitted);
+
+    // partial ballots are accepted otherwise, the unranked choice goes out as tied last
+    test.data
+        .db
+        .update_voting_checked(&voting.id, voting.version, |voting| {
+            voting.require_full_ranking = false;
+        })
+        .await
+        .expect("Failed to update voting");
+
+    let mut ballot_mock = test.dd_server.mock(|when, then| {
+        when.method(POST)
+            .path(format!("/v1/votings/{}/ballots/{}", voting.id, user_id))
+            .json_body(json!({"ballot": {"Spinoza": 1, "Kant": 0}}));
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(json!({"revoted": false}));
+    });
+    let mut dialog_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH).path_contains("/api/v10/channels/");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json("1202277900045262871", "319674150115610528"));
+    });
+
+    run_test!(
+        "partial ballot accepted",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        ack(),
+        true
+    );
+
+    ballot_mock.assert();
+    ballot_mock.delete();
+    dialog_mock.assert();
+    dialog_mock.delete();
+
+    let dialog = test
+        .data
+        .db
+        .get_voting_dialog(&voting.id, user_id)
+        .await
+        .expect("voting dialog should be kept");
+    assert!(dialog.submitted);
+}
+
+#[tokio::test]
+async fn handle_vote_channel_allowed_role_test() {
+    let custom_uuid = "df4db2bc-9fd1-43fb-8e17-97170379159a"; // vote_channel.json
+    let dm_channel_id = "319674150115610528";
+    let dm_message_id = "1202277900045262871";
+    let user_id = "82198898841029460"; // vote_channel.json
+    let role_id = "1187313045127581999";
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        channel_id: "1187315505103638638".to_string(),
+        message_id: "3589723985723".to_string(),
+        name: "Who do you prefer?".to_string(),
+        allowed_role_id: Some(role_id.to_string()),
+        ..Default::default()
+    };
+
+    let mut test = setup_test_env("vote_channel.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![(
+            custom_uuid.to_string(),
+            CustomID {
+                action: Action::VoteFromChannel,
+                voting_id: voting.id.clone(),
+                user_id: None,
+                page: None,
+                index: None,
+            },
+        )])
+        .await
+        .expect("Failed to save custom ids");
+
+    run_test!(
+        "member without the role",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        Ok((
+            http::StatusCode::OK,
+            Json(InteractionResponse {
+                kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    content: Some("You don't have permission to vote in this poll.".to_string()),
+                    flags: Some(MessageFlags::EPHEMERAL),
+                    ..Default::default()
+                }),
+            })
+        )),
+        true
+    );
+
+    let err = test
+        .data
+        .db
+        .get_voting_dialog(&voting.id, user_id)
+        .await
+        .expect_err("voting dialog should not be created");
+    assert_eq!(err, dd_discord::db::DbError::NotFound);
+
+    let mut body: serde_json::Value = serde_json::from_str(&test.body).unwrap();
+    body["member"]["roles"] = json!([role_id]);
+    test.set_body(body.to_string());
+
+    let mocks = run_test!(
+        "member with the role",
+        &test,
+        empty_mock_vec(),
+        [
+            (
+                POST,
+                "/api/v10/users/@me/channels".to_string(),
+                json!({
+                  "id": dm_channel_id,
+                  "type": 1,
+                  "last_message_id": null,
+                  "recipients": [],
+                  "application_id": null
+                }),
+            ),
+            (
+                POST,
+                format!("/api/v10/channels/{}/messages", dm_channel_id),
+                message_json(dm_message_id, dm_channel_id),
+            ),
+        ],
+        Ok((
+            http::StatusCode::OK,
+            Json(InteractionResponse {
+                kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    content: Some("You will receive dm with voting dialog".to_string()),
+                    flags: Some(MessageFlags::EPHEMERAL),
+                    ..Default::default()
+                }),
+            })
+        )),
+        false
+    );
+
+    test.wait_for_tasks().await;
+
+    let start = tokio::time::Instant::now();
+    loop {
+        match test.data.db.get_voting_dialog(&voting.id, user_id).await {
+            Ok(dialog) if !dialog.message_id.is_empty() => break,
+            _ => {
+                if start.elapsed() > Duration::from_secs(5) {
+                    panic!("get voting dialog timeout");
+                }
+
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        }
+    }
+
+    for mut mock in mocks {
+        mock.assert();
+        mock.delete();
+    }
+}
+
+#[tokio::test]
+async fn handle_undo_vote_test() {
+    let custom_uuid = "0c8a5a3e-6f3e-4d0c-9f69-1c2f3bd5e6a7";
+    let dm_channel_id = "319674150115610528"; // dm_component.json
+    let dm_message_id = "1202277900045262871"; // dm_component.json
+    let user_id = "82198898841029460"; // dm_component.json
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec![
+            "Spinoza".to_string(),
+            "Kant".to_string(),
+            "Nietzsche".to_string(),
+        ],
+        channel_id: "1187315505103638638".to_string(),
+        message_id: "3589723985723".to_string(),
+        name: "Who do you prefer?".to_string(),
+        is_completed: false,
+        is_deleted: false,
+        creator_message_id: "812746127846424".to_string(),
+        creator_dm_channel_id: "319674150115610529".to_string(),
+        ..Default::default()
+    };
+
+    let mut test = setup_test_env("dm_component.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .save_voting_dialog(
+            voting.id.clone(),
+            user_id.to_string(),
+            vec![2, 1, 3],
+            dm_message_id.to_string(),
+            dm_channel_id.to_string(),
+            false,
+        )
+        .await
+        .expect("Failed to save voting dialog");
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![(
+            custom_uuid.to_string(),
+            CustomID {
+                action: Action::VoteFromDM,
+                voting_id: voting.id.clone(),
+                user_id: None,
+                page: None,
+                index: None,
+            },
+        )])
+        .await
+        .expect("Failed to save custom ids");
+
+    let ballot_path = format!("/v1/votings/{}/ballots/{}", voting.id, user_id);
+    let message_path = format!(
+        "/api/v10/channels/{}/messages/{}",
+        dm_channel_id, dm_message_id
+    );
+
+    run_test!(
+        "submit vote",
+        &test,
+        [(POST, ballot_path.clone(), json!({"revoted": false}))],
+        [(
+            PATCH,
+            message_path.clone(),
+            message_json(dm_message_id, dm_channel_id)
+        )],
+        ack(),
+        true
+    );
+
+    let dialog = test
+        .data
+        .db
+        .get_voting_dialog(&voting.id, user_id)
+        .await
+        .expect("voting dialog should be kept for the undo window");
+    assert!(dialog.submitted);
+
+    let pending = test.data.db.get_pending_votes().await.unwrap();
+    assert!(pending.is_empty());
+
+    let (undo_uuid, _) = test
+        .data
+        .db
+        .get_custom_ids(&voting.id)
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|(_, custom_id)| custom_id.action == Action::UndoVote)
+        .expect("undo custom id should be saved");
+
+    test.set_body(dm_component_body(&undo_uuid, &[]));
+    run_test!(
+        "undo vote",
+        &test,
+        [(DELETE, ballot_path, json!({"code": 200, "message": "OK"}))],
+        [(
+            PATCH,
+            message_path,
+            message_json(dm_message_id, dm_channel_id)
+        )],
+        ack(),
+        true
+    );
+
+    let dialog = test
+        .data
+        .db
+        .get_voting_dialog(&voting.id, user_id)
+        .await
+        .expect("voting dialog should be restored");
+    assert!(!dialog.submitted);
+    assert_eq!(dialog.ballot, vec![2, 1, 3]);
+}
+
+#[tokio::test]
+async fn handle_complete_voting_double_click_test() {
+    let complete_uuid = "0c8a5a3e-6f3e-4d0c-9f69-1c2f3bd5e6a7"; // dm_component.json
+    let dm_channel_id = "319674150115610528"; // dm_component.json
+    let creator_message_id = "1202277900045262871"; // dm_component.json
+    let channel_id = "1187315505103638638";
+    let message_id = "3589723985723";
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        channel_id: channel_id.to_string(),
+        message_id: message_id.to_string(),
+        name: "Who do you prefer?".to_string(),
+        creator_message_id: creator_message_id.to_string(),
+        creator_dm_channel_id: dm_channel_id.to_string(),
+        ..Default::default()
+    };
+
+    let test = setup_test_env("dm_component.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![(
+            complete_uuid.to_string(),
+            CustomID {
+                action: Action::Complete,
+                voting_id: voting.id.clone(),
+                user_id: None,
+                page: None,
+                index: None,
+            },
+        )])
+        .await
+        .expect("Failed to save custom ids");
+
+    let mut results_mock = test.dd_server.mock(|when, then| {
+        when.method(GET)
+            .path(format!("/v1/votings/{}/results/duels", voting.id));
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(json!({
+                "tie": false,
+                "results": [
+                    {"choice": "Kant", "index": 1, "wins": 1, "percentage": 100.0, "strength": 1, "advantage": 1},
+                    {"choice": "Spinoza", "index": 0, "wins": 0, "percentage": 0.0, "strength": 0, "advantage": 0},
+                ],
+                "duels": null,
+            }));
+    });
+    let mut channel_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH).path(format!(
+            "/api/v10/channels/{}/messages/{}",
+            channel_id, message_id
+        ));
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json(message_id, channel_id));
+    });
+    let mut creator_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH).path(format!(
+            "/api/v10/channels/{}/messages/{}",
+            dm_channel_id, creator_message_id
+        ));
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json(creator_message_id, dm_channel_id));
+    });
+
+    // the second click finds the voting completed and is only acked
+    for assertion in ["complete voting", "complete voting again"] {
+        run_test!(
+            assertion,
+            &test,
+            empty_mock_vec(),
+            empty_mock_vec(),
+            ack(),
+            true
+        );
+    }
+
+    results_mock.assert_hits(1);
+    results_mock.delete();
+    channel_mock.assert_hits(1);
+    channel_mock.delete();
+    creator_mock.assert_hits(1);
+    creator_mock.delete();
+
+    assert!(
+        test.data
+            .db
+            .get_voting(&voting.id)
+            .await
+            .unwrap()
+            .is_completed
+    );
+}
+
+#[tokio::test]
+async fn handle_result_page_test() {
+    let custom_uuid = "0c8a5a3e-6f3e-4d0c-9f69-1c2f3bd5e6a7";
+    let dm_channel_id = "319674150115610528"; // dm_component.json
+    let creator_message_id = "1202277900045262871"; // dm_component.json
+    let channel_id = "1187315505103638638";
+    let message_id = "3589723985723";
+
+    let choices: Vec<String> = (1..=20).map(|i| format!("Choice {}", i)).collect();
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: choices.clone(),
+        channel_id: channel_id.to_string(),
+        message_id: message_id.to_string(),
+        name: "Who do you prefer?".to_string(),
+        is_completed: false,
+        is_deleted: false,
+        creator_message_id: creator_message_id.to_string(),
+        creator_dm_channel_id: dm_channel_id.to_string(),
+        ..Default::default()
+    };
+
+    let mut test = setup_test_env("dm_component.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![(
+            custom_uuid.to_string(),
+            CustomID {
+                action: Action::Complete,
+                voting_id: voting.id.clone(),
+                user_id: None,
+                page: None,
+                index: None,
+            },
+        )])
+        .await
+        .expect("Failed to save custom ids");
+
+    let results = json!({
+        "tie": false,
+        "results": choices
+            .iter()
+            .enumerate()
+            .map(|(i, choice)| json!({
+                "choice": choice,
+                "index": i,
+                "wins": 19 - i,
+                "percentage": (19 - i) as f32 / 19.0 * 100.0,
+                "strength": 0,
+                "advantage": 0,
+            }))
+            .collect::<Vec<_>>(),
+        "duels": null,
+    });
+    let results_path = format!("/v1/votings/{}/results/duels", voting.id);
+    let channel_message_path = format!("/api/v10/channels/{}/messages/{}", channel_id, message_id);
+
+    run_test!(
+        "complete voting",
+        &test,
+        [(GET, results_path, results)],
+        [
+            (
+                PATCH,
+                channel_message_path.clone(),
+                message_json(message_id, channel_id)
+            ),
+            (
+                PATCH,
+                format!(
+                    "/api/v10/channels/{}/messages/{}",
+                    dm_channel_id, creator_message_id
+                ),
+                message_json(creator_message_id, dm_channel_id)
+            ),
+        ],
+        ack(),
+        true
+    );
+
+    let (next_uuid, next) = test
+        .data
+        .db
+        .get_custom_ids(&voting.id)
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|(_, custom_id)| custom_id.action == Action::ResultNext)
+        .expect("next results page custom id should be saved");
+    assert_eq!(next.page, Some(2));
+
+    test.set_body(dm_component_body(&next_uuid, &[]));
+    let mut page_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH)
+            .path(channel_message_path.clone())
+            .body_contains("Page 2 of 2")
+            .body_contains("Choice 20");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json(message_id, channel_id));
+    });
+
+    // the results are cached when the voting completes, paging doesn't query DD again
+    run_test!(
+        "next results page",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        ack(),
+        true
+    );
+
+    page_mock.assert();
+    page_mock.delete();
+
+    let page_ids = test
+        .data
+        .db
+        .get_custom_ids(&voting.id)
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|(_, custom_id)| {
+            custom_id.action == Action::ResultNext || custom_id.action == Action::ResultPrevious
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(page_ids.len(), 2);
+    let (previous_uuid, previous) = page_ids
+        .iter()
+        .find(|(_, custom_id)| custom_id.action == Action::ResultPrevious)
+        .expect("previous results page custom id should be saved");
+    assert_eq!(previous.page, Some(1));
+
+    test.set_body(dm_component_body(previous_uuid, &[]));
+    let mut page_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH)
+            .path(channel_message_path.clone())
+            .body_contains("Page 1 of 2")
+            .body_contains(next_uuid.as_str());
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json(message_id, channel_id));
+    });
+
+    run_test!(
+        "previous results page",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        ack(),
+        true
+    );
+
+    page_mock.assert();
+    page_mock.delete();
+
+    // the page buttons keep their custom ids
+    let page_ids = test
+        .data
+        .db
+        .get_custom_ids(&voting.id)
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|(_, custom_id)| {
+            custom_id.action == Action::ResultNext || custom_id.action == Action::ResultPrevious
+        })
+        .count();
+    assert_eq!(page_ids, 2);
+}
+
+#[tokio::test]
+async fn handle_refresh_test() {
+    let user_id = "82198898841029460"; // dm_component.json
+    let channel_id = "1187315505103638638";
+    let message_id = "3589723985723";
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        channel_id: channel_id.to_string(),
+        message_id: message_id.to_string(),
+        name: "Who do you prefer?".to_string(),
+        creator_message_id: "812746127846424".to_string(),
+        creator_dm_channel_id: "319674150115610529".to_string(),
+        ..Default::default()
+    };
+
+    let refresh_uuid = "a1b2c3d4-0000-4000-8000-000000000001";
+    let vote_channel_uuid = "a1b2c3d4-0000-4000-8000-000000000002";
+    let vote_dm_uuid = "a1b2c3d4-0000-4000-8000-000000000003";
+
+    let mut test = setup_test_env("dm_component.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+
+    let custom_id = |action: Action, user_id: Option<&str>| CustomID {
+        action,
+        voting_id: voting.id.clone(),
+        user_id: user_id.map(str::to_string),
+        page: None,
+        index: None,
+    };
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![
+            (refresh_uuid.to_string(), custom_id(Action::Refresh, None)),
+            (
+                vote_channel_uuid.to_string(),
+                custom_id(Action::VoteFromChannel, None),
+            ),
+            (
+                vote_dm_uuid.to_string(),
+                custom_id(Action::VoteFromDM, Some(user_id)),
+            ),
+        ])
+        .await
+        .expect("Failed to save custom ids");
+
+    test.set_body(dm_component_body(refresh_uuid, &[]));
+    let mut announcement_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH)
+            .path(format!(
+                "/api/v10/channels/{}/messages/{}",
+                channel_id, message_id
+            ))
+            // matchers can't capture, the uuid is the old vote button
+            .matches(|req| {
+                let body = String::from_utf8_lossy(req.body.as_deref().unwrap_or_default());
+                body.contains("custom_id") && !body.contains("a1b2c3d4-0000-4000-8000-000000000002")
+            });
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json(message_id, channel_id));
+    });
+
+    run_test!(
+        "refresh",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        Ok((
+            http::StatusCode::OK,
+            Json(InteractionResponse {
+                kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    content: Some("The voting announcement was refreshed.".to_string()),
+                    flags: Some(MessageFlags::EPHEMERAL),
+                    ..Default::default()
+                }),
+            })
+        )),
+        true
+    );
+
+    announcement_mock.assert();
+    announcement_mock.delete();
+
+    // the old vote button is gone, the dialog custom ids are left alone
+    assert_eq!(
+        test.data.db.get_custom_id(vote_channel_uuid).await,
+        Err(dd_discord::db::DbError::NotFound)
+    );
+    test.data
+        .db
+        .get_custom_id(vote_dm_uuid)
+        .await
+        .expect("dialog custom id should be kept");
+
+    let custom_ids = test.data.db.get_custom_ids(&voting.id).await.unwrap();
+    let vote_channel_uuids: Vec<String> = custom_ids
+        .into_iter()
+        .filter(|(_, custom_id)| custom_id.action == Action::VoteFromChannel)
+        .map(|(uuid, _)| uuid)
+        .collect();
+    assert_eq!(vote_channel_uuids.len(), 1);
+    assert_ne!(vote_channel_uuids[0], vote_channel_uuid);
+}
+
+#[tokio::test]
+async fn handle_sort_choices_test() {
+    let dm_channel_id = "319674150115610528"; // dm_component.json
+    let dm_message_id = "1202277900045262871"; // dm_component.json
+    let user_id = "82198898841029460"; // dm_component.json
+    let channel_id = "1187315505103638638";
+    let message_id = "3589723985723";
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec![
+            "Spinoza".to_string(),
+            "Kant".to_string(),
+            "Aristotle".to_string(),
+        ],
+        channel_id: channel_id.to_string(),
+        message_id: message_id.to_string(),
+        name: "Who do you prefer?".to_string(),
+        creator_message_id: "812746127846424".to_string(),
+        creator_dm_channel_id: "319674150115610529".to_string(),
+        ..Default::default()
+    };
+
+    let sort_uuid = "a1b2c3d4-0000-4000-8000-000000000001";
+    let vote_channel_uuid = "a1b2c3d4-0000-4000-8000-000000000002";
+    let first_page_uuid = "a1b2c3d4-0000-4000-8000-000000000003";
+    let vote_dm_uuid = "a1b2c3d4-0000-4000-8000-000000000004";
+
+    let mut test = setup_test_env("dm_component.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .save_voting_dialog(
+            voting.id.clone(),
+            user_id.to_string(),
+            vec![0, 0, 0],
+            dm_message_id.to_string(),
+            dm_channel_id.to_string(),
+            false,
+        )
+        .await
+        .expect("Failed to save voting dialog");
+
+    let custom_id = |action: Action, page: Option<usize>| CustomID {
+        action,
+        voting_id: voting.id.clone(),
+        user_id: None,
+        page,
+        index: None,
+    };
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![
+            (sort_uuid.to_string(), custom_id(Action::SortChoices, None)),
+            (
+                vote_channel_uuid.to_string(),
+                custom_id(Action::VoteFromChannel, None),
+            ),
+            (
+                first_page_uuid.to_string(),
+                custom_id(Action::VoteNext, Some(1)),
+            ),
+            (
+                vote_dm_uuid.to_string(),
+                custom_id(Action::VoteFromDM, None),
+            ),
+        ])
+        .await
+        .expect("Failed to save custom ids");
+
+    // the announcement is re-rendered alphabetically, keeping the vote button
+    test.set_body(dm_component_body(sort_uuid, &[]));
+    let mut announcement_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH)
+            .path(format!(
+                "/api/v10/channels/{}/messages/{}",
+                channel_id, message_id
+            ))
+            .body_contains("Aristotle\\nKant\\nSpinoza")
+            .body_contains(vote_channel_uuid);
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json(message_id, channel_id));
+    });
+
+    run_test!(
+        "sort choices",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        Ok((
+            http::StatusCode::OK,
+            Json(InteractionResponse {
+                kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    content: Some("Choices are now displayed alphabetically.".to_string()),
+                    flags: Some(MessageFlags::EPHEMERAL),
+                    ..Default::default()
+                }),
+            })
+        )),
+        true
+    );
+
+    announcement_mock.assert();
+    announcement_mock.delete();
+
+    let sorted = test.data.db.get_voting(&voting.id).await.unwrap();
+    assert_eq!(sorted.display_order, Some(vec![2, 1, 0]));
+    assert_eq!(sorted.choices, voting.choices);
+
+    // the dialog lists the choices alphabetically
+    test.set_body(dm_component_body(first_page_uuid, &[]));
+    let mut dialog_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH)
+            .path(format!(
+                "/api/v10/channels/{}/messages/{}",
+                dm_channel_id, dm_message_id
+            ))
+            .body_contains("**1**: Aristotle\\n**2**: Kant\\n**3**: Spinoza");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json(dm_message_id, dm_channel_id));
+    });
+
+    run_test!(
+        "render sorted dialog",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        ack(),
+        true
+    );
+
+    dialog_mock.assert();
+    dialog_mock.delete();
+
+    // rank the choices through the rendered selects, which keep the original indices
+    let selects: Vec<(String, CustomID)> = test
+        .data
+        .db
+        .get_custom_ids(&voting.id)
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|(_, custom_id)| custom_id.action == Action::VoteSelect)
+        .collect();
+    assert_eq!(selects.len(), 3);
+
+    for (uuid, custom_id) in selects {
+        let rank = match voting.choices[custom_id.index.unwrap()].as_str() {
+            "Aristotle" => "1",
+            "Kant" => "2",
+            _ => "3",
+        };
+
+        test.set_body(dm_component_body(&uuid, &[rank]));
+        run_test!(
+            "select rank",
+            &test,
+            empty_mock_vec(),
+            empty_mock_vec(),
+            ack(),
+            true
+        );
+    }
+
+    test.set_body(dm_component_body(vote_dm_uuid, &[]));
+    let mut ballot_mock = test.dd_server.mock(|when, then| {
+        when.method(POST)
+            .path(format!("/v1/votings/{}/ballots/{}", voting.id, user_id))
+            .json_body(json!({
+                "ballot": {
+                    "Aristotle": 1,
+                    "Kant": 2,
+                    "Spinoza": 3,
+                }
+            }));
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(json!({"revoted": false}));
+    });
+
+    run_test!(
+        "submit sorted ballot",
+        &test,
+        empty_mock_vec(),
+        [(
+            PATCH,
+            format!(
+                "/api/v10/channels/{}/messages/{}",
+                dm_channel_id, dm_message_id
+            ),
+            message_json(dm_message_id, dm_channel_id)
+        )],
+        ack(),
+        true
+    );
+
+    ballot_mock.assert();
+    ballot_mock.delete();
+}
+
+#[tokio::test]
+async fn handle_set_deadline_test() {
+    let extend_uuid = "a1b2c3d4-0000-4000-8000-000000000006";
+    let clear_uuid = "a1b2c3d4-0000-4000-8000-000000000007";
+    let vote_uuid = "a1b2c3d4-0000-4000-8000-000000000008";
+    let channel_id = "1187315505103638638";
+    let message_id = "3589723985723";
+    let deadline_unix = util::unix_timestamp() + 2 * 60 * 60;
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        name: "Who do you prefer?".to_string(),
+        deadline_unix: Some(deadline_unix),
+        message_id: message_id.to_string(),
+        channel_id: channel_id.to_string(),
+        ..Default::default()
+    };
+
+    let mut test = setup_test_env("dm_component.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+
+    let custom_id = |action: Action| CustomID {
+        action,
+        voting_id: voting.id.clone(),
+        user_id: None,
+        page: None,
+        index: None,
+    };
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![
+            (extend_uuid.to_string(), custom_id(Action::ExtendDeadline)),
+            (clear_uuid.to_string(), custom_id(Action::ClearDeadline)),
+            (vote_uuid.to_string(), custom_id(Action::VoteFromChannel)),
+        ])
+        .await
+        .expect("Failed to save custom ids");
+
+    // the creator dm as rendered on creation
+    let component_body = |custom_id: &str| {
+        let mut body: serde_json::Value =
+            serde_json::from_str(&dm_component_body(custom_id, &[])).unwrap();
+        body["message"]["embeds"] = json!([{
+            "type": "rich",
+            "title": "Voting Created: Who do you prefer?",
+            "fields": [
+                {"name": "Choices", "value": "Spinoza\nKant", "inline": false},
+                {"name": "Deadline", "value": format!("<t:{}:f> (<t:{}:R>)", deadline_unix, deadline_unix), "inline": false},
+            ],
+        }]);
+        body.to_string()
+    };
+
+    let field_values = |response: &InteractionResponse| -> Vec<(String, String)> {
+        let data = response.data.as_ref().expect("response data should be set");
+        data.embeds.as_ref().expect("embeds should be set")[0]
+            .fields
+            .iter()
+            .map(|field| (field.name.clone(), field.value.clone()))
+            .collect()
+    };
+
+    let button_labels = |response: &InteractionResponse| -> Vec<String> {
+        let data = response.data.as_ref().expect("response data should be set");
+        data.components
+            .as_ref()
+            .expect("components should be set")
+            .iter()
+            .flat_map(|row| match row {
+                Component::ActionRow(row) => row.components.clone(),
+                _ => vec![],
+            })
+            .filter_map(|component| match component {
+                Component::Button(button) => button.label,
+                _ => None,
+            })
+            .collect()
+    };
+
+    // the announcement shows the new deadline too
+    let extended = deadline_unix + 60 * 60;
+    let mut announcement_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH)
+            .path(format!(
+                "/api/v10/channels/{}/messages/{}",
+                channel_id, message_id
+            ))
+            .body_contains(format!("<t:{}:R>", extended));
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json(message_id, channel_id));
+    });
+
+    test.set_body(component_body(extend_uuid));
+    let (status, response) = handle_interaction(
+        test.data.clone(),
+        test.headers.clone(),
+        test.body.to_string(),
+    )
+    .await
+    .expect("extending the deadline should succeed");
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        response.0.kind,
+        twilight_model::http::interaction::InteractionResponseType::UpdateMessage
+    );
+    announcement_mock.assert();
+    announcement_mock.delete();
+    assert_eq!(
+        test.data
+            .db
+            .get_voting(&voting.id)
+            .await
+            .unwrap()
+            .deadline_unix,
+        Some(extended)
+    );
+    assert_eq!(
+        field_values(&response.0),
+        vec![
+            ("Choices".to_string(), "Spinoza\nKant".to_string()),
+            (
+                "Deadline".to_string(),
+                format!("<t:{}:f> (<t:{}:R>)", extended, extended)
+            ),
+        ]
+    );
+    assert!(button_labels(&response.0).contains(&"Extend +1h".to_string()));
+
+    let mut announcement_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH).path(format!(
+            "/api/v10/channels/{}/messages/{}",
+            channel_id, message_id
+        ));
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json(message_id, channel_id));
+    });
+
+    test.set_body(component_body(clear_uuid));
+    let (_, response) = handle_interaction(
+        test.data.clone(),
+        test.headers.clone(),
+        test.body.to_string(),
+    )
+    .await
+    .expect("clearing the deadline should succeed");
+    announcement_mock.assert();
+    announcement_mock.delete();
+
+    assert_eq!(
+        test.data
+            .db
+            .get_voting(&voting.id)
+            .await
+            .unwrap()
+            .deadline_unix,
+        None
+    );
+    assert_eq!(
+        field_values(&response.0),
+        vec![("Choices".to_string(), "Spinoza\nKant".to_string())]
+    );
+    assert!(!button_labels(&response.0).contains(&"Extend +1h".to_string()));
+
+    // the deadline fired but the completion did not run yet
+    test.data
+        .db
+        .set_voting_deadline(&voting.id, Some(util::unix_timestamp() - 1))
+        .await
+        .unwrap();
+    test.set_body(component_body(extend_uuid));
+
+    run_test!(
+        "deadline passed",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        Ok((
+            http::StatusCode::OK,
+            Json(InteractionResponse {
+                kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    content: Some("The deadline of the voting has already passed.".to_string()),
+                    flags: Some(MessageFlags::EPHEMERAL),
+                    ..Default::default()
+                }),
+            })
+        )),
+        true
+    );
+}
+
+#[tokio::test]
+async fn handle_view_audit_test() {
+    let creator_id = "399954205235871744";
+    let audit_uuid = "a1b2c3d4-0000-4000-8000-000000000005";
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        name: "Who do you prefer?".to_string(),
+        creator_user_id: creator_id.to_string(),
+        ..Default::default()
+    };
+
+    let mut test = setup_test_env("dm_component.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .complete_open_voting(&voting.id, dd_discord::db::SYSTEM_USER_ID)
+        .await
+        .expect("Failed to complete voting");
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![(
+            audit_uuid.to_string(),
+            CustomID {
+                action: Action::ViewAudit,
+                voting_id: voting.id.clone(),
+                user_id: None,
+                page: None,
+                index: None,
+            },
+        )])
+        .await
+        .expect("Failed to save custom ids");
+
+    let events = test.data.db.get_audit(&voting.id).await.unwrap();
+    assert_eq!(events.len(), 2);
+
+    test.set_body(dm_component_body(audit_uuid, &[]));
+    let (status, response) = handle_interaction(
+        test.data.clone(),
+        test.headers.clone(),
+        test.body.to_string(),
+    )
+    .await
+    .expect("view audit should succeed");
+
+    assert_eq!(status, StatusCode::OK);
+    let data = response.0.data.expect("response data should be set");
+    assert_eq!(data.flags, Some(MessageFlags::EPHEMERAL));
+
+    let embeds = data.embeds.expect("embeds should be set");
+    assert_eq!(embeds.len(), 1);
+    assert_eq!(
+        embeds[0].title.as_deref(),
+        Some("Audit log: Who do you prefer?")
+    );
+    assert_eq!(
+        embeds[0].description.clone(),
+        Some(format!(
+            "<t:{}:f> Created by <@{creator_id}>\n<t:{}:f> Completed by the bot",
+            events[0].timestamp_unix, events[1].timestamp_unix
+        ))
+    );
+}
+
+#[tokio::test]
+async fn handle_remind_non_voters_test() {
+    let remind_uuid = "a1b2c3d4-0000-4000-8000-000000000006";
+    let voted_user = (
+        "82198898841029461",
+        "319674150115610531",
+        "1202277900045262881",
+    );
+    let pending_user = (
+        "82198898841029462",
+        "319674150115610532",
+        "1202277900045262882",
+    );
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        name: "Who do you prefer?".to_string(),
+        ..Default::default()
+    };
+
+    let mut test = setup_test_env("dm_component.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    for (user_id, channel_id, message_id) in [voted_user, pending_user] {
+        test.data
+            .db
+            .save_voting_dialog(
+                voting.id.clone(),
+                user_id.to_string(),
+                vec![0, 0],
+                message_id.to_string(),
+                channel_id.to_string(),
+                false,
+            )
+            .await
+            .expect("Failed to save voting dialog");
+    }
+    test.data
+        .db
+        .set_voting_dialog_submitted(&voting.id, voted_user.0, true, 0)
+        .await
+        .expect("Failed to submit voting dialog");
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![(
+            remind_uuid.to_string(),
+            CustomID {
+                action: Action::RemindNonVoters,
+                voting_id: voting.id.clone(),
+                user_id: None,
+                page: None,
+                index: None,
+            },
+        )])
+        .await
+        .expect("Failed to save custom ids");
+    test.set_body(dm_component_body(remind_uuid, &[]));
+
+    let mut voted_mock = create_mock!(
+        test.discord_server,
+        POST,
+        format!("/api/v10/channels/{}/messages", voted_user.1),
+        message_json(voted_user.2, voted_user.1)
+    );
+    let mut pending_mock = test.discord_server.mock(|when, then| {
+        when.method(POST)
+            .path(format!("/api/v10/channels/{}/messages", pending_user.1))
+            .body_contains("you haven't submitted your vote in **Who do you prefer?**")
+            .body_contains(format!(
+                "https://discord.com/channels/@me/{}/{}",
+                pending_user.1, pending_user.2
+            ));
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json(pending_user.2, pending_user.1));
+    });
+
+    run_test!(
+        "remind non voters",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        Ok((
+            http::StatusCode::OK,
+            Json(InteractionResponse {
+                kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    content: Some("Reminding 1 voters.".to_string()),
+                    flags: Some(MessageFlags::EPHEMERAL),
+                    ..Default::default()
+                }),
+            })
+        )),
+        true
+    );
+
+    test.wait_for_tasks().await;
+    pending_mock.assert();
+    pending_mock.delete();
+    voted_mock.assert_hits(0);
+    voted_mock.delete();
+
+    // reminders are rate limited per voting
+    let reminded_unix = test
+        .data
+        .db
+        .get_voting(&voting.id)
+        .await
+        .unwrap()
+        .reminded_unix
+        .expect("reminder time should be saved");
+    run_test!(
+        "remind again",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        Ok((
+            http::StatusCode::OK,
+            Json(InteractionResponse {
+                kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    content: Some(format!(
+                        "Non-voters were already reminded, you can remind them again <t:{}:R>.",
+                        reminded_unix + 3600
+                    )),
+                    flags: Some(MessageFlags::EPHEMERAL),
+                    ..Default::default()
+                }),
+            })
+        )),
+        true
+    );
+}
+
+#[tokio::test]
+async fn handle_vote_select_closed_voting_test() {
+    let dm_channel_id = "319674150115610528"; // dm_component.json
+    let dm_message_id = "1202277900045262871"; // dm_component.json
+    let user_id = "82198898841029460"; // dm_component.json
+    let select_uuid = "0c8a5a3e-6f3e-4d0c-9f69-1c2f3bd5e6a7"; // dm_component.json
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        name: "Who do you prefer?".to_string(),
+        ..Default::default()
+    };
+
+    let mut test = setup_test_env("dm_component.json");
+    test.set_body(dm_component_body(select_uuid, &["1"]));
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .save_voting_dialog(
+            voting.id.clone(),
+            user_id.to_string(),
+            vec![0, 0],
+            dm_message_id.to_string(),
+            dm_channel_id.to_string(),
+            false,
+        )
+        .await
+        .expect("Failed to save voting dialog");
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![(
+            select_uuid.to_string(),
+            CustomID {
+                action: Action::VoteSelect,
+                voting_id: voting.id.clone(),
+                user_id: None,
+                page: None,
+                index: Some(0),
+            },
+        )])
+        .await
+        .expect("Failed to save custom ids");
+
+    test.data
+        .db
+        .complete_open_voting(&voting.id, "creator")
+        .await
+        .expect("Failed to complete voting");
+
+    run_test!(
+        "select on completed voting",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        ack(),
+        true
+    );
+
+    let dialog = test
+        .data
+        .db
+        .get_voting_dialog(&voting.id, user_id)
+        .await
+        .expect("Failed to get voting dialog");
+    assert_eq!(dialog.ballot, vec![0, 0]);
+}
+
+#[test]
+fn disable_components_test() {
+    let components = vec![Component::ActionRow(ActionRow {
+        components: vec![
+            Component::Button(Button {
+                custom_id: Some("complete".to_string()),
+                disabled: false,
+                emoji: None,
+                label: Some("Complete Voting".to_string()),
+                style: ButtonStyle::Primary,
+                url: None,
+            }),
+            Component::Button(Button {
+                custom_id: Some("delete".to_string()),
+                disabled: false,
+                emoji: None,
+                label: Some("Delete Voting".to_string()),
+                style: ButtonStyle::Danger,
+                url: None,
+            }),
+            Component::SelectMenu(SelectMenu {
+                custom_id: "select".to_string(),
+                disabled: false,
+                max_values: Some(1),
+                min_values: Some(1),
+                options: Vec::new(),
+                placeholder: Some("Select".to_string()),
+            }),
+        ],
+    })];
+
+    let disabled = disable_components(&components);
+
+    assert_eq!(
+        disabled,
+        vec![Component::ActionRow(ActionRow {
+            components: vec![
+                Component::Button(Button {
+                    custom_id: Some("complete".to_string()),
+                    disabled: true,
+                    emoji: None,
+                    label: Some("Voting closed".to_string()),
+                    style: ButtonStyle::Primary,
+                    url: None,
+                }),
+                Component::Button(Button {
+                    custom_id: Some("delete".to_string()),
+                    disabled: true,
+                    emoji: None,
+                    label: Some("Delete Voting".to_string()),
+                    style: ButtonStyle::Danger,
+                    url: None,
+                }),
+                Component::SelectMenu(SelectMenu {
+                    custom_id: "select".to_string(),
+                    disabled: true,
+                    max_values: Some(1),
+                    min_values: Some(1),
+                    options: Vec::new(),
+                    placeholder: Some("Voting closed".to_string()),
+                }),
+            ],
+        })]
+    );
+}
+
+#[tokio::test]
+async fn handle_delete_voting_keep_closed_components_test() {
+    let custom_uuid = "0c8a5a3e-6f3e-4d0c-9f69-1c2f3bd5e6a7"; // dm_component.json
+    let dm_channel_id = "319674150115610528"; // dm_component.json
+    let creator_message_id = "1202277900045262871"; // dm_component.json
+    let channel_id = "1187315505103638638";
+    let message_id = "3589723985723";
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        channel_id: channel_id.to_string(),
+        message_id: message_id.to_string(),
+        name: "Who do you prefer?".to_string(),
+        creator_message_id: creator_message_id.to_string(),
+        creator_dm_channel_id: dm_channel_id.to_string(),
+        ..Default::default()
+    };
+
+    let test = setup_test_env_with_config(
+        "dm_component.json",
+        dd_discord::Config {
+            keep_closed_components: true,
+            ..Default::default()
+        },
+    );
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![(
+            custom_uuid.to_string(),
+            CustomID {
+                action: Action::Delete,
+                voting_id: voting.id.clone(),
+                user_id: None,
+                page: None,
+                index: None,
+            },
+        )])
+        .await
+        .expect("Failed to save custom ids");
+
+    // the vote button stays on the announcement, disabled
+    let mut channel_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH)
+            .path(format!(
+                "/api/v10/channels/{}/messages/{}",
+                channel_id, message_id
+            ))
+            .body_contains(r#""disabled":true"#)
+            .body_contains(r#""label":"Voting closed""#);
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json(message_id, channel_id));
+    });
+
+    let mut creator_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH)
+            .path(format!(
+                "/api/v10/channels/{}/messages/{}",
+                dm_channel_id, creator_message_id
+            ))
+            .body_contains(r#""disabled":true"#)
+            .body_contains(r#""label":"Delete Voting""#);
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json(creator_message_id, dm_channel_id));
+    });
+
+    run_test!(
+        "delete voting",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        ack(),
+        true
+    );
+
+    channel_mock.assert();
+    channel_mock.delete();
+    creator_mock.assert();
+    creator_mock.delete();
+}
+
+#[tokio::test]
+async fn handle_delete_voting_cancels_deadline_test() {
+    let custom_uuid = "0c8a5a3e-6f3e-4d0c-9f69-1c2f3bd5e6a7"; // dm_component.json
+    let dm_channel_id = "319674150115610528"; // dm_component.json
+    let creator_message_id = "1202277900045262871"; // dm_component.json
+    let channel_id = "1187315505103638638";
+    let message_id = "3589723985723";
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        channel_id: channel_id.to_string(),
+        message_id: message_id.to_string(),
+        name: "Who do you prefer?".to_string(),
+        creator_message_id: creator_message_id.to_string(),
+        creator_dm_channel_id: dm_channel_id.to_string(),
+        deadline_unix: Some(util::unix_timestamp() + 365 * 24 * 60 * 60),
+        ..Default::default()
+    };
+
+    let test = setup_test_env("dm_component.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![(
+            custom_uuid.to_string(),
+            CustomID {
+                action: Action::Delete,
+                voting_id: voting.id.clone(),
+                user_id: None,
+                page: None,
+                index: None,
+            },
+        )])
+        .await
+        .expect("Failed to save custom ids");
+
+    dd_discord::schedule_pending_deadlines(&test.data).await;
+    assert_eq!(test.data.scheduled_deadlines(), vec![voting.id.clone()]);
+
+    // no dd mocks, the scheduled completion never fetches the results
+    run_test!(
+        "delete voting with deadline",
+        &test,
+        empty_mock_vec(),
+        [
+            (
+                PATCH,
+                format!("/api/v10/channels/{}/messages/{}", channel_id, message_id),
+                message_json(message_id, channel_id)
+            ),
+            (
+                PATCH,
+                format!(
+                    "/api/v10/channels/{}/messages/{}",
+                    dm_channel_id, creator_message_id
+                ),
+                message_json(creator_message_id, dm_channel_id)
+            )
+        ],
+        ack(),
+        true
+    );
+
+    assert!(test.data.scheduled_deadlines().is_empty());
+    assert!(!test.data.cancel_deadline(&voting.id));
+
+    // the aborted task doesn't keep the tracker waiting for a year
+    tokio::time::timeout(Duration::from_secs(5), test.wait_for_tasks())
+        .await
+        .expect("scheduled deadline was not aborted");
+}
+
+#[tokio::test]
+async fn cancel_all_deadlines_test() {
+    let test = setup_test_env("dm_component.json");
+    for id in ["4712947128794", "4712947128795"] {
+        test.data
+            .db
+            .save_voting(dd_discord::db::Voting {
+                id: id.to_string(),
+                choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+                deadline_unix: Some(util::unix_timestamp() + 365 * 24 * 60 * 60),
+                ..Default::default()
+            })
+            .await
+            .expect("Failed to save voting");
+    }
+
+    dd_discord::schedule_pending_deadlines(&test.data).await;
+    assert_eq!(test.data.scheduled_deadlines().len(), 2);
+
+    assert_eq!(test.data.cancel_all_deadlines(), 2);
+    assert!(test.data.scheduled_deadlines().is_empty());
+
+    // a shutdown doesn't wait for the deadlines, they are scheduled again on the next start
+    tokio::time::timeout(Duration::from_secs(5), test.wait_for_tasks())
+        .await
+        .expect("scheduled deadlines were not aborted");
+}
+
+#[tokio::test]
+async fn handle_delete_voting_failed_cleanup_test() {
+    let custom_uuid = "0c8a5a3e-6f3e-4d0c-9f69-1c2f3bd5e6a7"; // dm_component.json
+    let dm_channel_id = "319674150115610528"; // dm_component.json
+    let creator_message_id = "1202277900045262871"; // dm_component.json
+    let channel_id = "1187315505103638638";
+    let message_id = "3589723985723";
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        channel_id: channel_id.to_string(),
+        message_id: message_id.to_string(),
+        name: "Who do you prefer?".to_string(),
+        creator_message_id: creator_message_id.to_string(),
+        creator_dm_channel_id: dm_channel_id.to_string(),
+        ..Default::default()
+    };
+
+    let test = setup_test_env("dm_component.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![(
+            custom_uuid.to_string(),
+            CustomID {
+                action: Action::Delete,
+                voting_id: voting.id.clone(),
+                user_id: None,
+                page: None,
+                index: None,
+            },
+        )])
+        .await
+        .expect("Failed to save custom ids");
+
+    // (user id, dm channel id, dialog message id)
+    let dialogs = [
+        (
+            "82198898841029460",
+            "1202277900045262001",
+            "1202277900045263001",
+        ),
+        (
+            "82198898841029461",
+            "1202277900045262002",
+            "1202277900045263002",
+        ),
+        (
+            "82198898841029462",
+            "1202277900045262003",
+            "1202277900045263003",
+        ),
+    ];
+    for (user_id, dialog_channel_id, dialog_message_id) in dialogs {
+        test.data
+            .db
+            .save_voting_dialog(
+                voting.id.clone(),
+                user_id.to_string(),
+                vec![1, 2],
+                dialog_message_id.to_string(),
+                dialog_channel_id.to_string(),
+                false,
+            )
+            .await
+            .expect("Failed to save voting dialog");
+    }
+
+    let mut channel_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH).path(format!(
+            "/api/v10/channels/{}/messages/{}",
+            channel_id, message_id
+        ));
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json(message_id, channel_id));
+    });
+
+    let mut creator_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH).path(format!(
+            "/api/v10/channels/{}/messages/{}",
+            dm_channel_id, creator_message_id
+        ));
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json(creator_message_id, dm_channel_id));
+    });
+
+    let mut dialog_mocks = Vec::new();
+    for (i, (_, dialog_channel_id, dialog_message_id)) in dialogs.iter().enumerate() {
+        let mock = test.discord_server.mock(|when, then| {
+            when.method(PATCH).path(format!(
+                "/api/v10/channels/{}/messages/{}",
+                dialog_channel_id, dialog_message_id
+            ));
+            // the second dialog message can not be updated
+            if i == 1 {
+                then.status(403)
+                    .header("Content-Type", "application/json")
+                    .json_body(json!({"message": "Missing Access", "code": 50001}));
+            } else {
+                then.status(200)
+                    .header("Content-Type", "application/json")
+                    .json_body(message_json(dialog_message_id, dialog_channel_id));
+            }
+        });
+        dialog_mocks.push(mock);
+    }
+
+    run_test!(
+        "delete voting",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        ack(),
+        true
+    );
+    test.wait_for_tasks().await;
+
+    for mock in dialog_mocks.iter_mut() {
+        mock.assert();
+        mock.delete();
+    }
+    channel_mock.assert();
+    channel_mock.delete();
+    creator_mock.assert();
+    creator_mock.delete();
+
+    // every dialog row is gone, the failed one is kept for a retry instead
+    let remaining = test
+        .data
+        .db
+        .get_voting_dialogs(&voting.id)
+        .await
+        .expect("Failed to get voting dialogs");
+    assert!(remaining.is_empty());
+
+    let failed = test
+        .data
+        .db
+        .get_due_failed_cleanups(u64::MAX)
+        .await
+        .expect("Failed to get failed cleanups");
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].user_id, dialogs[1].0);
+    assert_eq!(failed[0].channel_id, dialogs[1].1);
+    assert_eq!(failed[0].message_id, dialogs[1].2);
+    assert_eq!(failed[0].content, "Voting deleted: Who do you prefer?");
+    assert_eq!(failed[0].attempts, 1);
+    assert!(failed[0].retry_unix > util::unix_timestamp());
+
+    // nothing is due yet, the retry waits for the backoff
+    dd_discord::retry_failed_cleanups(&test.data).await;
+    let failed = test
+        .data
+        .db
+        .get_due_failed_cleanups(u64::MAX)
+        .await
+        .expect("Failed to get failed cleanups");
+    assert_eq!(failed[0].attempts, 1);
+}
+
+#[test]
+fn build_voting_command_localizations_test() {
+    let localizations = HashMap::from([(
+        "de".to_string(),
+        (
+            "abstimmung".to_string(),
+            "Eine Abstimmung erstellen".to_string(),
+        ),
+    )]);
+
+    let cmd = serde_json::to_value(util::build_voting_command(5, &localizations)).unwrap();
+
+    assert_eq!(cmd["name"], "voting");
+    assert_eq!(cmd["description"], "Create a voting");
+    assert_eq!(cmd["name_localizations"], json!({"de": "abstimmung"}));
+    assert_eq!(
+        cmd["description_localizations"],
+        json!({"de": "Eine Abstimmung erstellen"})
+    );
+
+    // without translations only the default strings are registered
+    let cmd = serde_json::to_value(util::build_voting_command(5, &HashMap::new())).unwrap();
+    assert!(cmd.get("name_localizations").is_none());
+    assert!(cmd.get("description_localizations").is_none());
+}
+
+#[test]
+fn validate_ballot_max_ranks_test() {
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec![
+            "Spinoza".to_string(),
+            "Kant".to_string(),
+            "Nietzsche".to_string(),
+            "Hume".to_string(),
+            "Plato".to_string(),
+        ],
+        max_ranks: Some(3),
+        ..Default::default()
+    };
+
+    assert_eq!(validate_ballot(&voting, &[1, 2, 3, 0, 0]), Ok(()));
+    assert_eq!(validate_ballot(&voting, &[1, 1, 0, 0, 0]), Ok(()));
+    assert_eq!(
+        validate_ballot(&voting, &[1, 2, 3, 4, 0]),
+        Err("You can only rank choices from 1 to 3.".to_string())
+    );
+    assert_eq!(
+        validate_ballot(&voting, &[1, 2, 3]),
+        Err("Your ballot does not match the voting choices.".to_string())
+    );
+
+    let voting = dd_discord::db::Voting {
+        max_ranks: None,
+        ..voting
+    };
+    assert_eq!(validate_ballot(&voting, &[1, 2, 3, 4, 5]), Ok(()));
+}
+
+#[tokio::test]
+async fn handle_vote_page_max_ranks_test() {
+    let dm_channel_id = "319674150115610528"; // dm_component.json
+    let dm_message_id = "1202277900045262871"; // dm_component.json
+    let user_id = "82198898841029460"; // dm_component.json
+    let page_uuid = "0c8a5a3e-6f3e-4d0c-9f69-1c2f3bd5e6a7"; // dm_component.json
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec![
+            "Spinoza".to_string(),
+            "Kant".to_string(),
+            "Nietzsche".to_string(),
+            "Hume".to_string(),
+        ],
+        name: "Who do you prefer?".to_string(),
+        max_ranks: Some(3),
+        ..Default::default()
+    };
+
+    let test = setup_test_env("dm_component.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .save_voting_dialog(
+            voting.id.clone(),
+            user_id.to_string(),
+            vec![0, 0, 0, 0],
+            dm_message_id.to_string(),
+            dm_channel_id.to_string(),
+            false,
+        )
+        .await
+        .expect("Failed to save voting dialog");
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![(
+            page_uuid.to_string(),
+            CustomID {
+                action: Action::VoteNext,
+                voting_id: voting.id.clone(),
+                user_id: None,
+                page: Some(1),
+                index: None,
+            },
+        )])
+        .await
+        .expect("Failed to save custom ids");
+
+    // only ranks 1 to 3 are offered, even though there are 4 choices
+    let mut page_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH)
+            .path(format!(
+                "/api/v10/channels/{}/messages/{}",
+                dm_channel_id, dm_message_id
+            ))
+            .body_contains(r#""options":[{"default":false,"label":"1","value":"1"},{"default":false,"label":"2","value":"2"},{"default":false,"label":"3","value":"3"}]"#);
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json(dm_message_id, dm_channel_id));
+    });
+
+    run_test!(
+        "render page with max ranks",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        ack(),
+        true
+    );
+
+    page_mock.assert();
+    page_mock.delete();
+}
+
+#[tokio::test]
+async fn handle_reset_ballot_test() {
+    let dm_channel_id = "319674150115610528"; // dm_component.json
+    let dm_message_id = "1202277900045262871"; // dm_component.json
+    let user_id = "82198898841029460"; // dm_component.json
+    let reset_uuid = "0c8a5a3e-6f3e-4d0c-9f69-1c2f3bd5e6a7"; // dm_component.json
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec![
+            "Spinoza".to_string(),
+            "Kant".to_string(),
+            "Nietzsche".to_string(),
+            "Hume".to_string(),
+            "Locke".to_string(),
+        ],
+        name: "Who do you prefer?".to_string(),
+        ..Default::default()
+    };
+
+    let test = setup_test_env("dm_component.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .save_voting_dialog(
+            voting.id.clone(),
+            user_id.to_string(),
+            vec![2, 1, 0, 3, 4],
+            dm_message_id.to_string(),
+            dm_channel_id.to_string(),
+            false,
+        )
+        .await
+        .expect("Failed to save voting dialog");
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![(
+            reset_uuid.to_string(),
+            CustomID {
+                action: Action::ResetBallot,
+                voting_id: voting.id.clone(),
+                user_id: Some(user_id.to_string()),
+                page: None,
+                index: None,
+            },
+        )])
+        .await
+        .expect("Failed to save custom ids");
+
+    // the first page is rendered again, every select is unranked
+    let mut page_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH)
+            .path(format!(
+                "/api/v10/channels/{}/messages/{}",
+                dm_channel_id, dm_message_id
+            ))
+            .body_contains("Voting Choices - Page 1 of 2")
+            .matches(|req| {
+                let body = String::from_utf8_lossy(req.body.as_deref().unwrap_or_default());
+                body.matches(r#""placeholder":"Select""#).count() == 4
+            });
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json(dm_message_id, dm_channel_id));
+    });
+
+    run_test!(
+        "reset ballot",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        ack(),
+        true
+    );
+
+    page_mock.assert();
+    page_mock.delete();
+
+    let dialog = test
+        .data
+        .db
+        .get_voting_dialog(&voting.id, user_id)
+        .await
+        .unwrap();
+    assert_eq!(dialog.ballot, vec![0; 5]);
+}
+
+#[test]
+fn split_choice_description_test() {
+    assert_eq!(
+        split_choice_description("Kant | Critique of Pure Reason", true),
+        ("Kant".to_string(), "Critique of Pure Reason".to_string())
+    );
+    assert_eq!(
+        split_choice_description(" Kant ", true),
+        ("Kant".to_string(), String::new())
+    );
+
+    // without descriptions the | is part of the choice
+    assert_eq!(
+        split_choice_description(" Rock | Paper ", false),
+        ("Rock | Paper".to_string(), String::new())
+    );
+
+    let (choice, description) =
+        split_choice_description(&format!("Kant|{}", "a".repeat(150)), true);
+    assert_eq!(choice, "Kant");
+    assert_eq!(description.chars().count(), MAX_CHOICE_DESCRIPTION_LENGTH);
+}
+
+#[tokio::test]
+async fn handle_vote_page_choice_descriptions_test() {
+    let dm_channel_id = "319674150115610528"; // dm_component.json
+    let dm_message_id = "1202277900045262871"; // dm_component.json
+    let user_id = "82198898841029460"; // dm_component.json
+    let page_uuid = "0c8a5a3e-6f3e-4d0c-9f69-1c2f3bd5e6a7"; // dm_component.json
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        choice_descriptions: vec![String::new(), "Critique of Pure Reason".to_string()],
+        name: "Who do you prefer?".to_string(),
+        ..Default::default()
+    };
+
+    let test = setup_test_env("dm_component.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .save_voting_dialog(
+            voting.id.clone(),
+            user_id.to_string(),
+            vec![0, 0],
+            dm_message_id.to_string(),
+            dm_channel_id.to_string(),
+            false,
+        )
+        .await
+        .expect("Failed to save voting dialog");
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![(
+            page_uuid.to_string(),
+            CustomID {
+                action: Action::VoteNext,
+                voting_id: voting.id.clone(),
+                user_id: Some(user_id.to_string()),
+                page: Some(1),
+                index: None,
+            },
+        )])
+        .await
+        .expect("Failed to save custom ids");
+
+    // both rank options of Kant are described, the ones of Spinoza are not
+    let mut page_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH)
+            .path(format!(
+                "/api/v10/channels/{}/messages/{}",
+                dm_channel_id, dm_message_id
+            ))
+            .matches(|req| {
+                let body = String::from_utf8_lossy(req.body.as_deref().unwrap_or_default());
+                body.matches(r#""description":"Critique of Pure Reason""#)
+                    .count()
+                    == 2
+            });
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json(dm_message_id, dm_channel_id));
+    });
+
+    run_test!(
+        "vote page with descriptions",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        ack(),
+        true
+    );
+
+    page_mock.assert();
+    page_mock.delete();
+}
+
+#[test]
+fn render_completion_template_test() {
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        name: "Who do you prefer?".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        ..Default::default()
+    };
+
+    let mut results: VotingResults = serde_json::from_value(json!({
+        "tie": false,
+        "results": [
+            {"choice": "Kant", "index": 1, "wins": 1, "percentage": 100.0, "strength": 1, "advantage": 1},
+            {"choice": "Spinoza", "index": 0, "wins": 0, "percentage": 0.0, "strength": 0, "advantage": 0},
+        ],
+        "duels": null,
+    }))
+    .unwrap();
+
+    let template = "{name} - <@&1187313045127581999> winner: {winner}, runner up: {runner_up}";
+
+    assert_eq!(
+        render_completion_template(template, &voting, &results),
+        "Who do you prefer? - <@&1187313045127581999> winner: Kant, runner up: Spinoza"
+    );
+
+    results.tie = true;
+    assert_eq!(
+        render_completion_template(template, &voting, &results),
+        "Who do you prefer? - <@&1187313045127581999> winner: tie, runner up: Spinoza"
+    );
+
+    results.results.clear();
+    results.tie = false;
+    assert_eq!(
+        render_completion_template("{winner}{runner_up}", &voting, &results),
+        ""
+    );
+}
+
+#[test]
+fn result_summary_test() {
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        name: "Who do you prefer?".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        ..Default::default()
+    };
+
+    let mut results: VotingResults = serde_json::from_value(json!({
+        "tie": false,
+        "results": [
+            {"choice": "Kant", "index": 1, "wins": 1, "percentage": 100.0, "strength": 1, "advantage": 1},
+            {"choice": "Spinoza", "index": 0, "wins": 0, "percentage": 0.0, "strength": 0, "advantage": 0},
+        ],
+        "duels": null,
+    }))
+    .unwrap();
+
+    assert_eq!(
+        result_summary(&voting, &results),
+        "**Who do you prefer?** is completed, the winner is **Kant**."
+    );
+
+    results.tie = true;
+    assert_eq!(
+        result_summary(&voting, &results),
+        "**Who do you prefer?** is completed and ended in a tie."
+    );
+
+    results.results.clear();
+    results.tie = false;
+    assert_eq!(
+        result_summary(&voting, &results),
+        "**Who do you prefer?** is completed, nobody voted."
+    );
+}
+
+#[test]
+fn results_artifact_test() {
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        name: "Who do you prefer?".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        ..Default::default()
+    };
+
+    let results: VotingResults = serde_json::from_value(json!({
+        "tie": false,
+        "results": [
+            {"choice": "Kant", "index": 1, "wins": 1, "percentage": 100.0, "strength": 1, "advantage": 1},
+            {"choice": "Spinoza", "index": 0, "wins": 0, "percentage": 0.0, "strength": 0, "advantage": 0},
+        ],
+        "duels": [
+            {
+                "left": {"index": 1, "choice": "Kant", "strength": 2},
+                "right": {"index": 0, "choice": "Spinoza", "strength": 1},
+            },
+        ],
+    }))
+    .unwrap();
+
+    // the ids of the voting and its voters are left out
+    assert_eq!(
+        serde_json::to_value(ResultsArtifact::new(&voting, &results)).unwrap(),
+        json!({
+            "name": "Who do you prefer?",
+            "is_completed": false,
+            "choices": ["Spinoza", "Kant"],
+            "ranking": [
+                {"choice": "Kant", "wins": 1, "percentage": 100.0},
+                {"choice": "Spinoza", "wins": 0, "percentage": 0.0},
+            ],
+            "duels": [
+                {"left": "Kant", "left_strength": 2, "right": "Spinoza", "right_strength": 1},
+            ],
+            "tie": false,
+        })
+    );
+
+    // results without duels have an empty list
+    let results: VotingResults = serde_json::from_value(json!({
+        "tie": true,
+        "results": [],
+        "duels": null,
+    }))
+    .unwrap();
+    let artifact = ResultsArtifact::new(&voting, &results);
+    assert!(artifact.duels.is_empty());
+    assert!(artifact.ranking.is_empty());
+    assert!(artifact.tie);
+}
+
+fn tied_results() -> VotingResults {
+    // Hume, Kant and Spinoza share the top, Nietzsche is last
+    serde_json::from_value(json!({
+        "tie": true,
+        "results": [
+            {"choice": "Hume", "index": 3, "wins": 2, "percentage": 66.67, "strength": 2, "advantage": 0},
+            {"choice": "Kant", "index": 1, "wins": 2, "percentage": 66.67, "strength": 2, "advantage": 0},
+            {"choice": "Spinoza", "index": 0, "wins": 2, "percentage": 66.67, "strength": 2, "advantage": 0},
+            {"choice": "Nietzsche", "index": 2, "wins": 0, "percentage": 0.0, "strength": 0, "advantage": 0},
+        ],
+        "duels": null,
+    }))
+    .unwrap()
+}
+
+fn tie_break_voting(tie_break: TieBreak) -> dd_discord::db::Voting {
+    dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        name: "Who do you prefer?".to_string(),
+        choices: vec![
+            "Spinoza".to_string(),
+            "Kant".to_string(),
+            "Nietzsche".to_string(),
+            "Hume".to_string(),
+        ],
+        tie_break,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn break_tie_none_test() {
+    let results = tied_results();
+    assert_eq!(break_tie(&tie_break_voting(TieBreak::None), &results), None);
+
+    // nothing to break without a tie
+    let mut results = tied_results();
+    results.tie = false;
+    assert_eq!(
+        break_tie(&tie_break_voting(TieBreak::EarliestChoice), &results),
+        None
+    );
+}
+
+#[test]
+fn break_tie_random_test() {
+    let results = tied_results();
+    let voting = tie_break_voting(TieBreak::Random);
+
+    let winner = break_tie(&voting, &results).expect("tie should be broken");
+    assert!(["Hume", "Kant", "Spinoza"].contains(&winner.choice.as_str()));
+
+    // every render of the results draws the same winner
+    for _ in 0..10 {
+        assert_eq!(break_tie(&voting, &results), Some(winner));
+    }
+}
+
+#[test]
+fn break_tie_earliest_choice_test() {
+    let results = tied_results();
+    let winner = break_tie(&tie_break_voting(TieBreak::EarliestChoice), &results);
+    assert_eq!(winner.map(|result| result.choice.as_str()), Some("Spinoza"));
+}
+
+#[test]
+fn break_tie_first_choice_count_test() {
+    let results = tied_results();
+    let mut voting = tie_break_voting(TieBreak::FirstChoiceCount);
+
+    // Nietzsche has the most first-choice votes, but is not tied for the win
+    voting.first_choice_counts = Some(HashMap::from([(0, 1), (1, 1), (2, 5), (3, 3)]));
+    let winner = break_tie(&voting, &results);
+    assert_eq!(winner.map(|result| result.choice.as_str()), Some("Hume"));
+
+    // equal counts fall back to the earliest choice
+    voting.first_choice_counts = Some(HashMap::from([(0, 2), (1, 2), (3, 1)]));
+    let winner = break_tie(&voting, &results);
+    assert_eq!(winner.map(|result| result.choice.as_str()), Some("Spinoza"));
+
+    voting.first_choice_counts = None;
+    let winner = break_tie(&voting, &results);
+    assert_eq!(winner.map(|result| result.choice.as_str()), Some("Spinoza"));
+}
+
+#[test]
+fn render_completion_template_tie_break_test() {
+    let results = tied_results();
+    let voting = tie_break_voting(TieBreak::EarliestChoice);
+    assert_eq!(
+        render_completion_template("winner: {winner}", &voting, &results),
+        "winner: Spinoza"
+    );
+}
+
+#[tokio::test]
+async fn handle_complete_voting_private_results_test() {
+    let custom_uuid = "0c8a5a3e-6f3e-4d0c-9f69-1c2f3bd5e6a7"; // dm_component.json
+    let dm_channel_id = "319674150115610528"; // dm_component.json
+    let creator_message_id = "1202277900045262871"; // dm_component.json
+    let channel_id = "1187315505103638638";
+    let message_id = "3589723985723";
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        channel_id: channel_id.to_string(),
+        message_id: message_id.to_string(),
+        name: "Who do you prefer?".to_string(),
+        creator_message_id: creator_message_id.to_string(),
+        creator_dm_channel_id: dm_channel_id.to_string(),
+        private_results: true,
+        ..Default::default()
+    };
+
+    let test = setup_test_env("dm_component.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![(
+            custom_uuid.to_string(),
+            CustomID {
+                action: Action::Complete,
+                voting_id: voting.id.clone(),
+                user_id: None,
+                page: None,
+                index: None,
+            },
+        )])
+        .await
+        .expect("Failed to save custom ids");
+
+    let results = json!({
+        "tie": false,
+        "results": [
+            {"choice": "Kant", "index": 1, "wins": 1, "percentage": 100.0, "strength": 1, "advantage": 1},
+            {"choice": "Spinoza", "index": 0, "wins": 0, "percentage": 0.0, "strength": 0, "advantage": 0},
+        ],
+        "duels": [
+            {
+                "left": {"index": 1, "choice": "Kant", "strength": 2},
+                "right": {"index": 0, "choice": "Spinoza", "strength": 1},
+            },
+        ],
+    });
+
+    // the channel only learns that the voting is over
+    let mut channel_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH)
+            .path(format!(
+                "/api/v10/channels/{}/messages/{}",
+                channel_id, message_id
+            ))
+            .body_contains(r#""content":"Voting has concluded.""#)
+            .body_contains(r#""embeds":[]"#)
+            .matches(|req| {
+                let body = String::from_utf8_lossy(req.body.as_deref().unwrap_or_default());
+                !body.contains("Kant") && !body.contains("Percentage")
+            });
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json(message_id, channel_id));
+    });
+
+    // the creator dm carries the ranking and the head-to-head breakdown
+    let mut creator_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH)
+            .path(format!(
+                "/api/v10/channels/{}/messages/{}",
+                dm_channel_id, creator_message_id
+            ))
+            .body_contains(r#""content":"Voting completed!""#)
+            .body_contains(r#""name":"Kant","value":"Wins: 1, Percentage: 100.00%"#)
+            .body_contains("**Kant** defeats **Spinoza** by (2 - 1) = 1 votes");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json(creator_message_id, dm_channel_id));
+    });
+
+    run_test!(
+        "complete voting with private results",
+        &test,
+        [(
+            GET,
+            format!("/v1/votings/{}/results/duels", voting.id),
+            results
+        )],
+        empty_mock_vec(),
+        ack(),
+        true
+    );
+
+    channel_mock.assert();
+    channel_mock.delete();
+    creator_mock.assert();
+    creator_mock.delete();
+}
+
+#[tokio::test]
+async fn handle_download_ballots_test() {
+    let custom_uuid = "0c8a5a3e-6f3e-4d0c-9f69-1c2f3bd5e6a7"; // dm_component.json
+    let dm_channel_id = "319674150115610528"; // dm_component.json
+    let user_id = "82198898841029460"; // dm_component.json
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec![
+            "Spinoza".to_string(),
+            "Kant, Immanuel".to_string(),
+            "=HYPERLINK(\"x\")".to_string(),
+        ],
+        name: "Who do you prefer?".to_string(),
+        creator_user_id: user_id.to_string(),
+        creator_dm_channel_id: dm_channel_id.to_string(),
+        is_completed: true,
+        ..Default::default()
+    };
+    let ballots = vec![
+        (user_id.to_string(), vec![1, 2, 3]),
+        ("319674150115610529".to_string(), vec![0, 1, 0]),
+    ];
+
+    let test = setup_test_env("dm_component.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .set_voting_ballots(
+            &voting.id,
+            VotingBallots {
+                ballots: ballots.clone(),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Failed to save voting ballots");
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![(
+            custom_uuid.to_string(),
+            CustomID {
+                action: Action::DownloadBallots,
+                voting_id: voting.id.clone(),
+                user_id: None,
+                page: None,
+                index: None,
+            },
+        )])
+        .await
+        .expect("Failed to save custom ids");
+
+    // the formula choice stays text in a spreadsheet
+    let expected_csv = format!(
+        "voter,Spinoza,\"Kant, Immanuel\",\"'=HYPERLINK(\"\"x\"\")\"\n{},1,2,3\n319674150115610529,,1,\n",
+        user_id
+    );
+    assert_eq!(ballots_csv(&voting, &ballots), expected_csv);
+
+    let mut ballots_mock = test.discord_server.mock(|when, then| {
+        when.method(POST)
+            .path(format!("/api/v10/channels/{}/messages", dm_channel_id))
+            .body_contains(format!("ballots-{}.csv", voting.id))
+            .body_contains(expected_csv.clone());
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json("1202277900045262872", dm_channel_id));
+    });
+
+    run_test!(
+        "download ballots",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        ack(),
+        true
+    );
+
+    ballots_mock.assert();
+    ballots_mock.delete();
+}
+
+#[tokio::test]
+async fn handle_complete_voting_template_test() {
+    let custom_uuid = "0c8a5a3e-6f3e-4d0c-9f69-1c2f3bd5e6a7"; // dm_component.json
+    let dm_channel_id = "319674150115610528"; // dm_component.json
+    let creator_message_id = "1202277900045262871"; // dm_component.json
+    let channel_id = "1187315505103638638";
+    let message_id = "3589723985723";
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        channel_id: channel_id.to_string(),
+        message_id: message_id.to_string(),
+        name: "Who do you prefer?".to_string(),
+        creator_message_id: creator_message_id.to_string(),
+        creator_dm_channel_id: dm_channel_id.to_string(),
+        completion_template: Some("@everyone {name}: {winner} wins".to_string()),
+        ..Default::default()
+    };
+
+    let test = setup_test_env("dm_component.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![(
+            custom_uuid.to_string(),
+            CustomID {
+                action: Action::Complete,
+                voting_id: voting.id.clone(),
+                user_id: None,
+                page: None,
+                index: None,
+            },
+        )])
+        .await
+        .expect("Failed to save custom ids");
+
+    let results = json!({
+        "tie": false,
+        "results": [
+            {"choice": "Kant", "index": 1, "wins": 1, "percentage": 100.0, "strength": 1, "advantage": 1},
+            {"choice": "Spinoza", "index": 0, "wins": 0, "percentage": 0.0, "strength": 0, "advantage": 0},
+        ],
+        "duels": null,
+    });
+
+    // mentions are not allowed unless the creator enabled them
+    let mut channel_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH)
+            .path(format!(
+                "/api/v10/channels/{}/messages/{}",
+                channel_id, message_id
+            ))
+            .body_contains(r#""content":"@everyone Who do you prefer?: Kant wins""#)
+            .body_contains(r#""allowed_mentions":{"parse":[]}"#);
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json(message_id, channel_id));
+    });
+
+    run_test!(
+        "complete voting with template",
+        &test,
+        [(
+            GET,
+            format!("/v1/votings/{}/results/duels", voting.id),
+            results
+        )],
+        [(
+            PATCH,
+            format!(
+                "/api/v10/channels/{}/messages/{}",
+                dm_channel_id, creator_message_id
+            ),
+            message_json(creator_message_id, dm_channel_id)
+        )],
+        ack(),
+        true
+    );
+
+    channel_mock.assert();
+    channel_mock.delete();
+}
+
+#[tokio::test]
+async fn handle_complete_voting_pin_result_test() {
+    let custom_uuid = "0c8a5a3e-6f3e-4d0c-9f69-1c2f3bd5e6a7"; // dm_component.json
+    let dm_channel_id = "319674150115610528"; // dm_component.json
+    let creator_message_id = "1202277900045262871"; // dm_component.json
+    let channel_id = "1187315505103638638";
+    let message_id = "3589723985723";
+    let summary_message_id = "3589723985724";
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        channel_id: channel_id.to_string(),
+        message_id: message_id.to_string(),
+        name: "Who do you prefer?".to_string(),
+        creator_message_id: creator_message_id.to_string(),
+        creator_dm_channel_id: dm_channel_id.to_string(),
+        pin_result: true,
+        ..Default::default()
+    };
+
+    let test = setup_test_env("dm_component.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![(
+            custom_uuid.to_string(),
+            CustomID {
+                action: Action::Complete,
+                voting_id: voting.id.clone(),
+                user_id: None,
+                page: None,
+                index: None,
+            },
+        )])
+        .await
+        .expect("Failed to save custom ids");
+
+    let results = json!({
+        "tie": false,
+        "results": [
+            {"choice": "Kant", "index": 1, "wins": 1, "percentage": 100.0, "strength": 1, "advantage": 1},
+            {"choice": "Spinoza", "index": 0, "wins": 0, "percentage": 0.0, "strength": 0, "advantage": 0},
+        ],
+        "duels": null,
+    });
+
+    // the summary is a new message, the announcement is still edited with the results
+    let mut summary_mock = test.discord_server.mock(|when, then| {
+        when.method(POST)
+            .path(format!("/api/v10/channels/{}/messages", channel_id))
+            .body_contains(
+                r#""content":"**Who do you prefer?** is completed, the winner is **Kant**.""#,
+            );
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json(summary_message_id, channel_id));
+    });
+
+    let mut pin_mock = test.discord_server.mock(|when, then| {
+        when.method(PUT).path(format!(
+            "/api/v10/channels/{}/pins/{}",
+            channel_id, summary_message_id
+        ));
+        then.status(204);
+    });
+
+    run_test!(
+        "complete voting with pinned result",
+        &test,
+        [(
+            GET,
+            format!("/v1/votings/{}/results/duels", voting.id),
+            results
+        )],
+        [
+            (
+                PATCH,
+                format!("/api/v10/channels/{}/messages/{}", channel_id, message_id),
+                message_json(message_id, channel_id)
+            ),
+            (
+                PATCH,
+                format!(
+                    "/api/v10/channels/{}/messages/{}",
+                    dm_channel_id, creator_message_id
+                ),
+                message_json(creator_message_id, dm_channel_id)
+            )
+        ],
+        ack(),
+        true
+    );
+
+    summary_mock.assert();
+    summary_mock.delete();
+    pin_mock.assert();
+    pin_mock.delete();
+}
+
+#[tokio::test]
+async fn handle_complete_voting_deleted_message_test() {
+    let custom_uuid = "0c8a5a3e-6f3e-4d0c-9f69-1c2f3bd5e6a7"; // dm_component.json
+    let dm_channel_id = "319674150115610528"; // dm_component.json
+    let creator_message_id = "1202277900045262871"; // dm_component.json
+    let channel_id = "1187315505103638638";
+    let message_id = "3589723985723";
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        channel_id: channel_id.to_string(),
+        message_id: message_id.to_string(),
+        name: "Who do you prefer?".to_string(),
+        creator_message_id: creator_message_id.to_string(),
+        creator_dm_channel_id: dm_channel_id.to_string(),
+        ..Default::default()
+    };
+
+    let test = setup_test_env("dm_component.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![(
+            custom_uuid.to_string(),
+            CustomID {
+                action: Action::Complete,
+                voting_id: voting.id.clone(),
+                user_id: None,
+                page: None,
+                index: None,
+            },
+        )])
+        .await
+        .expect("Failed to save custom ids");
+
+    let results = json!({
+        "tie": false,
+        "results": [
+            {"choice": "Kant", "index": 1, "wins": 1, "percentage": 100.0, "strength": 1, "advantage": 1},
+            {"choice": "Spinoza", "index": 0, "wins": 0, "percentage": 0.0, "strength": 0, "advantage": 0},
+        ],
+        "duels": null,
+    });
+
+    // a moderator deleted the announcement
+    let mut channel_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH).path(format!(
+            "/api/v10/channels/{}/messages/{}",
+            channel_id, message_id
+        ));
+        then.status(404)
+            .header("Content-Type", "application/json")
+            .json_body(json!({"code": 10008, "message": "Unknown Message"}));
+    });
+
+    run_test!(
+        "complete voting with deleted announcement",
+        &test,
+        [(
+            GET,
+            format!("/v1/votings/{}/results/duels", voting.id),
+            results
+        )],
+        [(
+            PATCH,
+            format!(
+                "/api/v10/channels/{}/messages/{}",
+                dm_channel_id, creator_message_id
+            ),
+            message_json(creator_message_id, dm_channel_id)
+        )],
+        ack(),
+        true
+    );
+
+    channel_mock.assert();
+    channel_mock.delete();
+
+    let voting = test.data.db.get_voting(&voting.id).await.unwrap();
+    assert!(voting.is_completed);
+}
+
+#[tokio::test]
+async fn handle_complete_voting_no_votes_test() {
+    let custom_uuid = "0c8a5a3e-6f3e-4d0c-9f69-1c2f3bd5e6a7"; // dm_component.json
+    let dm_channel_id = "319674150115610528"; // dm_component.json
+    let creator_message_id = "1202277900045262871"; // dm_component.json
+    let channel_id = "1187315505103638638";
+    let message_id = "3589723985723";
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        channel_id: channel_id.to_string(),
+        message_id: message_id.to_string(),
+        name: "Who do you prefer?".to_string(),
+        creator_message_id: creator_message_id.to_string(),
+        creator_dm_channel_id: dm_channel_id.to_string(),
+        ..Default::default()
+    };
+
+    let test = setup_test_env("dm_component.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![(
+            custom_uuid.to_string(),
+            CustomID {
+                action: Action::Complete,
+                voting_id: voting.id.clone(),
+                user_id: None,
+                page: None,
+                index: None,
+            },
+        )])
+        .await
+        .expect("Failed to save custom ids");
+
+    // a single neutral embed, without a ranking or a breakdown
+    let mut channel_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH)
+            .path(format!(
+                "/api/v10/channels/{}/messages/{}",
+                channel_id, message_id
+            ))
+            .body_contains(r#""description":"No votes were cast.""#)
+            .body_contains(r#""color":10070709"#)
+            .body_contains(r#""components":[]"#)
+            .matches(|req| {
+                let body = String::from_utf8_lossy(req.body.as_deref().unwrap_or_default());
+                !body.contains("Result breakdown") && !body.contains("fields")
+            });
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json(message_id, channel_id));
+    });
+
+    run_test!(
+        "complete voting without votes",
+        &test,
+        [(
+            GET,
+            format!("/v1/votings/{}/results/duels", voting.id),
+            json!({
+                "tie": false,
+                "results": [],
+                "duels": null,
+            })
+        )],
+        [(
+            PATCH,
+            format!(
+                "/api/v10/channels/{}/messages/{}",
+                dm_channel_id, creator_message_id
+            ),
+            message_json(creator_message_id, dm_channel_id)
+        )],
+        ack(),
+        true
+    );
+
+    channel_mock.assert();
+    channel_mock.delete();
+}
+
+#[test]
+fn first_choice_counts_test() {
+    let dialog = |ballot: Vec<i32>, submitted: bool| VoteDialog {
+        voting_id: "4712947128794".to_string(),
+        user_id: "82198898841029460".to_string(),
+        ballot,
+        message_id: String::new(),
+        channel_id: String::new(),
+        submitted,
+        submitted_unix: 0,
+        display_order: None,
+        created_unix: 0,
+        weight: 1,
+        comment: None,
+        question: 0,
+        question_ballots: Vec::new(),
+        placeholder: false,
+    };
+
+    assert!(first_choice_counts(&[]).is_empty());
+
+    let dialogs = vec![
+        dialog(vec![1, 2, 3], true),
+        dialog(vec![2, 1, 3], true),
+        dialog(vec![1, 0, 0], true),
+        // ties for the first place count for every tied choice
+        dialog(vec![1, 1, 2], true),
+        // not submitted ballots are not counted
+        dialog(vec![0, 0, 1], false),
+    ];
+
+    assert_eq!(
+        first_choice_counts(&dialogs),
+        HashMap::from([(0, 3), (1, 2)])
+    );
+
+    // weighted ballots count once per weight
+    let weighted = vec![
+        dialog(vec![1, 2, 3], true),
+        VoteDialog {
+            weight: 3,
+            ..dialog(vec![2, 1, 3], true)
+        },
+    ];
+    assert_eq!(
+        first_choice_counts(&weighted),
+        HashMap::from([(0, 1), (1, 3)])
+    );
+}
+
+#[test]
+fn with_list_fields_test() {
+    let embed = with_list_fields(EmbedBuilder::new(), "Choices", &["Spinoza", "Kant"]).build();
+    assert_eq!(embed.fields.len(), 1);
+    assert_eq!(embed.fields[0].name, "Choices");
+    assert_eq!(embed.fields[0].value, "Spinoza\nKant");
+
+    // every line is 300 characters, three of them fit into a field
+    let choices: Vec<String> = (0..7).map(|i| i.to_string().repeat(300)).collect();
+    let embed = with_list_fields(EmbedBuilder::new(), "Choices", &choices).build();
+    assert_eq!(embed.fields.len(), 3);
+    assert_eq!(embed.fields[1].name, "Choices (continued)");
+    assert_eq!(embed.fields[1].value, choices[3..6].join("\n"));
+    assert_eq!(embed.fields[2].value, choices[6]);
+    assert!(embed
+        .fields
+        .iter()
+        .all(|field| field.value.chars().count() <= EMBED_FIELD_VALUE_MAX_LENGTH));
+
+    // a line over the limit is cut
+    let long = "a".repeat(EMBED_FIELD_VALUE_MAX_LENGTH + 10);
+    let embed = with_list_fields(EmbedBuilder::new(), "Choices", &[long]).build();
+    assert_eq!(embed.fields.len(), 1);
+    assert_eq!(
+        embed.fields[0].value.chars().count(),
+        EMBED_FIELD_VALUE_MAX_LENGTH
+    );
+}
+
+#[test]
+fn parse_role_weights_test() {
+    assert_eq!(
+        parse_role_weights("<@&1187313045127581999>:3, 1187313045127582000:2"),
+        Some(vec![
+            ("1187313045127581999".to_string(), 3),
+            ("1187313045127582000".to_string(), 2)
+        ])
+    );
+    assert_eq!(parse_role_weights(""), Some(Vec::new()));
+    assert_eq!(parse_role_weights("1187313045127581999"), None);
+    assert_eq!(parse_role_weights("council:2"), None);
+    assert_eq!(parse_role_weights("1187313045127581999:0"), None);
+    assert_eq!(
+        parse_role_weights(&format!("1187313045127581999:{}", MAX_ROLE_WEIGHT + 1)),
+        None
+    );
+
+    assert_eq!(
+        weighted_voter_ids("82198898841029460", 3),
+        vec![
+            "82198898841029460".to_string(),
+            "82198898841029460-2".to_string(),
+            "82198898841029460-3".to_string()
+        ]
+    );
+}
+
+#[test]
+fn parse_questions_test() {
+    let questions = parse_questions("Venue: Park, Hall | Food: Pizza, Tacos, Sushi").unwrap();
+    assert_eq!(questions.len(), 2);
+    assert_eq!(questions[0].name, "Venue");
+    assert_eq!(questions[0].choices, vec!["Park", "Hall"]);
+    assert_eq!(questions[1].name, "Food");
+    assert_eq!(questions[1].choices, vec!["Pizza", "Tacos", "Sushi"]);
+    assert!(questions[0].dd_voting_id.is_empty());
+
+    assert_eq!(parse_questions(""), Some(Vec::new()));
+    assert_eq!(parse_questions("Venue Park, Hall"), None);
+    assert_eq!(parse_questions(": Park, Hall"), None);
+    assert_eq!(parse_questions("Venue: Park"), None);
+    assert_eq!(parse_questions("Venue: Park, , Hall"), None);
+    assert_eq!(parse_questions("Venue: Park, park"), None);
+    let too_many = vec!["Venue: Park, Hall"; MAX_QUESTIONS + 1].join(" | ");
+    assert_eq!(parse_questions(&too_many), None);
+}
+
+#[tokio::test]
+async fn handle_survey_questions_test() {
+    let dm_channel_id = "319674150115610528"; // dm_component.json
+    let dm_message_id = "1202277900045262871"; // dm_component.json
+    let user_id = "82198898841029460"; // dm_component.json
+    let next_question_uuid = "0c8a5a3e-6f3e-4d0c-9f69-1c2f3bd5e6a7"; // dm_component.json
+    let question_voting_id = "4712947128795";
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        name: "Who do you prefer?".to_string(),
+        questions: vec![Question {
+            dd_voting_id: question_voting_id.to_string(),
+            name: "Food".to_string(),
+            choices: vec!["Pizza".to_string(), "Tacos".to_string()],
+        }],
+        ..Default::default()
+    };
+
+    let mut test = setup_test_env("dm_component.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .save_voting_dialog(
+            voting.id.clone(),
+            user_id.to_string(),
+            vec![1, 2],
+            dm_message_id.to_string(),
+            dm_channel_id.to_string(),
+            false,
+        )
+        .await
+        .expect("Failed to save voting dialog");
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![(
+            next_question_uuid.to_string(),
+            CustomID {
+                action: Action::NextQuestion,
+                voting_id: voting.id.clone(),
+                user_id: Some(user_id.to_string()),
+                page: None,
+                index: None,
+            },
+        )])
+        .await
+        .expect("Failed to save custom ids");
+
+    let dialog_path = format!(
+        "/api/v10/channels/{}/messages/{}",
+        dm_channel_id, dm_message_id
+    );
+
+    // the first question is ranked, the dialog moves on to the second one
+    let mut question_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH)
+            .path(dialog_path.clone())
+            .body_contains("Question 2 of 2: Food")
+            .body_contains(r#""label":"Previous Question""#)
+            .body_contains(r#""label":"Vote""#);
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json(dm_message_id, dm_channel_id));
+    });
+
+    run_test!(
+        "next question",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        ack(),
+        true
+    );
+
+    question_mock.assert();
+    question_mock.delete();
+
+    let dialog = test
+        .data
+        .db
+        .get_voting_dialog(&voting.id, user_id)
+        .await
+        .unwrap();
+    assert_eq!(dialog.question, 1);
+    assert_eq!(dialog.ballot, vec![1, 2]);
+    assert_eq!(dialog.question_ballots, vec![vec![0, 0]]);
+
+    // the selects of the second question rank its own ballot
+    let custom_ids = test.data.db.get_custom_ids(&voting.id).await.unwrap();
+    for (uuid, custom_id) in custom_ids
+        .iter()
+        .filter(|(_, custom_id)| custom_id.action == Action::VoteSelect)
+    {
+        let rank = match custom_id.index.unwrap() {
+            0 => "2",
+            _ => "1",
+        };
+        test.set_body(dm_component_body(uuid, &[rank]));
+        run_test!(
+            "rank question choice",
+            &test,
+            empty_mock_vec(),
+            empty_mock_vec(),
+            ack(),
+            true
+        );
+    }
+
+    let dialog = test
+        .data
+        .db
+        .get_voting_dialog(&voting.id, user_id)
+        .await
+        .unwrap();
+    assert_eq!(dialog.ballot, vec![1, 2]);
+    assert_eq!(dialog.question_ballots, vec![vec![2, 1]]);
+
+    // every question is submitted to its own voting
+    let (vote_uuid, _) = custom_ids
+        .iter()
+        .find(|(_, custom_id)| custom_id.action == Action::VoteFromDM)
+        .unwrap();
+    test.set_body(dm_component_body(vote_uuid, &[]));
+
+    let mut question_ballot_mock = test.dd_server.mock(|when, then| {
+        when.method(POST)
+            .path(format!(
+                "/v1/votings/{}/ballots/{}",
+                question_voting_id, user_id
+            ))
+            .body_contains(r#""Tacos":1"#);
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(json!({"revoted": false}));
+    });
+    let mut ballot_mock = test.dd_server.mock(|when, then| {
+        when.method(POST)
+            .path(format!("/v1/votings/{}/ballots/{}", voting.id, user_id))
+            .body_contains(r#""Spinoza":1"#);
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(json!({"revoted": false}));
+    });
+    let mut dialog_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH).path(dialog_path.clone());
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json(dm_message_id, dm_channel_id));
+    });
+
+    run_test!(
+        "submit survey",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        ack(),
+        true
+    );
+
+    question_ballot_mock.assert();
+    question_ballot_mock.delete();
+    ballot_mock.assert();
+    ballot_mock.delete();
+    dialog_mock.assert();
+    dialog_mock.delete();
+
+    assert!(
+        test.data
+            .db
+            .get_voting_dialog(&voting.id, user_id)
+            .await
+            .unwrap()
+            .submitted
+    );
+}
+
+#[tokio::test]
+async fn handle_complete_voting_first_choice_counts_test() {
+    let custom_uuid = "0c8a5a3e-6f3e-4d0c-9f69-1c2f3bd5e6a7"; // dm_component.json
+    let dm_channel_id = "319674150115610528"; // dm_component.json
+    let creator_message_id = "1202277900045262871"; // dm_component.json
+    let channel_id = "1187315505103638638";
+    let message_id = "3589723985723";
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        channel_id: channel_id.to_string(),
+        message_id: message_id.to_string(),
+        name: "Who do you prefer?".to_string(),
+        creator_message_id: creator_message_id.to_string(),
+        creator_dm_channel_id: dm_channel_id.to_string(),
+        ..Default::default()
+    };
+
+    let test = setup_test_env("dm_component.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![(
+            custom_uuid.to_string(),
+            CustomID {
+                action: Action::Complete,
+                voting_id: voting.id.clone(),
+                user_id: None,
+                page: None,
+                index: None,
+            },
+        )])
+        .await
+        .expect("Failed to save custom ids");
+
+    // in channel dialogs, so the cleanup after completion has no dm to update
+    for (user_id, ballot) in [("1", vec![2, 1]), ("2", vec![2, 1]), ("3", vec![1, 2])] {
+        test.data
+            .db
+            .save_voting_dialog(
+                voting.id.clone(),
+                user_id.to_string(),
+                ballot,
+                String::new(),
+                String::new(),
+                false,
+            )
+            .await
+            .expect("Failed to save voting dialog");
+        test.data
+            .db
+            .set_voting_dialog_submitted(&voting.id, user_id, true, util::unix_timestamp())
+            .await
+            .expect("Failed to submit voting dialog");
+    }
+
+    let mut channel_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH)
+            .path(format!(
+                "/api/v10/channels/{}/messages/{}",
+                channel_id, message_id
+            ))
+            .body_contains("Wins: 1, Percentage: 100.00% · 2 first-choice votes")
+            .body_contains("Wins: 0, Percentage: 0.00% · 1 first-choice votes");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json(message_id, channel_id));
+    });
+
+    run_test!(
+        "complete voting with first choice counts",
+        &test,
+        [(
+            GET,
+            format!("/v1/votings/{}/results/duels", voting.id),
+            json!({
+                "tie": false,
+                "results": [
+                    {"choice": "Kant", "index": 1, "wins": 1, "percentage": 100.0, "strength": 2, "advantage": 1},
+                    {"choice": "Spinoza", "index": 0, "wins": 0, "percentage": 0.0, "strength": 1, "advantage": 0},
+                ],
+                "duels": null,
+            })
+        )],
+        [(
+            PATCH,
+            format!(
+                "/api/v10/channels/{}/messages/{}",
+                dm_channel_id, creator_message_id
+            ),
+            message_json(creator_message_id, dm_channel_id)
+        )],
+        ack(),
+        true
+    );
+
+    channel_mock.assert();
+    channel_mock.delete();
+
+    // the counts outlive the dialogs, for the result pages
+    let completed = test.data.db.get_voting(&voting.id).await.unwrap();
+    assert_eq!(
+        completed.first_choice_counts,
+        Some(HashMap::from([(0, 1), (1, 2)]))
+    );
+}
+
+#[tokio::test]
+async fn handle_complete_voting_comments_test() {
+    let complete_uuid = "0c8a5a3e-6f3e-4d0c-9f69-1c2f3bd5e6a7";
+    let comment_uuid = "5b0f4f4e-3c1b-4f57-a2a3-0d6b7d5c9e11";
+    let user_id = "82198898841029460"; // dm_component.json
+    let dm_channel_id = "319674150115610528"; // dm_component.json
+    let creator_message_id = "1202277900045262871"; // dm_component.json
+    let channel_id = "1187315505103638638";
+    let message_id = "3589723985723";
+
+    for anonymous in [false, true] {
+        let voting = dd_discord::db::Voting {
+            id: "4712947128794".to_string(),
+            choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+            channel_id: channel_id.to_string(),
+            message_id: message_id.to_string(),
+            name: "Who do you prefer?".to_string(),
+            creator_message_id: creator_message_id.to_string(),
+            creator_dm_channel_id: dm_channel_id.to_string(),
+            anonymous,
+            ..Default::default()
+        };
+
+        let mut test = setup_test_env("dm_component.json");
+        test.data
+            .db
+            .save_voting(voting.clone())
+            .await
+            .expect("Failed to save voting");
+        test.data
+            .db
+            .bulk_save_custom_ids(vec![
+                (
+                    complete_uuid.to_string(),
+                    CustomID {
+                        action: Action::Complete,
+                        voting_id: voting.id.clone(),
+                        user_id: None,
+                        page: None,
+                        index: None,
+                    },
+                ),
+                (
+                    comment_uuid.to_string(),
+                    CustomID {
+                        action: Action::AddComment,
+                        voting_id: voting.id.clone(),
+                        user_id: Some(user_id.to_string()),
+                        page: None,
+                        index: None,
+                    },
+                ),
+            ])
+            .await
+            .expect("Failed to save custom ids");
+
+        // in channel dialogs, so the cleanup after completion has no dm to update
+        for (voter_id, ballot) in [(user_id, vec![2, 1]), ("2", vec![1, 2])] {
+            test.data
+                .db
+                .save_voting_dialog(
+                    voting.id.clone(),
+                    voter_id.to_string(),
+                    ballot,
+                    String::new(),
+                    String::new(),
+                    false,
+                )
+                .await
+                .expect("Failed to save voting dialog");
+            test.data
+                .db
+                .set_voting_dialog_submitted(&voting.id, voter_id, true, util::unix_timestamp())
+                .await
+                .expect("Failed to submit voting dialog");
+        }
+        test.data
+            .db
+            .set_dialog_comment(&voting.id, "2", Some("Spinoza wrote clearer.".to_string()))
+            .await
+            .expect("Failed to save comment");
+
+        let mut body: serde_json::Value =
+            serde_json::from_str(&dm_component_body(comment_uuid, &[])).unwrap();
+        body["type"] = json!(5);
+        body["data"] = json!({
+            "custom_id": comment_uuid,
+            "components": [{
+                "type": 1,
+                "components": [{"type": 4, "custom_id": "comment", "value": " Kant settled it. "}]
+            }]
+        });
+        test.set_body(body.to_string());
+        run_test!(
+            "add comment",
+            &test,
+            empty_mock_vec(),
+            empty_mock_vec(),
+            Ok((
+                http::StatusCode::OK,
+                Json(InteractionResponse {
+                    kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                    data: Some(InteractionResponseData {
+                        content: Some(
+                            "Your comment was saved, it will be shown with the results."
+                                .to_string()
+                        ),
+                        flags: Some(MessageFlags::EPHEMERAL),
+                        ..Default::default()
+                    }),
+                })
+            )),
+            true
+        );
+
+        let dialog = test
+            .data
+            .db
+            .get_voting_dialog(&voting.id, user_id)
+            .await
+            .unwrap();
+        assert_eq!(dialog.comment.as_deref(), Some("Kant settled it."));
+
+        test.set_body(dm_component_body(complete_uuid, &[]));
+        let mut channel_mock = test.discord_server.mock(|when, then| {
+            let when = when.method(PATCH).path(format!(
+                "/api/v10/channels/{}/messages/{}",
+                channel_id, message_id
+            ));
+            if anonymous {
+                when.matches(|req| {
+                    !String::from_utf8_lossy(req.body.as_deref().unwrap_or_default())
+                        .contains("Comments")
+                });
+            } else {
+                when.body_contains(r#""title":"Comments""#)
+                    .body_contains(format!("<@{}>: Kant settled it.", user_id))
+                    .body_contains("<@2>: Spinoza wrote clearer.");
+            }
+            then.status(200)
+                .header("Content-Type", "application/json")
+                .json_body(message_json(message_id, channel_id));
+        });
+
+        run_test!(
+            "complete voting with comments",
+            &test,
+            [(
+                GET,
+                format!("/v1/votings/{}/results/duels", voting.id),
+                json!({
+                    "tie": false,
+                    "results": [
+                        {"choice": "Kant", "index": 1, "wins": 1, "percentage": 100.0, "strength": 2, "advantage": 1},
+                        {"choice": "Spinoza", "index": 0, "wins": 0, "percentage": 0.0, "strength": 1, "advantage": 0},
+                    ],
+                    "duels": null,
+                })
+            )],
+            [(
+                PATCH,
+                format!(
+                    "/api/v10/channels/{}/messages/{}",
+                    dm_channel_id, creator_message_id
+                ),
+                message_json(creator_message_id, dm_channel_id)
+            )],
+            ack(),
+            true
+        );
+
+        channel_mock.assert();
+        channel_mock.delete();
+
+        // comments are not even recorded for anonymous votings
+        let recorded = test.data.db.get_voting_ballots(&voting.id).await.unwrap();
+        assert_eq!(recorded.comments.is_empty(), anonymous);
+    }
+}
+
+#[tokio::test]
+#[traced_test]
+async fn handle_interaction_body_too_large_test() {
+    let test = setup_test_env("slash_command.json");
+
+    // padded valid json, it would parse if the size was not checked first
+    let mut body: serde_json::Value = serde_json::from_str(&test.body).unwrap();
+    body["padding"] = json!("x".repeat(MAX_INTERACTION_BODY_BYTES));
+    let body = body.to_string();
+    let headers = signing_headers(&test.signing_key, &body);
+
+    let resp = handle_interaction(test.data.clone(), headers, body).await;
+    assert_eq!(
+        resp.unwrap_err(),
+        InteractionError::Status(StatusCode::PAYLOAD_TOO_LARGE)
+    );
+    assert!(logs_contain("interaction body too large"));
+    assert!(!logs_contain("verifying signature failed"));
+}
+
+#[tokio::test]
+async fn handle_edit_name_modal_test() {
+    let custom_uuid = "0c8a5a3e-6f3e-4d0c-9f69-1c2f3bd5e6a7"; // dm_component.json
+    let vote_uuid = "df4db2bc-9fd1-43fb-8e17-97170379159a";
+    let channel_id = "1187315505103638638";
+    let message_id = "3589723985723";
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        name: "Who do you prefer?".to_string(),
+        channel_id: channel_id.to_string(),
+        message_id: message_id.to_string(),
+        ..Default::default()
+    };
+
+    let mut test = setup_test_env("dm_component.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .bulk_save_custom_ids(vec![
+            (
+                custom_uuid.to_string(),
+                CustomID {
+                    action: Action::EditName,
+                    voting_id: voting.id.clone(),
+                    user_id: None,
+                    page: None,
+                    index: None,
+                },
+            ),
+            (
+                vote_uuid.to_string(),
+                CustomID {
+                    action: Action::VoteFromChannel,
+                    voting_id: voting.id.clone(),
+                    user_id: None,
+                    page: None,
+                    index: None,
+                },
+            ),
+        ])
+        .await
+        .expect("Failed to save custom ids");
+
+    // the button opens the modal under its own custom id
+    run_test!(
+        "open modal",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        Ok((
+            http::StatusCode::OK,
+            Json(InteractionResponse {
+                kind: twilight_model::http::interaction::InteractionResponseType::Modal,
+                data: Some(InteractionResponseData {
+                    custom_id: Some(custom_uuid.to_string()),
+                    title: Some("Rename voting".to_string()),
+                    components: Some(vec![Component::ActionRow(ActionRow {
+                        components: vec![Component::TextInput(TextInput {
+                            custom_id: "name".to_string(),
+                            label: "Name".to_string(),
+                            max_length: Some(100),
+                            min_length: Some(1),
+                            placeholder: None,
+                            required: Some(true),
+                            style: TextInputStyle::Short,
+                            value: Some(voting.name.clone()),
+                        })],
+                    })]),
+                    ..Default::default()
+                }),
+            })
+        )),
+        true
+    );
+
+    let component_body: serde_json::Value = serde_json::from_str(&test.body).unwrap();
+    let modal_body = |custom_id: &str, name: &str| {
+        let mut body = component_body.clone();
+        body["type"] = json!(5);
+        body["data"] = json!({
+            "custom_id": custom_id,
+            "components": [{
+                "type": 1,
+                "components": [{"type": 4, "custom_id": "name", "value": name}]
+            }]
+        });
+        body.to_string()
+    };
+
+    test.set_body(modal_body(custom_uuid, "  "));
+    run_test!(
+        "empty name",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        Ok((
+            http::StatusCode::OK,
+            Json(InteractionResponse {
+                kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    content: Some("The voting name can not be empty.".to_string()),
+                    flags: Some(MessageFlags::EPHEMERAL),
+                    ..Default::default()
+                }),
+            })
+        )),
+        true
+    );
+    assert_eq!(
+        test.data.db.get_voting(&voting.id).await.unwrap().name,
+        voting.name
+    );
+
+    test.set_body(modal_body(custom_uuid, " Kant or Spinoza? "));
+
+    // the announcement shows the new name and keeps its vote button
+    let mut announcement_mock = test.discord_server.mock(|when, then| {
+        when.method(PATCH)
+            .path(format!(
+                "/api/v10/channels/{}/messages/{}",
+                channel_id, message_id
+            ))
+            .body_contains("Created a voting with name:Kant or Spinoza?")
+            .body_contains(format!(r#""custom_id":"{}""#, vote_uuid))
+            .body_contains(r#""label":"Vote""#);
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(message_json(message_id, channel_id));
+    });
+
+    run_test!(
+        "rename",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        Ok((
+            http::StatusCode::OK,
+            Json(InteractionResponse {
+                kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    content: Some("Voting renamed to **Kant or Spinoza?**.".to_string()),
+                    flags: Some(MessageFlags::EPHEMERAL),
+                    ..Default::default()
+                }),
+            })
+        )),
+        true
+    );
+    assert_eq!(
+        test.data.db.get_voting(&voting.id).await.unwrap().name,
+        "Kant or Spinoza?"
+    );
+    announcement_mock.assert();
+    announcement_mock.delete();
+
+    // lingering modals of cleaned up votings are acked
+    test.set_body(modal_body(
+        "e4c8e9a8-0d4d-4bd4-8f43-3c9b2f0a1b2c",
+        "Other name",
+    ));
+    run_test!(
+        "unknown custom id",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        ack(),
+        true
+    );
+}
+
+#[tokio::test]
+async fn handle_admin_register_test() {
+    let register_server = MockServer::start();
+    let register_path = "/api/v10/applications/1187313045127581999/commands";
+
+    let test = setup_test_env_with_config(
+        "dm_component.json",
+        dd_discord::Config {
+            admin_api_secret: Some("secret".to_string()),
+            voting_command: Some(VotingCommandRegistration {
+                bot_token: "bot_token".to_string(),
+                register_url: register_server.url(register_path),
+                max_choices: 4,
+                localizations: HashMap::new(),
+            }),
+            ..Default::default()
+        },
+    );
+
+    let secret_headers = |secret: &str| {
+        let mut headers = http::HeaderMap::new();
+        headers.insert(ADMIN_SECRET_HEADER, secret.parse().unwrap());
+        headers
+    };
+
+    let mut register_mock = register_server.mock(|when, then| {
+        when.method(POST)
+            .path(register_path)
+            .header("Authorization", "Bot bot_token")
+            .body_contains(r#""name":"voting""#)
+            .body_contains(r#""name":"choice4""#);
+        then.status(201)
+            .header("Content-Type", "application/json")
+            .json_body(json!({}));
+    });
+
+    let resp = handle_admin_register(test.data.clone(), secret_headers("wrong")).await;
+    assert_eq!(resp.unwrap_err(), StatusCode::UNAUTHORIZED);
+
+    let resp = handle_admin_register(test.data.clone(), http::HeaderMap::new()).await;
+    assert_eq!(resp.unwrap_err(), StatusCode::UNAUTHORIZED);
+    register_mock.assert_hits(0);
+
+    let resp = handle_admin_register(test.data.clone(), secret_headers("secret"))
+        .await
+        .expect("registering should succeed");
+    assert_eq!(
+        resp.0,
+        RegisterResponse {
+            discord_status: 201
+        }
+    );
+    register_mock.assert();
+    register_mock.delete();
+
+    // the discord status is passed through, even when the registration is rejected
+    let register_mock = register_server.mock(|when, then| {
+        when.method(POST).path(register_path);
+        then.status(400)
+            .header("Content-Type", "application/json")
+            .json_body(json!({"message": "Invalid Form Body", "code": 50035}));
+    });
+
+    let resp = handle_admin_register(test.data.clone(), secret_headers("secret"))
+        .await
+        .expect("discord answered");
+    assert_eq!(
+        resp.0,
+        RegisterResponse {
+            discord_status: 400
+        }
+    );
+    register_mock.assert();
+}
+
+#[tokio::test]
+async fn handle_voting_results_test() {
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        name: "Who do you prefer?".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        ..Default::default()
+    };
+
+    let test = setup_test_env_with_config(
+        "dm_component.json",
+        dd_discord::Config {
+            results_api_secret: Some("secret".to_string()),
+            ..Default::default()
+        },
+    );
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+
+    let secret_headers = |secret: &str| {
+        let mut headers = http::HeaderMap::new();
+        headers.insert(RESULTS_SECRET_HEADER, secret.parse().unwrap());
+        headers
+    };
+
+    let resp = handle_voting_results(
+        test.data.clone(),
+        Path(voting.id.clone()),
+        secret_headers("wrong"),
+    )
+    .await;
+    assert_eq!(resp.unwrap_err(), StatusCode::UNAUTHORIZED);
+
+    let resp = handle_voting_results(
+        test.data.clone(),
+        Path(voting.id.clone()),
+        http::HeaderMap::new(),
+    )
+    .await;
+    assert_eq!(resp.unwrap_err(), StatusCode::UNAUTHORIZED);
+
+    let resp = handle_voting_results(
+        test.data.clone(),
+        Path("unknown".to_string()),
+        secret_headers("secret"),
+    )
+    .await;
+    assert_eq!(resp.unwrap_err(), StatusCode::NOT_FOUND);
+
+    // private results are reported as missing
+    test.data
+        .db
+        .save_voting(dd_discord::db::Voting {
+            id: "4712947128795".to_string(),
+            private_results: true,
+            ..voting.clone()
+        })
+        .await
+        .expect("Failed to save voting");
+    let resp = handle_voting_results(
+        test.data.clone(),
+        Path("4712947128795".to_string()),
+        secret_headers("secret"),
+    )
+    .await;
+    assert_eq!(resp.unwrap_err(), StatusCode::NOT_FOUND);
+
+    let mut results_mock = create_mock!(
+        test.dd_server,
+        GET,
+        format!("/v1/votings/{}/results/duels", voting.id),
+        json!({
+            "tie": false,
+            "results": [
+                {"choice": "Kant", "index": 1, "wins": 1, "percentage": 100.0, "strength": 1, "advantage": 1},
+                {"choice": "Spinoza", "index": 0, "wins": 0, "percentage": 0.0, "strength": 0, "advantage": 0},
+            ],
+            "duels": [],
+        })
+    );
+
+    let Json(body) = handle_voting_results(
+        test.data.clone(),
+        Path(voting.id.clone()),
+        secret_headers("secret"),
+    )
+    .await
+    .expect("results should be returned");
+
+    results_mock.assert();
+    results_mock.delete();
+
+    assert_eq!(
+        serde_json::to_value(&body).unwrap(),
+        json!({
+            "name": "Who do you prefer?",
+            "is_completed": false,
+            "choices": ["Spinoza", "Kant"],
+            "ranking": [
+                {"choice": "Kant", "wins": 1, "percentage": 100.0},
+                {"choice": "Spinoza", "wins": 0, "percentage": 0.0},
+            ],
+            "duels": [],
+            "tie": false,
+        })
+    );
+}
+
+#[tokio::test]
+async fn reconcile_pending_completions_test() {
+    let dm_channel_id = "319674150115610528";
+    let creator_message_id = "812746127846424";
+    let channel_id = "1187315505103638638";
+    let message_id = "3589723985723";
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        channel_id: channel_id.to_string(),
+        message_id: message_id.to_string(),
+        name: "Who do you prefer?".to_string(),
+        is_completed: false,
+        is_deleted: false,
+        creator_message_id: creator_message_id.to_string(),
+        creator_dm_channel_id: dm_channel_id.to_string(),
+        ..Default::default()
+    };
+
+    let test = setup_test_env("dm_component.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+
+    // the process died right after marking the voting as completed
+    test.data
+        .db
+        .complete_open_voting(&voting.id, "creator")
+        .await
+        .expect("Failed to complete voting");
+
+    // without results the completion stays pending for the next replay
+    dd_discord::reconcile_pending_completions(&test.data).await;
+    let pending = test
+        .data
+        .db
+        .get_pending_completions()
+        .await
+        .expect("Failed to get pending completions");
+    assert_eq!(pending, vec![voting.id.clone()]);
+
+    let mut mocks = [
+        create_mock!(
+            test.dd_server,
+            GET,
+            format!("/v1/votings/{}/results/duels", voting.id),
+            json!({
+                "tie": false,
+                "results": [
+                    {"choice": "Kant", "index": 1, "wins": 1, "percentage": 100.0, "strength": 1, "advantage": 1},
+                    {"choice": "Spinoza", "index": 0, "wins": 0, "percentage": 0.0, "strength": 0, "advantage": 0},
+                ],
+                "duels": null,
+            })
+        ),
+        create_mock!(
+            test.discord_server,
+            PATCH,
+            format!("/api/v10/channels/{}/messages/{}", channel_id, message_id),
+            message_json(message_id, channel_id)
+        ),
+        create_mock!(
+            test.discord_server,
+            PATCH,
+            format!(
+                "/api/v10/channels/{}/messages/{}",
+                dm_channel_id, creator_message_id
+            ),
+            message_json(creator_message_id, dm_channel_id)
+        ),
+    ];
+
+    // the periodic retry leaves a completion which just started to its caller
+    dd_discord::retry_pending_completions(&test.data).await;
+    for mock in mocks.iter() {
+        mock.assert_hits(0);
+    }
+
+    dd_discord::reconcile_pending_completions(&test.data).await;
+
+    for mock in mocks.iter_mut() {
+        mock.assert();
+        mock.delete();
+    }
+
+    let pending = test
+        .data
+        .db
+        .get_pending_completions()
+        .await
+        .expect("Failed to get pending completions");
+    assert!(pending.is_empty());
+}
+
+#[tokio::test]
+async fn replay_pending_votes_test() {
+    let user_id = "82198898841029460";
+
+    let voting = dd_discord::db::Voting {
+        id: "4712947128794".to_string(),
+        choices: vec!["Spinoza".to_string(), "Kant".to_string()],
+        name: "Who do you prefer?".to_string(),
+        ..Default::default()
+    };
+
+    let test = setup_test_env("dm_component.json");
+    test.data
+        .db
+        .save_voting(voting.clone())
+        .await
+        .expect("Failed to save voting");
+    test.data
+        .db
+        .save_voting_dialog(
+            voting.id.clone(),
+            user_id.to_string(),
+            vec![2, 1],
+            "1202277900045262871".to_string(),
+            "319674150115610528".to_string(),
+            false,
+        )
+        .await
+        .expect("Failed to save voting dialog");
+    test.data
+        .db
+        .save_pending_vote(PendingVote {
+            voting_id: voting.id.clone(),
+            user_id: user_id.to_string(),
+            ballot: HashMap::from([("Spinoza".to_string(), 2), ("Kant".to_string(), 1)]),
+            weight: 1,
+        })
+        .await
+        .expect("Failed to save pending vote");
+
+    // pending vote of a deleted voting is dropped without calling DD
+    test.data
+        .db
+        .save_pending_vote(PendingVote {
+            voting_id: "deleted-voting".to_string(),
+            user_id: user_id.to_string(),
+            ballot: HashMap::from([("a".to_string(), 1)]),
+            weight: 1,
+        })
+        .await
+        .expect("Failed to save pending vote");
+
+    let mut ballot_mock = test.dd_server.mock(|when, then| {
+        when.method(POST)
+            .path(format!("/v1/votings/{}/ballots/{}", voting.id, user_id))
+            .json_body(json!({"ballot": {"Spinoza": 2, "Kant": 1}}));
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(json!({"revoted": true}));
+    });
+
+    dd_discord::replay_pending_votes(&test.data).await;
+
+    ballot_mock.assert();
+    ballot_mock.delete();
+
+    let pending = test.data.db.get_pending_votes().await.unwrap();
+    assert!(pending.is_empty());
+
+    let dialog = test
+        .data
+        .db
+        .get_voting_dialog(&voting.id, user_id)
+        .await
+        .expect("Failed to get voting dialog");
+    assert!(dialog.submitted);
+}
+
+#[tokio::test]
+async fn handle_my_votes_test() {
+    let user_id = "399954205235871744"; // from slash_command.json
+    let dm_channel_id = "319674150115610528";
+
+    let mut test = setup_test_env("slash_command.json");
+
+    let mut body: serde_json::Value = serde_json::from_str(&test.body).unwrap();
+    body["data"]["name"] = json!("my-votes");
+    body["data"]["options"] = json!([]);
+    test.set_body(body.to_string());
+
+    run_test!(
+        "no open dialogs",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        Ok((
+            http::StatusCode::OK,
+            Json(InteractionResponse {
+                kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    content: Some("You have no open voting dialogs.".to_string()),
+                    flags: Some(MessageFlags::EPHEMERAL),
+                    ..Default::default()
+                }),
+            })
+        )),
+        true
+    );
+
+    for (voting_id, name, is_completed) in [
+        ("voting1", "Who do you prefer?", false),
+        ("voting2", "Best pizza", false),
+        ("voting3", "Already completed", true),
+    ] {
+        test.data
+            .db
+            .save_voting(dd_discord::db::Voting {
+                id: voting_id.to_string(),
+                name: name.to_string(),
+                choices: vec!["a".to_string(), "b".to_string()],
+                is_completed,
+                ..Default::default()
+            })
+            .await
+            .expect("Failed to save voting");
+        test.data
+            .db
+            .save_voting_dialog(
+                voting_id.to_string(),
+                user_id.to_string(),
+                vec![0, 0],
+                format!("{}-message", voting_id),
+                dm_channel_id.to_string(),
+                false,
+            )
+            .await
+            .expect("Failed to save voting dialog");
+    }
+
+    let (status, response) = handle_interaction(
+        test.data.clone(),
+        test.headers.clone(),
+        test.body.to_string(),
+    )
+    .await
+    .expect("my-votes should succeed");
+
+    assert_eq!(status, StatusCode::OK);
+    let data = response.0.data.expect("response data should be set");
+    assert_eq!(data.flags, Some(MessageFlags::EPHEMERAL));
+
+    let embeds = data.embeds.expect("embeds should be set");
+    assert_eq!(embeds.len(), 1);
+    assert_eq!(
+        embeds[0].description.as_deref(),
+        Some(
+            "[Who do you prefer?](https://discord.com/channels/@me/319674150115610528/voting1-message)\n[Best pizza](https://discord.com/channels/@me/319674150115610528/voting2-message)"
+        )
+    );
+}
+
+#[tokio::test]
+async fn handle_read_only_mode_test() {
+    let mut test = setup_test_env_with_config(
+        "slash_command.json",
+        dd_discord::Config {
+            admin_api_secret: Some("secret".to_string()),
+            ..Default::default()
+        },
+    );
+
+    let mut headers = http::HeaderMap::new();
+    headers.insert(ADMIN_SECRET_HEADER, "secret".parse().unwrap());
+
+    let resp = handle_admin_read_only(
+        test.data.clone(),
+        http::HeaderMap::new(),
+        Json(ReadOnlyMode { read_only: true }),
+    )
+    .await;
+    assert_eq!(resp.unwrap_err(), StatusCode::UNAUTHORIZED);
+    assert!(!test.data.is_read_only());
+
+    let resp = handle_admin_read_only(
+        test.data.clone(),
+        headers.clone(),
+        Json(ReadOnlyMode { read_only: true }),
+    )
+    .await
+    .expect("enabling read-only mode should succeed");
+    assert_eq!(resp.0, ReadOnlyMode { read_only: true });
+
+    // the voting is neither created on the dd backend nor stored
+    let mut dd_mock = test.dd_server.mock(|when, then| {
+        when.path_contains("/v1/");
+        then.status(500);
+    });
+
+    run_test!(
+        "read-only slash voting",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        Ok((
+            http::StatusCode::OK,
+            Json(InteractionResponse {
+                kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    content: Some(
+                        "The bot is in maintenance mode, please try again shortly.".to_string()
+                    ),
+                    flags: Some(MessageFlags::EPHEMERAL),
+                    ..Default::default()
+                }),
+            }),
+        )),
+        true
+    );
+
+    dd_mock.assert_hits(0);
+    dd_mock.delete();
+
+    let (votings, _) = test.data.db.get_votings_page(None, 10, true).await.unwrap();
+    assert!(votings.is_empty());
+
+    // the other commands which write are rejected as well
+    let mut body: serde_json::Value = serde_json::from_str(&test.body).unwrap();
+    body["data"]["name"] = json!("voting-config");
+    body["data"]["options"] = json!([{"name": "max_choices", "type": 4, "value": 5}]);
+    body["member"]["permissions"] = json!("8"); // administrator
+    test.set_body(body.to_string());
+
+    run_test!(
+        "read-only voting config",
+        &test,
+        empty_mock_vec(),
+        empty_mock_vec(),
+        Ok((
+            http::StatusCode::OK,
+            Json(InteractionResponse {
+                kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+                data: Some(InteractionResponseData {
+                    content: Some(
+                        "The bot is in maintenance mode, please try again shortly.".to_string()
+                    ),
+                    flags: Some(MessageFlags::EPHEMERAL),
+                    ..Default::default()
+                }),
+            }),
+        )),
+        true
+    );
+
+    let guild_id = "1187313045127581796"; // slash_command.json
+    let config = test.data.db.get_guild_config(guild_id).await.unwrap();
+    assert_eq!(config.max_choices, None);
+
+    // the background writers pause too
+    test.data
+        .db
+        .save_votings_listings(vec![(
+            "listing".to_string(),
+            dd_discord::db::VotingsListing {
+                guild_id: guild_id.to_string(),
+                cursors: vec![String::new()],
+                expires_unix: 0,
+            },
+        )])
+        .await
+        .unwrap();
+    dd_discord::expire_votings_listings(&test.data).await;
+    assert!(test.data.db.get_votings_listing("listing").await.is_ok());
+
+    let resp = handle_admin_read_only(
+        test.data.clone(),
+        headers,
+        Json(ReadOnlyMode { read_only: false }),
+    )
+    .await
+    .expect("disabling read-only mode should succeed");
+    assert_eq!(resp.0, ReadOnlyMode { read_only: false });
+    assert!(!test.data.is_read_only());
+
+    dd_discord::expire_votings_listings(&test.data).await;
+    assert_eq!(
+        test.data.db.get_votings_listing("listing").await,
+        Err(dd_discord::db::DbError::NotFound)
+    );
+}
+
+#[tokio::test]
+async fn handle_ping_status_test() {
+    let mut test = setup_test_env("slash_command.json");
+
+    let mut body: serde_json::Value = serde_json::from_str(&test.body).unwrap();
+    body["data"]["name"] = json!("ping");
+    body["data"]["options"] = json!([]);
+    test.set_body(body.to_string());
+
+    // the status check voting does not exist, but the backend answered
+    let mut dd_mock = test.dd_server.mock(|when, then| {
+        when.method(GET).path("/v1/votings/status-check");
+        then.status(404)
+            .header("Content-Type", "application/json")
+            .json_body(json!({"code": 404, "message": "Not Found"}));
+    });
+
+    let (status, response) = handle_interaction(
+        test.data.clone(),
+        test.headers.clone(),
+        test.body.to_string(),
+    )
+    .await
+    .expect("ping should succeed");
+
+    dd_mock.assert();
+    dd_mock.delete();
+
+    assert_eq!(status, StatusCode::OK);
+    let data = response.0.data.expect("response data should be set");
+    assert_eq!(data.flags, Some(MessageFlags::EPHEMERAL));
+
+    let embeds = data.embeds.expect("status embed should be set");
+    let fields = &embeds[0].fields;
+    assert_eq!(fields.len(), 2);
+    assert_eq!(fields[0].name, "Database");
+    assert!(fields[0].value.starts_with("✅"), "{}", fields[0].value);
+    assert_eq!(fields[1].name, "Direct Decisions API");
+    assert!(fields[1].value.starts_with("✅"), "{}", fields[1].value);
 }
 
 fn create_dd_client_server() -> (MockServer, ddclient_rs::Client) {
@@ -491,21 +8169,55 @@ struct TestEnvironment {
     body: String,
     data: State<Arc<dd_discord::AppState>>,
     headers: http::HeaderMap,
+    signing_key: SigningKey,
+}
+
+impl TestEnvironment {
+    // replace the interaction body, signing it with the environment key
+    fn set_body(&mut self, body: String) {
+        self.headers = signing_headers(&self.signing_key, &body);
+        self.body = body;
+    }
+
+    // waits for the tasks spawned by the handled interactions to finish
+    async fn wait_for_tasks(&self) {
+        self.data.task_tracker.close();
+        self.data.task_tracker.wait().await;
+        self.data.task_tracker.reopen();
+    }
+
+    // path of the original interaction response, edited by the background tasks
+    fn original_response_path(&self) -> String {
+        let body: serde_json::Value = serde_json::from_str(&self.body).unwrap();
+        format!(
+            "/api/v10/webhooks/{}/{}/messages/@original",
+            body["application_id"].as_str().unwrap(),
+            body["token"].as_str().unwrap()
+        )
+    }
 }
 
 fn setup_test_env(filename: &str) -> TestEnvironment {
+    setup_test_env_with_config(filename, dd_discord::Config::default())
+}
+
+fn setup_test_env_with_config(filename: &str, config: dd_discord::Config) -> TestEnvironment {
     let filename = format!("{}/{}", "tests/data", filename);
     let body = fs::read_to_string(filename).expect("Failed to read file");
     let (_drop_db, db) = create_test_db();
     let (dd_server, dd_client) = create_dd_client_server();
     let (discord_server, discord_client) = create_discord_client_server();
 
-    let (headers, discord_public_key) = signing_headers(&body);
+    let mut csprng = OsRng;
+    let signing_key: SigningKey = SigningKey::generate(&mut csprng);
+    let discord_public_key = hex::encode(signing_key.verifying_key().as_bytes());
+    let headers = signing_headers(&signing_key, &body);
     let app_state = State(dd_discord::new_app_state(
         db,
         discord_client,
         dd_client,
         discord_public_key,
+        config,
     ));
 
     TestEnvironment {
@@ -515,13 +8227,11 @@ fn setup_test_env(filename: &str) -> TestEnvironment {
         body: body.to_string(),
         data: app_state,
         headers,
+        signing_key,
     }
 }
 
-fn signing_headers(body: &str) -> (http::HeaderMap, String) {
-    let mut csprng = OsRng;
-    let signing_key: SigningKey = SigningKey::generate(&mut csprng);
-
+fn signing_headers(signing_key: &SigningKey, body: &str) -> http::HeaderMap {
     let timestamp = "timestamp".to_string();
     let mut signing_buff = timestamp.as_bytes().to_vec();
     signing_buff.extend_from_slice(body.as_bytes());
@@ -529,17 +8239,71 @@ fn signing_headers(body: &str) -> (http::HeaderMap, String) {
     let signature = signing_key.sign(&signing_buff);
     let signature = signature.to_bytes();
     let signature = hex::encode(signature);
-    let public_key = hex::encode(signing_key.verifying_key().as_bytes());
 
     let mut headers = http::HeaderMap::new();
     headers.insert("X-Signature-Ed25519", signature.parse().unwrap());
     headers.insert("X-Signature-Timestamp", timestamp.parse().unwrap());
+    headers.insert("Content-Type", "application/json".parse().unwrap());
+
+    headers
+}
+
+// dm component interaction (dm_component.json) clicked with the provided custom id
+fn dm_component_body(custom_id: &str, values: &[&str]) -> String {
+    let body = fs::read_to_string("tests/data/dm_component.json").expect("Failed to read file");
+    let mut body: serde_json::Value = serde_json::from_str(&body).expect("Failed to parse file");
+    body["data"]["custom_id"] = json!(custom_id);
+    if !values.is_empty() {
+        body["data"]["component_type"] = json!(3);
+        body["data"]["values"] = json!(values);
+    }
+
+    body.to_string()
+}
+
+fn message_json(id: &str, channel_id: &str) -> serde_json::Value {
+    json!({
+        "attachments": [],
+        "author": {
+          "username": "test",
+          "discriminator": "9999",
+          "id": "1187314136292528198",
+          "avatar": "33ecab261d4681afa4d85a04691c4a01"
+        },
+        "channel_id": channel_id,
+        "content": "test",
+        "edited_timestamp": null,
+        "embeds": [],
+        "flags": 0,
+        "id": id,
+        "mention_everyone": false,
+        "mention_roles": [],
+        "mentions": [],
+        "pinned": false,
+        "timestamp": "2018-02-04T19:51:45.941000+00:00",
+        "tts": false,
+        "type": 0
+    })
+}
 
-    (headers, public_key)
+fn ack() -> dd_discord::InteractionResult {
+    Ok((
+        http::StatusCode::OK,
+        Json(InteractionResponse {
+            kind:
+                twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
+            data: None,
+        }),
+    ))
 }
 
-fn internal_server_error_response() -> dd_discord::InteractionResult {
-    Err(InteractionError::InternalServerError)
+// internal errors are answered with the interaction id as reference
+fn internal_server_error_response(test: &TestEnvironment) -> dd_discord::InteractionResult {
+    let body: serde_json::Value = serde_json::from_str(&test.body).unwrap();
+    Ok((
+        http::StatusCode::OK,
+        internal_error_response(body["id"].as_str().unwrap(), None),
+    ))
 }
 
 // this can be used for debugging tests
@@ -0,0 +1,38 @@
+use dd_discord::util::{discord_relative_timestamp, invert_permutation, stable_permutation};
+
+#[test]
+fn stable_permutation_same_seed_test() {
+    let seed = "4712947128794-82198898841029460";
+    assert_eq!(stable_permutation(seed, 10), stable_permutation(seed, 10));
+
+    let mut sorted = stable_permutation(seed, 10);
+    sorted.sort();
+    assert_eq!(sorted, (0..10).collect::<Vec<_>>());
+}
+
+#[test]
+fn stable_permutation_different_seeds_test() {
+    assert_ne!(
+        stable_permutation("4712947128794-82198898841029460", 10),
+        stable_permutation("4712947128794-82198898841029461", 10)
+    );
+}
+
+#[test]
+fn invert_permutation_test() {
+    for n in 0..=25 {
+        let order = stable_permutation("4712947128794", n);
+        let inverted = invert_permutation(&order);
+
+        let values: Vec<usize> = (0..n).map(|i| i * 10).collect();
+        let permuted: Vec<usize> = order.iter().map(|&i| values[i]).collect();
+        let restored: Vec<usize> = inverted.iter().map(|&i| permuted[i]).collect();
+        assert_eq!(restored, values);
+    }
+}
+
+#[test]
+fn discord_relative_timestamp_test() {
+    assert_eq!(discord_relative_timestamp(1706000000), "<t:1706000000:R>");
+    assert_eq!(discord_relative_timestamp(0), "<t:0:R>");
+}